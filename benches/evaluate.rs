@@ -0,0 +1,84 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use logos::Logos;
+
+use erm::ast;
+use erm::env;
+use erm::evaluator::{self, limits::Usage, values::Value};
+use erm::lexer::Token;
+use erm::parser;
+use erm::project;
+
+/* Parses the source and builds its environment ahead of the measurements so that each benchmark
+ * only times the evaluation
+ */
+fn bench_eval(c: &mut Criterion, name: &str, source: &str) {
+    let tokens = Token::lexer(source);
+    let mut iter = tokens.spanned().peekable();
+    let module = parser::parse(&mut iter).expect("Failed to parse");
+    let module = ast::with_default_imports(&module);
+
+    let settings = project::Settings::new();
+    let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to build scope");
+    let environment = env::Environment::from_module_scope(scope);
+
+    c.bench_function(name, |b| {
+        b.iter(|| -> Value {
+            evaluator::evaluate(&module, vec![], &environment, &settings, &Usage::default())
+                .expect("Failed to evaluate")
+        })
+    });
+}
+
+fn fib(c: &mut Criterion) {
+    let source = "module Main exposing (main)
+
+fib n =
+    if n < 2 then
+        n
+
+    else
+        fib (n - 1) + fib (n - 2)
+
+main args =
+    String.fromInt (fib 15)
+";
+
+    bench_eval(c, "fib 15", source);
+}
+
+fn list_sum(c: &mut Criterion) {
+    let items = (1..=500)
+        .map(|num| num.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let source = format!(
+        "module Main exposing (main)
+
+main args =
+    String.fromInt (List.sum [{}])
+",
+        items
+    );
+
+    bench_eval(c, "list sum 500", &source);
+}
+
+fn string_building(c: &mut Criterion) {
+    let appends = (1..=100)
+        .map(|num| format!("\"{}\"", num))
+        .collect::<Vec<_>>()
+        .join(" ++ ");
+    let source = format!(
+        "module Main exposing (main)
+
+main args =
+    String.join \", \" [ {}, String.fromInt 100 ]
+",
+        appends
+    );
+
+    bench_eval(c, "string building 100", &source);
+}
+
+criterion_group!(benches, fib, list_sum, string_building);
+criterion_main!(benches);
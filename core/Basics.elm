@@ -1,4 +1,4 @@
-module Basics exposing ((+), (-), (*))
+module Basics exposing ((<), (>), (++), (+), (-), (*), Never, Order(..), always, compare, identity, never, not, xor)
 
 infix non   4 (<)  = lt
 infix non   4 (>)  = gt
@@ -24,3 +24,39 @@ sub =
 
 mul =
     Elm.Kernel.Basics.mul
+
+identity x =
+    x
+
+always a _ =
+    a
+
+not bool =
+    case bool of
+        True ->
+            False
+
+        False ->
+            True
+
+xor a b =
+    case a of
+        True ->
+            not b
+
+        False ->
+            b
+
+type Never
+    = JustOneMore Never
+
+never =
+    Elm.Kernel.Basics.never
+
+type Order
+    = LT
+    | EQ
+    | GT
+
+compare =
+    Elm.Kernel.Basics.compare
@@ -0,0 +1,19 @@
+module Bytes exposing (append, decodeInt, encodeInt, fromString, toString, width)
+
+fromString =
+    Elm.Kernel.Bytes.fromString
+
+toString =
+    Elm.Kernel.Bytes.toString
+
+width =
+    Elm.Kernel.Bytes.width
+
+append =
+    Elm.Kernel.Bytes.append
+
+encodeInt =
+    Elm.Kernel.Bytes.encodeInt
+
+decodeInt =
+    Elm.Kernel.Bytes.decodeInt
@@ -0,0 +1,7 @@
+module Csv exposing (parse, parseWith)
+
+parse =
+    Elm.Kernel.Csv.parse
+
+parseWith =
+    Elm.Kernel.Csv.parseWith
@@ -0,0 +1,7 @@
+module Debug exposing (log, toString)
+
+toString =
+    Elm.Kernel.Debug.toString
+
+log =
+    Elm.Kernel.Debug.log
@@ -0,0 +1,4 @@
+module Env exposing (get)
+
+get =
+    Elm.Kernel.Env.get
@@ -0,0 +1,7 @@
+module Expect exposing (equal, true)
+
+equal =
+    Elm.Kernel.Expect.equal
+
+true =
+    Elm.Kernel.Expect.true
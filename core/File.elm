@@ -0,0 +1,4 @@
+module File exposing (read)
+
+read =
+    Elm.Kernel.File.read
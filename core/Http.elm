@@ -0,0 +1,7 @@
+module Http exposing (getString, post)
+
+getString =
+    Elm.Kernel.Http.getString
+
+post =
+    Elm.Kernel.Http.post
@@ -1,4 +1,31 @@
-module List exposing (sum)
+module List exposing (drop, intersperse, map, maximum, minimum, partition, product, sum, take, unzip)
 
 sum =
     Elm.Kernel.List.sum
+
+product =
+    Elm.Kernel.List.product
+
+maximum =
+    Elm.Kernel.List.maximum
+
+minimum =
+    Elm.Kernel.List.minimum
+
+take =
+    Elm.Kernel.List.take
+
+drop =
+    Elm.Kernel.List.drop
+
+map =
+    Elm.Kernel.List.map
+
+partition =
+    Elm.Kernel.List.partition
+
+unzip =
+    Elm.Kernel.List.unzip
+
+intersperse =
+    Elm.Kernel.List.intersperse
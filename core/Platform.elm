@@ -0,0 +1,4 @@
+module Platform exposing (worker)
+
+worker =
+    Elm.Kernel.Platform.worker
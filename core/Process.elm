@@ -0,0 +1,4 @@
+module Process exposing (run)
+
+run =
+    Elm.Kernel.Process.run
@@ -0,0 +1,16 @@
+module Random exposing (float, initialSeed, int, list, step)
+
+initialSeed =
+    Elm.Kernel.Random.initialSeed
+
+int =
+    Elm.Kernel.Random.int
+
+float =
+    Elm.Kernel.Random.float
+
+list =
+    Elm.Kernel.Random.list
+
+step =
+    Elm.Kernel.Random.step
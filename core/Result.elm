@@ -0,0 +1,5 @@
+module Result exposing (Result(..))
+
+type Result error value
+    = Ok value
+    | Err error
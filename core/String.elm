@@ -1,4 +1,4 @@
-module String exposing (append, fromInt, join)
+module String exposing (append, concat, filter, foldl, fromBool, fromInt, join, left, length, lines, map, padLeft, padRight, repeat, reverse, right, words)
 
 append =
     Elm.Kernel.Basics.append
@@ -8,3 +8,45 @@ join =
 
 fromInt =
     Elm.Kernel.String.fromInt
+
+fromBool =
+    Elm.Kernel.String.fromBool
+
+length =
+    Elm.Kernel.String.length
+
+reverse =
+    Elm.Kernel.String.reverse
+
+left =
+    Elm.Kernel.String.left
+
+right =
+    Elm.Kernel.String.right
+
+concat =
+    Elm.Kernel.String.concat
+
+repeat =
+    Elm.Kernel.String.repeat
+
+padLeft =
+    Elm.Kernel.String.padLeft
+
+padRight =
+    Elm.Kernel.String.padRight
+
+words =
+    Elm.Kernel.String.words
+
+lines =
+    Elm.Kernel.String.lines
+
+map =
+    Elm.Kernel.String.map
+
+filter =
+    Elm.Kernel.String.filter
+
+foldl =
+    Elm.Kernel.String.foldl
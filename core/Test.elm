@@ -0,0 +1,7 @@
+module Test exposing (describe, test)
+
+test =
+    Elm.Kernel.Test.test
+
+describe =
+    Elm.Kernel.Test.describe
@@ -0,0 +1,7 @@
+module Time exposing (monotonic, now)
+
+now =
+    Elm.Kernel.Time.now
+
+monotonic =
+    Elm.Kernel.Time.monotonic
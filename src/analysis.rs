@@ -0,0 +1,542 @@
+use std::path::PathBuf;
+
+use logos::Logos;
+
+use crate::ast::{self, Module, Stmt};
+use crate::checker;
+use crate::env;
+use crate::error::{self, Error};
+use crate::lexer::{Range, Token};
+use crate::parser;
+use crate::project;
+
+/* Queries about a single source file for editor tooling like the language server. Each query
+ * works from the source text so that it can be used on unsaved files.
+ */
+
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    pub range: Range,
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    // Something that doesn't stop the program from running but might not be what was intended,
+    // like a project module hiding a core module
+    Warning,
+    // Something that the checker found out which might help, like the types of annotation holes
+    Information,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Information => "information",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Location {
+    // None when the location is in the source that was queried
+    pub path: Option<PathBuf>,
+    pub range: Range,
+}
+
+/* Finds the problems with the source without evaluating it. Every character that the lexer can't
+ * make sense of is reported, along with the first parse or check error, the warnings from loading
+ * the imported modules and what the checker found out about annotation holes. This is what the
+ * language server & 'erm --report=json' show.
+ */
+pub fn diagnostics(source: &str, settings: &project::Settings) -> Vec<Diagnostic> {
+    log::trace!("diagnostics");
+    let mut diagnostics: Vec<Diagnostic> = Token::lexer(source)
+        .spanned()
+        .filter(|(token, _range)| *token == Token::Error)
+        .map(|(_token, range)| Diagnostic {
+            message: format!("Unexpected character: {:?}", &source[range.clone()]),
+            range,
+            severity: Severity::Error,
+        })
+        .collect();
+
+    let loaded = load(source, settings);
+
+    diagnostics.extend(settings.warnings.take().iter().map(warning_diagnostic));
+
+    let (module, environment) = match loaded {
+        Ok(loaded) => loaded,
+        Err(diagnostic) => {
+            // The parser stops at the first character that the lexer couldn't read, which has
+            // already been reported
+            if !diagnostics
+                .iter()
+                .any(|existing| existing.range.start == diagnostic.range.start)
+            {
+                diagnostics.push(diagnostic);
+            }
+            return diagnostics;
+        }
+    };
+
+    // Only modules with a main function can be checked at the moment
+    if find_statement(&module, "main").is_none() {
+        return diagnostics;
+    }
+
+    let checked = checker::check_with_holes(&module, &environment, settings);
+    diagnostics.extend(settings.warnings.take().iter().map(warning_diagnostic));
+
+    match checked {
+        Ok(holes) => diagnostics.extend(holes.into_iter().map(|hole| Diagnostic {
+            range: hole.range.unwrap_or(0..0),
+            severity: Severity::Information,
+            message: format!("The _ in the annotation for {} is {}", hole.name, hole.term),
+        })),
+        Err(error) => diagnostics.push(Diagnostic {
+            range: error.range().unwrap_or(0..0),
+            severity: Severity::Error,
+            message: error::to_user_output(Error::CheckError(error)),
+        }),
+    }
+
+    diagnostics
+}
+
+fn warning_diagnostic(warning: &project::Warning) -> Diagnostic {
+    Diagnostic {
+        range: warning.range().unwrap_or(0..0),
+        severity: Severity::Warning,
+        message: error::warning_to_user_output(warning),
+    }
+}
+
+/* Returns the name and type of the binding at the given offset in the source */
+pub fn hover(source: &str, offset: usize, settings: &project::Settings) -> Option<String> {
+    log::trace!("hover: {}", offset);
+    let (name, _range) = name_at(source, offset)?;
+    let (_module, environment) = load(source, settings).ok()?;
+
+    checker::infer(&name, &environment)
+        .ok()
+        .map(|term| format!("{} : {}", name.as_string(), term))
+}
+
+/* Finds where the name at the given offset is defined, either in the source itself or in one of
+ * the modules that it imports.
+ */
+pub fn definition(source: &str, offset: usize, settings: &project::Settings) -> Option<Location> {
+    log::trace!("definition: {}", offset);
+    let (name, _range) = name_at(source, offset)?;
+    let access = name.access.first()?;
+    let (module, scope) = load_scope(source, settings).ok()?;
+
+    if name.modules.is_empty() {
+        if let Some(range) = module.arena.definition(ast::DefinitionKind::Value, access) {
+            return Some(Location {
+                path: None,
+                range: range.clone(),
+            });
+        }
+    }
+
+    scope
+        .module_imports
+        .iter()
+        .filter(|import| name.modules.is_empty() || import.qualifier() == name.modules)
+        .find_map(|import| {
+            let module_scope = &import.module_scope;
+            let range = module_scope
+                .arena
+                .definition(ast::DefinitionKind::Value, access)?;
+            Some(Location {
+                path: Some(module_path(&module_scope.name, settings)?),
+                range: range.clone(),
+            })
+        })
+}
+
+#[cfg(feature = "fs")]
+fn module_path(module_name: &ast::ModuleName, settings: &project::Settings) -> Option<PathBuf> {
+    env::find_module_path(module_name, settings).map(|(path, _is_core)| path)
+}
+
+// Without the filesystem there are no files for an editor to open
+#[cfg(not(feature = "fs"))]
+fn module_path(_module_name: &ast::ModuleName, _settings: &project::Settings) -> Option<PathBuf> {
+    None
+}
+
+/* Every top level value, type & constructor that the source can refer to along with the module
+ * that defines it and the range of its name in that module's source
+ */
+pub fn definitions(
+    source: &str,
+    settings: &project::Settings,
+) -> Result<Vec<env::Definition>, Diagnostic> {
+    log::trace!("definitions");
+    let (_module, scope) = load_scope(source, settings)?;
+    Ok(scope.definitions_in_scope())
+}
+
+fn load(
+    source: &str,
+    settings: &project::Settings,
+) -> Result<(Module, env::Environment), Diagnostic> {
+    let (module, scope) = load_scope(source, settings)?;
+    Ok((module, env::Environment::from_module_scope(scope)))
+}
+
+fn load_scope(
+    source: &str,
+    settings: &project::Settings,
+) -> Result<(Module, env::ModuleScope), Diagnostic> {
+    let imported_operators = |import: &ast::Import| env::imported_operators(import, settings);
+    let module = parser::parse_source(source, imported_operators).map_err(|error| Diagnostic {
+        range: error.range().unwrap_or(0..0),
+        severity: Severity::Error,
+        message: error::to_user_output(Error::ParserError(error, source.to_string())),
+    })?;
+
+    let module = ast::with_default_imports(&module);
+    let scope = env::ModuleScope::from_module(&module, settings).map_err(|error| Diagnostic {
+        range: 0..0,
+        severity: Severity::Error,
+        message: error::to_user_output(Error::ScopeError(error)),
+    })?;
+
+    Ok((module, scope))
+}
+
+fn find_statement<'a>(module: &'a Module, target_name: &str) -> Option<&'a Stmt> {
+    module
+        .statements
+        .iter()
+        .map(|stmt| &**stmt)
+        .find(|stmt| match stmt {
+            Stmt::Binding { name, .. } | Stmt::Function { name, .. } => name.0 == target_name,
+            _ => false,
+        })
+}
+
+fn name_at(source: &str, offset: usize) -> Option<(ast::QualifiedLowerName, Range)> {
+    // Includes the end of the range so that a cursor just after a name still finds it
+    Token::lexer(source)
+        .spanned()
+        .find_map(|(token, range)| match token {
+            Token::LowerName(name) | Token::LowerPath(name)
+                if range.start <= offset && offset <= range.end =>
+            {
+                Some((ast::QualifiedLowerName::from(name.to_string()), range))
+            }
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SOURCE: &str = "module Main exposing (main)
+
+count : Int
+count =
+    3
+
+main args =
+    String.fromInt count
+";
+
+    #[test]
+    fn definition_skips_annotation() {
+        let settings = project::Settings::new();
+        let offset = SOURCE.rfind("count").unwrap_or(0);
+
+        assert_eq!(
+            definition(SOURCE, offset, &settings),
+            Some(Location {
+                path: None,
+                range: 41..46
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn definition_in_imported_module() {
+        let settings = project::Settings::new();
+        let offset = SOURCE.find("fromInt").unwrap_or(0);
+
+        let location = definition(SOURCE, offset, &settings);
+        let defined = location.as_ref().and_then(|location| {
+            let contents = std::fs::read_to_string(location.path.as_ref()?).ok()?;
+            contents.get(location.range.clone()).map(str::to_string)
+        });
+        assert_eq!(
+            location.and_then(|location| location.path),
+            Some(PathBuf::from("core/String.elm"))
+        );
+        assert_eq!(defined.as_deref(), Some("fromInt"));
+    }
+
+    #[test]
+    fn definitions_include_types_and_imports() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+type Shape
+    = Circle Int
+    | Square Int
+
+count : Int
+count =
+    3
+
+main args =
+    String.fromInt count
+";
+        let definitions = definitions(source, &settings).unwrap_or_default();
+        let find = |kind, name: &str| {
+            definitions
+                .iter()
+                .find(|definition| definition.kind == kind && definition.name == name)
+                .map(|definition| (definition.module.join("."), definition.range.clone()))
+        };
+
+        let shape_start = source.find("Shape").unwrap_or(0);
+        let square_start = source.find("Square").unwrap_or(0);
+        let count_start = source.rfind("count =").unwrap_or(0);
+
+        assert_eq!(
+            find(ast::DefinitionKind::Type, "Shape"),
+            Some(("Main".to_string(), Some(shape_start..shape_start + 5)))
+        );
+        assert_eq!(
+            find(ast::DefinitionKind::Constructor, "Square"),
+            Some(("Main".to_string(), Some(square_start..square_start + 6)))
+        );
+        assert_eq!(
+            find(ast::DefinitionKind::Value, "count"),
+            Some(("Main".to_string(), Some(count_start..count_start + 5)))
+        );
+        assert_eq!(
+            find(ast::DefinitionKind::Value, "fromInt").map(|(module, _range)| module),
+            Some("String".to_string())
+        );
+        assert_eq!(
+            find(ast::DefinitionKind::Constructor, "Just").map(|(module, _range)| module),
+            Some("Maybe".to_string())
+        );
+    }
+
+    #[test]
+    fn diagnostic_at_mismatched_branch() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+size : Int -> Int
+size x =
+    if x > 0 then
+        x
+    else
+        \"none\"
+
+main args =
+    String.fromInt (size 3)
+";
+        let diagnostics = diagnostics(source, &settings);
+        let else_start = source.find("else").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .first()
+                .map(|diagnostic| diagnostic.range.clone()),
+            Some(else_start..else_start + 4)
+        );
+    }
+
+    #[test]
+    fn diagnostic_at_call_with_too_many_arguments() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+main args =
+    String.fromInt 1 2
+";
+        let diagnostics = diagnostics(source, &settings);
+        let call_start = source.find("String.fromInt").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .first()
+                .map(|diagnostic| diagnostic.range.clone()),
+            Some(call_start..call_start + 14)
+        );
+    }
+
+    #[test]
+    fn diagnostic_at_mismatched_record_argument() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+double : Int -> Int
+double x =
+    x * 2
+
+main args =
+    String.fromInt (double { count = 3 })
+";
+        let diagnostics = diagnostics(source, &settings);
+        let record_start = source.find("{ count").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .first()
+                .map(|diagnostic| diagnostic.range.clone()),
+            Some(record_start..record_start + 13)
+        );
+    }
+
+    #[test]
+    fn diagnostic_at_cyclic_value() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+first =
+    second
+
+second =
+    first
+
+main args =
+    first
+";
+        let diagnostics = diagnostics(source, &settings);
+        let first_start = source.find("first =").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .first()
+                .map(|diagnostic| diagnostic.range.clone()),
+            Some(first_start..first_start + 5)
+        );
+    }
+
+    #[test]
+    fn diagnostic_for_annotation_hole() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+double : _ -> Int
+double x =
+    x * 2
+
+main args =
+    String.fromInt (double 3)
+";
+        let diagnostics = diagnostics(source, &settings);
+        let hole_start = source.find("_ ->").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics,
+            vec![Diagnostic {
+                range: hole_start..hole_start + 1,
+                severity: Severity::Information,
+                message: "The _ in the annotation for double is Int".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn diagnostics_for_each_annotation_hole() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+label : _ -> ( Int, _ )
+label x =
+    ( x + 1, String.fromInt x )
+
+main args =
+    case label 3 of
+        ( n, _ ) ->
+            String.fromInt n
+";
+        let diagnostics = diagnostics(source, &settings);
+        let first = source.find("_ ->").unwrap_or(0);
+        let second = source.find("_ )\n").unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.range.clone(), diagnostic.message.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (first..first + 1, "The _ in the annotation for label is Int"),
+                (
+                    second..second + 1,
+                    "The _ in the annotation for label is String"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn diagnostics_for_each_unknown_character() {
+        let settings = project::Settings::new();
+        let source = "module Main exposing (main)
+
+main args =
+    \"a\" ¬ \"b\" ¬ \"c\"
+";
+        let diagnostics = diagnostics(source, &settings);
+        let first = source.find('¬').unwrap_or(0);
+        let second = source.rfind('¬').unwrap_or(0);
+
+        assert_eq!(
+            diagnostics
+                .iter()
+                .map(|diagnostic| (diagnostic.range.start, diagnostic.severity))
+                .collect::<Vec<_>>(),
+            vec![(first, Severity::Error), (second, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn hover_shows_type() {
+        let settings = project::Settings::new();
+        let offset = SOURCE.rfind("count").unwrap_or(0);
+
+        assert_eq!(
+            hover(SOURCE, offset, &settings),
+            Some("count : Int".to_string())
+        );
+    }
+
+    #[test]
+    fn hover_shows_partially_applied_type() {
+        let source = "module Main exposing (main)
+
+add : Int -> Int -> Int
+add a b =
+    a + b
+
+addTwo =
+    add 2
+
+main args =
+    String.fromInt (addTwo 3)
+";
+        let settings = project::Settings::new();
+        let offset = source.rfind("addTwo").unwrap_or(0);
+
+        assert_eq!(
+            hover(source, offset, &settings),
+            Some("addTwo : Int -> Int".to_string())
+        );
+    }
+}
@@ -1,18 +1,29 @@
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::lexer::Range;
 
 pub type ModuleName = Vec<String>;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Module {
     pub name: ModuleName,
     pub exposing: Exposing,
     pub imports: Vec<Import>,
-    pub statements: Vec<Rc<Stmt>>,
+    // Statements and the arena are shared via Arc so that modules can be parsed on other threads
+    pub statements: Vec<Arc<Stmt>>,
+    // Storage for all the expressions in the module. Statements and expressions refer to their
+    // sub-expressions by id
+    pub arena: Arc<Arena>,
 }
 
 pub fn with_default_imports(module: &Module) -> Module {
     log::trace!("with_default_imports");
     let mut imports = Import::prelude();
+    // A project module can take the place of a prelude module but it can't import itself
+    imports.retain(|import| import.module_name != module.name);
     imports.append(&mut module.imports.clone());
 
     Module {
@@ -20,16 +31,28 @@ pub fn with_default_imports(module: &Module) -> Module {
         exposing: module.exposing.clone(),
         imports,
         statements: module.statements.clone(),
+        arena: module.arena.clone(),
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Import {
     pub module_name: Vec<String>,
+    // The name given with 'as', eg. 'import Dict as D', which then qualifies the module's names
+    // in place of the module name
+    pub alias: Option<String>,
     pub exposing: Option<Exposing>,
 }
 
 impl Import {
+    /* The module name that qualifies references to the imported names */
+    pub fn qualifier(&self) -> ModuleName {
+        match &self.alias {
+            Some(alias) => vec![alias.clone()],
+            None => self.module_name.clone(),
+        }
+    }
+
     // How Elm determines whether to include the prelude:
     // https://github.com/elm/compiler/blob/770071accf791e8171440709effe71e78a9ab37c/compiler/src/Parse/Module.hs#L80
     pub fn prelude() -> Vec<Import> {
@@ -37,55 +60,70 @@ impl Import {
         vec![
             Import {
                 module_name: vec!["Basics".to_string()],
-                exposing: Some(Exposing::List(vec![ExposingDetail::Operator(
-                    "+".to_string(),
-                )])),
+                alias: None,
+                exposing: Some(Exposing::All),
             },
             Import {
                 module_name: vec!["String".to_string()],
+                alias: None,
                 exposing: None,
             },
             Import {
                 module_name: vec!["List".to_string()],
+                alias: None,
+                exposing: None,
+            },
+            Import {
+                module_name: vec!["Debug".to_string()],
+                alias: None,
                 exposing: None,
             },
             Import {
                 module_name: vec!["Maybe".to_string()],
+                alias: None,
                 exposing: Some(Exposing::List(vec![ExposingDetail::Type(
                     UpperName("Maybe".to_string()),
                     TypeState::Open,
                 )])),
             },
+            Import {
+                module_name: vec!["Result".to_string()],
+                alias: None,
+                exposing: Some(Exposing::List(vec![ExposingDetail::Type(
+                    UpperName("Result".to_string()),
+                    TypeState::Open,
+                )])),
+            },
         ]
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Exposing {
     All,
     List(Vec<ExposingDetail>),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ExposingDetail {
     Type(UpperName, TypeState),
     Operator(String),
     Name(String),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TypeState {
     Open,
     Closed,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct UpperName(pub String);
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct LowerName(pub String);
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct QualifiedLowerName {
     pub modules: Vec<String>,
     pub access: Vec<String>,
@@ -104,7 +142,7 @@ impl QualifiedLowerName {
         let (modules, access) = segments
             .into_iter()
             .map(|str| str.to_string())
-            .partition(|name| name.starts_with(|ch| ('A'..='Z').contains(&ch)));
+            .partition(|name| name.starts_with(|ch: char| ch.is_ascii_uppercase()));
 
         Self { modules, access }
     }
@@ -126,7 +164,7 @@ impl QualifiedLowerName {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize)]
 pub struct QualifiedUpperName {
     pub modules: Vec<String>,
     pub access: String,
@@ -134,11 +172,7 @@ pub struct QualifiedUpperName {
 
 impl QualifiedUpperName {
     pub fn from(name: &str) -> Option<Self> {
-        let mut segments: Vec<String> = name
-            .split('.')
-            .into_iter()
-            .map(|str| str.to_string())
-            .collect();
+        let mut segments: Vec<String> = name.split('.').map(|str| str.to_string()).collect();
 
         let last = segments.pop();
 
@@ -149,12 +183,7 @@ impl QualifiedUpperName {
     }
 
     pub fn as_string(&self) -> String {
-        let mut string = self
-            .modules
-            .iter()
-            .cloned()
-            .collect::<Vec<String>>()
-            .join(".");
+        let mut string = self.modules.join(".");
 
         if string.is_empty() {
             self.access.clone()
@@ -172,18 +201,18 @@ impl QualifiedUpperName {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Stmt {
     Binding {
         type_annotation: Option<TypeAnnotation>,
         name: LowerName,
-        expr: Rc<Expr>,
+        expr: ExprId,
     },
     Function {
         type_annotation: Option<TypeAnnotation>,
         name: LowerName,
         args: Vec<Pattern>,
-        expr: Rc<Expr>,
+        expr: ExprId,
     },
     Infix {
         operator_name: String,
@@ -196,16 +225,57 @@ pub enum Stmt {
         args: Vec<LowerName>,
         constructors: Vec<Type>,
     },
+    // A channel to the program embedding erm, eg. 'port sendThing : String -> Cmd msg'
+    Port {
+        name: LowerName,
+        type_: Type,
+        direction: PortDirection,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum PortDirection {
+    // Ports that take a value and return a 'Cmd msg' send the value to the host
+    Outgoing,
+    // Ports that take a function to tag incoming values and return a 'Sub msg' receive them
+    Incoming,
+}
+
+impl PortDirection {
+    /* Works out which way the values flow from the type of the port */
+    pub fn from_type(type_: &Type) -> Option<PortDirection> {
+        match type_ {
+            Type::Function { from, to } => match (&**from, &**to) {
+                (Type::Function { .. }, Type::UserDefined { name, .. }) if name.access == "Sub" => {
+                    Some(PortDirection::Incoming)
+                }
+                (_, Type::UserDefined { name, .. }) if name.access == "Cmd" => {
+                    Some(PortDirection::Outgoing)
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TypeAnnotation {
     pub name: LowerName,
     pub type_: Type,
 }
 
+// A binding in a let expression which can have an annotation like a top level binding when the
+// pattern is a single name
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LetBinding {
+    pub type_annotation: Option<TypeAnnotation>,
+    pub pattern: Pattern,
+    pub expr: ExprId,
+}
+
 // Based on: https://github.com/elm-in-elm/compiler/blob/master/src/Elm/Data/Type.elm
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Type {
     Var(LowerName),
     Bool,
@@ -215,6 +285,7 @@ pub enum Type {
     String,
     Unit,
     List(Box<Type>),
+    Tuple(Vec<Type>),
     Function {
         from: Box<Type>,
         to: Box<Type>,
@@ -223,9 +294,11 @@ pub enum Type {
         name: QualifiedUpperName,
         args: Vec<Type>,
     },
+    // An underscore in an annotation which leaves the type to the checker
+    Hole,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Serialize, Deserialize)]
 pub enum Associativity {
     Left,
     Right,
@@ -233,50 +306,330 @@ pub enum Associativity {
 }
 
 // Based on: https://github.com/elm-in-elm/compiler/blob/master/src/Elm/AST/Canonical.elm#L97-L111
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Pattern {
     Anything,
     Bool(bool),
-    Integer(i32),
+    Integer(i64),
+    String(String),
+    Char(char),
     Name(String),
+    Tuple(Vec<Pattern>),
+    List(Vec<Pattern>),
+    Cons(Box<Pattern>, Box<Pattern>),
+    Constructor(QualifiedUpperName, Vec<Pattern>),
+    // Binds each of the listed fields of a record to a name of its own, eg. '{ name, age }'
+    Record(Vec<String>),
 }
 
 impl Pattern {
-    // TODO: This feels wrong now that we have more than just 'Name'
+    // The names bound by the pattern in the order that they appear
     pub fn names(&self) -> Vec<String> {
         match self {
-            Pattern::Anything => vec![],
-            Pattern::Bool(_) => vec![],
-            Pattern::Integer(_) => vec![],
+            Pattern::Anything
+            | Pattern::Bool(_)
+            | Pattern::Integer(_)
+            | Pattern::String(_)
+            | Pattern::Char(_) => vec![],
             Pattern::Name(name) => vec![name.to_string()],
+            Pattern::Record(fields) => fields.clone(),
+            Pattern::Tuple(patterns)
+            | Pattern::List(patterns)
+            | Pattern::Constructor(_, patterns) => patterns
+                .iter()
+                .flat_map(|pattern| pattern.names())
+                .collect(),
+            Pattern::Cons(head, tail) => {
+                let mut names = head.names();
+                names.append(&mut tail.names());
+                names
+            }
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Expr {
     Bool(bool),
-    Integer(i32),
+    Integer(i64),
     Float(f32),
-    String(String),
-    List(Vec<Rc<Expr>>),
+    // Shared with the string values made from the literal so that evaluating it doesn't copy it
+    String(Arc<str>),
+    Char(char),
+    List(Vec<ExprId>),
+    Tuple(Vec<ExprId>),
+    // The fields of a record literal in the order they are written, eg. '{ name = "x", age = 3 }'
+    Record(Vec<(String, ExprId)>),
+    Unit,
+    // A reference to one of the constructors of a custom type, eg. 'Just' or 'Nothing'
+    Constructor(QualifiedUpperName),
     BinOp {
         operator: String,
-        left: Rc<Expr>,
-        right: Rc<Expr>,
+        left: ExprId,
+        right: ExprId,
     },
     If {
-        condition: Rc<Expr>,
-        then_branch: Rc<Expr>,
-        else_branch: Rc<Expr>,
+        condition: ExprId,
+        then_branch: ExprId,
+        else_branch: ExprId,
     },
     Case {
-        expr: Rc<Expr>,
-        branches: Vec<(Pattern, Expr)>,
+        expr: ExprId,
+        branches: Vec<(Pattern, ExprId)>,
+        // The range of the 'case ... of' line for reporting when no branch matches
+        range: Range,
+    },
+    // An anonymous function, eg. '\x y -> x + y'
+    Lambda {
+        args: Vec<Pattern>,
+        body: ExprId,
+    },
+    // Each binding can see the names bound by the ones before it and the body can see them all
+    Let {
+        bindings: Vec<LetBinding>,
+        body: ExprId,
     },
     Call {
-        function: Rc<Expr>,
-        args: Vec<Rc<Expr>>,
+        function: ExprId,
+        args: Vec<ExprId>,
     },
     VarName(QualifiedLowerName),
+    // A reference to a local name (eg. a function argument) that has been resolved to a slot in
+    // one of the enclosing local scopes. Depth 0 is the innermost scope. The name is kept for
+    // error reporting.
+    LocalVar {
+        name: String,
+        depth: usize,
+        slot: usize,
+    },
+}
+
+// Index of an expression within the Arena of the module that it was parsed from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExprId(usize);
+
+/* The sorts of names that a top level declaration can introduce. Types & constructors often share
+ * a name so they are kept apart
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DefinitionKind {
+    Value,
+    Type,
+    Constructor,
+}
+
+impl DefinitionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefinitionKind::Value => "value",
+            DefinitionKind::Type => "type",
+            DefinitionKind::Constructor => "constructor",
+        }
+    }
+}
+
+// Expressions are allocated into a single flat vector per module rather than individually on the
+// heap. This keeps them close together in memory and means that sharing or cloning a module only
+// involves the Rc around the arena.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Arena {
+    exprs: Vec<Expr>,
+    // Source ranges for the expressions that need them for reporting. At the moment that is the
+    // branches of if & case expressions which are given the range of the 'then' or 'else' keyword
+    // or the case pattern that introduces them
+    ranges: HashMap<ExprId, Range>,
+    // The range of the function at each call site, for reporting calls with the wrong number of
+    // arguments. Kept apart from the branch ranges so that coverage only sees branches
+    call_ranges: HashMap<ExprId, Range>,
+    // The range of each expression from its first token to its last, for reporting problems with
+    // the expression as a whole
+    spans: HashMap<ExprId, Range>,
+    // The range of the name in each top level declaration so that tools can jump to where a value,
+    // type or constructor is defined
+    definitions: Vec<(DefinitionKind, String, Range)>,
+    // The range of each '_' in the top level annotations, with the name that the annotation is
+    // for, in the order they appear in the source
+    holes: Vec<(String, Range)>,
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self {
+            exprs: Vec::new(),
+            ranges: HashMap::new(),
+            call_ranges: HashMap::new(),
+            spans: HashMap::new(),
+            definitions: Vec::new(),
+            holes: Vec::new(),
+        }
+    }
+
+    pub fn alloc(&mut self, expr: Expr) -> ExprId {
+        self.exprs.push(expr);
+        ExprId(self.exprs.len() - 1)
+    }
+
+    /* Allocates an expression that covers the given range of the source */
+    pub fn alloc_at(&mut self, expr: Expr, span: Range) -> ExprId {
+        let id = self.alloc(expr);
+        self.spans.insert(id, span);
+        id
+    }
+
+    pub fn span(&self, id: ExprId) -> Option<&Range> {
+        self.spans.get(&id)
+    }
+
+    /* The range from the start of the first expression to the end of the last */
+    pub fn span_between(&self, first: ExprId, last: ExprId) -> Range {
+        let start = self.span(first).map_or(0, |range| range.start);
+        start..self.span_from(start, last).end
+    }
+
+    /* The range from the given position to the end of the expression */
+    pub fn span_from(&self, start: usize, last: ExprId) -> Range {
+        start..self.span(last).map_or(start, |range| range.end)
+    }
+
+    pub fn replace(&mut self, id: ExprId, expr: Expr) {
+        self.exprs[id.0] = expr;
+    }
+
+    pub fn set_range(&mut self, id: ExprId, range: Range) {
+        self.ranges.insert(id, range);
+    }
+
+    pub fn range(&self, id: ExprId) -> Option<&Range> {
+        self.ranges.get(&id)
+    }
+
+    pub fn set_call_range(&mut self, id: ExprId, range: Range) {
+        self.call_ranges.insert(id, range);
+    }
+
+    pub fn call_range(&self, id: ExprId) -> Option<&Range> {
+        self.call_ranges.get(&id)
+    }
+
+    pub fn add_definition(&mut self, kind: DefinitionKind, name: &str, range: Range) {
+        self.definitions.push((kind, name.to_string(), range));
+    }
+
+    /* Later declarations of the same name come after earlier ones so we want the last range */
+    pub fn definition(&self, kind: DefinitionKind, name: &str) -> Option<&Range> {
+        self.definitions
+            .iter()
+            .rev()
+            .find(|(found_kind, found_name, _range)| *found_kind == kind && found_name == name)
+            .map(|(_kind, _name, range)| range)
+    }
+
+    /* Removes the definitions added since there were the given number, for the incremental parser
+     * to keep with the declaration that added them
+     */
+    pub fn split_definitions(&mut self, from: usize) -> Vec<(DefinitionKind, String, Range)> {
+        self.definitions.split_off(from)
+    }
+
+    pub fn definitions_len(&self) -> usize {
+        self.definitions.len()
+    }
+
+    pub fn set_definitions(&mut self, definitions: Vec<(DefinitionKind, String, Range)>) {
+        self.definitions = definitions;
+    }
+
+    pub fn add_hole(&mut self, name: &str, range: Range) {
+        self.holes.push((name.to_string(), range));
+    }
+
+    /* The ranges of the holes in the annotation for the name, in the order they appear in it */
+    pub fn holes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Range> {
+        self.holes
+            .iter()
+            .filter(move |(found_name, _range)| found_name == name)
+            .map(|(_name, range)| range)
+    }
+
+    /* Removes the holes added since there were the given number, for the incremental parser to
+     * keep with the declaration that added them
+     */
+    pub fn split_holes(&mut self, from: usize) -> Vec<(String, Range)> {
+        self.holes.split_off(from)
+    }
+
+    pub fn holes_len(&self) -> usize {
+        self.holes.len()
+    }
+
+    pub fn set_holes(&mut self, holes: Vec<(String, Range)>) {
+        self.holes = holes;
+    }
+
+    /* Moves the source ranges of the expressions with the given ids by the offset, for when the
+     * incremental parser reuses a declaration that now starts at a different place in the source
+     */
+    pub fn shift_ranges(&mut self, ids: std::ops::Range<usize>, offset: isize) {
+        let shift = |range: &mut Range| {
+            range.start = range.start.saturating_add_signed(offset);
+            range.end = range.end.saturating_add_signed(offset);
+        };
+
+        for index in ids {
+            let id = ExprId(index);
+            if let Some(range) = self.ranges.get_mut(&id) {
+                shift(range);
+            }
+            if let Some(range) = self.call_ranges.get_mut(&id) {
+                shift(range);
+            }
+            if let Some(range) = self.spans.get_mut(&id) {
+                shift(range);
+            }
+            if let Some(Expr::Case { range, .. }) = self.exprs.get_mut(index) {
+                shift(range);
+            }
+        }
+    }
+
+    pub fn ranges(&self) -> impl Iterator<Item = (&ExprId, &Range)> {
+        self.ranges.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /* Returns the expressions in the tree of binary operators at the id so that the operands of
+     * each operator come before it, from left to right. Operands that aren't operators are
+     * returned without looking inside them. A chain of operators, like '1 + 2 + 3', makes a tree
+     * as deep as the chain is long so the passes over the tree use this rather than recursing
+     */
+    pub fn operator_tree(&self, id: ExprId) -> Vec<ExprId> {
+        let mut order = Vec::new();
+        let mut stack = vec![(id, false)];
+        while let Some((id, expanded)) = stack.pop() {
+            match &self[id] {
+                Expr::BinOp { left, right, .. } if !expanded => {
+                    stack.push((id, true));
+                    stack.push((*right, false));
+                    stack.push((*left, false));
+                }
+                _ => order.push(id),
+            }
+        }
+        order
+    }
+}
+
+impl std::ops::Index<ExprId> for Arena {
+    type Output = Expr;
+
+    fn index(&self, id: ExprId) -> &Expr {
+        &self.exprs[id.0]
+    }
 }
@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::error::Error;
+
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{GotoDefinition, HoverRequest, Request as RequestTrait};
+use lsp_types::{
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkedString, OneOf, Position, PublishDiagnosticsParams,
+    SaveOptions, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
+    TextDocumentSyncOptions, TextDocumentSyncSaveOptions, Url,
+};
+
+use erm::analysis;
+use erm::lexer::Range;
+use erm::project;
+
+type Documents = HashMap<Url, String>;
+
+fn main() -> Result<(), Box<dyn Error + Sync + Send>> {
+    // Logs go to stderr so they don't interfere with the protocol on stdout
+    env_logger::init();
+
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::SaveOptions(SaveOptions {
+                    include_text: Some(true),
+                })),
+                ..TextDocumentSyncOptions::default()
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+    main_loop(connection)?;
+    io_threads.join()?;
+
+    Ok(())
+}
+
+fn main_loop(connection: Connection) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let settings = project::Settings::new();
+    let mut documents = Documents::new();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+
+                let response = handle_request(request, &documents, &settings);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(uri) = handle_notification(notification, &mut documents) {
+                    publish_diagnostics(&connection, &uri, &documents, &settings)?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_request(
+    request: Request,
+    documents: &Documents,
+    settings: &project::Settings,
+) -> Response {
+    log::trace!("handle_request: {}", request.method);
+    let request_id = request.id.clone();
+    match request.method.as_str() {
+        HoverRequest::METHOD => match request.extract::<HoverParams>(HoverRequest::METHOD) {
+            Ok((id, params)) => {
+                let position = params.text_document_position_params;
+                let hover = documents
+                    .get(&position.text_document.uri)
+                    .and_then(|source| {
+                        let offset = position_to_offset(source, position.position);
+                        analysis::hover(source, offset, settings)
+                    })
+                    .map(|text| Hover {
+                        contents: HoverContents::Scalar(MarkedString::String(text)),
+                        range: None,
+                    });
+
+                Response::new_ok(id, hover)
+            }
+            Err(error) => invalid_request(request_id, format!("{:?}", error)),
+        },
+        GotoDefinition::METHOD => {
+            match request.extract::<GotoDefinitionParams>(GotoDefinition::METHOD) {
+                Ok((id, params)) => {
+                    let position = params.text_document_position_params;
+                    let uri = position.text_document.uri;
+                    let location = documents.get(&uri).and_then(|source| {
+                        let offset = position_to_offset(source, position.position);
+                        let location = analysis::definition(source, offset, settings)?;
+                        to_lsp_location(&uri, source, location)
+                    });
+
+                    Response::new_ok(id, location.map(GotoDefinitionResponse::Scalar))
+                }
+                Err(error) => invalid_request(request_id, format!("{:?}", error)),
+            }
+        }
+        _ => Response::new_err(
+            request_id,
+            lsp_server::ErrorCode::MethodNotFound as i32,
+            format!("Unsupported request: {}", request.method),
+        ),
+    }
+}
+
+fn invalid_request(request_id: RequestId, message: String) -> Response {
+    Response::new_err(
+        request_id,
+        lsp_server::ErrorCode::InvalidRequest as i32,
+        message,
+    )
+}
+
+/* Updates the documents and returns the document that should have fresh diagnostics. We only
+ * publish diagnostics when a document is opened or saved.
+ */
+fn handle_notification(notification: Notification, documents: &mut Documents) -> Option<Url> {
+    log::trace!("handle_notification: {}", notification.method);
+    match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params = notification
+                .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)
+                .ok()?;
+            let uri = params.text_document.uri;
+            documents.insert(uri.clone(), params.text_document.text);
+            Some(uri)
+        }
+        DidChangeTextDocument::METHOD => {
+            let params = notification
+                .extract::<lsp_types::DidChangeTextDocumentParams>(DidChangeTextDocument::METHOD)
+                .ok()?;
+            // We ask for full syncs so the last change is the whole document
+            if let Some(change) = params.content_changes.into_iter().last() {
+                documents.insert(params.text_document.uri, change.text);
+            }
+            None
+        }
+        DidSaveTextDocument::METHOD => {
+            let params = notification
+                .extract::<lsp_types::DidSaveTextDocumentParams>(DidSaveTextDocument::METHOD)
+                .ok()?;
+            let uri = params.text_document.uri;
+            if let Some(text) = params.text {
+                documents.insert(uri.clone(), text);
+            }
+            Some(uri)
+        }
+        _ => None,
+    }
+}
+
+fn publish_diagnostics(
+    connection: &Connection,
+    uri: &Url,
+    documents: &Documents,
+    settings: &project::Settings,
+) -> Result<(), Box<dyn Error + Sync + Send>> {
+    let source = match documents.get(uri) {
+        Some(source) => source,
+        None => return Ok(()),
+    };
+
+    let diagnostics = erm::diagnostics(source, settings)
+        .into_iter()
+        .map(|diagnostic| lsp_types::Diagnostic {
+            range: to_lsp_range(source, &diagnostic.range),
+            severity: Some(match diagnostic.severity {
+                erm::Severity::Error => lsp_types::DiagnosticSeverity::ERROR,
+                erm::Severity::Warning => lsp_types::DiagnosticSeverity::WARNING,
+                erm::Severity::Information => lsp_types::DiagnosticSeverity::INFORMATION,
+            }),
+            source: Some("erm".to_string()),
+            message: diagnostic.message,
+            ..lsp_types::Diagnostic::default()
+        })
+        .collect();
+
+    let params = PublishDiagnosticsParams {
+        uri: uri.clone(),
+        diagnostics,
+        version: None,
+    };
+
+    connection
+        .sender
+        .send(Message::Notification(Notification::new(
+            PublishDiagnostics::METHOD.to_string(),
+            params,
+        )))?;
+
+    Ok(())
+}
+
+fn to_lsp_location(uri: &Url, source: &str, location: analysis::Location) -> Option<Location> {
+    match location.path {
+        None => Some(Location {
+            uri: uri.clone(),
+            range: to_lsp_range(source, &location.range),
+        }),
+        Some(path) => {
+            let contents = std::fs::read_to_string(&path).ok()?;
+            let path = std::fs::canonicalize(&path).ok()?;
+            Some(Location {
+                uri: Url::from_file_path(path).ok()?,
+                range: to_lsp_range(&contents, &location.range),
+            })
+        }
+    }
+}
+
+fn to_lsp_range(source: &str, range: &Range) -> lsp_types::Range {
+    lsp_types::Range {
+        start: offset_to_position(source, range.start),
+        end: offset_to_position(source, range.end),
+    }
+}
+
+// Positions are lines and UTF-16 code units within the line
+fn offset_to_position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset.min(source.len())];
+    let line_start = before.rfind('\n').map(|index| index + 1).unwrap_or(0);
+
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+fn position_to_offset(source: &str, position: Position) -> usize {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(position.line as usize)
+        .map(|line| line.len())
+        .sum();
+
+    let mut units = 0;
+    for (index, char) in source[line_start..].char_indices() {
+        if units >= position.character as usize || char == '\n' {
+            return line_start + index;
+        }
+        units += char.len_utf16();
+    }
+
+    source.len()
+}
@@ -1,32 +1,812 @@
-use clap::{App, Arg};
-use logos::Logos;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Editor, Helper};
 
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
+use erm::ast::{Associativity, Stmt};
+use erm::bindings::Binding;
+use erm::checker;
 use erm::env;
 use erm::error::{self, Error};
-use erm::evaluator;
-use erm::lexer::Token;
+use erm::evaluator::values::{Expectation, Program, Value};
+use erm::evaluator::{self, coverage::Coverage, limits::Usage, platform, testing};
+use erm::highlight;
+use erm::lexer;
 use erm::parser;
 use erm::project;
+use erm::repl;
 
 fn run(
     contents: String,
     program_args: Vec<String>,
-    settings: erm::project::Settings,
-) -> Result<evaluator::values::Value, Error> {
-    let result = Token::lexer(&contents);
-    let mut iter = result.spanned().peekable();
-
-    let module =
-        parser::parse(&mut iter).map_err(|err| Error::ParserError(err, contents.clone()))?;
-    let module = erm::ast::with_default_imports(&module);
-    let scope = env::ModuleScope::from_module(&module, &settings).map_err(Error::ScopeError)?;
+    settings: &erm::project::Settings,
+    usage: &Usage,
+    coverage: Option<&Coverage>,
+) -> Result<Value, Error> {
+    let module = parser::parse_source(&contents, |import| {
+        env::imported_operators(import, settings)
+    })
+    .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+    run_module(
+        module,
+        &contents,
+        program_args,
+        settings,
+        usage,
+        coverage,
+        None,
+    )
+}
+
+fn run_module(
+    module: erm::ast::Module,
+    source: &str,
+    program_args: Vec<String>,
+    settings: &erm::project::Settings,
+    usage: &Usage,
+    coverage: Option<&Coverage>,
+    cache: Option<&mut env::ModuleCache>,
+) -> Result<Value, Error> {
+    let module = env::optimized(erm::ast::with_default_imports(&module), settings);
+    let scope = match cache {
+        Some(cache) => env::ModuleScope::from_module_cached(&module, settings, cache),
+        None => env::ModuleScope::from_module(&module, settings),
+    }
+    .map_err(Error::ScopeError)?;
     let environment = env::Environment::from_module_scope(scope);
 
-    evaluator::evaluate(&module, program_args, &environment, &settings)
-        .map_err(Error::EvaluateError)
+    for warning in settings.warnings.take() {
+        eprintln!("Warning: {}", error::warning_to_user_output(&warning));
+    }
+
+    // Running skips the checker but a value that depends on itself would overflow the stack
+    checker::check_cycles(&module).map_err(Error::CheckError)?;
+
+    let result = evaluator::evaluate(&module, program_args, &environment, settings, usage)
+        .and_then(|value| match value {
+            Value::Program(program) => run_worker(&program, settings),
+            value => Ok(value),
+        })
+        .map_err(|err| Error::EvaluateError(err, source.to_string()));
+
+    // Printed to stderr so that it doesn't get mixed up with the output of the program
+    if let Some(coverage) = coverage {
+        eprint!("{}", coverage.report(&environment.arena, source));
+    }
+
+    result
+}
+
+/* Feeds each line of standard input to a Platform.worker program as a message. Values sent to the
+ * program's ports are printed as they arrive and the final model is the result of the program.
+ */
+fn run_worker(program: &Program, settings: &project::Settings) -> Result<Value, evaluator::Error> {
+    let (sender, receiver) = mpsc::channel();
+    settings.ports.listen(sender);
+
+    let mut worker = platform::Worker::new(program);
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        // Each message is evaluated with its own usage so that the limits apply to them separately
+        let usage = Usage::new(settings.max_duration);
+        worker.update(Value::String(Arc::from(line)), settings, &usage)?;
+        for message in receiver.try_iter() {
+            match message.value {
+                serde_json::Value::String(string) => println!("{}", string),
+                value => println!("{}", value),
+            }
+        }
+    }
+
+    Ok(worker.model().clone())
+}
+
+/* Re-runs the file whenever its contents or the contents of the modules it imports change. The
+ * parse cache is kept between runs so that only the declarations that have been edited need to be
+ * parsed again, and the module cache means only the imported modules that have changed are rebuilt.
+ */
+fn watch(
+    path: &str,
+    program_args: Vec<String>,
+    settings: &erm::project::Settings,
+    coverage: Option<&Coverage>,
+) {
+    let mut cache = parser::incremental::Cache::new();
+    let mut module_cache = env::ModuleCache::new();
+    let mut last_contents = None;
+
+    loop {
+        match read_path(path) {
+            Ok(contents)
+                if Some(&contents) != last_contents.as_ref()
+                    || module_cache.has_changed(settings) =>
+            {
+                let result = cache
+                    .parse(&contents, |import| {
+                        env::imported_operators(import, settings)
+                    })
+                    .map_err(|err| Error::ParserError(err, contents.clone()))
+                    .and_then(|module| {
+                        run_module(
+                            module,
+                            &contents,
+                            program_args.clone(),
+                            settings,
+                            &Usage::new(settings.max_duration),
+                            coverage,
+                            Some(&mut module_cache),
+                        )
+                    });
+
+                print_result(result);
+                last_contents = Some(contents);
+            }
+            Ok(_) => {}
+            Err(error) => {
+                print_result(Err(error));
+                return;
+            }
+        }
+
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/* Splits a module root argument like 'Shared=../elm-lib' into the module prefix & directory */
+fn parse_module_root(value: &str) -> Option<(String, PathBuf)> {
+    let (prefix, dir) = value.split_once('=')?;
+    if prefix.is_empty() || dir.is_empty() {
+        return None;
+    }
+
+    Some((prefix.to_string(), PathBuf::from(dir)))
+}
+
+/* Where parsed modules are kept between runs */
+fn cache_directory() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("erm"))
+}
+
+/* The 'vendor' directory next to the script, made by 'erm vendor', if there is one */
+fn vendor_directory(path: &str) -> Option<PathBuf> {
+    let directory = Path::new(path).parent()?.join("vendor");
+    directory.is_dir().then_some(directory)
+}
+
+/* The core modules that 'erm vendor' copied into the vendor directory, if it did */
+fn vendored_core(directory: &Path) -> Option<PathBuf> {
+    let core = env::vendored_core_path(directory);
+    core.is_dir().then_some(core)
+}
+
+/* Settings that read the script's imports, and the core modules, from its vendor directory when
+ * it has one
+ */
+fn script_settings(path: Option<&str>) -> project::Settings {
+    let mut settings = project::Settings::new();
+    if let Some(directory) = path.and_then(vendor_directory) {
+        if let Some(core) = vendored_core(&directory) {
+            settings.core_path = Some(core);
+        }
+        settings.source_directories = vec![directory];
+    }
+    settings
+}
+
+fn read_path(path: &str) -> Result<String, Error> {
+    std::fs::metadata(path)
+        .map_err(|_| Error::FileError)
+        .and_then(|attr| {
+            if attr.is_dir() {
+                Err(Error::FileError)
+            } else {
+                let mut f = File::open(path).map_err(|_| Error::FileError)?;
+
+                let mut contents = String::new();
+                f.read_to_string(&mut contents)
+                    .map_err(|_| Error::FileError)?;
+
+                Ok(filter_hash_bang(contents))
+            }
+        })
+}
+
+fn print_result(result: Result<Value, Error>) {
+    match result {
+        Err(error) => {
+            println!("{}", error::to_user_output(error));
+        }
+        Ok(Value::String(string)) => {
+            println!("{}", string);
+        }
+        Ok(value) => {
+            println!("{:?}", value);
+        }
+    }
+}
+
+/* Prints the tokens of the file with their kinds & ranges so that editor plugins can highlight
+ * code without running the language server.
+ */
+fn print_highlights(matches: &ArgMatches) {
+    // Read the file directly rather than via read_path so that the ranges match the file on disk
+    let contents = match matches.value_of("path").map(std::fs::read_to_string) {
+        Some(Ok(contents)) => contents,
+        _ => {
+            println!("{}", error::to_user_output(Error::FileError));
+            return;
+        }
+    };
+
+    let spans = highlight::highlight(&contents);
+
+    match matches.value_of("format") {
+        Some("json") => {
+            let tokens: Vec<_> = spans
+                .iter()
+                .map(|span| {
+                    serde_json::json!({
+                        "kind": span.kind.as_str(),
+                        "start": span.range.start,
+                        "end": span.range.end,
+                        "line": span.line,
+                        "column": span.column,
+                    })
+                })
+                .collect();
+
+            println!("{}", serde_json::Value::Array(tokens));
+        }
+        _ => {
+            for span in spans {
+                println!(
+                    "{}:{} {} {:?}",
+                    span.line + 1,
+                    span.column + 1,
+                    span.kind.as_str(),
+                    &contents[span.range]
+                );
+            }
+        }
+    }
+}
+
+/* Prints the problems with the source as JSON, without running it, and exits with a failure status
+ * if any of them are errors. Lines & columns are zero based and columns are in bytes.
+ */
+fn report_diagnostics(contents: &str, settings: &project::Settings) {
+    let diagnostics = erm::diagnostics(contents, settings);
+
+    let report: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let before = &contents[..diagnostic.range.start];
+            let line = before.matches('\n').count();
+            let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1);
+            serde_json::json!({
+                "severity": diagnostic.severity.as_str(),
+                "message": diagnostic.message,
+                "start": diagnostic.range.start,
+                "end": diagnostic.range.end,
+                "line": line,
+                "column": column,
+            })
+        })
+        .collect();
+
+    println!("{}", serde_json::Value::Array(report));
+
+    if diagnostics
+        .iter()
+        .any(|diagnostic| diagnostic.severity == erm::Severity::Error)
+    {
+        std::process::exit(1);
+    }
+}
+
+/* Prints every token in the file, including whitespace & comments, with its kind, text & range so
+ * that lexer bugs can be reported and tools can build on the token stream.
+ */
+fn print_tokens(matches: &ArgMatches) {
+    // Read the file directly rather than via read_path so that the ranges match the file on disk.
+    // It is lexed as it is read so that large generated files don't need to fit in memory
+    let file = match matches.value_of("path").map(File::open) {
+        Some(Ok(file)) => file,
+        _ => {
+            println!("{}", error::to_user_output(Error::FileError));
+            return;
+        }
+    };
+
+    let json = matches.value_of("format") == Some("json");
+    let mut separator = "[";
+    // Locked once rather than for every token as there can be a great many of them
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    let result = lexer::lex_reader(file, |located, slice| {
+        if json {
+            let token = serde_json::json!({
+                "kind": located.token.kind(),
+                "slice": slice,
+                "start": located.range.start,
+                "end": located.range.end,
+                "line": located.line,
+                "column": located.column,
+            });
+            write!(out, "{}{}", separator, token)?;
+            separator = ",";
+        } else {
+            writeln!(
+                out,
+                "{}:{} {}..{} {} {:?}",
+                located.line + 1,
+                located.column + 1,
+                located.range.start,
+                located.range.end,
+                located.token.kind(),
+                slice
+            )?;
+        }
+        Ok(())
+    })
+    .and_then(|()| {
+        if json {
+            // An empty file has no tokens to open the array
+            writeln!(out, "{}]", if separator == "[" { "[" } else { "" })?;
+        }
+        out.flush()
+    });
+
+    match result {
+        // The reader has stopped listening, eg. 'erm tokens Big.elm | head', which isn't a problem
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(_) => println!("{}", error::to_user_output(Error::FileError)),
+        Ok(()) => {}
+    }
+}
+
+/* Prints every binding & operator that the file's module can resolve along with the module that
+ * defines it, and the type of each binding where it can be inferred. Useful for working out what
+ * imports & exposing lists make available.
+ */
+fn print_env(matches: &ArgMatches) {
+    let settings = script_settings(matches.value_of("path"));
+    let result = matches
+        .value_of("path")
+        .map_or(Err(Error::FileError), read_path)
+        .and_then(|contents| {
+            let module = parser::parse_source(&contents, |import| {
+                env::imported_operators(import, &settings)
+            })
+            .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+            let module = erm::ast::with_default_imports(&module);
+            let scope =
+                env::ModuleScope::from_module(&module, &settings).map_err(Error::ScopeError)?;
+            Ok((module.name, env::Environment::from_module_scope(scope)))
+        });
+
+    let (module_name, environment) = match result {
+        Ok(found) => found,
+        Err(error) => {
+            println!("{}", error::to_user_output(error));
+            return;
+        }
+    };
+
+    for warning in settings.warnings.take() {
+        eprintln!("Warning: {}", error::warning_to_user_output(&warning));
+    }
+
+    println!("Bindings:");
+    for resolvable in environment.resolvable_bindings() {
+        let kind = match &resolvable.binding {
+            Binding::UserFunc(stmt) if matches!(**stmt, Stmt::Port { .. }) => "port",
+            Binding::UserFunc(_) => "function",
+            Binding::UserArg(_) => "argument",
+            Binding::UserBinding(_) | Binding::UserPattern(..) | Binding::Value(_) => "value",
+        };
+        let module = resolvable.module.as_ref().unwrap_or(&module_name).join(".");
+        let name = resolvable.name.as_string();
+
+        match checker::infer(&resolvable.name, &environment) {
+            Ok(term) => println!("  {} : {} ({} from {})", name, term, kind, module),
+            Err(_) => println!("  {} ({} from {})", name, kind, module),
+        }
+    }
+
+    println!("Operators:");
+    for (operator, module) in environment.resolvable_operators() {
+        let associativity = match operator.associativity {
+            Associativity::Left => "left",
+            Associativity::Right => "right",
+            Associativity::Non => "non",
+        };
+        println!(
+            "  infix {} {} ({}) = {} (from {})",
+            associativity,
+            operator.precedence,
+            operator.operator_name,
+            operator.function_name.as_string(),
+            module.as_ref().unwrap_or(&module_name).join(".")
+        );
+    }
+}
+
+/* Prints where each top level value, type & constructor that the file's module can refer to is
+ * defined, for editors to jump to definitions. Lines & columns in the JSON are zero based and
+ * columns are in bytes, as for 'erm --report=json'.
+ */
+fn print_definitions(matches: &ArgMatches) {
+    let Some(path) = matches.value_of("path") else {
+        println!("{}", error::to_user_output(Error::FileError));
+        return;
+    };
+
+    let settings = script_settings(Some(path));
+    let contents = match read_path(path) {
+        Ok(contents) => contents,
+        Err(error) => {
+            println!("{}", error::to_user_output(error));
+            return;
+        }
+    };
+
+    let definitions = match erm::analysis::definitions(&contents, &settings) {
+        Ok(definitions) => definitions,
+        Err(diagnostic) => {
+            println!("{}", diagnostic.message);
+            return;
+        }
+    };
+
+    // The source of each module that the definitions come from, to turn ranges into lines
+    let mut sources: HashMap<Vec<String>, Option<(PathBuf, String)>> = HashMap::new();
+    if let Some(definition) = definitions.first() {
+        sources.insert(
+            definition.module.clone(),
+            Some((PathBuf::from(path), contents.clone())),
+        );
+    }
+    for definition in &definitions {
+        sources.entry(definition.module.clone()).or_insert_with(|| {
+            let (module_path, _is_core) = env::find_module_path(&definition.module, &settings)?;
+            let source = std::fs::read_to_string(&module_path).ok()?;
+            Some((module_path, source))
+        });
+    }
+
+    let rows: Vec<_> = definitions
+        .iter()
+        .map(|definition| {
+            let source = sources.get(&definition.module).and_then(Option::as_ref);
+            let position = source
+                .zip(definition.range.as_ref())
+                .map(|((path, source), range)| {
+                    let before = &source[..range.start];
+                    let line = before.matches('\n').count();
+                    let column = before.len() - before.rfind('\n').map_or(0, |index| index + 1);
+                    (path, range, line, column)
+                });
+
+            (definition, position)
+        })
+        .collect();
+
+    if matches.is_present("json") {
+        let report: Vec<_> = rows
+            .iter()
+            .map(|(definition, position)| {
+                let mut entry = serde_json::json!({
+                    "name": definition.name,
+                    "kind": definition.kind.as_str(),
+                    "module": definition.module.join("."),
+                });
+                if let Some((path, range, line, column)) = position {
+                    entry["path"] = serde_json::json!(path.display().to_string());
+                    entry["start"] = serde_json::json!(range.start);
+                    entry["end"] = serde_json::json!(range.end);
+                    entry["line"] = serde_json::json!(line);
+                    entry["column"] = serde_json::json!(column);
+                }
+                entry
+            })
+            .collect();
+
+        println!("{}", serde_json::Value::Array(report));
+        return;
+    }
+
+    for (definition, position) in rows {
+        let location = match position {
+            Some((path, _range, line, column)) => {
+                format!("{}:{}:{}", path.display(), line + 1, column + 1)
+            }
+            None => "unknown".to_string(),
+        };
+        println!(
+            "{} {} {} ({})",
+            location,
+            definition.kind.as_str(),
+            definition.name,
+            definition.module.join(".")
+        );
+    }
+}
+
+/* Copies the modules that the file imports, and the modules that they import, into a directory so
+ * that the file and the directory can be moved elsewhere and still run. Runs look in the 'vendor'
+ * directory next to the file so that is where they go unless another directory is given, which
+ * runs are then given with '--vendor'.
+ */
+fn vendor_imports(matches: &ArgMatches) {
+    let Some(path) = matches.value_of("path") else {
+        println!("{}", error::to_user_output(Error::FileError));
+        return;
+    };
+
+    let mut settings = project::Settings::new();
+    settings.module_roots = matches
+        .values_of("module-root")
+        .map(|values| values.filter_map(parse_module_root).collect())
+        .unwrap_or_default();
+    let directory = matches
+        .value_of("into")
+        .map(PathBuf::from)
+        .or_else(|| Path::new(path).parent().map(|dir| dir.join("vendor")))
+        .unwrap_or_else(|| PathBuf::from("vendor"));
+
+    let result = read_path(path).and_then(|contents| {
+        let module = parser::parse_source(&contents, |import| {
+            env::imported_operators(import, &settings)
+        })
+        .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+        env::vendor(&module, &settings, &directory).map_err(Error::ScopeError)
+    });
+
+    match result {
+        Ok(paths) => {
+            for path in paths {
+                println!("{}", path.display());
+            }
+        }
+        Err(error) => {
+            println!("{}", error::to_user_output(error));
+            std::process::exit(1);
+        }
+    }
+}
+
+/* Parses the modules that the file imports, checking that they can all be found, and keeps them
+ * in the cache so that later runs of the file start straight away. With '--optimize' their
+ * constants are worked out too, for runs that are also given '--optimize'.
+ */
+fn build(matches: &ArgMatches) {
+    let mut settings = script_settings(matches.value_of("path"));
+    settings.cache_directory = cache_directory();
+    settings.optimize = matches.is_present("optimize");
+
+    let result = matches
+        .value_of("path")
+        .map_or(Err(Error::FileError), read_path)
+        .and_then(|contents| {
+            let module = parser::parse_source(&contents, |import| {
+                env::imported_operators(import, &settings)
+            })
+            .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+            let module = erm::ast::with_default_imports(&module);
+            env::ModuleScope::from_module(&module, &settings).map_err(Error::ScopeError)
+        });
+
+    for warning in settings.warnings.take() {
+        eprintln!("Warning: {}", error::warning_to_user_output(&warning));
+    }
+
+    if let Err(error) = result {
+        println!("{}", error::to_user_output(error));
+        std::process::exit(1);
+    }
+}
+
+/* Runs the elm-test style suites exposed by the file and exits with a failure status if any of
+ * the tests fail.
+ */
+fn run_tests(matches: &ArgMatches) {
+    let mut settings = script_settings(matches.value_of("path"));
+    // A suite that fails to build is reported with the other failures
+    settings.recover_errors = true;
+    let result = matches
+        .value_of("path")
+        .map_or(Err(Error::FileError), read_path)
+        .and_then(|contents| {
+            let module = parser::parse_source(&contents, |import| {
+                env::imported_operators(import, &settings)
+            })
+            .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+            let module = erm::ast::with_default_imports(&module);
+            let scope =
+                env::ModuleScope::from_module(&module, &settings).map_err(Error::ScopeError)?;
+            let environment = env::Environment::from_module_scope(scope);
+
+            let usage = Usage::new(settings.max_duration);
+            testing::run_tests(&module, &environment, &settings, &usage)
+                .map_err(|err| Error::EvaluateError(err, contents.clone()))
+        });
+
+    match result {
+        Ok(results) => {
+            print!("{}", testing::report(&results));
+            if results
+                .iter()
+                .any(|result| result.outcome != Expectation::Pass)
+            {
+                std::process::exit(1);
+            }
+        }
+        Err(error) => {
+            println!("{}", error::to_user_output(error));
+            std::process::exit(1);
+        }
+    }
+}
+
+/* Completes names from the session's environment when tab is pressed at the prompt. The session
+ * is shared with the loop that evaluates the inputs so that new declarations can be completed.
+ */
+struct ReplHelper {
+    session: Rc<RefCell<repl::Session>>,
+}
+
+impl Completer for ReplHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _context: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.session.borrow().complete(line, pos))
+    }
+}
+
+impl Hinter for ReplHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ReplHelper {}
+
+impl Validator for ReplHelper {}
+
+impl Helper for ReplHelper {}
+
+/* Reads inputs at the prompt until the input closes, printing the value of each one. Lines are
+ * gathered until they make a complete input so that definitions can span several lines. History
+ * is kept in the home directory between sessions.
+ */
+fn run_repl() {
+    let settings = project::Settings {
+        recover_errors: true,
+        ..project::Settings::new()
+    };
+    let session = Rc::new(RefCell::new(repl::Session::new(settings)));
+    let mut editor = match Editor::<ReplHelper, DefaultHistory>::new() {
+        Ok(editor) => editor,
+        Err(error) => {
+            println!("Unable to start the repl: {}", error);
+            return;
+        }
+    };
+    editor.set_helper(Some(ReplHelper {
+        session: session.clone(),
+    }));
+
+    let history_path =
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".erm_history"));
+    if let Some(path) = &history_path {
+        // There is no history the first time the repl is run
+        let _ = editor.load_history(path);
+    }
+
+    let mut input = String::new();
+
+    loop {
+        let line = match editor.readline(if input.is_empty() { "> " } else { "| " }) {
+            Ok(line) => line,
+            // Ctrl-C abandons the current input rather than leaving the repl
+            Err(ReadlineError::Interrupted) => {
+                input.clear();
+                continue;
+            }
+            Err(_) => {
+                // Evaluate whatever is left when the input closes part way through a block
+                if !input.is_empty() {
+                    print_repl_result(session.borrow_mut().eval(&input));
+                }
+                break;
+            }
+        };
+
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+
+        // Skip blank lines between inputs rather than evaluating nothing
+        if input.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+
+        if input.is_empty() {
+            if let Some(command) = repl::parse_command(&line) {
+                run_repl_command(&mut session.borrow_mut(), command);
+                continue;
+            }
+        }
+
+        input.push_str(&line);
+        input.push('\n');
+        if !repl::is_complete(&input) {
+            continue;
+        }
+
+        print_repl_result(session.borrow_mut().eval(&input));
+        input.clear();
+    }
+
+    if let Some(path) = &history_path {
+        let _ = editor.save_history(path);
+    }
+}
+
+fn run_repl_command(session: &mut repl::Session, command: repl::Command) {
+    match command {
+        repl::Command::Type(input) => match session.type_of(&input) {
+            Ok(term) => println!("{}", term),
+            Err(error) => println!("{}", error::to_user_output(error)),
+        },
+        repl::Command::Load(path) => {
+            if let Err(error) = session.load_file(&path) {
+                println!("{}", error::to_user_output(error));
+            }
+        }
+        repl::Command::Reset => session.reset(),
+        repl::Command::Unknown(name) => {
+            println!("Unknown command :{}. Try :type, :load or :reset", name)
+        }
+    }
+}
+
+fn print_repl_result(result: Result<Option<Value>, Error>) {
+    match result {
+        Ok(Some(Value::RuntimeError(message))) => println!("{}", message),
+        Ok(Some(value)) => println!("{}", value),
+        Ok(None) => {}
+        Err(error) => println!("{}", error::to_user_output(error)),
+    }
 }
 
 fn init_logger() {
@@ -55,7 +835,16 @@ fn filter_hash_bang(code: String) -> String {
         // Remove the first line if it starts with #!
         .filter(|(index, line)| !(*index == 0 && line.starts_with("#!")))
         .map(|(_, line)| line)
-        .collect()
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn read_stdin_lines() -> Result<Vec<String>, Error> {
+    std::io::stdin()
+        .lock()
+        .lines()
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::FileError)
 }
 
 fn main() {
@@ -66,15 +855,287 @@ fn main() {
     let matches = App::new("erm")
         .version("0.1")
         .arg(Arg::with_name("path").index(1))
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .help("Re-run the file whenever it changes"),
+        )
+        .arg(
+            Arg::with_name("coverage")
+                .long("coverage")
+                .help("Print which branches of the code were executed"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .help("Print counts of the work done to run the program"),
+        )
+        .arg(
+            Arg::with_name("optimize")
+                .long("optimize")
+                .help("Work out constants as modules are loaded and cache them that way"),
+        )
+        .arg(
+            Arg::with_name("allow-fs")
+                .long("allow-fs")
+                .help("Allow the program to read files"),
+        )
+        .arg(
+            Arg::with_name("allow-env")
+                .long("allow-env")
+                .help("Allow the program to read environment variables"),
+        )
+        .arg(
+            Arg::with_name("allow-all")
+                .long("allow-all")
+                .help("Allow the program everything that the other --allow flags do"),
+        )
+        .arg(
+            Arg::with_name("allow-time")
+                .long("allow-time")
+                .help("Allow the program to read the clock"),
+        )
+        .arg(
+            Arg::with_name("allow-process")
+                .long("allow-process")
+                .help("Allow the program to run other programs"),
+        )
+        .arg(
+            Arg::with_name("allow-net")
+                .long("allow-net")
+                .help("Allow the program to make network requests"),
+        )
+        .arg(
+            Arg::with_name("module-root")
+                .long("module-root")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("PREFIX=DIR")
+                .validator(|value| {
+                    parse_module_root(&value)
+                        .map(|_| ())
+                        .ok_or_else(|| "expected PREFIX=DIR, eg. Shared=../elm-lib".to_string())
+                })
+                .help("Read modules starting with the prefix from the directory"),
+        )
+        .arg(
+            Arg::with_name("vendor")
+                .long("vendor")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Read the imports from the directory that 'erm vendor --into DIR' made"),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .takes_value(true)
+                .possible_values(&["json"])
+                .help("Print the problems with the file instead of running it"),
+        )
+        .arg(
+            Arg::with_name("stdin-lines")
+                .long("stdin-lines")
+                .help("Pass each line of standard input to main after the other arguments"),
+        )
         .arg(Arg::with_name("arguments").multiple(true))
+        // Arguments after the path are for the program so shouldn't be read as subcommands
+        .setting(AppSettings::ArgsNegateSubcommands)
+        .subcommand(
+            SubCommand::with_name("highlight")
+                .about("Print the tokens in the file for syntax highlighting")
+                .arg(Arg::with_name("path").index(1).required(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("lex")
+                .about("Print every token in the file with its kind & range")
+                .arg(Arg::with_name("path").index(1).required(true))
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json"])
+                        .default_value("text"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about("Run the tests exposed by the file")
+                .arg(Arg::with_name("path").index(1).required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("env")
+                .about("Print the bindings & operators that the file's module can use")
+                .arg(Arg::with_name("path").index(1).required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("defs")
+                .about("Print where the values & types that the file's module can use are defined")
+                .arg(Arg::with_name("path").index(1).required(true))
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .help("Print the definitions as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Parse the modules that the file imports into the cache")
+                .arg(Arg::with_name("path").index(1).required(true))
+                .arg(
+                    Arg::with_name("optimize")
+                        .long("optimize")
+                        .help("Work out the constants in the modules as well"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vendor")
+                .about("Copy the modules that the file imports into a directory next to it")
+                .arg(Arg::with_name("path").index(1).required(true))
+                .arg(
+                    Arg::with_name("into")
+                        .long("into")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .help("Copy the modules into the directory instead of 'vendor'"),
+                )
+                .arg(
+                    Arg::with_name("module-root")
+                        .long("module-root")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .value_name("PREFIX=DIR")
+                        .help("Read modules starting with the prefix from the directory"),
+                ),
+        )
+        .subcommand(SubCommand::with_name("repl").about("Evaluate expressions interactively"))
         .get_matches();
 
-    let program_args: Vec<String> = matches
+    if let Some(matches) = matches.subcommand_matches("highlight") {
+        print_highlights(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("lex") {
+        print_tokens(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("test") {
+        run_tests(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("env") {
+        print_env(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("defs") {
+        print_definitions(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("build") {
+        build(matches);
+        return;
+    }
+
+    if let Some(matches) = matches.subcommand_matches("vendor") {
+        vendor_imports(matches);
+        return;
+    }
+
+    if matches.subcommand_matches("repl").is_some() {
+        run_repl();
+        return;
+    }
+
+    let mut program_args: Vec<String> = matches
         .values_of("arguments")
         .map(|values| values.map(|value| value.to_string()).collect())
-        .unwrap_or_else(Vec::new);
+        .unwrap_or_default();
+
+    if matches.is_present("stdin-lines") {
+        // Standard input can't hold both the program and the lines for it
+        let lines = match matches.value_of("path") {
+            Some(path) if path != "-" => read_stdin_lines(),
+            _ => Err(Error::FileError),
+        };
+        match lines {
+            Ok(lines) => program_args.extend(lines),
+            Err(error) => {
+                println!("{}", error::to_user_output(error));
+                return;
+            }
+        }
+    }
+
+    let capabilities = if matches.is_present("allow-all") {
+        project::Capabilities::all()
+    } else {
+        project::Capabilities {
+            fs: matches.is_present("allow-fs"),
+            net: matches.is_present("allow-net"),
+            env: matches.is_present("allow-env"),
+            process: matches.is_present("allow-process"),
+            time: matches.is_present("allow-time"),
+        }
+    };
+    let mut builder = project::Settings::builder().capabilities(capabilities);
+    let vendor = matches
+        .value_of("vendor")
+        .map(PathBuf::from)
+        .or_else(|| matches.value_of("path").and_then(vendor_directory));
+    if let Some(directory) = vendor {
+        if let Some(core) = vendored_core(&directory) {
+            builder = builder.core_path(core);
+        }
+        builder = builder.source_directory(directory);
+    }
+    if let Some(directory) = cache_directory() {
+        builder = builder.cache_directory(directory);
+    }
+    for (prefix, directory) in matches
+        .values_of("module-root")
+        .into_iter()
+        .flatten()
+        .filter_map(parse_module_root)
+    {
+        builder = builder.module_root(&prefix, directory);
+    }
 
-    let settings = project::Settings::new();
+    // Missing directories are reported before anything is run
+    let mut settings = match builder.build() {
+        Ok(settings) => settings,
+        Err(error) => {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    settings.optimize = matches.is_present("optimize");
+    let coverage = matches
+        .is_present("coverage")
+        .then(|| Arc::new(Coverage::new()));
+    if let Some(coverage) = &coverage {
+        settings.observers.push(coverage.clone());
+    }
+
+    if matches.is_present("watch") {
+        match matches.value_of("path") {
+            Some(path) if path != "-" => watch(path, program_args, &settings, coverage.as_deref()),
+            _ => println!("{}", error::to_user_output(Error::FileError)),
+        }
+        return;
+    }
 
     let contents_result = matches
         .value_of("path")
@@ -92,36 +1153,43 @@ fn main() {
                 Ok(input)
             },
             // If we have a path from the args
-            |path| {
-                std::fs::metadata(path)
-                    .map_err(|_| Error::FileError)
-                    .and_then(|attr| {
-                        if attr.is_dir() {
-                            Err(Error::FileError)
-                        } else {
-                            let mut f = File::open(path).map_err(|_| Error::FileError)?;
-
-                            let mut contents = String::new();
-                            f.read_to_string(&mut contents)
-                                .map_err(|_| Error::FileError)?;
-
-                            Ok(filter_hash_bang(contents))
-                        }
-                    })
-            },
+            read_path,
         );
 
-    let result = contents_result.and_then(|contents| run(contents, program_args, settings));
-
-    match result {
-        Err(error) => {
-            println!("{}", error::to_user_output(error));
-        }
-        Ok(evaluator::values::Value::String(string)) => {
-            println!("{}", string);
-        }
-        Ok(value) => {
-            println!("{:?}", value);
+    if matches.is_present("report") {
+        match contents_result {
+            Ok(contents) => report_diagnostics(&contents, &settings),
+            Err(error) => println!("{}", error::to_user_output(error)),
         }
+        return;
+    }
+
+    let usage = Usage::new(settings.max_duration);
+    let result = contents_result.and_then(|contents| {
+        run(
+            contents,
+            program_args,
+            &settings,
+            &usage,
+            coverage.as_deref(),
+        )
+    });
+
+    // Printed to stderr, like the coverage, so that it doesn't get mixed up with the output
+    if matches.is_present("stats") {
+        let stats = usage.stats();
+        eprintln!("Expressions evaluated: {}", stats.expressions);
+        eprintln!("Function calls: {}", stats.calls);
+        eprintln!("Lists built: {}", stats.lists);
+        eprintln!("Strings built: {}", stats.strings);
+        eprintln!("Maximum scope depth: {}", stats.max_scope_depth);
     }
+
+    // Programs whose main returns an Int are using it as the exit code rather than as output
+    if let Ok(Value::Integer(code)) = result {
+        // Exit codes are 32 bit so anything larger is treated as a failure
+        std::process::exit(i32::try_from(code).unwrap_or(1));
+    }
+
+    print_result(result);
 }
@@ -1,15 +1,18 @@
-use std::rc::Rc;
+use std::sync::Arc;
 
-use super::ast::{Expr, Stmt};
+use super::ast::{ExprId, Pattern, Stmt};
 use super::checker::term;
 use super::evaluator::values;
 
 #[derive(Debug, Clone)]
 pub enum Binding {
     // Represents a binding of a name to function statement
-    UserFunc(Rc<Stmt>),
+    UserFunc(Arc<Stmt>),
     // Represents a binding of a name to a simple expression (ie. no arguments involved.)
-    UserBinding(Rc<Expr>),
+    UserBinding(ExprId),
+    // Represents a name bound by the pattern of a let binding to the expression that the pattern
+    // matches
+    UserPattern(Pattern, ExprId),
     // TODO: Feels wrong to have a 'term ' in here with other things
     UserArg(term::Term),
     // TODO: Unsure about this entry especially as it means we need to make Value 'Clone' which
@@ -1,15 +1,180 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::{Arc, OnceLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use unicode_segmentation::UnicodeSegmentation;
+
 use super::checker::term;
-use super::evaluator::values;
+use super::evaluator::{self, limits, values};
+use super::project::{self, IntegerOverflow, StringUnits};
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     WrongArity,
     WrongArgumentType,
+    IntegerOverflow,
+    // A function given to a higher-order builtin failed when it was applied
+    Callback(Box<evaluator::Error>),
+    // The value that the builtin would build is over the limit in the settings
+    ResourceLimit {
+        resource: limits::Resource,
+        limit: usize,
+    },
+    // The value that the builtin would build is too large to fit in memory
+    TooLarge,
+}
+
+// Applies a function value from the program to its arguments by evaluating it
+pub type Apply<'a> = dyn Fn(values::Value, Vec<values::Value>) -> Result<values::Value, Error> + 'a;
+
+// Makes a fresh instance of a builtin
+type Constructor = fn() -> Rc<dyn Func>;
+
+/* Every kernel function by the name that the core modules bind it to. The environment checks names
+ * against this to know which bindings are builtins and the checker & evaluator fetch the functions
+ * from it, so adding a builtin only takes an entry here and a binding in a core module.
+ */
+fn registry() -> &'static HashMap<&'static str, Constructor> {
+    static REGISTRY: OnceLock<HashMap<&'static str, Constructor>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let entries: Vec<(&'static str, Constructor)> = vec![
+            // core/Basics
+            ("Elm.Kernel.Basics.add", || Rc::new(Add {})),
+            ("Elm.Kernel.Basics.sub", || Rc::new(Sub {})),
+            ("Elm.Kernel.Basics.mul", || Rc::new(Mul {})),
+            ("Elm.Kernel.Basics.gt", || Rc::new(Gt {})),
+            ("Elm.Kernel.Basics.lt", || Rc::new(Lt {})),
+            ("Elm.Kernel.Basics.append", || Rc::new(Append {})),
+            ("Elm.Kernel.Basics.never", || Rc::new(Never {})),
+            ("Elm.Kernel.Basics.compare", || Rc::new(Compare {})),
+            // core/String
+            ("Elm.Kernel.String.fromInt", || Rc::new(StringFromInt {})),
+            ("Elm.Kernel.String.join", || Rc::new(StringJoin {})),
+            ("Elm.Kernel.String.length", || Rc::new(StringLength {})),
+            ("Elm.Kernel.String.reverse", || Rc::new(StringReverse {})),
+            ("Elm.Kernel.String.left", || Rc::new(StringLeft {})),
+            ("Elm.Kernel.String.right", || Rc::new(StringRight {})),
+            ("Elm.Kernel.String.concat", || Rc::new(StringConcat {})),
+            ("Elm.Kernel.String.repeat", || Rc::new(StringRepeat {})),
+            ("Elm.Kernel.String.padLeft", || Rc::new(StringPadLeft {})),
+            ("Elm.Kernel.String.padRight", || Rc::new(StringPadRight {})),
+            ("Elm.Kernel.String.words", || Rc::new(StringWords {})),
+            ("Elm.Kernel.String.lines", || Rc::new(StringLines {})),
+            ("Elm.Kernel.String.map", || Rc::new(StringMap {})),
+            ("Elm.Kernel.String.filter", || Rc::new(StringFilter {})),
+            ("Elm.Kernel.String.foldl", || Rc::new(StringFoldl {})),
+            ("Elm.Kernel.String.fromBool", || Rc::new(StringFromBool {})),
+            // core/List
+            ("Elm.Kernel.List.sum", || Rc::new(ListSum {})),
+            ("Elm.Kernel.List.product", || Rc::new(ListProduct {})),
+            ("Elm.Kernel.List.maximum", || Rc::new(ListMaximum {})),
+            ("Elm.Kernel.List.minimum", || Rc::new(ListMinimum {})),
+            ("Elm.Kernel.List.take", || Rc::new(ListTake {})),
+            ("Elm.Kernel.List.drop", || Rc::new(ListDrop {})),
+            ("Elm.Kernel.List.map", || Rc::new(ListMap {})),
+            ("Elm.Kernel.List.partition", || Rc::new(ListPartition {})),
+            ("Elm.Kernel.List.unzip", || Rc::new(ListUnzip {})),
+            ("Elm.Kernel.List.intersperse", || {
+                Rc::new(ListIntersperse {})
+            }),
+            // core/Random
+            ("Elm.Kernel.Random.initialSeed", || {
+                Rc::new(RandomInitialSeed {})
+            }),
+            ("Elm.Kernel.Random.int", || Rc::new(RandomInt {})),
+            ("Elm.Kernel.Random.float", || Rc::new(RandomFloat {})),
+            ("Elm.Kernel.Random.list", || Rc::new(RandomList {})),
+            ("Elm.Kernel.Random.step", || Rc::new(RandomStep {})),
+            // core/Bytes
+            ("Elm.Kernel.Bytes.fromString", || {
+                Rc::new(BytesFromString {})
+            }),
+            ("Elm.Kernel.Bytes.toString", || Rc::new(BytesToString {})),
+            ("Elm.Kernel.Bytes.width", || Rc::new(BytesWidth {})),
+            ("Elm.Kernel.Bytes.append", || Rc::new(BytesAppend {})),
+            ("Elm.Kernel.Bytes.encodeInt", || Rc::new(BytesEncodeInt {})),
+            ("Elm.Kernel.Bytes.decodeInt", || Rc::new(BytesDecodeInt {})),
+            // core/Csv
+            ("Elm.Kernel.Csv.parse", || Rc::new(CsvParse {})),
+            ("Elm.Kernel.Csv.parseWith", || Rc::new(CsvParseWith {})),
+            // core/Env
+            ("Elm.Kernel.Env.get", || Rc::new(EnvGet {})),
+            // core/File
+            ("Elm.Kernel.File.read", || Rc::new(FileRead {})),
+            // core/Http
+            ("Elm.Kernel.Http.getString", || Rc::new(HttpGetString {})),
+            ("Elm.Kernel.Http.post", || Rc::new(HttpPost {})),
+            // core/Platform
+            ("Elm.Kernel.Platform.worker", || Rc::new(PlatformWorker {})),
+            // core/Process
+            ("Elm.Kernel.Process.run", || Rc::new(ProcessRun {})),
+            // core/Time
+            ("Elm.Kernel.Time.now", || Rc::new(TimeNow {})),
+            ("Elm.Kernel.Time.monotonic", || Rc::new(TimeMonotonic {})),
+            // core/Debug
+            ("Elm.Kernel.Debug.toString", || Rc::new(DebugToString {})),
+            ("Elm.Kernel.Debug.log", || Rc::new(DebugLog {})),
+            // core/Expect
+            ("Elm.Kernel.Expect.equal", || Rc::new(ExpectEqual {})),
+            ("Elm.Kernel.Expect.true", || Rc::new(ExpectTrue {})),
+            // core/Test
+            ("Elm.Kernel.Test.test", || Rc::new(TestTest {})),
+            ("Elm.Kernel.Test.describe", || Rc::new(TestDescribe {})),
+        ];
+        entries.into_iter().collect()
+    })
+}
+
+pub fn is_built_in(name: &str) -> bool {
+    registry().contains_key(name)
+}
+
+pub fn get(name: &str) -> Option<Rc<dyn Func>> {
+    registry().get(name).map(|constructor| constructor())
+}
+
+// Access to the world outside of the program which the user has to allow before a builtin can use it
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Capability {
+    Fs,
+    Net,
+    Env,
+    Process,
+    Time,
 }
 
 pub trait Func {
     fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error>;
     fn term(&self) -> term::Term;
+
+    // Pure builtins don't need anything to be allowed
+    fn capability(&self) -> Option<Capability> {
+        None
+    }
+
+    // Builtins that behave differently depending on the settings, like how the String functions
+    // count characters, override this
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        self.call(args)
+    }
+
+    // Higher-order builtins, like List.partition, override this to apply the functions that they
+    // are given. The evaluator always calls builtins through here
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+        _apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        self.call_with_settings(args, settings)
+    }
 }
 
 // stringFromInt
@@ -22,7 +187,9 @@ impl Func for StringFromInt {
         }
 
         match args.first() {
-            Some(values::Value::Integer(int)) => Ok(values::Value::String(int.to_string())),
+            Some(values::Value::Integer(int)) => {
+                Ok(values::Value::String(Arc::from(int.to_string())))
+            }
             _ => Err(Error::WrongArgumentType),
         }
     }
@@ -35,6 +202,27 @@ impl Func for StringFromInt {
     }
 }
 
+// Elm.Kernel.String.fromBool
+pub struct StringFromBool {}
+
+impl Func for StringFromBool {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Bool(true)] => Ok(values::Value::String(Arc::from("True"))),
+            [values::Value::Bool(false)] => Ok(values::Value::String(Arc::from("False"))),
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Bool)),
+            Box::new(term::Term::Constant(term::Value::String)),
+        )
+    }
+}
+
 // stringJoin
 pub struct StringJoin {}
 
@@ -46,19 +234,19 @@ impl Func for StringJoin {
 
         match (args.first(), args.last()) {
             (Some(values::Value::String(joiner)), Some(values::Value::List(entries))) => {
-                Ok(values::Value::String(
+                Ok(values::Value::String(Arc::from(
                     entries
                         .iter()
                         .flat_map(|value| {
                             if let values::Value::String(string) = value {
-                                Some(string.clone())
+                                Some(&**string)
                             } else {
                                 None
                             }
                         })
-                        .collect::<Vec<String>>()
+                        .collect::<Vec<&str>>()
                         .join(joiner),
-                ))
+                )))
             }
             _ => Err(Error::WrongArgumentType),
         }
@@ -78,207 +266,2446 @@ impl Func for StringJoin {
     }
 }
 
-// Elm.Kernel.Basics.add
-pub struct Add {}
+/* Splits the string into the units that the String functions count, either grapheme clusters, so
+ * that an accented letter or an emoji made of several code points is one character as the reader
+ * would see it, or code points. Slicing on these boundaries means that the result is always valid.
+ */
+fn string_units(string: &str, units: StringUnits) -> Vec<&str> {
+    match units {
+        StringUnits::Graphemes => string.graphemes(true).collect(),
+        StringUnits::CodePoints => string
+            .char_indices()
+            .map(|(index, char)| &string[index..index + char.len_utf8()])
+            .collect(),
+    }
+}
 
-impl Func for Add {
-    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 2 {
-            return Err(Error::WrongArity);
-        }
+fn string_to_string() -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Constant(term::Value::String)),
+        Box::new(term::Term::Constant(term::Value::String)),
+    )
+}
 
-        match (args.first(), args.last()) {
-            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
-                Ok(values::Value::Integer(a + b))
-            }
-            _ => Err(Error::WrongArgumentType),
+fn int_to_string_to_string() -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Constant(term::Value::Integer)),
+        Box::new(string_to_string()),
+    )
+}
+
+// Elm.Kernel.String.length
+pub struct StringLength {}
+
+impl Func for StringLength {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::String(string)] => Ok(values::Value::Integer(
+                string_units(string, settings.string_units).len() as i64,
+            )),
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
     }
 
     fn term(&self) -> term::Term {
         term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
             Box::new(term::Term::Constant(term::Value::Integer)),
-            Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::Integer)),
-                Box::new(term::Term::Constant(term::Value::Integer)),
-            )),
         )
     }
 }
 
-// Elm.Kernel.Basics.sub
-pub struct Sub {}
+// Elm.Kernel.String.reverse
+pub struct StringReverse {}
 
-impl Func for Sub {
-    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 2 {
-            return Err(Error::WrongArity);
+impl Func for StringReverse {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::String(string)] => {
+                let reversed: String = string_units(string, settings.string_units)
+                    .into_iter()
+                    .rev()
+                    .collect();
+                Ok(values::Value::String(Arc::from(reversed)))
+            }
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
+    }
 
-        match (args.first(), args.last()) {
-            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
-                Ok(values::Value::Integer(a - b))
+    fn term(&self) -> term::Term {
+        string_to_string()
+    }
+}
+
+// Elm.Kernel.String.left
+pub struct StringLeft {}
+
+impl Func for StringLeft {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Integer(count), values::Value::String(string)] => {
+                let count = (*count).max(0) as usize;
+                let left: String = string_units(string, settings.string_units)
+                    .into_iter()
+                    .take(count)
+                    .collect();
+                Ok(values::Value::String(Arc::from(left)))
             }
-            _ => Err(Error::WrongArgumentType),
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        int_to_string_to_string()
+    }
+}
+
+// Elm.Kernel.String.right
+pub struct StringRight {}
+
+impl Func for StringRight {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Integer(count), values::Value::String(string)] => {
+                let units = string_units(string, settings.string_units);
+                let count = (*count).max(0) as usize;
+                let right = units[units.len().saturating_sub(count)..].concat();
+                Ok(values::Value::String(Arc::from(right)))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        int_to_string_to_string()
+    }
+}
+
+fn string_list_term() -> term::Term {
+    term::Term::Type(
+        "List".to_string(),
+        vec![term::Term::Constant(term::Value::String)],
+    )
+}
+
+// Elm.Kernel.String.concat
+pub struct StringConcat {}
+
+impl Func for StringConcat {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::List(entries)] => entries
+                .iter()
+                .map(|value| match value {
+                    values::Value::String(string) => Ok(&**string),
+                    _ => Err(Error::WrongArgumentType),
+                })
+                .collect::<Result<String, Error>>()
+                .map(|string| values::Value::String(Arc::from(string))),
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
     }
 
     fn term(&self) -> term::Term {
         term::Term::Function(
-            Box::new(term::Term::Constant(term::Value::Integer)),
-            Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::Integer)),
-                Box::new(term::Term::Constant(term::Value::Integer)),
-            )),
+            Box::new(string_list_term()),
+            Box::new(term::Term::Constant(term::Value::String)),
         )
     }
 }
 
-// Elm.Kernel.Basics.mul
-pub struct Mul {}
+// Elm.Kernel.String.repeat
+pub struct StringRepeat {}
 
-impl Func for Mul {
-    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 2 {
-            return Err(Error::WrongArity);
+impl Func for StringRepeat {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Integer(count), values::Value::String(string)] => {
+                let count = (*count).max(0) as usize;
+                let mut repeated = reserve_string(string.len().checked_mul(count), settings)?;
+                // The count can only be huge when the string is empty as the length was checked
+                if !string.is_empty() {
+                    for _ in 0..count {
+                        repeated.push_str(string);
+                    }
+                }
+                Ok(values::Value::String(Arc::from(repeated)))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
+    }
 
-        match (args.first(), args.last()) {
-            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
-                Ok(values::Value::Integer(a * b))
+    fn term(&self) -> term::Term {
+        int_to_string_to_string()
+    }
+}
+
+fn string_to_string_list() -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Constant(term::Value::String)),
+        Box::new(string_list_term()),
+    )
+}
+
+fn string_list_value<'a>(strings: impl Iterator<Item = &'a str>) -> values::Value {
+    values::Value::List(
+        strings
+            .map(|string| values::Value::String(Arc::from(string)))
+            .collect(),
+    )
+}
+
+// Elm.Kernel.String.words
+pub struct StringWords {}
+
+impl Func for StringWords {
+    // Runs of whitespace separate words and there are no empty words at either end
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::String(string)] => Ok(string_list_value(string.split_whitespace())),
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        string_to_string_list()
+    }
+}
+
+// Elm.Kernel.String.lines
+pub struct StringLines {}
+
+impl Func for StringLines {
+    // Splits on "\n", "\r\n" or "\r" like Elm, so a trailing newline gives an empty last line
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::String(string)] => {
+                Ok(string_list_value(string.split('\n').flat_map(|line| {
+                    line.strip_suffix('\r').unwrap_or(line).split('\r')
+                })))
             }
-            _ => Err(Error::WrongArgumentType),
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        string_to_string_list()
+    }
+}
+
+// Elm.Kernel.String.map
+pub struct StringMap {}
+
+impl Func for StringMap {
+    fn call(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        // The function can only be applied through the evaluator
+        Err(Error::WrongArity)
+    }
+
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+        apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [func, values::Value::String(string)] => string
+                .chars()
+                .map(
+                    |char| match apply(func.clone(), vec![values::Value::Char(char)])? {
+                        values::Value::Char(char) => Ok(char),
+                        _ => Err(Error::WrongArgumentType),
+                    },
+                )
+                .collect::<Result<String, Error>>()
+                .map(|string| values::Value::String(Arc::from(string))),
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
     }
 
     fn term(&self) -> term::Term {
         term::Term::Function(
-            Box::new(term::Term::Constant(term::Value::Integer)),
             Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::Integer)),
-                Box::new(term::Term::Constant(term::Value::Integer)),
+                Box::new(term::Term::Constant(term::Value::Char)),
+                Box::new(term::Term::Constant(term::Value::Char)),
             )),
+            Box::new(string_to_string()),
         )
     }
 }
 
-// Elm.Kernel.Basics.gt
-pub struct Gt {}
+// Elm.Kernel.String.filter
+pub struct StringFilter {}
 
-impl Func for Gt {
-    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 2 {
-            return Err(Error::WrongArity);
-        }
+impl Func for StringFilter {
+    fn call(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        // The predicate can only be applied through the evaluator
+        Err(Error::WrongArity)
+    }
 
-        match (args.first(), args.last()) {
-            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
-                Ok(values::Value::Bool(a > b))
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+        apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [predicate, values::Value::String(string)] => {
+                let mut filtered = String::with_capacity(string.len());
+                for char in string.chars() {
+                    match apply(predicate.clone(), vec![values::Value::Char(char)])? {
+                        values::Value::Bool(true) => filtered.push(char),
+                        values::Value::Bool(false) => {}
+                        _ => return Err(Error::WrongArgumentType),
+                    }
+                }
+                Ok(values::Value::String(Arc::from(filtered)))
             }
-            _ => Err(Error::WrongArgumentType),
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
     }
 
     fn term(&self) -> term::Term {
         term::Term::Function(
-            Box::new(term::Term::Constant(term::Value::Integer)),
             Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::Integer)),
+                Box::new(term::Term::Constant(term::Value::Char)),
                 Box::new(term::Term::Constant(term::Value::Bool)),
             )),
+            Box::new(string_to_string()),
         )
     }
 }
 
-// Elm.Kernel.Basics.gt
-pub struct Lt {}
+// Elm.Kernel.String.foldl
+pub struct StringFoldl {}
 
-impl Func for Lt {
-    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 2 {
-            return Err(Error::WrongArity);
-        }
+impl Func for StringFoldl {
+    fn call(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        // The function can only be applied through the evaluator
+        Err(Error::WrongArity)
+    }
 
-        match (args.first(), args.last()) {
-            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
-                Ok(values::Value::Bool(a < b))
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+        apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [func, initial, values::Value::String(string)] => {
+                string.chars().try_fold(initial.clone(), |acc, char| {
+                    apply(func.clone(), vec![values::Value::Char(char), acc])
+                })
             }
-            _ => Err(Error::WrongArgumentType),
+            [_, _, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
         }
     }
 
     fn term(&self) -> term::Term {
+        let acc = || term::Term::Var("b".to_string());
         term::Term::Function(
-            Box::new(term::Term::Constant(term::Value::Integer)),
             Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::Integer)),
-                Box::new(term::Term::Constant(term::Value::Bool)),
+                Box::new(term::Term::Constant(term::Value::Char)),
+                Box::new(term::Term::Function(Box::new(acc()), Box::new(acc()))),
+            )),
+            Box::new(term::Term::Function(
+                Box::new(acc()),
+                Box::new(term::Term::Function(
+                    Box::new(term::Term::Constant(term::Value::String)),
+                    Box::new(acc()),
+                )),
             )),
         )
     }
 }
 
-// Elm.Kernel.Basics.append
-pub struct Append {}
+/* Pads the string with the character until it is the given number of characters long, counting
+ * characters in the same units as String.length. Strings that are already long enough are left
+ * alone.
+ */
+fn pad(
+    args: Vec<values::Value>,
+    settings: &project::Settings,
+    join: fn(&str, &str) -> String,
+) -> Result<values::Value, Error> {
+    match args.as_slice() {
+        [values::Value::Integer(width), values::Value::Char(char), values::Value::String(string)] =>
+        {
+            let length = string_units(string, settings.string_units).len() as i64;
+            let count = width.saturating_sub(length).max(0) as usize;
+            let padding_length = char.len_utf8().checked_mul(count);
+            check_string_length(
+                padding_length.and_then(|padding| padding.checked_add(string.len())),
+                settings,
+            )?;
 
-impl Func for Append {
+            let mut padding = reserve_string(padding_length, settings)?;
+            padding.extend(std::iter::repeat_n(*char, count));
+            Ok(values::Value::String(Arc::from(join(&padding, string))))
+        }
+        [_, _, _] => Err(Error::WrongArgumentType),
+        _ => Err(Error::WrongArity),
+    }
+}
+
+/* Checks the length, in bytes, of a string that a builtin is about to build from a count given by
+ * the program so that a huge count is reported rather than failing to allocate. None is a length
+ * that is too large to count.
+ */
+fn check_string_length(
+    length: Option<usize>,
+    settings: &project::Settings,
+) -> Result<usize, Error> {
+    check_length(
+        length,
+        settings.max_string_length,
+        limits::Resource::StringLength,
+    )
+}
+
+fn check_length(
+    length: Option<usize>,
+    limit: Option<usize>,
+    resource: limits::Resource,
+) -> Result<usize, Error> {
+    match (length, limit) {
+        (None, _) => Err(Error::TooLarge),
+        (Some(length), Some(limit)) if length > limit => {
+            Err(Error::ResourceLimit { resource, limit })
+        }
+        (Some(length), _) => Ok(length),
+    }
+}
+
+/* Returns an empty string with room for exactly the given length, once it has been checked */
+fn reserve_string(length: Option<usize>, settings: &project::Settings) -> Result<String, Error> {
+    let length = check_string_length(length, settings)?;
+    let mut string = String::new();
+    string
+        .try_reserve_exact(length)
+        .map_err(|_| Error::TooLarge)?;
+    Ok(string)
+}
+
+fn pad_term() -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Constant(term::Value::Integer)),
+        Box::new(term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Char)),
+            Box::new(string_to_string()),
+        )),
+    )
+}
+
+// Elm.Kernel.String.padLeft
+pub struct StringPadLeft {}
+
+impl Func for StringPadLeft {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        pad(args, settings, |padding, string| {
+            format!("{}{}", padding, string)
+        })
+    }
+
+    fn term(&self) -> term::Term {
+        pad_term()
+    }
+}
+
+// Elm.Kernel.String.padRight
+pub struct StringPadRight {}
+
+impl Func for StringPadRight {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        pad(args, settings, |padding, string| {
+            format!("{}{}", string, padding)
+        })
+    }
+
+    fn term(&self) -> term::Term {
+        pad_term()
+    }
+}
+
+/* Arithmetic and comparisons accept Ints or Floats. The checker only lets the two sides differ when
+ * an integer literal has been used as a Float, so we treat a mix of the two as Floats.
+ */
+fn as_floats(a: &values::Value, b: &values::Value) -> Option<(f32, f32)> {
+    match (a, b) {
+        (values::Value::Float(a), values::Value::Float(b)) => Some((*a, *b)),
+        (values::Value::Integer(a), values::Value::Float(b)) => Some((*a as f32, *b)),
+        (values::Value::Float(a), values::Value::Integer(b)) => Some((*a, *b as f32)),
+        _ => None,
+    }
+}
+
+// number
+fn number_term() -> term::Term {
+    term::Term::Var("number".to_string())
+}
+
+// number -> number -> result
+fn number_function(result: term::Term) -> term::Term {
+    term::Term::Function(
+        Box::new(number_term()),
+        Box::new(term::Term::Function(
+            Box::new(number_term()),
+            Box::new(result),
+        )),
+    )
+}
+
+/* Int arithmetic is checked so that a result which doesn't fit in 64 bits is an error rather than
+ * a wrong answer, unless the settings ask for it to wrap around instead.
+ */
+pub fn int_arithmetic(
+    checked: fn(i64, i64) -> Option<i64>,
+    wrapping: fn(i64, i64) -> i64,
+    a: i64,
+    b: i64,
+    overflow: IntegerOverflow,
+) -> Result<i64, Error> {
+    match overflow {
+        IntegerOverflow::Error => checked(a, b).ok_or(Error::IntegerOverflow),
+        IntegerOverflow::Wrap => Ok(wrapping(a, b)),
+    }
+}
+
+// Elm.Kernel.Basics.add
+pub struct Add {}
+
+impl Func for Add {
     fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
         if args.len() != 2 {
             return Err(Error::WrongArity);
         }
 
         match (args.first(), args.last()) {
-            (Some(values::Value::String(a)), Some(values::Value::String(b))) => {
-                Ok(values::Value::String(a.to_owned() + b))
-            }
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => int_arithmetic(
+                i64::checked_add,
+                i64::wrapping_add,
+                *a,
+                *b,
+                settings.integer_overflow,
+            )
+            .map(values::Value::Integer),
+            (Some(a), Some(b)) => match as_floats(a, b) {
+                Some((a, b)) => Ok(values::Value::Float(a + b)),
+                None => Err(Error::WrongArgumentType),
+            },
             _ => Err(Error::WrongArgumentType),
         }
     }
 
     fn term(&self) -> term::Term {
-        term::Term::Function(
-            Box::new(term::Term::Constant(term::Value::String)),
-            Box::new(term::Term::Function(
-                Box::new(term::Term::Constant(term::Value::String)),
-                Box::new(term::Term::Constant(term::Value::String)),
-            )),
-        )
+        number_function(number_term())
     }
 }
 
-// Elm.Kernel.List.sum
-pub struct ListSum {}
+// Elm.Kernel.Basics.sub
+pub struct Sub {}
 
-impl Func for ListSum {
+impl Func for Sub {
     fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
-        if args.len() != 1 {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        if args.len() != 2 {
             return Err(Error::WrongArity);
         }
 
-        match args.first() {
-            Some(values::Value::List(entries)) => Ok(values::Value::Integer(
-                entries
-                    .iter()
-                    .flat_map(|value| {
-                        if let values::Value::Integer(int) = value {
-                            Some(int)
-                        } else {
-                            None
-                        }
-                    })
-                    .sum(),
-            )),
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => int_arithmetic(
+                i64::checked_sub,
+                i64::wrapping_sub,
+                *a,
+                *b,
+                settings.integer_overflow,
+            )
+            .map(values::Value::Integer),
+            (Some(a), Some(b)) => match as_floats(a, b) {
+                Some((a, b)) => Ok(values::Value::Float(a - b)),
+                None => Err(Error::WrongArgumentType),
+            },
             _ => Err(Error::WrongArgumentType),
         }
     }
 
     fn term(&self) -> term::Term {
-        term::Term::Function(
-            Box::new(term::Term::Type(
-                "List".to_string(),
-                vec![term::Term::Constant(term::Value::Integer)],
+        number_function(number_term())
+    }
+}
+
+// Elm.Kernel.Basics.mul
+pub struct Mul {}
+
+impl Func for Mul {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => int_arithmetic(
+                i64::checked_mul,
+                i64::wrapping_mul,
+                *a,
+                *b,
+                settings.integer_overflow,
+            )
+            .map(values::Value::Integer),
+            (Some(a), Some(b)) => match as_floats(a, b) {
+                Some((a, b)) => Ok(values::Value::Float(a * b)),
+                None => Err(Error::WrongArgumentType),
+            },
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        number_function(number_term())
+    }
+}
+
+// Elm.Kernel.Basics.gt
+pub struct Gt {}
+
+impl Func for Gt {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
+                Ok(values::Value::Bool(a > b))
+            }
+            (Some(a), Some(b)) => match as_floats(a, b) {
+                Some((a, b)) => Ok(values::Value::Bool(a > b)),
+                None => Err(Error::WrongArgumentType),
+            },
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        number_function(term::Term::Constant(term::Value::Bool))
+    }
+}
+
+// Elm.Kernel.Basics.lt
+pub struct Lt {}
+
+impl Func for Lt {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => {
+                Ok(values::Value::Bool(a < b))
+            }
+            (Some(a), Some(b)) => match as_floats(a, b) {
+                Some((a, b)) => Ok(values::Value::Bool(a < b)),
+                None => Err(Error::WrongArgumentType),
+            },
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        number_function(term::Term::Constant(term::Value::Bool))
+    }
+}
+
+/* Orders two comparable values. Lists and tuples are ordered by their first differing entry, as in
+ * Elm. Ints and Floats can be compared with each other as Int literals can stand in for Floats.
+ */
+fn compare_values(a: &values::Value, b: &values::Value) -> Option<Ordering> {
+    match (a, b) {
+        (values::Value::Integer(a), values::Value::Integer(b)) => Some(a.cmp(b)),
+        (values::Value::Char(a), values::Value::Char(b)) => Some(a.cmp(b)),
+        (values::Value::String(a), values::Value::String(b)) => Some(a.cmp(b)),
+        (values::Value::List(a), values::Value::List(b))
+        | (values::Value::Tuple(a), values::Value::Tuple(b)) => {
+            for (a, b) in a.iter().zip(b.iter()) {
+                match compare_values(a, b)? {
+                    Ordering::Equal => {}
+                    ordering => return Some(ordering),
+                }
+            }
+            Some(a.len().cmp(&b.len()))
+        }
+        (a, b) => as_floats(a, b).and_then(|(a, b)| a.partial_cmp(&b)),
+    }
+}
+
+// Elm.Kernel.Basics.compare
+pub struct Compare {}
+
+impl Func for Compare {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [a, b] => {
+                let name = match compare_values(a, b).ok_or(Error::WrongArgumentType)? {
+                    Ordering::Less => "LT",
+                    Ordering::Equal => "EQ",
+                    Ordering::Greater => "GT",
+                };
+                Ok(values::Value::Custom {
+                    name: name.to_string(),
+                    args: vec![],
+                })
+            }
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let comparable = || term::Term::Var("comparable".to_string());
+        term::Term::Function(
+            Box::new(comparable()),
+            Box::new(term::Term::Function(
+                Box::new(comparable()),
+                Box::new(term::Term::Type("Order".to_string(), vec![])),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Basics.append
+pub struct Append {}
+
+impl Func for Append {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(a)), Some(values::Value::String(b))) => {
+                Ok(values::Value::String(values::append(a, b)))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::String)),
+                Box::new(term::Term::Constant(term::Value::String)),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Basics.never
+pub struct Never {}
+
+impl Func for Never {
+    // There are no values of type Never so this can't be called from code that type checks
+    fn call<'a>(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        Err(Error::WrongArgumentType)
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Type("Never".to_string(), vec![])),
+            Box::new(term::Term::Var("a".to_string())),
+        )
+    }
+}
+
+/* The entries of a list of numbers. The checker makes sure that a list holds one kind of number
+ * but Int literals can stand in for Floats, so a list with any Floats in it is treated as Floats.
+ */
+enum Numbers {
+    Ints(Vec<i64>),
+    Floats(Vec<f32>),
+}
+
+fn numbers(entries: &[values::Value]) -> Result<Numbers, Error> {
+    if let Some(ints) = entries
+        .iter()
+        .map(|value| match value {
+            values::Value::Integer(int) => Some(*int),
+            _ => None,
+        })
+        .collect::<Option<Vec<i64>>>()
+    {
+        return Ok(Numbers::Ints(ints));
+    }
+
+    entries
+        .iter()
+        .map(|value| match value {
+            values::Value::Integer(int) => Ok(*int as f32),
+            values::Value::Float(float) => Ok(*float),
+            _ => Err(Error::WrongArgumentType),
+        })
+        .collect::<Result<Vec<f32>, Error>>()
+        .map(Numbers::Floats)
+}
+
+/* Folds the numbers in the list with Int arithmetic that respects the overflow setting or with
+ * Float arithmetic. An empty list gives the starting value.
+ */
+fn fold_numbers(
+    args: Vec<values::Value>,
+    settings: &project::Settings,
+    start: i64,
+    checked: fn(i64, i64) -> Option<i64>,
+    wrapping: fn(i64, i64) -> i64,
+    float: fn(f32, f32) -> f32,
+) -> Result<values::Value, Error> {
+    match args.as_slice() {
+        [values::Value::List(entries)] => match numbers(entries)? {
+            Numbers::Ints(ints) => ints
+                .into_iter()
+                .try_fold(start, |total, int| {
+                    int_arithmetic(checked, wrapping, total, int, settings.integer_overflow)
+                })
+                .map(values::Value::Integer),
+            Numbers::Floats(floats) => Ok(values::Value::Float(
+                floats.into_iter().fold(start as f32, float),
+            )),
+        },
+        [_] => Err(Error::WrongArgumentType),
+        _ => Err(Error::WrongArity),
+    }
+}
+
+/* Picks the largest or smallest number from the list, or Nothing if the list is empty. NaN is
+ * never picked over another number.
+ */
+fn pick_number(args: Vec<values::Value>, ordering: Ordering) -> Result<values::Value, Error> {
+    match args.as_slice() {
+        [values::Value::List(entries)] => Ok(maybe_value(match numbers(entries)? {
+            Numbers::Ints(ints) => ints
+                .into_iter()
+                .reduce(|best, int| {
+                    if int.cmp(&best) == ordering {
+                        int
+                    } else {
+                        best
+                    }
+                })
+                .map(values::Value::Integer),
+            Numbers::Floats(floats) => floats
+                .into_iter()
+                .reduce(|best, float| {
+                    if best.is_nan() || float.partial_cmp(&best) == Some(ordering) {
+                        float
+                    } else {
+                        best
+                    }
+                })
+                .map(values::Value::Float),
+        })),
+        [_] => Err(Error::WrongArgumentType),
+        _ => Err(Error::WrongArity),
+    }
+}
+
+fn number_list_term() -> term::Term {
+    term::Term::Type("List".to_string(), vec![number_term()])
+}
+
+// Elm.Kernel.List.sum
+pub struct ListSum {}
+
+impl Func for ListSum {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        fold_numbers(
+            args,
+            settings,
+            0,
+            i64::checked_add,
+            i64::wrapping_add,
+            |a, b| a + b,
+        )
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(Box::new(number_list_term()), Box::new(number_term()))
+    }
+}
+
+// Elm.Kernel.List.product
+pub struct ListProduct {}
+
+impl Func for ListProduct {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        fold_numbers(
+            args,
+            settings,
+            1,
+            i64::checked_mul,
+            i64::wrapping_mul,
+            |a, b| a * b,
+        )
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(Box::new(number_list_term()), Box::new(number_term()))
+    }
+}
+
+// Elm.Kernel.List.maximum
+pub struct ListMaximum {}
+
+impl Func for ListMaximum {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        pick_number(args, Ordering::Greater)
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(number_list_term()),
+            Box::new(maybe_term(number_term())),
+        )
+    }
+}
+
+// Elm.Kernel.List.minimum
+pub struct ListMinimum {}
+
+impl Func for ListMinimum {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        pick_number(args, Ordering::Less)
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(number_list_term()),
+            Box::new(maybe_term(number_term())),
+        )
+    }
+}
+
+fn list_term(item: term::Term) -> term::Term {
+    term::Term::Type("List".to_string(), vec![item])
+}
+
+fn tuple_term(items: Vec<term::Term>) -> term::Term {
+    term::Term::Type("Tuple".to_string(), items)
+}
+
+fn var_term(name: &str) -> term::Term {
+    term::Term::Var(name.to_string())
+}
+
+// Int -> List a -> List a
+fn count_list_term() -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Constant(term::Value::Integer)),
+        Box::new(term::Term::Function(
+            Box::new(list_term(var_term("a"))),
+            Box::new(list_term(var_term("a"))),
+        )),
+    )
+}
+
+// Elm.Kernel.List.take
+pub struct ListTake {}
+
+impl Func for ListTake {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Integer(count), values::Value::List(entries)] => {
+                let count = (*count).max(0) as usize;
+                Ok(values::Value::List(
+                    entries.iter().take(count).cloned().collect(),
+                ))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        count_list_term()
+    }
+}
+
+// Elm.Kernel.List.drop
+pub struct ListDrop {}
+
+impl Func for ListDrop {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::Integer(count), values::Value::List(entries)] => {
+                let count = (*count).max(0) as usize;
+                Ok(values::Value::List(
+                    entries.iter().skip(count).cloned().collect(),
+                ))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        count_list_term()
+    }
+}
+
+// Elm.Kernel.List.map
+pub struct ListMap {}
+
+impl Func for ListMap {
+    fn call<'a>(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        // The function can only be applied through the evaluator
+        Err(Error::WrongArity)
+    }
+
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+        apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [function, values::Value::List(entries)] => entries
+                .iter()
+                .map(|entry| apply(function.clone(), vec![entry.clone()]))
+                .collect::<Result<_, _>>()
+                .map(values::Value::List),
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Function(
+                Box::new(var_term("a")),
+                Box::new(var_term("b")),
+            )),
+            Box::new(term::Term::Function(
+                Box::new(list_term(var_term("a"))),
+                Box::new(list_term(var_term("b"))),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.List.partition
+pub struct ListPartition {}
+
+impl Func for ListPartition {
+    fn call<'a>(&self, _args: Vec<values::Value>) -> Result<values::Value, Error> {
+        // The predicate can only be applied through the evaluator
+        Err(Error::WrongArity)
+    }
+
+    fn call_with_apply(
+        &self,
+        args: Vec<values::Value>,
+        _settings: &project::Settings,
+        apply: &Apply,
+    ) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [predicate, values::Value::List(entries)] => {
+                let mut passed = vec![];
+                let mut failed = vec![];
+                for entry in entries {
+                    match apply(predicate.clone(), vec![entry.clone()])? {
+                        values::Value::Bool(true) => passed.push(entry.clone()),
+                        values::Value::Bool(false) => failed.push(entry.clone()),
+                        _ => return Err(Error::WrongArgumentType),
+                    }
+                }
+                Ok(values::Value::Tuple(vec![
+                    values::Value::List(passed),
+                    values::Value::List(failed),
+                ]))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Function(
+                Box::new(var_term("a")),
+                Box::new(term::Term::Constant(term::Value::Bool)),
+            )),
+            Box::new(term::Term::Function(
+                Box::new(list_term(var_term("a"))),
+                Box::new(tuple_term(vec![
+                    list_term(var_term("a")),
+                    list_term(var_term("a")),
+                ])),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.List.unzip
+pub struct ListUnzip {}
+
+impl Func for ListUnzip {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [values::Value::List(entries)] => {
+                let mut firsts = vec![];
+                let mut seconds = vec![];
+                for entry in entries {
+                    match entry {
+                        values::Value::Tuple(items) if items.len() == 2 => {
+                            firsts.push(items[0].clone());
+                            seconds.push(items[1].clone());
+                        }
+                        _ => return Err(Error::WrongArgumentType),
+                    }
+                }
+                Ok(values::Value::Tuple(vec![
+                    values::Value::List(firsts),
+                    values::Value::List(seconds),
+                ]))
+            }
+            [_] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(list_term(tuple_term(vec![var_term("a"), var_term("b")]))),
+            Box::new(tuple_term(vec![
+                list_term(var_term("a")),
+                list_term(var_term("b")),
+            ])),
+        )
+    }
+}
+
+// Elm.Kernel.List.intersperse
+pub struct ListIntersperse {}
+
+impl Func for ListIntersperse {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        match args.as_slice() {
+            [separator, values::Value::List(entries)] => {
+                let mut interspersed = Vec::with_capacity(entries.len() * 2);
+                for (index, entry) in entries.iter().enumerate() {
+                    if index > 0 {
+                        interspersed.push(separator.clone());
+                    }
+                    interspersed.push(entry.clone());
+                }
+                Ok(values::Value::List(interspersed))
+            }
+            [_, _] => Err(Error::WrongArgumentType),
+            _ => Err(Error::WrongArity),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(var_term("a")),
+            Box::new(term::Term::Function(
+                Box::new(list_term(var_term("a"))),
+                Box::new(list_term(var_term("a"))),
+            )),
+        )
+    }
+}
+
+fn expectation_term() -> term::Term {
+    term::Term::Type("Expectation".to_string(), vec![])
+}
+
+fn test_term() -> term::Term {
+    term::Term::Type("Test".to_string(), vec![])
+}
+
+// Elm.Kernel.Debug.toString
+pub struct DebugToString {}
+
+impl Func for DebugToString {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(value) => Ok(values::Value::String(Arc::from(value.to_string()))),
+            None => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Var("a".to_string())),
+            Box::new(term::Term::Constant(term::Value::String)),
+        )
+    }
+}
+
+// Elm.Kernel.Debug.log
+pub struct DebugLog {}
+
+impl Func for DebugLog {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(tag)), Some(value)) => {
+                // Written to stderr by default, like the console in the browser, so that it
+                // doesn't get mixed up with the output of the program
+                settings.output.write_line(&format!("{}: {}", tag, value));
+                Ok(value.clone())
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Var("a".to_string())),
+                Box::new(term::Term::Var("a".to_string())),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Expect.equal
+pub struct ExpectEqual {}
+
+impl Func for ExpectEqual {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(expected), Some(actual)) => {
+                if values::equal(expected, actual) {
+                    Ok(values::Value::Expectation(values::Expectation::Pass))
+                } else {
+                    // Laid out in the same way as elm-test with the actual value on top
+                    Ok(values::Value::Expectation(values::Expectation::Fail(
+                        format!("{}\n╷\n│ Expect.equal\n╵\n{}", actual, expected),
+                    )))
+                }
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Var("a".to_string())),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Var("a".to_string())),
+                Box::new(expectation_term()),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Expect.true
+pub struct ExpectTrue {}
+
+impl Func for ExpectTrue {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(_)), Some(values::Value::Bool(true))) => {
+                Ok(values::Value::Expectation(values::Expectation::Pass))
+            }
+            (Some(values::Value::String(message)), Some(values::Value::Bool(false))) => Ok(
+                values::Value::Expectation(values::Expectation::Fail(message.to_string())),
+            ),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::Bool)),
+                Box::new(expectation_term()),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Test.test
+pub struct TestTest {}
+
+impl Func for TestTest {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(name)), Some(body)) => {
+                Ok(values::Value::Test(Rc::new(values::Test::Test {
+                    name: name.to_string(),
+                    body: body.clone(),
+                })))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Function(
+                    Box::new(term::Term::Type("()".to_string(), vec![])),
+                    Box::new(expectation_term()),
+                )),
+                Box::new(test_term()),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Test.describe
+pub struct TestDescribe {}
+
+impl Func for TestDescribe {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(name)), Some(values::Value::List(entries))) => {
+                let tests = entries
+                    .iter()
+                    .map(|entry| match entry {
+                        values::Value::Test(test) => Ok(test.clone()),
+                        _ => Err(Error::WrongArgumentType),
+                    })
+                    .collect::<Result<_, _>>()?;
+
+                Ok(values::Value::Test(Rc::new(values::Test::Describe {
+                    name: name.to_string(),
+                    tests,
+                })))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Type("List".to_string(), vec![test_term()])),
+                Box::new(test_term()),
+            )),
+        )
+    }
+}
+
+/* The Random module follows the permuted congruential generator used by Elm's own Random module
+ * so that a seed produces the same values here as it does in Elm. Elm does its arithmetic with
+ * JavaScript numbers, which we mirror where the results depend on it.
+ */
+fn next_seed(seed: values::Seed) -> values::Seed {
+    values::Seed {
+        state: (seed.state as u64 * 1664525 + seed.increment as u64) as u32,
+        increment: seed.increment,
+    }
+}
+
+fn peel(seed: values::Seed) -> u32 {
+    let state = seed.state;
+    let mixed = state ^ (state >> ((state >> 28) + 4));
+    // The multiplication happens in floating point in JavaScript so can lose precision
+    let word = (mixed as i32 as f64 * 277803737.0).trunc();
+    let word = word.rem_euclid(4294967296.0) as u32;
+    (word >> 22) ^ word
+}
+
+fn generate(generator: &values::Generator, seed: values::Seed) -> (values::Value, values::Seed) {
+    match generator {
+        values::Generator::Int(a, b) => {
+            let (low, high) = if a < b { (*a, *b) } else { (*b, *a) };
+            // Elm works out the range in 32 bits so larger ranges wrap in the same way. The value
+            // added to low is always less than the real range so it can't go past high
+            let range = (high as u32).wrapping_sub(low as u32).wrapping_add(1);
+
+            if range.wrapping_sub(1) & range == 0 {
+                let value = (range.wrapping_sub(1) & peel(seed)) as i64 + low;
+                (values::Value::Integer(value), next_seed(seed))
+            } else {
+                // Skip values that would make the lower numbers in the range more likely
+                let threshold = range.wrapping_neg() % range;
+                let mut seed = seed;
+                loop {
+                    let value = peel(seed);
+                    seed = next_seed(seed);
+                    if value >= threshold {
+                        let value = (value % range) as i64 + low;
+                        return (values::Value::Integer(value), seed);
+                    }
+                }
+            }
+        }
+        values::Generator::Float(a, b) => {
+            let next = next_seed(seed);
+            let range = (*b as f64 - *a as f64).abs();
+            let low = (peel(next) & 0x07FFFFFF) as f64;
+            let high = (peel(seed) & 0x03FFFFFF) as f64;
+            let value = (high * 134217728.0 + low) / 9007199254740992.0;
+            (
+                values::Value::Float((value * range + *a as f64) as f32),
+                next_seed(next),
+            )
+        }
+        values::Generator::List(count, generator) => {
+            let mut seed = seed;
+            let mut items = Vec::new();
+            for _ in 0..*count {
+                let (item, next) = generate(generator, seed);
+                items.push(item);
+                seed = next;
+            }
+            (values::Value::List(items), seed)
+        }
+    }
+}
+
+fn seed_term() -> term::Term {
+    term::Term::Type("Seed".to_string(), vec![])
+}
+
+fn generator_term(value: term::Term) -> term::Term {
+    term::Term::Type("Generator".to_string(), vec![value])
+}
+
+// Elm.Kernel.Random.initialSeed
+pub struct RandomInitialSeed {}
+
+impl Func for RandomInitialSeed {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::Integer(int)) => {
+                let seed = next_seed(values::Seed {
+                    state: 0,
+                    increment: 1013904223,
+                });
+                Ok(values::Value::Seed(next_seed(values::Seed {
+                    state: seed.state.wrapping_add(*int as u32),
+                    increment: seed.increment,
+                })))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Integer)),
+            Box::new(seed_term()),
+        )
+    }
+}
+
+// Elm.Kernel.Random.int
+pub struct RandomInt {}
+
+impl Func for RandomInt {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(a)), Some(values::Value::Integer(b))) => Ok(
+                values::Value::Generator(Rc::new(values::Generator::Int(*a, *b))),
+            ),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Integer)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::Integer)),
+                Box::new(generator_term(term::Term::Constant(term::Value::Integer))),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Random.float
+pub struct RandomFloat {}
+
+impl Func for RandomFloat {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        // Integer literals can be used as Floats so either might be given
+        let bound = |value: &values::Value| match value {
+            values::Value::Float(float) => Some(*float),
+            values::Value::Integer(int) => Some(*int as f32),
+            _ => None,
+        };
+
+        match (args.first().and_then(bound), args.last().and_then(bound)) {
+            (Some(a), Some(b)) => Ok(values::Value::Generator(Rc::new(values::Generator::Float(
+                a, b,
+            )))),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Float)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::Float)),
+                Box::new(generator_term(term::Term::Constant(term::Value::Float))),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Random.list
+pub struct RandomList {}
+
+impl Func for RandomList {
+    fn call(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        self.call_with_settings(args, &project::Settings::default())
+    }
+
+    fn call_with_settings(
+        &self,
+        args: Vec<values::Value>,
+        settings: &project::Settings,
+    ) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(count)), Some(values::Value::Generator(generator))) => {
+                // Like Elm, asking for a negative number of values gives an empty list. The count
+                // is checked here so that no values are generated for a list over the limit
+                let count = check_length(
+                    Some((*count).max(0) as usize),
+                    settings.max_list_length,
+                    limits::Resource::ListLength,
+                )?;
+                Ok(values::Value::Generator(Rc::new(values::Generator::List(
+                    count,
+                    generator.clone(),
+                ))))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let a = term::Term::Var("a".to_string());
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Integer)),
+            Box::new(term::Term::Function(
+                Box::new(generator_term(a.clone())),
+                Box::new(generator_term(term::Term::Type(
+                    "List".to_string(),
+                    vec![a],
+                ))),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Random.step
+pub struct RandomStep {}
+
+impl Func for RandomStep {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Generator(generator)), Some(values::Value::Seed(seed))) => {
+                let (value, seed) = generate(generator, *seed);
+                Ok(values::Value::Tuple(vec![value, values::Value::Seed(seed)]))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let a = term::Term::Var("a".to_string());
+        term::Term::Function(
+            Box::new(generator_term(a.clone())),
+            Box::new(term::Term::Function(
+                Box::new(seed_term()),
+                Box::new(term::Term::Type("Tuple".to_string(), vec![a, seed_term()])),
+            )),
+        )
+    }
+}
+
+fn unit_function(result: term::Term) -> term::Term {
+    term::Term::Function(
+        Box::new(term::Term::Type("()".to_string(), vec![])),
+        Box::new(result),
+    )
+}
+
+// Elm.Kernel.Time.now
+pub struct TimeNow {}
+
+impl Func for TimeNow {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        // Time.now gives whole seconds since the epoch
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+        Ok(values::Value::Integer(seconds as i64))
+    }
+
+    fn term(&self) -> term::Term {
+        unit_function(term::Term::Constant(term::Value::Integer))
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Time)
+    }
+}
+
+// Elm.Kernel.Time.monotonic
+pub struct TimeMonotonic {}
+
+impl Func for TimeMonotonic {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        // Measured from the first call so that the values are small enough to fit in an Int
+        static START: OnceLock<Instant> = OnceLock::new();
+        let start = START.get_or_init(Instant::now);
+        Ok(values::Value::Integer(start.elapsed().as_millis() as i64))
+    }
+
+    fn term(&self) -> term::Term {
+        unit_function(term::Term::Constant(term::Value::Integer))
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Time)
+    }
+}
+
+// Elm.Kernel.Process.run
+pub struct ProcessRun {}
+
+impl Func for ProcessRun {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        let (program, arguments) = match (args.first(), args.last()) {
+            (Some(values::Value::String(program)), Some(values::Value::List(arguments))) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| match argument {
+                        values::Value::String(argument) => Ok(argument.to_string()),
+                        _ => Err(Error::WrongArgumentType),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                (program, arguments)
+            }
+            _ => return Err(Error::WrongArgumentType),
+        };
+
+        // Failing to start the program is reported to the script rather than stopping it. Programs
+        // stopped by a signal have no exit code so we use -1 for them
+        let result = match Command::new(&**program).args(arguments).output() {
+            Ok(output) => result_value(
+                "Ok",
+                values::Value::Tuple(vec![
+                    values::Value::Integer(output.status.code().unwrap_or(-1) as i64),
+                    values::Value::String(Arc::from(String::from_utf8_lossy(&output.stdout))),
+                    values::Value::String(Arc::from(String::from_utf8_lossy(&output.stderr))),
+                ]),
+            ),
+            Err(error) => result_value("Err", values::Value::String(Arc::from(error.to_string()))),
+        };
+
+        Ok(result)
+    }
+
+    // String -> List String -> Result String ( Int, String, String )
+    fn term(&self) -> term::Term {
+        let string = || term::Term::Constant(term::Value::String);
+        term::Term::Function(
+            Box::new(string()),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Type("List".to_string(), vec![string()])),
+                Box::new(result_term(
+                    string(),
+                    term::Term::Type(
+                        "Tuple".to_string(),
+                        vec![
+                            term::Term::Constant(term::Value::Integer),
+                            string(),
+                            string(),
+                        ],
+                    ),
+                )),
+            )),
+        )
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Process)
+    }
+}
+
+fn result_value(constructor: &str, value: values::Value) -> values::Value {
+    values::Value::Custom {
+        name: constructor.to_string(),
+        args: vec![value],
+    }
+}
+
+fn result_term(error: term::Term, value: term::Term) -> term::Term {
+    term::Term::Type("Result".to_string(), vec![error, value])
+}
+
+// Elm.Kernel.File.read
+pub struct FileRead {}
+
+impl Func for FileRead {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        // Like Process.run, a file that can't be read is reported to the script
+        match args.first() {
+            Some(values::Value::String(path)) => Ok(match std::fs::read_to_string(&**path) {
+                Ok(contents) => result_value("Ok", values::Value::String(Arc::from(contents))),
+                Err(error) => {
+                    result_value("Err", values::Value::String(Arc::from(error.to_string())))
+                }
+            }),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    // String -> Result String String
+    fn term(&self) -> term::Term {
+        let string = || term::Term::Constant(term::Value::String);
+        term::Term::Function(
+            Box::new(string()),
+            Box::new(result_term(string(), string())),
+        )
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Fs)
+    }
+}
+
+// Elm.Kernel.Env.get
+pub struct EnvGet {}
+
+impl Func for EnvGet {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::String(name)) => Ok(maybe_value(
+                std::env::var(&**name)
+                    .ok()
+                    .map(|value| values::Value::String(Arc::from(value))),
+            )),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    // String -> Maybe String
+    fn term(&self) -> term::Term {
+        let string = || term::Term::Constant(term::Value::String);
+        term::Term::Function(Box::new(string()), Box::new(maybe_term(string())))
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Env)
+    }
+}
+
+// Elm.Kernel.Http.getString
+pub struct HttpGetString {}
+
+impl Func for HttpGetString {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::String(url)) => Ok(http_result(http::get(url))),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let string = || term::Term::Constant(term::Value::String);
+        term::Term::Function(
+            Box::new(string()),
+            Box::new(result_term(string(), string())),
+        )
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Net)
+    }
+}
+
+// Elm.Kernel.Http.post
+pub struct HttpPost {}
+
+impl Func for HttpPost {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::String(url)), Some(values::Value::String(body))) => {
+                Ok(http_result(http::post(url, body)))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let string = || term::Term::Constant(term::Value::String);
+        term::Term::Function(
+            Box::new(string()),
+            Box::new(term::Term::Function(
+                Box::new(string()),
+                Box::new(result_term(string(), string())),
+            )),
+        )
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Net)
+    }
+}
+
+fn http_result(result: Result<String, String>) -> values::Value {
+    match result {
+        Ok(body) => result_value("Ok", values::Value::String(Arc::from(body))),
+        Err(error) => result_value("Err", values::Value::String(Arc::from(error))),
+    }
+}
+
+/* Blocking requests which return the body of the response, or a description of what went wrong.
+ * Responses with an error status are failures.
+ */
+#[cfg(feature = "net")]
+mod http {
+    pub fn get(url: &str) -> Result<String, String> {
+        read_response(ureq::get(url).call())
+    }
+
+    pub fn post(url: &str, body: &str) -> Result<String, String> {
+        read_response(ureq::post(url).send_string(body))
+    }
+
+    fn read_response(response: Result<ureq::Response, ureq::Error>) -> Result<String, String> {
+        match response {
+            Ok(response) => response.into_string().map_err(|error| error.to_string()),
+            Err(ureq::Error::Status(code, _response)) => Err(format!("Bad status: {}", code)),
+            Err(error) => Err(error.to_string()),
+        }
+    }
+}
+
+#[cfg(not(feature = "net"))]
+mod http {
+    const UNSUPPORTED: &str = "erm was built without support for HTTP requests";
+
+    pub fn get(_url: &str) -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+
+    pub fn post(_url: &str, _body: &str) -> Result<String, String> {
+        Err(UNSUPPORTED.to_string())
+    }
+}
+
+// Elm.Kernel.Csv.parse
+pub struct CsvParse {}
+
+impl Func for CsvParse {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::String(text)) => Ok(csv_rows(csv::parse(text, ','))),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(csv_rows_term()),
+        )
+    }
+}
+
+// Elm.Kernel.Csv.parseWith
+pub struct CsvParseWith {}
+
+impl Func for CsvParseWith {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Char(separator)), Some(values::Value::String(text))) => {
+                Ok(csv_rows(csv::parse(text, *separator)))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Char)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::String)),
+                Box::new(csv_rows_term()),
+            )),
+        )
+    }
+}
+
+fn csv_rows(rows: Vec<Vec<String>>) -> values::Value {
+    values::Value::List(
+        rows.into_iter()
+            .map(|row| {
+                values::Value::List(
+                    row.into_iter()
+                        .map(|field| values::Value::String(Arc::from(field)))
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+// List (List String)
+fn csv_rows_term() -> term::Term {
+    term::Term::Type(
+        "List".to_string(),
+        vec![term::Term::Type(
+            "List".to_string(),
+            vec![term::Term::Constant(term::Value::String)],
+        )],
+    )
+}
+
+mod csv {
+    /* Splits the text into rows of fields. Fields can be wrapped in double quotes so that they can
+     * hold separators and line breaks, with a doubled quote standing for a quote inside the field.
+     * Rows end with either "\n" or "\r\n" and a line break at the end of the text doesn't start
+     * another row.
+     */
+    pub fn parse(text: &str, separator: char) -> Vec<Vec<String>> {
+        let mut rows = vec![];
+        let mut row = vec![];
+        let mut field = String::new();
+        let mut in_quotes = false;
+        let mut chars = text.chars().peekable();
+
+        while let Some(char) = chars.next() {
+            if in_quotes {
+                match char {
+                    '"' if chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' => in_quotes = false,
+                    char => field.push(char),
+                }
+                continue;
+            }
+
+            match char {
+                '"' => in_quotes = true,
+                '\r' if chars.peek() == Some(&'\n') => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    rows.push(std::mem::take(&mut row));
+                }
+                char if char == separator => row.push(std::mem::take(&mut field)),
+                char => field.push(char),
+            }
+        }
+
+        if !field.is_empty() || !row.is_empty() || in_quotes {
+            row.push(field);
+            rows.push(row);
+        }
+
+        rows
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn parses_quoted_fields() {
+            assert_eq!(
+                parse(
+                    "name,notes\r\n\"Smith, J\",\"said \"\"hi\"\"\nthen left\"\n",
+                    ','
+                ),
+                vec![
+                    vec!["name".to_string(), "notes".to_string()],
+                    vec!["Smith, J".to_string(), "said \"hi\"\nthen left".to_string()],
+                ]
+            );
+        }
+
+        #[test]
+        fn keeps_empty_fields() {
+            assert_eq!(
+                parse("a;;c\n;\n", ';'),
+                vec![
+                    vec!["a".to_string(), "".to_string(), "c".to_string()],
+                    vec!["".to_string(), "".to_string()],
+                ]
+            );
+        }
+    }
+}
+
+fn bytes_term() -> term::Term {
+    term::Term::Type("Bytes".to_string(), vec![])
+}
+
+fn maybe_value(value: Option<values::Value>) -> values::Value {
+    match value {
+        Some(value) => values::Value::Custom {
+            name: "Just".to_string(),
+            args: vec![value],
+        },
+        None => values::Value::Custom {
+            name: "Nothing".to_string(),
+            args: vec![],
+        },
+    }
+}
+
+fn maybe_term(value: term::Term) -> term::Term {
+    term::Term::Type("Maybe".to_string(), vec![value])
+}
+
+// Elm.Kernel.Bytes.fromString
+pub struct BytesFromString {}
+
+impl Func for BytesFromString {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::String(string)) => {
+                Ok(values::Value::Bytes(Rc::from(string.as_bytes())))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::String)),
+            Box::new(bytes_term()),
+        )
+    }
+}
+
+// Elm.Kernel.Bytes.toString
+pub struct BytesToString {}
+
+impl Func for BytesToString {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            // Nothing when the bytes aren't valid UTF-8
+            Some(values::Value::Bytes(bytes)) => Ok(maybe_value(
+                std::str::from_utf8(bytes)
+                    .ok()
+                    .map(|string| values::Value::String(Arc::from(string))),
+            )),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(bytes_term()),
+            Box::new(maybe_term(term::Term::Constant(term::Value::String))),
+        )
+    }
+}
+
+// Elm.Kernel.Bytes.width
+pub struct BytesWidth {}
+
+impl Func for BytesWidth {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 1 {
+            return Err(Error::WrongArity);
+        }
+
+        match args.first() {
+            Some(values::Value::Bytes(bytes)) => Ok(values::Value::Integer(bytes.len() as i64)),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(bytes_term()),
+            Box::new(term::Term::Constant(term::Value::Integer)),
+        )
+    }
+}
+
+// Elm.Kernel.Bytes.append
+pub struct BytesAppend {}
+
+impl Func for BytesAppend {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Bytes(left)), Some(values::Value::Bytes(right))) => Ok(
+                values::Value::Bytes(left.iter().chain(right.iter()).copied().collect()),
+            ),
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(bytes_term()),
+            Box::new(term::Term::Function(
+                Box::new(bytes_term()),
+                Box::new(bytes_term()),
+            )),
+        )
+    }
+}
+
+/* Integers are written big-endian in 1, 2 or 4 bytes. Values which don't fit in the width are
+ * truncated to their lowest bytes, as in Elm.
+ */
+fn is_int_width(width: i64) -> bool {
+    matches!(width, 1 | 2 | 4)
+}
+
+// Elm.Kernel.Bytes.encodeInt
+pub struct BytesEncodeInt {}
+
+impl Func for BytesEncodeInt {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.last()) {
+            (Some(values::Value::Integer(width)), Some(values::Value::Integer(int)))
+                if is_int_width(*width) =>
+            {
+                let bytes = int.to_be_bytes();
+                Ok(values::Value::Bytes(Rc::from(
+                    &bytes[bytes.len() - *width as usize..],
+                )))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
+            Box::new(term::Term::Constant(term::Value::Integer)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::Integer)),
+                Box::new(bytes_term()),
             )),
+        )
+    }
+}
+
+// Elm.Kernel.Bytes.decodeInt
+pub struct BytesDecodeInt {}
+
+impl Func for BytesDecodeInt {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 3 {
+            return Err(Error::WrongArity);
+        }
+
+        match (args.first(), args.get(1), args.last()) {
+            (
+                Some(values::Value::Integer(width)),
+                Some(values::Value::Integer(offset)),
+                Some(values::Value::Bytes(bytes)),
+            ) if is_int_width(*width) => {
+                // Nothing when there aren't enough bytes after the offset
+                let int = usize::try_from(*offset)
+                    .ok()
+                    .and_then(|offset| bytes.get(offset..offset + *width as usize))
+                    .map(|slice| {
+                        // 1 and 2 byte values are unsigned and 4 byte values are signed, as in Elm
+                        slice
+                            .iter()
+                            .fold(0u32, |int, byte| (int << 8) | *byte as u32)
+                            as i32 as i64
+                    })
+                    .map(values::Value::Integer);
+                Ok(maybe_value(int))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        term::Term::Function(
             Box::new(term::Term::Constant(term::Value::Integer)),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Constant(term::Value::Integer)),
+                Box::new(term::Term::Function(
+                    Box::new(bytes_term()),
+                    Box::new(maybe_term(term::Term::Constant(term::Value::Integer))),
+                )),
+            )),
+        )
+    }
+}
+
+// Elm.Kernel.Platform.worker
+pub struct PlatformWorker {}
+
+impl Func for PlatformWorker {
+    fn call<'a>(&self, args: Vec<values::Value>) -> Result<values::Value, Error> {
+        if args.len() != 2 {
+            return Err(Error::WrongArity);
+        }
+
+        let mut args = args.into_iter();
+        match (args.next(), args.next()) {
+            (Some(init), Some(update @ values::Value::PartiallyAppliedFunc { .. })) => {
+                Ok(values::Value::Program(Rc::new(values::Program {
+                    init,
+                    update,
+                })))
+            }
+            _ => Err(Error::WrongArgumentType),
+        }
+    }
+
+    fn term(&self) -> term::Term {
+        let model = || term::Term::Var("model".to_string());
+        let msg = || term::Term::Var("msg".to_string());
+        term::Term::Function(
+            Box::new(model()),
+            Box::new(term::Term::Function(
+                Box::new(term::Term::Function(
+                    Box::new(msg()),
+                    Box::new(term::Term::Function(Box::new(model()), Box::new(model()))),
+                )),
+                Box::new(term::Term::Type(
+                    "Program".to_string(),
+                    vec![model(), msg()],
+                )),
+            )),
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Every kernel name that a core module binds has to be in the registry
+    #[test]
+    fn core_modules_only_use_registered_builtins() {
+        let core = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("core");
+        for entry in std::fs::read_dir(core).expect("Failed to read core directory") {
+            let source = std::fs::read_to_string(entry.expect("Failed to read entry").path())
+                .expect("Failed to read core module");
+            for name in source
+                .split_whitespace()
+                .filter(|word| word.starts_with("Elm.Kernel."))
+            {
+                assert!(is_built_in(name), "{} is not registered", name);
+            }
+        }
+    }
+}
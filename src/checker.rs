@@ -1,39 +1,143 @@
+pub mod cycles;
+pub mod patterns;
+pub mod resolve;
 pub mod term;
 pub mod unify;
 
-use std::rc::Rc;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 use self::term::{Term, Value};
-use super::ast::{self, Expr, Module, Pattern, Stmt};
+use super::ast::{self, Expr, ExprId, LetBinding, Module, Pattern, Stmt};
 use super::bindings::Binding;
-use super::env::{self, FoundBinding};
+use super::env::{self, FoundBinding, GetBindingError};
+use super::lexer::Range;
 use super::project;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     UnknownBinding(String),
     UnhandledExpression(String),
-    UnifyError(unify::Error),
+    // The terms for the expression at the range can't be made the same
+    UnifyError {
+        error: unify::Error,
+        range: Option<Range>,
+    },
+    // The two terms, or the parts of them given by the clash, can't be the same type where the
+    // expression at the range needs them to be
+    TypeMismatch {
+        clash: Box<unify::Clash>,
+        range: Option<Range>,
+    },
     UnknownFunction(ast::QualifiedLowerName),
     UnknownOperator(String),
     UnknownVarName(String),
     UnknownPattern(String),
-    ArgumentMismatch(u32),
-    TooManyArguments,
+    UnknownConstructor(String),
+    // The name is defined by the module but the module doesn't expose it
+    NotExposed {
+        name: String,
+        module: String,
+    },
+    // The part of an annotated function at the range doesn't have the type that the annotation
+    // gives it
+    AnnotationMismatch {
+        name: String,
+        expected: String,
+        found: String,
+        range: Option<Range>,
+    },
+    // A function is used with fewer arguments than it needs where a value is expected, or is
+    // defined with a different number of arguments to its annotation
+    ArgumentMismatch {
+        name: String,
+        expected: usize,
+        supplied: usize,
+        range: Option<Range>,
+    },
+    // A function is called with more arguments than its type allows
+    TooManyArguments {
+        name: String,
+        expected: usize,
+        supplied: usize,
+        range: Option<Range>,
+    },
+    // The top level value depends on itself through the names, which start & end with the value
+    // defined at the range
+    CyclicDefinition {
+        names: Vec<String>,
+        range: Option<Range>,
+    },
     Broken(&'static str),
     ScopeError(env::Error),
     ImpossiblyEmptyList,
     ImpossiblyEmptyCase,
+    // A case expression on literals at the range has no '_' branch for the values it doesn't list
+    MissingCaseFallback(Range),
+    // A record pattern lists a field that the record it matches doesn't have
+    MissingField {
+        field: String,
+        record: String,
+        range: Option<Range>,
+    },
     Unknown,
 }
 
+impl Error {
+    /* The part of the source that the error is about, if it is known */
+    pub fn range(&self) -> Option<Range> {
+        match self {
+            Error::UnifyError { range, .. }
+            | Error::TypeMismatch { range, .. }
+            | Error::MissingField { range, .. }
+            | Error::AnnotationMismatch { range, .. }
+            | Error::ArgumentMismatch { range, .. }
+            | Error::TooManyArguments { range, .. }
+            | Error::CyclicDefinition { range, .. } => range.clone(),
+            Error::MissingCaseFallback(range) => Some(range.clone()),
+            _ => None,
+        }
+    }
+}
+
+/* A hole in the annotation of a function and the type that the checker found for it */
+#[derive(Debug, PartialEq)]
+pub struct Hole {
+    pub name: String,
+    pub term: Term,
+    // Where the '_' is in the annotation
+    pub range: Option<Range>,
+}
+
+// The name given to holes in annotations until they are replaced with fresh variables
+const HOLE: &str = "_";
+
 pub struct Context {
     pub next_unique_id: u32,
+    // Provisional signatures for the functions that we're in the middle of checking, keyed by the
+    // address of their statements. References to these functions from within their own bodies, or
+    // from functions that they call, use the provisional signature rather than walking into the
+    // function again which would never end
+    checking: HashMap<usize, Term>,
+    // What we've learnt about the variables in the terms from the function calls so far
+    subs: unify::Substitutions,
+    // The holes in the annotations of the functions checked so far, keyed by the statement & the
+    // position of the hole so that functions which are checked more than once report them once
+    holes: Vec<((usize, usize), Hole)>,
+    // The record patterns seen since they were last settled, each with the variable for the record
+    // that it matches and the terms for the fields that it lists, see settle_record_patterns
+    record_patterns: Vec<(Term, Vec<(String, Term)>)>,
 }
 
 impl Context {
     pub fn new() -> Self {
-        Self { next_unique_id: 1 }
+        Self {
+            next_unique_id: 1,
+            checking: HashMap::new(),
+            subs: unify::Substitutions::new(),
+            holes: Vec::new(),
+            record_patterns: Vec::new(),
+        }
     }
 
     pub fn unique_var(&mut self) -> Term {
@@ -42,6 +146,21 @@ impl Context {
 
         Term::Var(format!("var-{}", id))
     }
+
+    // A variable that can only become an Int or a Float, used for integer literals
+    pub fn unique_number_var(&mut self) -> Term {
+        let id = self.next_unique_id;
+        self.next_unique_id += 1;
+
+        Term::Var(format!("number-{}", id))
+    }
+
+    pub fn unique_comparable_var(&mut self) -> Term {
+        let id = self.next_unique_id;
+        self.next_unique_id += 1;
+
+        Term::Var(format!("comparable-{}", id))
+    }
 }
 
 impl Default for Context {
@@ -50,230 +169,884 @@ impl Default for Context {
     }
 }
 
+fn type_mismatch(clash: unify::Clash) -> Error {
+    Error::TypeMismatch {
+        clash: Box::new(clash),
+        range: None,
+    }
+}
+
+/* Gives an error that doesn't yet say where it happened the range of the expression that it came
+ * from. The innermost expression gets there first so errors point at the smallest expression that
+ * they can
+ */
+fn locate(error: Error, expr_id: ExprId, environment: &env::Environment) -> Error {
+    let range = || expression_range(expr_id, environment);
+    match error {
+        Error::UnifyError { error, range: None } => Error::UnifyError {
+            error,
+            range: range(),
+        },
+        Error::TypeMismatch { clash, range: None } => Error::TypeMismatch {
+            clash,
+            range: range(),
+        },
+        Error::MissingField {
+            field,
+            record,
+            range: None,
+        } => Error::MissingField {
+            field,
+            record,
+            range: range(),
+        },
+        error => error,
+    }
+}
+
+/* The range of the expression in the source, preferring the range of its name when it has one */
+fn expression_range(expr_id: ExprId, environment: &env::Environment) -> Option<Range> {
+    environment
+        .arena
+        .range(expr_id)
+        .or_else(|| environment.arena.span(expr_id))
+        .cloned()
+}
+
 pub fn check(
-    _module: &Module,
+    module: &Module,
     environment: &env::Environment,
-    _settings: &project::Settings,
+    settings: &project::Settings,
 ) -> Result<(), Error> {
-    log::trace!("check");
+    check_with_holes(module, environment, settings).map(|_| ())
+}
+
+/* Checks that no top level value depends on itself, which the evaluator would follow until it ran
+ * out of stack. This is cheaper than the full check so it is also run before evaluating.
+ */
+pub fn check_cycles(module: &Module) -> Result<(), Error> {
+    log::trace!("check_cycles");
+    match cycles::find_cycle(module) {
+        Some(names) => {
+            let range = names
+                .first()
+                .and_then(|name| module.arena.definition(ast::DefinitionKind::Value, name))
+                .cloned();
+            Err(Error::CyclicDefinition { names, range })
+        }
+        None => Ok(()),
+    }
+}
+
+/* Checks the module and returns the types found for any holes in the annotations of the functions
+ * that main uses
+ */
+pub fn check_with_holes(
+    module: &Module,
+    environment: &env::Environment,
+    settings: &project::Settings,
+) -> Result<Vec<Hole>, Error> {
+    log::trace!("check_with_holes");
+
+    check_cycles(module)?;
+
+    let literal_cases = patterns::check_literal_cases(module);
+    for range in literal_cases.unreachable {
+        settings
+            .warnings
+            .add(project::Warning::UnreachableBranch { range });
+    }
+    if let Some(range) = literal_cases.missing_fallback.into_iter().next() {
+        return Err(Error::MissingCaseFallback(range));
+    }
 
     let main_name = ast::QualifiedLowerName::simple("main".to_string());
     let mut context = Context::default();
+    check_annotated_values(module, &mut context, environment)?;
 
-    // let mut var_generator = VarGenerator::new();
-    match environment.get_binding(&main_name) {
-        Ok(FoundBinding::WithEnv(Binding::UserFunc(stmt_rc), _env)) => match &*stmt_rc {
+    let main_term = match environment.get_binding(&main_name) {
+        Ok(FoundBinding::WithEnv(Binding::UserFunc(stmt_rc), env)) => match &*stmt_rc {
             Stmt::Function { args, expr, .. } => {
-                let mut bindings = env::Bindings::new();
-                for arg in args {
-                    for name in arg.names() {
-                        bindings.insert(
-                            ast::QualifiedLowerName {
-                                modules: Vec::new(),
-                                access: vec![name.clone()],
-                            },
-                            Binding::UserArg(Term::Var(name.clone())),
-                        );
-                    }
-                }
-
-                let scope = env::Scope::from_bindings(bindings);
-                let environment = env::add_local_scope(environment, scope);
-
-                let body_term = expression_to_term(expr, &mut context, &environment)?;
-                let main_term = Term::Function(
-                    Box::new(Term::Type(
-                        "List".to_string(),
-                        vec![Term::Constant(Value::String)],
-                    )),
-                    Box::new(body_term),
-                );
-
-                let target_term = Term::Function(
-                    Box::new(Term::Type(
-                        "List".to_string(),
-                        vec![Term::Constant(Value::String)],
-                    )),
-                    Box::new(Term::Constant(Value::String)),
-                );
-
-                let subs = unify::Substitutions::new();
-                unify::unify(&main_term, &target_term, &subs)
-                    .map(|_| ())
-                    .map_err(Error::UnifyError)
+                function_to_term(&stmt_rc, args, *expr, &mut context, &env)?
             }
-            _ => Err(Error::UnknownBinding("main".to_string())),
+            _ => return Err(Error::UnknownBinding("main".to_string())),
         },
+        Ok(FoundBinding::WithEnv(Binding::UserBinding(expr), env)) => {
+            expression_to_term(expr, &mut context, &env)?
+        }
         entry => {
             println!("entry {:?}", entry);
-            Err(Error::UnknownBinding("main".to_string()))
+            return Err(Error::UnknownBinding("main".to_string()));
+        }
+    };
+
+    // Main is accepted if it has any of the signatures that the evaluator knows how to run. We
+    // report the error for the first, most common, signature if it has none of them
+    let mut first_error = None;
+    for signature in main_signatures(&mut context) {
+        match unify::unify(&main_term, &signature, &context.subs) {
+            Ok(subs) => {
+                return Ok(context
+                    .holes
+                    .into_iter()
+                    .map(|(_key, hole)| Hole {
+                        term: unify::default_numbers(&subs.apply(&hole.term)),
+                        ..hole
+                    })
+                    .collect())
+            }
+            Err(error) => {
+                first_error.get_or_insert(error);
+            }
         }
     }
+
+    Err(
+        first_error.map_or(Error::Unknown, |error| Error::UnifyError {
+            error,
+            range: None,
+        }),
+    )
+}
+
+/* The types that main can have. It either takes the command line arguments or is a plain value and
+ * it produces the output to print, an exit code, or a program from Platform.worker which can have
+ * any model & messages.
+ */
+fn main_signatures(context: &mut Context) -> Vec<Term> {
+    let results = [
+        Term::Constant(Value::String),
+        Term::Constant(Value::Integer),
+        Term::Type(
+            "Program".to_string(),
+            vec![context.unique_var(), context.unique_var()],
+        ),
+    ];
+
+    let args = Term::Type("List".to_string(), vec![Term::Constant(Value::String)]);
+    results
+        .iter()
+        .map(|result| Term::Function(Box::new(args.clone()), Box::new(result.clone())))
+        .chain(results.iter().cloned())
+        .collect()
 }
 
 fn expression_to_term(
-    expr: &Expr,
+    expr_id: ExprId,
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
+    infer_expression(expr_id, context, environment)
+        .map_err(|error| locate(error, expr_id, environment))
+}
+
+fn infer_expression(
+    expr_id: ExprId,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    let expr = &environment.arena[expr_id];
     log::trace!("expression_to_term: {:?}", expr);
     match expr {
         Expr::Bool(_) => Ok(Term::Constant(Value::Bool)),
-        Expr::Integer(_) => Ok(Term::Constant(Value::Integer)),
+        Expr::Integer(_) => Ok(context.unique_number_var()),
+        Expr::Float(_) => Ok(Term::Constant(Value::Float)),
         Expr::String(_) => Ok(Term::Constant(Value::String)),
-        Expr::Call { function, args } => call_to_term(function, args, context, environment),
-        Expr::BinOp {
-            operator,
-            left,
-            right,
-        } => binary_expression_to_term(operator, left, right, context, environment),
-        Expr::VarName(name) =>
-        // Want to be able to fetch 'x' from the scope where 'x' is an typed or untyped
-        // argument to the function that we might be in the scope of
-        {
-            match environment.get_binding(name) {
-                Ok(FoundBinding::BuiltInFunc(name)) => {
-                    let built_in_func =
-                        env::get_built_in(&name).ok_or(Error::UnknownFunction(name))?;
-                    // TODO: Don't resolve with fake args - just resolve directly to a term definition
-                    // for a function
-                    // let args = Vec::new();
-                    Ok(built_in_func.term())
+        Expr::Char(_) => Ok(Term::Constant(Value::Char)),
+        Expr::Unit => Ok(Term::Type("()".to_string(), vec![])),
+        Expr::Tuple(items) => {
+            let terms = items
+                .iter()
+                .map(|item| expression_to_term(*item, context, environment))
+                .collect::<Result<_, _>>()?;
+            Ok(Term::Type("Tuple".to_string(), terms))
+        }
+        Expr::Record(fields) => {
+            let mut terms = fields
+                .iter()
+                .map(|(name, field)| {
+                    expression_to_term(*field, context, environment)
+                        .map(|term| (name.clone(), term))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Ok(Term::Record(terms))
+        }
+        Expr::Constructor(name) => constructor_to_term(name, context, environment),
+        Expr::Call { function, args } => {
+            call_to_term(expr_id, *function, args, context, environment)
+        }
+        Expr::BinOp { .. } => binary_expression_to_term(expr_id, context, environment),
+        Expr::VarName(name) => var_name_to_term(name, context, environment),
+        Expr::LocalVar { name, depth, slot } => match environment.get_local(*depth, *slot) {
+            Some(Binding::UserArg(term)) => Ok(term),
+            result => {
+                log::error!("{:#?}", result);
+                Err(Error::UnknownVarName(name.to_string()))
+            }
+        },
+        Expr::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => if_expression_to_term(*condition, *then_branch, *else_branch, context, environment),
+        Expr::Case { expr, branches, .. } => {
+            case_expression_to_term(*expr, branches, context, environment)
+        }
+        Expr::Lambda { args, body } => lambda_to_term(args, *body, context, environment),
+        Expr::Let { bindings, body } => {
+            let_expression_to_term(bindings, *body, context, environment)
+        }
+        Expr::List(expressions) => list_to_term(expressions, context, environment),
+    }
+}
+
+/* Infers the type of the named binding as seen from the given environment */
+pub fn infer(
+    name: &ast::QualifiedLowerName,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("infer: {:?}", name);
+    let mut context = Context::default();
+    var_name_to_term(name, &mut context, environment).map(|term| unify::default_numbers(&term))
+}
+
+// Want to be able to fetch 'x' from the scope where 'x' is an typed or untyped argument to the
+// function that we might be in the scope of
+fn var_name_to_term(
+    name: &ast::QualifiedLowerName,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("var_name_to_term: {:?}", name);
+    match environment.get_binding(name) {
+        Ok(FoundBinding::BuiltInFunc(name)) => {
+            let built_in_func = env::get_built_in(&name, environment.host_functions())
+                .ok_or(Error::UnknownFunction(name))?;
+            Ok(instantiate(&built_in_func.term(), context))
+        }
+        Ok(FoundBinding::WithEnv(Binding::UserBinding(expr), env)) => {
+            expression_to_term(expr, context, &env)
+        }
+        Ok(FoundBinding::WithEnv(Binding::UserFunc(stmt), env)) => match &*stmt {
+            Stmt::Function { args, expr, .. } => {
+                function_to_term(&stmt, args, *expr, context, &env)
+            }
+            // Ports can't be inferred so they always have a type
+            Stmt::Port { type_, .. } => {
+                Ok(instantiate(&type_to_term(type_, &HashMap::new()), context))
+            }
+            result => {
+                log::error!("{:#?}", result);
+                Err(Error::UnknownVarName(name.as_string()))
+            }
+        },
+        Ok(FoundBinding::WithEnv(Binding::UserArg(term), _env)) => Ok(term),
+        Err(GetBindingError::NotExposed { module, name }) => {
+            Err(Error::NotExposed { name, module })
+        }
+        result => {
+            log::error!("{:#?}", result);
+            Err(Error::UnknownVarName(name.as_string()))
+        }
+    }
+}
+
+/* Gives the variables in a declared signature fresh names so that each use of the function can
+ * settle them differently, eg. 'List.length' applied to a list of Ints in one place and a list of
+ * Strings in another. Variables keep their number or comparable constraint.
+ */
+fn instantiate(term: &Term, context: &mut Context) -> Term {
+    fn freshen(term: &Term, context: &mut Context, fresh: &mut HashMap<String, Term>) -> Term {
+        match term {
+            Term::Constant(_) => term.clone(),
+            // Each hole is a separate variable which is given out by annotation_to_term
+            Term::Var(name) if name == HOLE => term.clone(),
+            Term::Var(name) => {
+                if let Some(var) = fresh.get(name) {
+                    return var.clone();
                 }
-                Ok(FoundBinding::WithEnv(Binding::UserBinding(expr), env)) => {
-                    expression_to_term(&expr, context, &env)
+                let var = if unify::is_number_var(name) {
+                    context.unique_number_var()
+                } else if unify::is_comparable_var(name) {
+                    context.unique_comparable_var()
+                } else {
+                    context.unique_var()
+                };
+                fresh.insert(name.clone(), var.clone());
+                var
+            }
+            Term::Type(name, args) => Term::Type(
+                name.clone(),
+                args.iter()
+                    .map(|arg| freshen(arg, context, fresh))
+                    .collect(),
+            ),
+            Term::Function(from, to) => Term::Function(
+                Box::new(freshen(from, context, fresh)),
+                Box::new(freshen(to, context, fresh)),
+            ),
+            Term::Record(fields) => Term::Record(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), freshen(field, context, fresh)))
+                    .collect(),
+            ),
+        }
+    }
+
+    freshen(term, context, &mut HashMap::new())
+}
+
+/* Converts an annotation into the term that the annotated body is checked against. The
+ * annotation's variables are rigid, see unify::is_rigid, and each hole is given a variable of its
+ * own which is added to holes so that we can report what it turns out to be.
+ */
+fn annotation_to_term(type_: &ast::Type, context: &mut Context, holes: &mut Vec<Term>) -> Term {
+    fn fill_holes(term: Term, context: &mut Context, holes: &mut Vec<Term>) -> Term {
+        match term {
+            Term::Var(name) if name == HOLE => {
+                let var = context.unique_var();
+                holes.push(var.clone());
+                var
+            }
+            Term::Var(name) => Term::Type(name, vec![]),
+            Term::Constant(_) => term,
+            Term::Type(name, args) => Term::Type(
+                name,
+                args.into_iter()
+                    .map(|arg| fill_holes(arg, context, holes))
+                    .collect(),
+            ),
+            Term::Function(from, to) => {
+                let from = fill_holes(*from, context, holes);
+                let to = fill_holes(*to, context, holes);
+                Term::Function(Box::new(from), Box::new(to))
+            }
+            Term::Record(fields) => Term::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, field)| (name, fill_holes(field, context, holes)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fill_holes(type_to_term(type_, &HashMap::new()), context, holes)
+}
+
+/* Gives the rigid variables in the term of a checked annotation fresh variables, so that each use
+ * of the annotated function or value can settle them however it needs, as with instantiate
+ */
+fn flexible(term: &Term, context: &mut Context) -> Term {
+    fn loosen(term: &Term, context: &mut Context, fresh: &mut HashMap<String, Term>) -> Term {
+        match term {
+            Term::Type(name, args) if args.is_empty() && unify::is_rigid(name) => {
+                if let Some(var) = fresh.get(name) {
+                    return var.clone();
                 }
-                Ok(FoundBinding::WithEnv(Binding::UserFunc(stmt), _env)) => match &*stmt {
-                    Stmt::Function { args, expr, .. } => {
-                        let mut bindings = env::Bindings::new();
-                        for arg in args {
-                            for name in arg.names() {
-                                bindings.insert(
-                                    ast::QualifiedLowerName {
-                                        modules: Vec::new(),
-                                        access: vec![name.clone()],
-                                    },
-                                    Binding::UserArg(context.unique_var()),
-                                );
-                            }
-                        }
-                        let scope = env::Scope::from_bindings(bindings);
-                        // TODO: The called function should probably not have the scope of the callee but
-                        // rather than scope of where it was parsed
-                        let environment = env::add_local_scope(environment, scope);
-
-                        // TODO: Might infer substitutions from this work that we should return and make
-                        // available
-                        let body_term = expression_to_term(expr, context, &environment)?;
-
-                        let mut signature_term = body_term;
-                        for arg in args.iter().rev() {
-                            signature_term = Term::Function(
-                                Box::new(pattern_to_term(arg, context, &environment)?),
-                                Box::new(signature_term),
-                            )
-                        }
-
-                        // log::error!("Error");
-                        Ok(signature_term)
+                let var = if unify::is_number_var(name) {
+                    context.unique_number_var()
+                } else if unify::is_comparable_var(name) {
+                    context.unique_comparable_var()
+                } else {
+                    context.unique_var()
+                };
+                fresh.insert(name.clone(), var.clone());
+                var
+            }
+            Term::Constant(_) | Term::Var(_) => term.clone(),
+            Term::Type(name, args) => Term::Type(
+                name.clone(),
+                args.iter().map(|arg| loosen(arg, context, fresh)).collect(),
+            ),
+            Term::Function(from, to) => Term::Function(
+                Box::new(loosen(from, context, fresh)),
+                Box::new(loosen(to, context, fresh)),
+            ),
+            Term::Record(fields) => Term::Record(
+                fields
+                    .iter()
+                    .map(|(name, field)| (name.clone(), loosen(field, context, fresh)))
+                    .collect(),
+            ),
+        }
+    }
+
+    loosen(term, context, &mut HashMap::new())
+}
+
+/* Infers the signature of a function from its body. The function's statement is passed alongside
+ * its arguments & body so that we can recognise recursive references to it.
+ */
+fn function_to_term(
+    stmt: &Arc<Stmt>,
+    args: &[Pattern],
+    expr: ExprId,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("function_to_term");
+    if let Some(provisional_term) = context.checking.get(&stmt_key(stmt)) {
+        log::trace!("recursive reference");
+        return Ok(provisional_term.clone());
+    }
+
+    // The names bound by the arguments are given fresh variables which are shared between the
+    // provisional signature and the scope of the body so that both the body & any recursive calls
+    // can constrain them
+    let mut bound_terms = Vec::new();
+    let arg_terms = args
+        .iter()
+        .map(|arg| pattern_to_term(arg, context, environment, &mut bound_terms))
+        .collect::<Result<Vec<Term>, Error>>()?;
+    settle_record_patterns(context)?;
+
+    // An annotation gives the types that the arguments & body are expected to have up front so
+    // that the body can be checked against them rather than inferred & compared at the end
+    let annotation = match &**stmt {
+        Stmt::Function {
+            type_annotation: Some(annotation),
+            name,
+            ..
+        } => Some((
+            name.0.clone(),
+            top_level_annotation_to_term(stmt, annotation, context, environment),
+        )),
+        _ => None,
+    };
+
+    let result_term = match &annotation {
+        Some((name, annotation_term)) => {
+            let mut term = annotation_term.clone();
+            for arg_term in &arg_terms {
+                match term {
+                    Term::Function(from, to) => {
+                        context.subs =
+                            unify::unify(arg_term, &from, &context.subs).map_err(|_| {
+                                Error::AnnotationMismatch {
+                                    name: name.clone(),
+                                    expected: describe(&from, &context.subs),
+                                    found: describe(arg_term, &context.subs),
+                                    range: definition_range(name, environment),
+                                }
+                            })?;
+                        term = *to;
                     }
-                    result => {
-                        log::error!("{:#?}", result);
-                        Err(Error::UnknownVarName(name.as_string()))
+                    _ => {
+                        return Err(Error::ArgumentMismatch {
+                            name: name.clone(),
+                            expected: arrows(annotation_term),
+                            supplied: args.len(),
+                            range: definition_range(name, environment),
+                        })
                     }
-                },
-                Ok(FoundBinding::WithEnv(Binding::UserArg(term), _env)) => Ok(term),
-                result => {
-                    log::error!("{:#?}", result);
-                    Err(Error::UnknownVarName(name.as_string()))
                 }
             }
+            term
+        }
+        None => context.unique_var(),
+    };
+
+    let provisional_term = to_function_term(&arg_terms, result_term.clone());
+    context.checking.insert(stmt_key(stmt), provisional_term);
+
+    let slots = args
+        .iter()
+        .flat_map(|arg| arg.names())
+        .zip(bound_terms)
+        .map(|(name, term)| (name, Binding::UserArg(term)))
+        .collect();
+    let scope = env::Scope::from_slots(slots);
+    // The function body is checked in the scope where it was defined as its expressions live in
+    // the arena of that module
+    let environment = environment.push_scope(scope);
+
+    let body_term = match &annotation {
+        Some((name, _)) => {
+            check_expression(expr, &result_term, name, context, &environment).map(|()| result_term)
+        }
+        None => expression_to_term(expr, context, &environment),
+    };
+    context.checking.remove(&stmt_key(stmt));
+    let body_term = body_term?;
+
+    // The annotation's variables are only rigid within the function
+    let signature_term = to_function_term(&arg_terms, body_term);
+    Ok(flexible(&context.subs.apply(&signature_term), context))
+}
+
+/* The term for the annotation of a top level function or value. Its holes are added to the
+ * context's holes the first time that the statement is checked.
+ */
+fn top_level_annotation_to_term(
+    stmt: &Arc<Stmt>,
+    annotation: &ast::TypeAnnotation,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Term {
+    let mut holes = Vec::new();
+    let term = annotation_to_term(&annotation.type_, context, &mut holes);
+    let mut ranges = environment.arena.holes(&annotation.name.0);
+    for (index, hole) in holes.into_iter().enumerate() {
+        let range = ranges.next().cloned();
+        let key = (stmt_key(stmt), index);
+        if !context.holes.iter().any(|(existing, _)| *existing == key) {
+            context.holes.push((
+                key,
+                Hole {
+                    name: annotation.name.0.clone(),
+                    term: hole,
+                    range,
+                },
+            ));
+        }
+    }
+    term
+}
+
+/* Checks the top level values that have annotations against them. Functions are checked against
+ * their annotations when they're used but values are only inferred where they're used.
+ */
+fn check_annotated_values(
+    module: &Module,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<(), Error> {
+    for stmt in &module.statements {
+        if let Stmt::Binding {
+            type_annotation: Some(annotation),
+            name,
+            expr,
+        } = &**stmt
+        {
+            let term = top_level_annotation_to_term(stmt, annotation, context, environment);
+            check_expression(*expr, &term, &name.0, context, environment)?;
         }
+    }
+    Ok(())
+}
+
+/* Checks the expression against the type that it is expected to have, pushing the expectation
+ * down into the branches of if, case & let expressions and the items of lists so that any
+ * disagreement is reported against the part of the expression that causes it. The name is the
+ * annotated function that the expectation comes from.
+ */
+fn check_expression(
+    expr_id: ExprId,
+    expected: &Term,
+    name: &str,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<(), Error> {
+    let expr = &environment.arena[expr_id];
+    log::trace!("check_expression: {:?} {:?}", expr, expected);
+    match expr {
         Expr::If {
             condition,
             then_branch,
             else_branch,
-        } => if_expression_to_term(condition, then_branch, else_branch, context, environment),
-        Expr::Case { expr, branches } => {
-            case_expression_to_term(expr, branches, context, environment)
+        } => {
+            let condition_term = expression_to_term(*condition, context, environment)?;
+            context.subs =
+                unify::explain(&condition_term, &Term::Constant(Value::Bool), &context.subs)
+                    .map_err(type_mismatch)?;
+
+            check_expression(*then_branch, expected, name, context, environment)?;
+            check_expression(*else_branch, expected, name, context, environment)
         }
-        Expr::List(expressions) => list_to_term(expressions.to_vec(), context, environment),
-        _ => Err(Error::UnhandledExpression(format!("{:?}", expr))),
+        Expr::Case { expr, branches, .. } => {
+            let expr_term = expression_to_term(*expr, context, environment)?;
+            for (pattern, branch_expr) in branches {
+                let branch_environment = bind_pattern(pattern, &expr_term, context, environment)?;
+                check_expression(*branch_expr, expected, name, context, &branch_environment)?;
+            }
+            Ok(())
+        }
+        Expr::Let { bindings, body } => {
+            let environment = let_bindings_environment(bindings, context, environment)?;
+            check_expression(*body, expected, name, context, &environment)
+        }
+        Expr::List(items) if !items.is_empty() => match context.subs.apply(expected) {
+            Term::Type(type_name, args) if type_name == "List" && args.len() == 1 => {
+                for item in items {
+                    check_expression(*item, &args[0], name, context, environment)?;
+                }
+                Ok(())
+            }
+            _ => infer_and_compare(expr_id, expected, name, context, environment),
+        },
+        _ => infer_and_compare(expr_id, expected, name, context, environment),
     }
 }
 
+/* Infers the type of an expression that has no parts to push the expectation into and compares it
+ * with the expectation
+ */
+fn infer_and_compare(
+    expr_id: ExprId,
+    expected: &Term,
+    name: &str,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<(), Error> {
+    let term = expression_to_term(expr_id, context, environment)?;
+    context.subs = unify::unify(&term, expected, &context.subs).map_err(|_| {
+        under_applied(expr_id, &term, expected, context, environment).unwrap_or_else(|| {
+            Error::AnnotationMismatch {
+                name: name.to_string(),
+                expected: describe(expected, &context.subs),
+                found: describe(&term, &context.subs),
+                range: expression_range(expr_id, environment),
+            }
+        })
+    })?;
+    Ok(())
+}
+
+/* The term as it should be shown in an error, with what we know about its variables filled in */
+fn describe(term: &Term, subs: &unify::Substitutions) -> String {
+    unify::default_numbers(&subs.apply(term)).to_string()
+}
+
+fn to_function_term(arg_terms: &[Term], result_term: Term) -> Term {
+    arg_terms
+        .iter()
+        .rev()
+        .fold(result_term, |signature_term, arg_term| {
+            Term::Function(Box::new(arg_term.clone()), Box::new(signature_term))
+        })
+}
+
+// The range of the name of the top level function, for errors about its definition as a whole
+fn definition_range(name: &str, environment: &env::Environment) -> Option<Range> {
+    environment
+        .arena
+        .definition(ast::DefinitionKind::Value, name)
+        .cloned()
+}
+
+fn stmt_key(stmt: &Arc<Stmt>) -> usize {
+    Arc::as_ptr(stmt) as usize
+}
+
+/* Infers the term of a tree of binary operators, walking it in order rather than recursing for
+ * each operator as a chain of them can be very deep
+ */
 fn binary_expression_to_term(
-    operator_name: &str,
-    left: &Rc<Expr>,
-    right: &Rc<Expr>,
+    expr_id: ExprId,
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
     log::trace!("binary_expression_to_term");
-    if let Some(operator) = env::get_operator(environment, operator_name) {
-        // TODO: Make sure we get the function that corresponds to the same scope as the operator
-        // otherwise we might get another function
-        match operator.binding {
-            Binding::UserFunc(stmt_rc) => match &*stmt_rc {
-                Stmt::Function { .. } => {
-                    // TODO: Figure out how to get from this function def to a usable signature for
-                    // checking against with the args that we have
-                    //
-                    // We want to create a list of terms representing the signature. The last entry
-                    // is the result of evaluating the expression which forms the body of the
-                    // function. If the function has any arguments then we want to figure out terms
-                    // for those to and join them to the return type to form the signature
-                    //
-                    // As we're processing a binary expression and the attached signature then we
-                    // expect/assume there are two arguments to the function
-
-                    // TODO: Check with the args that we have
-                    // let args = vec![left.clone(), right.clone()];
-                    // resolve_function_term(&signature, &args, &environment)
-                    Err(Error::UnknownFunction(operator.function_name))
-                }
-                _ => Err(Error::UnknownFunction(operator.function_name)),
-            },
-            Binding::UserBinding(expr_rc) => {
-                let signature_term = expression_to_term(&expr_rc, context, environment)?;
-                let left_term = expression_to_term(left, context, environment)?;
-                let right_term = expression_to_term(right, context, environment)?;
-                let arg_terms = [left_term, right_term];
-                // println!("About to resolve for {:#?}", expr_rc);
-                resolve_function_and_args(&signature_term, &arg_terms, environment)
+    let mut terms = Vec::new();
+    for id in environment.arena.operator_tree(expr_id) {
+        match &environment.arena[id] {
+            Expr::BinOp {
+                operator,
+                left,
+                right,
+            } => {
+                let right_term = terms
+                    .pop()
+                    .ok_or(Error::Broken("operator without operands"))?;
+                let left_term = terms
+                    .pop()
+                    .ok_or(Error::Broken("operator without operands"))?;
+                let term = operator_to_term(operator, context, environment)
+                    .and_then(|signature_term| {
+                        apply_arguments(
+                            &signature_term,
+                            &[(*left, left_term), (*right, right_term)],
+                            context,
+                            environment,
+                        )
+                    })
+                    .map_err(|error| locate(error, id, environment))?;
+                terms.push(term);
             }
-            _ => Err(Error::UnknownFunction(operator.function_name)),
+            _ => terms.push(expression_to_term(id, context, environment)?),
         }
-    } else {
-        Err(Error::UnknownOperator(operator_name.to_string()))
+    }
+
+    terms
+        .pop()
+        .ok_or(Error::Broken("operator without operands"))
+}
+
+/* Returns the term of the function that the operator stands for */
+fn operator_to_term(
+    operator_name: &str,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    let (operator, operator_env) = env::get_operator(environment, operator_name)
+        .ok_or_else(|| Error::UnknownOperator(operator_name.to_string()))?;
+
+    // TODO: Make sure we get the function that corresponds to the same scope as the operator
+    // otherwise we might get another function
+    match operator.binding {
+        Binding::UserFunc(stmt_rc) => match &*stmt_rc {
+            Stmt::Function { args, expr, .. } => {
+                function_to_term(&stmt_rc, args, *expr, context, &operator_env)
+            }
+            _ => Err(Error::UnknownFunction(operator.function_name)),
+        },
+        Binding::UserBinding(expr_id) => expression_to_term(expr_id, context, &operator_env),
+        _ => Err(Error::UnknownFunction(operator.function_name)),
     }
 }
 
 fn call_to_term(
-    function: &Rc<Expr>,
-    call_args: &[Rc<Expr>],
+    call: ExprId,
+    function: ExprId,
+    call_args: &[ExprId],
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
     log::trace!("call_to_term");
     let function_term = expression_to_term(function, context, environment)?;
 
-    let arg_terms = call_args
+    let args = call_args
         .iter()
-        .map(|arg| expression_to_term(arg, context, environment))
-        .collect::<Result<Vec<Term>, Error>>()?;
+        .map(|arg| Ok((*arg, expression_to_term(*arg, context, environment)?)))
+        .collect::<Result<Vec<(ExprId, Term)>, Error>>()?;
 
-    // println!("About to resolve for builtin {:?}", function_name);
-    resolve_function_and_args(&function_term, &arg_terms, environment)
+    if let Some(expected) = arity(&context.subs.apply(&function_term)) {
+        if args.len() > expected {
+            return Err(Error::TooManyArguments {
+                name: function_name(function, environment),
+                expected,
+                supplied: args.len(),
+                range: environment.arena.call_range(call).cloned(),
+            });
+        }
+    }
+
+    apply_arguments(&function_term, &args, context, environment)
+}
+
+/* Applies the arguments, along with the expressions that they come from, to the function's
+ * signature one at a time. A function given as an argument might only be known as a variable in
+ * which case it is taken to be a function of whatever it is given.
+ */
+fn apply_arguments(
+    signature_term: &Term,
+    args: &[(ExprId, Term)],
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("apply_arguments: {:?} {:?}", signature_term, args);
+    let mut signature_term = signature_term.clone();
+
+    for (arg, arg_term) in args {
+        match context.subs.apply(&signature_term) {
+            Term::Function(from, to) => {
+                context.subs = unify::unify(arg_term, &from, &context.subs).map_err(|error| {
+                    under_applied(*arg, arg_term, &from, context, environment).unwrap_or(
+                        Error::UnifyError {
+                            error,
+                            range: expression_range(*arg, environment),
+                        },
+                    )
+                })?;
+                signature_term = *to;
+            }
+            var @ Term::Var(_) => {
+                let result_term = context.unique_var();
+                let function_term =
+                    Term::Function(Box::new(arg_term.clone()), Box::new(result_term.clone()));
+                context.subs =
+                    unify::explain(&var, &function_term, &context.subs).map_err(type_mismatch)?;
+                signature_term = result_term;
+            }
+            term => {
+                log::error!("{:?}", term);
+                return Err(Error::Broken("signature is not a function"));
+            }
+        }
+    }
+
+    Ok(context.subs.apply(&signature_term))
+}
+
+/* The number of arguments that a function with the term takes, or None if it returns a variable
+ * which might turn out to be another function
+ */
+fn arity(term: &Term) -> Option<usize> {
+    match term {
+        Term::Function(_, to) => arity(to).map(|arity| arity + 1),
+        Term::Var(name) if !unify::is_number_var(name) && !unify::is_comparable_var(name) => None,
+        _ => Some(0),
+    }
+}
+
+/* The number of arguments that the term is known to take */
+fn arrows(term: &Term) -> usize {
+    match term {
+        Term::Function(_, to) => arrows(to) + 1,
+        _ => 0,
+    }
+}
+
+/* When a value is expected but the expression is a function then it is likely that it is missing
+ * some arguments, eg. 'String.fromInt (max 1)'. Returns an error describing the missing arguments
+ * if that is the case.
+ */
+fn under_applied(
+    expr_id: ExprId,
+    found: &Term,
+    expected: &Term,
+    context: &Context,
+    environment: &env::Environment,
+) -> Option<Error> {
+    let found = context.subs.apply(found);
+    let missing = arrows(&found);
+    if missing == 0 {
+        return None;
+    }
+    if !matches!(
+        context.subs.apply(expected),
+        Term::Constant(_) | Term::Type(_, _)
+    ) {
+        return None;
+    }
+
+    let (function, supplied) = match &environment.arena[expr_id] {
+        Expr::Call { function, args } => (*function, args.len()),
+        _ => (expr_id, 0),
+    };
+
+    Some(Error::ArgumentMismatch {
+        name: function_name(function, environment),
+        expected: supplied + missing,
+        supplied,
+        range: environment
+            .arena
+            .call_range(expr_id)
+            .cloned()
+            .or_else(|| expression_range(expr_id, environment)),
+    })
+}
+
+/* The name of the function in an expression for use in errors */
+fn function_name(expr_id: ExprId, environment: &env::Environment) -> String {
+    match &environment.arena[expr_id] {
+        Expr::VarName(name) => name.as_string(),
+        Expr::LocalVar { name, .. } => name.to_string(),
+        Expr::Constructor(name) => name.as_string(),
+        _ => "this function".to_string(),
+    }
 }
 
 /* Takes a function signature expressed as terms and arguments expressed as terms and applies the
- * arguments to the signature to resolve down to a shorter signature or a single non-function term
+ * arguments to the signature to resolve down to a shorter signature or a single non-function term.
+ * Fewer arguments than the signature takes leaves the function of the remaining arguments.
  */
 fn resolve_function_and_args(
     signature_term: &Term,
     arg_terms: &[Term],
-    environment: &env::Environment,
+    context: &mut Context,
 ) -> Result<Term, Error> {
     log::trace!(
         "resolve_function_and_args: {:?} {:?}",
@@ -282,27 +1055,15 @@ fn resolve_function_and_args(
     );
     match signature_term {
         Term::Function(from, to) => match arg_terms.split_first() {
-            Some((first, [])) => {
-                let subs = unify::Substitutions::new();
-                match unify::unify(first, &**from, &subs) {
-                    Ok(_subs) => Ok((**to).clone()),
-                    Err(err) => Err(Error::UnifyError(err)),
-                }
-            }
             Some((first, rest)) => {
-                let subs = unify::Substitutions::new();
-                match unify::unify(first, &**from, &subs) {
-                    Ok(_subs) => resolve_function_and_args(to, rest, environment),
-                    Err(err) => Err(Error::UnifyError(err)), // TODO: If they don't already match then we want to try to unify them - in
-                                                             // particular to detect if one is more general than the other and that they can
-                                                             // therefore be brought together by narrowing the more general one down and fixing
-                                                             // it to match the more specific one
-                                                             // println!("first {:#?}", first);
-                                                             // println!("from {:#?}", from);
-                                                             // Err(Error::Broken("arg didn't match function slot"))
+                context.subs = unify::explain(first, from, &context.subs).map_err(type_mismatch)?;
+                if rest.is_empty() {
+                    Ok(context.subs.apply(to))
+                } else {
+                    resolve_function_and_args(to, rest, context)
                 }
             }
-            None => Err(Error::Broken("no more args")),
+            None => Ok(context.subs.apply(signature_term)),
         },
         term => {
             log::error!("{:?}", term);
@@ -312,9 +1073,9 @@ fn resolve_function_and_args(
 }
 
 fn if_expression_to_term(
-    condition: &Expr,
-    then_branch: &Expr,
-    else_branch: &Expr,
+    condition: ExprId,
+    then_branch: ExprId,
+    else_branch: ExprId,
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
@@ -323,9 +1084,8 @@ fn if_expression_to_term(
     let condition_term = expression_to_term(condition, context, environment)?;
 
     // Unify condition
-    let subs = unify::Substitutions::new();
-    let _subs = unify::unify(&condition_term, &Term::Constant(Value::Bool), &subs)
-        .map_err(Error::UnifyError)?;
+    context.subs = unify::explain(&condition_term, &Term::Constant(Value::Bool), &context.subs)
+        .map_err(type_mismatch)?;
 
     // Infer then_branch
     let then_branch_term = expression_to_term(then_branch, context, environment)?;
@@ -333,51 +1093,368 @@ fn if_expression_to_term(
     // Infer else_branch
     let else_branch_term = expression_to_term(else_branch, context, environment)?;
 
-    // TODO: Unify instead of equality check
-    if then_branch_term == else_branch_term {
-        Ok(then_branch_term)
-    } else {
-        Err(Error::Broken("else & then don't match"))
-    }
+    // The branches might be the result of a recursive call which only has a provisional type so
+    // we unify them rather than requiring them to match exactly
+    context.subs = unify::unify(&then_branch_term, &else_branch_term, &context.subs)
+        .map_err(|_| Error::Broken("else & then don't match"))?;
+
+    Ok(context.subs.apply(&then_branch_term))
 }
 
 fn case_expression_to_term(
-    expr: &Expr,
-    branches: &[(Pattern, Expr)],
+    expr: ExprId,
+    branches: &[(Pattern, ExprId)],
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
     log::trace!("case_expression_to_term");
     let expr_term = expression_to_term(expr, context, environment)?;
-    let subs = unify::Substitutions::new();
 
-    let mut branch_expr_term = None;
+    let mut branch_expr_term: Option<Term> = None;
 
     for (pattern, branch_expr) in branches {
-        let pattern_term = pattern_to_term(pattern, context, environment)?;
-        unify::unify(&expr_term, &pattern_term, &subs).map_err(Error::UnifyError)?;
+        let branch_environment = bind_pattern(pattern, &expr_term, context, environment)?;
 
-        branch_expr_term = Some(expression_to_term(branch_expr, context, environment)?);
+        // Each branch has to agree with the ones before it. Branches which recurse only have a
+        // provisional type so this also resolves them to the type of the other branches
+        let term = expression_to_term(*branch_expr, context, &branch_environment)?;
+        if let Some(previous_term) = &branch_expr_term {
+            context.subs =
+                unify::explain(previous_term, &term, &context.subs).map_err(type_mismatch)?;
+        }
+        branch_expr_term = Some(term);
     }
 
-    branch_expr_term.ok_or(Error::ImpossiblyEmptyCase)
+    branch_expr_term
+        .map(|term| context.subs.apply(&term))
+        .ok_or(Error::ImpossiblyEmptyCase)
 }
 
+/* The arguments of a lambda are given fresh variables, like those of an unannotated function, and
+ * the body is inferred in a scope with them added to the scope where the lambda is written
+ */
+fn lambda_to_term(
+    args: &[Pattern],
+    body: ExprId,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("lambda_to_term");
+    let mut bound_terms = Vec::new();
+    let arg_terms = args
+        .iter()
+        .map(|arg| pattern_to_term(arg, context, environment, &mut bound_terms))
+        .collect::<Result<Vec<Term>, Error>>()?;
+    settle_record_patterns(context)?;
+
+    let slots = args
+        .iter()
+        .flat_map(|arg| arg.names())
+        .zip(bound_terms)
+        .map(|(name, term)| (name, Binding::UserArg(term)))
+        .collect();
+    let environment = environment.push_scope(env::Scope::from_slots(slots));
+
+    let body_term = expression_to_term(body, context, &environment)?;
+    Ok(context.subs.apply(&to_function_term(&arg_terms, body_term)))
+}
+
+fn let_expression_to_term(
+    bindings: &[LetBinding],
+    body: ExprId,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("let_expression_to_term");
+    let environment = let_bindings_environment(bindings, context, environment)?;
+    let body_term = expression_to_term(body, context, &environment)?;
+    Ok(context.subs.apply(&body_term))
+}
+
+/* Returns the environment for the body of a let expression with each of the bindings in scope. The
+ * names of all the bindings share one scope which is also used for each binding's expression so
+ * that bindings can refer to themselves & each other
+ */
+fn let_bindings_environment(
+    bindings: &[LetBinding],
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<env::Environment, Error> {
+    // Holes are only reported for top level annotations
+    let annotation_terms: Vec<Option<Term>> = bindings
+        .iter()
+        .map(|binding| {
+            binding
+                .type_annotation
+                .as_ref()
+                .map(|annotation| annotation_to_term(&annotation.type_, context, &mut Vec::new()))
+        })
+        .collect();
+
+    // Annotated names are given their annotation up front so that uses of them before their
+    // binding has been checked see it
+    let mut name_terms = Vec::new();
+    for (binding, annotation_term) in bindings.iter().zip(&annotation_terms) {
+        for _name in binding.pattern.names() {
+            name_terms.push(match annotation_term {
+                Some(term) => flexible(term, context),
+                None => context.unique_var(),
+            });
+        }
+    }
+
+    let slots = bindings
+        .iter()
+        .flat_map(|binding| binding.pattern.names())
+        .zip(&name_terms)
+        .map(|(name, term)| (name, Binding::UserArg(term.clone())))
+        .collect();
+    let environment = environment.push_scope(env::Scope::from_slots(slots));
+
+    let mut name_terms = name_terms.iter();
+    for (binding, annotation_term) in bindings.iter().zip(annotation_terms) {
+        // Annotated bindings are checked against their annotation in the same way as functions
+        let expr_term = match (annotation_term, &binding.type_annotation) {
+            (Some(term), Some(annotation)) => {
+                check_expression(
+                    binding.expr,
+                    &term,
+                    &annotation.name.0,
+                    context,
+                    &environment,
+                )?;
+                flexible(&context.subs.apply(&term), context)
+            }
+            _ => expression_to_term(binding.expr, context, &environment)?,
+        };
+
+        let mut bound_terms = Vec::new();
+        let pattern_term =
+            pattern_to_term(&binding.pattern, context, &environment, &mut bound_terms)?;
+        context.subs =
+            unify::explain(&expr_term, &pattern_term, &context.subs).map_err(type_mismatch)?;
+        settle_record_patterns(context)?;
+
+        for (bound_term, name_term) in bound_terms.iter().zip(name_terms.by_ref()) {
+            context.subs =
+                unify::explain(name_term, bound_term, &context.subs).map_err(type_mismatch)?;
+        }
+    }
+    Ok(environment)
+}
+
+/* Matches the pattern against a value of the given term and returns the environment with the
+ * names bound by the pattern in scope, each with the term that the pattern gave it
+ */
+fn bind_pattern(
+    pattern: &Pattern,
+    value_term: &Term,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<env::Environment, Error> {
+    let mut bound_terms = Vec::new();
+    let pattern_term = pattern_to_term(pattern, context, environment, &mut bound_terms)?;
+    context.subs =
+        unify::explain(value_term, &pattern_term, &context.subs).map_err(type_mismatch)?;
+    settle_record_patterns(context)?;
+
+    let slots = pattern
+        .names()
+        .into_iter()
+        .zip(bound_terms)
+        .map(|(name, term)| (name, Binding::UserArg(term)))
+        .collect();
+    Ok(environment.push_scope(env::Scope::from_slots(slots)))
+}
+
+/* Returns the term for the values that the pattern matches. The terms given to the names bound by
+ * the pattern are added to bound_terms in the same order as the names are returned by
+ * Pattern::names
+ */
 fn pattern_to_term(
     pattern: &Pattern,
     context: &mut Context,
-    _environment: &env::Environment,
+    environment: &env::Environment,
+    bound_terms: &mut Vec<Term>,
 ) -> Result<Term, Error> {
     match pattern {
         Pattern::Anything => Ok(context.unique_var()),
         Pattern::Bool(_) => Ok(Term::Constant(Value::Bool)),
         Pattern::Integer(_) => Ok(Term::Constant(Value::Integer)),
-        Pattern::Name(name) => Ok(term::Term::Var(name.to_string())),
+        Pattern::String(_) => Ok(Term::Constant(Value::String)),
+        Pattern::Char(_) => Ok(Term::Constant(Value::Char)),
+        Pattern::Name(_) => {
+            let term = context.unique_var();
+            bound_terms.push(term.clone());
+            Ok(term)
+        }
+        Pattern::Record(names) => {
+            // Which record this is isn't known until the pattern has been unified with the value
+            // that it matches so the fields are checked afterwards
+            let record_term = context.unique_var();
+            let field_terms = names
+                .iter()
+                .map(|name| {
+                    let term = context.unique_var();
+                    bound_terms.push(term.clone());
+                    (name.clone(), term)
+                })
+                .collect();
+            context
+                .record_patterns
+                .push((record_term.clone(), field_terms));
+            Ok(record_term)
+        }
+        Pattern::Tuple(patterns) => {
+            let terms = patterns
+                .iter()
+                .map(|pattern| pattern_to_term(pattern, context, environment, bound_terms))
+                .collect::<Result<_, _>>()?;
+            Ok(Term::Type("Tuple".to_string(), terms))
+        }
+        Pattern::List(patterns) => {
+            let item_term = context.unique_var();
+            for pattern in patterns {
+                let term = pattern_to_term(pattern, context, environment, bound_terms)?;
+                context.subs =
+                    unify::explain(&item_term, &term, &context.subs).map_err(type_mismatch)?;
+            }
+            Ok(context
+                .subs
+                .apply(&Term::Type("List".to_string(), vec![item_term])))
+        }
+        Pattern::Cons(head, tail) => {
+            let head_term = pattern_to_term(head, context, environment, bound_terms)?;
+            let tail_term = pattern_to_term(tail, context, environment, bound_terms)?;
+            let list_term = Term::Type("List".to_string(), vec![head_term]);
+            context.subs =
+                unify::explain(&list_term, &tail_term, &context.subs).map_err(type_mismatch)?;
+            Ok(context.subs.apply(&list_term))
+        }
+        Pattern::Constructor(name, args) => {
+            let constructor_term = constructor_to_term(name, context, environment)?;
+            if args.is_empty() {
+                Ok(constructor_term)
+            } else {
+                let arg_terms = args
+                    .iter()
+                    .map(|arg| pattern_to_term(arg, context, environment, bound_terms))
+                    .collect::<Result<Vec<_>, _>>()?;
+                resolve_function_and_args(&constructor_term, &arg_terms, context)
+            }
+        }
+    }
+}
+
+/* Checks the record patterns seen since they were last settled against what we now know about the
+ * records that they match. A pattern can list some of the fields of a known record. When nothing
+ * is known about the record it is taken to have just the listed fields, as records only unify
+ * when they have the same fields.
+ */
+fn settle_record_patterns(context: &mut Context) -> Result<(), Error> {
+    for (record_term, field_terms) in std::mem::take(&mut context.record_patterns) {
+        match context.subs.apply(&record_term) {
+            Term::Record(fields) => {
+                for (name, term) in field_terms {
+                    let (_name, field_term) = fields
+                        .iter()
+                        .find(|(field, _term)| *field == name)
+                        .ok_or_else(|| Error::MissingField {
+                        field: name.clone(),
+                        record: describe(&Term::Record(fields.clone()), &context.subs),
+                        range: None,
+                    })?;
+                    context.subs =
+                        unify::explain(&term, field_term, &context.subs).map_err(type_mismatch)?;
+                }
+            }
+            _ => {
+                let mut field_terms = field_terms;
+                field_terms.sort_by(|(a, _), (b, _)| a.cmp(b));
+                context.subs =
+                    unify::explain(&record_term, &Term::Record(field_terms), &context.subs)
+                        .map_err(type_mismatch)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/* The term for a constructor is a function from its arguments to the custom type, or just the
+ * custom type if it has no arguments. The type's variables are fresh for each use.
+ */
+fn constructor_to_term(
+    name: &ast::QualifiedUpperName,
+    context: &mut Context,
+    environment: &env::Environment,
+) -> Result<Term, Error> {
+    log::trace!("constructor_to_term: {:?}", name);
+    let constructor = env::get_constructor(environment, name).map_err(|error| match error {
+        GetBindingError::NotExposed { module, name } => Error::NotExposed { name, module },
+        GetBindingError::Unknown => Error::UnknownConstructor(name.as_string()),
+    })?;
+
+    let vars: HashMap<String, Term> = constructor
+        .type_args
+        .iter()
+        .map(|arg| (arg.0.clone(), context.unique_var()))
+        .collect();
+
+    let type_term = Term::Type(
+        constructor.type_name.clone(),
+        constructor
+            .type_args
+            .iter()
+            .filter_map(|arg| vars.get(&arg.0).cloned())
+            .collect(),
+    );
+
+    Ok(constructor
+        .args
+        .iter()
+        .rev()
+        .fold(type_term, |result, arg| {
+            Term::Function(Box::new(type_to_term(arg, &vars)), Box::new(result))
+        }))
+}
+
+/* Converts a type from the source into a term. Type variables are looked up in the given map so
+ * that they can be shared with other terms.
+ */
+fn type_to_term(type_: &ast::Type, vars: &HashMap<String, Term>) -> Term {
+    match type_ {
+        ast::Type::Var(name) => vars
+            .get(&name.0)
+            .cloned()
+            .unwrap_or_else(|| Term::Var(name.0.clone())),
+        ast::Type::Bool => Term::Constant(Value::Bool),
+        ast::Type::Int => Term::Constant(Value::Integer),
+        ast::Type::Float => Term::Constant(Value::Float),
+        ast::Type::Char => Term::Constant(Value::Char),
+        ast::Type::String => Term::Constant(Value::String),
+        ast::Type::Unit => Term::Type("()".to_string(), vec![]),
+        ast::Type::Hole => Term::Var(HOLE.to_string()),
+        ast::Type::List(arg) => Term::Type("List".to_string(), vec![type_to_term(arg, vars)]),
+        ast::Type::Tuple(items) => Term::Type(
+            "Tuple".to_string(),
+            items.iter().map(|item| type_to_term(item, vars)).collect(),
+        ),
+        ast::Type::Function { from, to } => Term::Function(
+            Box::new(type_to_term(from, vars)),
+            Box::new(type_to_term(to, vars)),
+        ),
+        ast::Type::UserDefined { name, args } if name.access == "Bool" && args.is_empty() => {
+            Term::Constant(Value::Bool)
+        }
+        ast::Type::UserDefined { name, args } => Term::Type(
+            name.access.clone(),
+            args.iter().map(|arg| type_to_term(arg, vars)).collect(),
+        ),
     }
 }
 
 fn list_to_term(
-    expressions: Vec<Rc<Expr>>,
+    expressions: &[ExprId],
     context: &mut Context,
     environment: &env::Environment,
 ) -> Result<Term, Error> {
@@ -387,24 +1464,20 @@ fn list_to_term(
     } else {
         let terms: Vec<Term> = expressions
             .iter()
-            .map(|expr| expression_to_term(expr, context, environment))
+            .map(|expr| expression_to_term(*expr, context, environment))
             .collect::<Result<_, _>>()?;
 
-        // Unify terms by comparing each item with its neighbour and making sure there are no
-        // issues unifying them with a consistent set of subs
-        let (_subs, term) = terms
-            .iter()
-            .fold(Err(Error::ImpossiblyEmptyList), |acc, term| match acc {
-                Err(Error::ImpossiblyEmptyList) => Ok((unify::Substitutions::new(), term)),
-                Err(err) => Err(err),
-                Ok((subs, last_term)) => unify::unify(term, last_term, &subs)
-                    .map(|subs| (subs, term))
-                    .map_err(Error::UnifyError),
-            })?;
-
-        // TODO: What is the best term to actually include from the list? The most basic? The most
-        // general?
-
-        Ok(Term::Type("List".to_string(), vec![term.clone()]))
+        // Each item has to agree with the ones before it. Unifying against the context's subs means
+        // that what we learn about the items, eg. that a number literal is a Float, is kept for the
+        // rest of the checking
+        let (first, rest) = terms.split_first().ok_or(Error::ImpossiblyEmptyList)?;
+        for term in rest {
+            context.subs = unify::explain(term, first, &context.subs).map_err(type_mismatch)?;
+        }
+
+        Ok(Term::Type(
+            "List".to_string(),
+            vec![context.subs.apply(first)],
+        ))
     }
 }
@@ -0,0 +1,164 @@
+use std::collections::{HashMap, HashSet};
+
+use super::resolve;
+use crate::ast::{Arena, Expr, ExprId, Module, Stmt};
+
+/* Finds a top level value which depends on itself, eg. 'x = y' & 'y = x', which would never finish
+ * evaluating. Functions are allowed to refer to themselves as they can stop recursing but a value
+ * which reaches itself, even through a function, is a cycle. Returns the names along the cycle
+ * starting & ending with the value.
+ */
+pub fn find_cycle(module: &Module) -> Option<Vec<String>> {
+    log::trace!("find_cycle: {:?}", module.name);
+    // Local names are resolved to slots so that any remaining simple names refer to the top level
+    let module = resolve::resolve_module(module);
+
+    let mut values = Vec::new();
+    let mut dependencies = HashMap::new();
+    for stmt in &module.statements {
+        match &**stmt {
+            Stmt::Binding { name, expr, .. } => {
+                values.push(name.0.clone());
+                dependencies.insert(name.0.clone(), references(*expr, &module.arena));
+            }
+            Stmt::Function { name, expr, .. } => {
+                dependencies.insert(name.0.clone(), references(*expr, &module.arena));
+            }
+            _ => {}
+        }
+    }
+
+    values.into_iter().find_map(|value| {
+        let mut path = vec![value.clone()];
+        let mut visited = HashSet::new();
+        find_path(&value, &value, &dependencies, &mut path, &mut visited).then_some(path)
+    })
+}
+
+/* Searches the dependencies of 'from' for 'target', adding the names along the way to the path */
+fn find_path(
+    from: &str,
+    target: &str,
+    dependencies: &HashMap<String, Vec<String>>,
+    path: &mut Vec<String>,
+    visited: &mut HashSet<String>,
+) -> bool {
+    for name in dependencies.get(from).into_iter().flatten() {
+        if name == target {
+            path.push(name.clone());
+            return true;
+        }
+
+        if visited.insert(name.clone()) {
+            path.push(name.clone());
+            if find_path(name, target, dependencies, path, visited) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+
+    false
+}
+
+/* The unqualified names that the expression refers to which aren't local to it */
+fn references(expr_id: ExprId, arena: &Arena) -> Vec<String> {
+    match &arena[expr_id] {
+        Expr::Bool(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::String(_)
+        | Expr::Char(_)
+        | Expr::Unit
+        | Expr::Constructor(_)
+        | Expr::LocalVar { .. } => vec![],
+        Expr::VarName(name) => match (name.modules.as_slice(), name.access.as_slice()) {
+            ([], [access]) => vec![access.clone()],
+            _ => vec![],
+        },
+        Expr::List(items) | Expr::Tuple(items) => items
+            .iter()
+            .flat_map(|item| references(*item, arena))
+            .collect(),
+        Expr::Record(fields) => fields
+            .iter()
+            .flat_map(|(_name, field)| references(*field, arena))
+            .collect(),
+        Expr::BinOp { .. } => arena
+            .operator_tree(expr_id)
+            .into_iter()
+            .filter(|operand| !matches!(arena[*operand], Expr::BinOp { .. }))
+            .flat_map(|operand| references(operand, arena))
+            .collect(),
+        Expr::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => [condition, then_branch, else_branch]
+            .iter()
+            .flat_map(|expr| references(**expr, arena))
+            .collect(),
+        Expr::Case { expr, branches, .. } => {
+            let mut names = references(*expr, arena);
+            for (_pattern, branch_expr) in branches {
+                names.extend(references(*branch_expr, arena));
+            }
+            names
+        }
+        Expr::Lambda { body, .. } => references(*body, arena),
+        Expr::Let { bindings, body } => {
+            let mut names: Vec<String> = bindings
+                .iter()
+                .flat_map(|binding| references(binding.expr, arena))
+                .collect();
+            names.extend(references(*body, arena));
+            names
+        }
+        Expr::Call { function, args } => {
+            let mut names = references(*function, arena);
+            for arg in args {
+                names.extend(references(*arg, arena));
+            }
+            names
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+
+    fn cycle(source: &str) -> Option<Vec<String>> {
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        find_cycle(&parser::parse(&mut iter).expect("Failed to parse"))
+    }
+
+    #[test]
+    fn values_that_refer_to_each_other() {
+        assert_eq!(
+            cycle("module Main exposing (..)\nx = y\ny = x\n"),
+            Some(vec!["x".to_string(), "y".to_string(), "x".to_string()])
+        );
+    }
+
+    #[test]
+    fn recursive_functions_are_allowed() {
+        assert_eq!(
+            cycle("module Main exposing (..)\nf n = f n\nx = f 1\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn value_through_function() {
+        assert_eq!(
+            cycle("module Main exposing (..)\nf n = x\nx = f 1\n"),
+            Some(vec!["x".to_string(), "f".to_string(), "x".to_string()])
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::ast::{Arena, Expr, ExprId, Module, Pattern, Stmt};
+use crate::lexer::Range;
+
+/* What we found out about the literal patterns in the module's case expressions */
+#[derive(Debug, Default, PartialEq)]
+pub struct LiteralCases {
+    // The branches that can never match because an earlier branch has the same literal
+    pub unreachable: Vec<Range>,
+    // The 'case ... of' lines of case expressions on literals that have no '_' branch to fall back
+    // on and don't list every value
+    pub missing_fallback: Vec<Range>,
+}
+
+/* Looks through the case expressions in the module's declarations for those that match on Int,
+ * String, Char or Bool literals. Only the patterns at the top of each branch are considered, so
+ * a literal inside a tuple or a constructor is left for the evaluator to report if nothing
+ * matches.
+ */
+pub fn check_literal_cases(module: &Module) -> LiteralCases {
+    log::trace!("check_literal_cases: {:?}", module.name);
+    let mut cases = Vec::new();
+    for stmt in &module.statements {
+        if let Stmt::Binding { expr, .. } | Stmt::Function { expr, .. } = &**stmt {
+            find_cases(*expr, &module.arena, &mut cases);
+        }
+    }
+
+    let mut literal_cases = LiteralCases::default();
+    for expr_id in cases {
+        let Expr::Case {
+            branches, range, ..
+        } = &module.arena[expr_id]
+        else {
+            continue;
+        };
+
+        let mut seen: Vec<&Pattern> = Vec::new();
+        let mut has_fallback = false;
+        let mut has_literal = false;
+
+        for (pattern, branch_expr) in branches {
+            match pattern {
+                Pattern::Anything | Pattern::Name(_) => has_fallback = true,
+                Pattern::Bool(_) | Pattern::Integer(_) | Pattern::String(_) | Pattern::Char(_) => {
+                    has_literal = true;
+                    if seen.contains(&pattern) {
+                        if let Some(branch_range) = module.arena.range(*branch_expr) {
+                            literal_cases.unreachable.push(branch_range.clone());
+                        }
+                    } else {
+                        seen.push(pattern);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let covers_bools =
+            seen.contains(&&Pattern::Bool(true)) && seen.contains(&&Pattern::Bool(false));
+
+        if has_literal && !has_fallback && !covers_bools {
+            literal_cases.missing_fallback.push(range.clone());
+        }
+    }
+
+    literal_cases
+}
+
+/* Collects the case expressions within the expression, outer ones before the ones they contain */
+fn find_cases(expr_id: ExprId, arena: &Arena, cases: &mut Vec<ExprId>) {
+    match &arena[expr_id] {
+        Expr::Bool(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::String(_)
+        | Expr::Char(_)
+        | Expr::Unit
+        | Expr::Constructor(_)
+        | Expr::VarName(_)
+        | Expr::LocalVar { .. } => {}
+        Expr::List(items) | Expr::Tuple(items) => {
+            for item in items {
+                find_cases(*item, arena, cases);
+            }
+        }
+        Expr::Record(fields) => {
+            for (_name, field) in fields {
+                find_cases(*field, arena, cases);
+            }
+        }
+        Expr::BinOp { .. } => {
+            for operand in arena.operator_tree(expr_id) {
+                if !matches!(arena[operand], Expr::BinOp { .. }) {
+                    find_cases(operand, arena, cases);
+                }
+            }
+        }
+        Expr::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            find_cases(*condition, arena, cases);
+            find_cases(*then_branch, arena, cases);
+            find_cases(*else_branch, arena, cases);
+        }
+        Expr::Case { expr, branches, .. } => {
+            cases.push(expr_id);
+            find_cases(*expr, arena, cases);
+            for (_pattern, branch_expr) in branches {
+                find_cases(*branch_expr, arena, cases);
+            }
+        }
+        Expr::Lambda { body, .. } => find_cases(*body, arena, cases),
+        Expr::Let { bindings, body } => {
+            for binding in bindings {
+                find_cases(binding.expr, arena, cases);
+            }
+            find_cases(*body, arena, cases);
+        }
+        Expr::Call { function, args } => {
+            find_cases(*function, arena, cases);
+            for arg in args {
+                find_cases(*arg, arena, cases);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+
+    fn check(source: &str) -> LiteralCases {
+        let tokens = Token::lexer(source);
+        let module = parser::parse(&mut tokens.spanned().peekable()).expect("Failed to parse");
+        check_literal_cases(&module)
+    }
+
+    #[test]
+    fn repeated_literal_is_unreachable() {
+        let source = "module Main exposing (main)
+
+main =
+    case 'b' of
+        'a' ->
+            1
+
+        'a' ->
+            2
+
+        _ ->
+            3
+";
+        let second = source.rfind("'a'").unwrap_or(0);
+        let literal_cases = check(source);
+        assert_eq!(literal_cases.unreachable.len(), 1);
+        assert_eq!(
+            literal_cases.unreachable.first(),
+            Some(&(second..second + 3))
+        );
+        assert!(literal_cases.missing_fallback.is_empty());
+    }
+
+    #[test]
+    fn literals_need_fallback() {
+        let source = "module Main exposing (main)
+
+main =
+    case \"yes\" of
+        \"yes\" ->
+            1
+
+        \"no\" ->
+            0
+";
+        let case_start = source.find("case").unwrap_or(0);
+        let of_end = source.find(" of").unwrap_or(0) + 3;
+        assert_eq!(
+            check(source).missing_fallback.first(),
+            Some(&(case_start..of_end))
+        );
+    }
+
+    #[test]
+    fn both_bools_cover_every_value() {
+        let source = "module Main exposing (main)
+
+main =
+    case True of
+        True ->
+            1
+
+        False ->
+            0
+";
+        assert_eq!(check(source), LiteralCases::default());
+    }
+}
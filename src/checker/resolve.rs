@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use crate::ast::{Arena, Expr, ExprId, Module, Stmt};
+
+/* Rewrites references to local names (function arguments & names bound by patterns) into (depth, slot) indices
+ * so that the checker and evaluator can fetch them directly from the scope stack rather than
+ * searching each scope by name. Names that don't resolve to a local scope are left alone and are
+ * looked up in the module scope & imports as before.
+ *
+ * The statements keep their expression ids, we just rewrite the entries in a copy of the arena.
+ */
+pub fn resolve_module(module: &Module) -> Module {
+    log::trace!("resolve_module: {:?}", module.name);
+    let mut arena = (*module.arena).clone();
+
+    for stmt in &module.statements {
+        resolve_statement(stmt, &mut arena);
+    }
+
+    Module {
+        name: module.name.clone(),
+        exposing: module.exposing.clone(),
+        imports: module.imports.clone(),
+        statements: module.statements.clone(),
+        arena: Arc::new(arena),
+    }
+}
+
+fn resolve_statement(stmt: &Stmt, arena: &mut Arena) {
+    match stmt {
+        Stmt::Binding { expr, .. } => resolve_expression(*expr, arena, &[]),
+        Stmt::Function { args, expr, .. } => {
+            // The slots of the argument scope are the names bound by the argument patterns in
+            // order which matches how the checker & evaluator construct the scope
+            let arg_scope = args.iter().flat_map(|arg| arg.names()).collect();
+            resolve_expression(*expr, arena, &[arg_scope])
+        }
+        Stmt::Infix { .. } | Stmt::Type { .. } | Stmt::Port { .. } => {}
+    }
+}
+
+// Scopes are ordered from outermost to innermost
+fn resolve_expression(expr_id: ExprId, arena: &mut Arena, scopes: &[Vec<String>]) {
+    match arena[expr_id].clone() {
+        Expr::Bool(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::String(_)
+        | Expr::Char(_)
+        | Expr::Unit
+        | Expr::Constructor(_)
+        | Expr::LocalVar { .. } => {}
+        Expr::List(items) | Expr::Tuple(items) => {
+            for item in items {
+                resolve_expression(item, arena, scopes);
+            }
+        }
+        Expr::Record(fields) => {
+            for (_name, field) in fields {
+                resolve_expression(field, arena, scopes);
+            }
+        }
+        Expr::BinOp { .. } => {
+            // Operators bind no names so we only need to resolve the operands of the whole tree
+            for operand in arena.operator_tree(expr_id) {
+                if !matches!(arena[operand], Expr::BinOp { .. }) {
+                    resolve_expression(operand, arena, scopes);
+                }
+            }
+        }
+        Expr::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            resolve_expression(condition, arena, scopes);
+            resolve_expression(then_branch, arena, scopes);
+            resolve_expression(else_branch, arena, scopes);
+        }
+        Expr::Case { expr, branches, .. } => {
+            resolve_expression(expr, arena, scopes);
+            // Each branch gets a scope for the names bound by its pattern
+            for (pattern, branch_expr) in branches {
+                let mut branch_scopes = scopes.to_vec();
+                branch_scopes.push(pattern.names());
+                resolve_expression(branch_expr, arena, &branch_scopes);
+            }
+        }
+        Expr::Lambda { args, body } => {
+            // The arguments get a scope in the same way as those of top level functions
+            let mut lambda_scopes = scopes.to_vec();
+            lambda_scopes.push(args.iter().flat_map(|arg| arg.names()).collect());
+            resolve_expression(body, arena, &lambda_scopes);
+        }
+        Expr::Let { bindings, body } => {
+            // The names bound by all of the patterns share one scope which is visible to every
+            // binding, so that bindings can refer to themselves & each other, and to the body
+            let mut let_scopes = scopes.to_vec();
+            let_scopes.push(
+                bindings
+                    .iter()
+                    .flat_map(|binding| binding.pattern.names())
+                    .collect(),
+            );
+            for binding in bindings {
+                resolve_expression(binding.expr, arena, &let_scopes);
+            }
+            resolve_expression(body, arena, &let_scopes);
+        }
+        Expr::Call { function, args } => {
+            resolve_expression(function, arena, scopes);
+            for arg in args {
+                resolve_expression(arg, arena, scopes);
+            }
+        }
+        Expr::VarName(name) => {
+            if let ([], [access]) = (name.modules.as_slice(), name.access.as_slice()) {
+                if let Some((depth, slot)) = find_slot(access, scopes) {
+                    arena.replace(
+                        expr_id,
+                        Expr::LocalVar {
+                            name: access.clone(),
+                            depth,
+                            slot,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn find_slot(name: &str, scopes: &[Vec<String>]) -> Option<(usize, usize)> {
+    scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+        scope
+            .iter()
+            .position(|slot_name| slot_name == name)
+            .map(|slot| (depth, slot))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+
+    fn resolved_module(source: &str) -> Module {
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        resolve_module(&parser::parse(&mut iter).expect("Failed to parse"))
+    }
+
+    fn resolved_body(source: &str, function_name: &str) -> Expr {
+        let module = resolved_module(source);
+
+        module
+            .statements
+            .iter()
+            .find_map(|stmt| match &**stmt {
+                Stmt::Function { name, expr, .. } if name.0 == function_name => {
+                    Some(module.arena[*expr].clone())
+                }
+                _ => None,
+            })
+            .expect("Failed to find function")
+    }
+
+    #[test]
+    fn arguments_resolve_to_slots() {
+        let body = resolved_body("module Main exposing (..)\nadd x y = y\n", "add");
+
+        assert!(matches!(
+            &body,
+            Expr::LocalVar { name, depth: 0, slot: 1 } if name == "y"
+        ));
+    }
+
+    #[test]
+    fn unknown_names_are_left_alone() {
+        let body = resolved_body("module Main exposing (..)\nadd x y = z\n", "add");
+
+        assert!(matches!(&body, Expr::VarName(_)));
+    }
+
+    #[test]
+    fn case_pattern_names_resolve_to_branch_scope() {
+        let source = "module Main exposing (..)\nfirst x =\n  case x of\n    a :: b -> b\n";
+        let module = resolved_module(source);
+        let body = resolved_body(source, "first");
+
+        let branch = match &body {
+            Expr::Case { branches, .. } => &module.arena[branches[0].1],
+            _ => panic!("Expected case expression"),
+        };
+
+        assert!(matches!(
+            branch,
+            Expr::LocalVar { name, depth: 0, slot: 1 } if name == "b"
+        ));
+    }
+}
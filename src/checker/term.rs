@@ -3,6 +3,7 @@ pub enum Value {
     Bool,
     Integer,
     Float,
+    Char,
     String,
 }
 
@@ -12,4 +13,59 @@ pub enum Term {
     Var(String),
     Type(String, Vec<Term>),
     Function(Box<Term>, Box<Term>),
+    // The types of a record's fields, sorted by field name so that equal records compare equal
+    Record(Vec<(String, Term)>),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Bool => write!(f, "Bool"),
+            Value::Integer => write!(f, "Int"),
+            Value::Float => write!(f, "Float"),
+            Value::Char => write!(f, "Char"),
+            Value::String => write!(f, "String"),
+        }
+    }
+}
+
+// Displays the term using Elm's type syntax, eg. "List Int -> String"
+impl std::fmt::Display for Term {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Term::Constant(value) => write!(f, "{}", value),
+            Term::Var(name) => write!(f, "{}", name),
+            Term::Type(name, items) if name == "Tuple" => {
+                let items: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "( {} )", items.join(", "))
+            }
+            Term::Type(name, args) => {
+                write!(f, "{}", name)?;
+                for arg in args {
+                    match arg {
+                        Term::Type(arg_name, arg_args)
+                            if arg_name != "Tuple" && !arg_args.is_empty() =>
+                        {
+                            write!(f, " ({})", arg)?
+                        }
+                        Term::Function(_, _) => write!(f, " ({})", arg)?,
+                        _ => write!(f, " {}", arg)?,
+                    }
+                }
+                Ok(())
+            }
+            Term::Function(from, to) => match **from {
+                Term::Function(_, _) => write!(f, "({}) -> {}", from, to),
+                _ => write!(f, "{} -> {}", from, to),
+            },
+            Term::Record(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Term::Record(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, term)| format!("{} : {}", name, term))
+                    .collect();
+                write!(f, "{{ {} }}", fields.join(", "))
+            }
+        }
+    }
 }
@@ -1,8 +1,123 @@
 use im::HashMap;
 
-use super::term::Term;
+use super::term::{Term, Value};
 
-pub type Substitutions = HashMap<String, Term>;
+/* What each type variable has been settled to so far. Persistent so that unifying can build on a
+ * set of substitutions without disturbing the one it started from.
+ */
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Substitutions(HashMap<String, Term>);
+
+impl Substitutions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Term> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, term: Term) {
+        self.0.insert(name, term);
+    }
+
+    // Returns new substitutions with the variable settled to the term
+    pub fn update(&self, name: String, term: Term) -> Self {
+        Self(self.0.update(name, term))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Term)> {
+        self.0.iter()
+    }
+
+    /* Follows a variable through the variables that it has been settled to and returns where the
+     * chain ends. A long chain of operators, like '1 + 2 + 3', settles each of their variables to
+     * the next so we follow them in a loop rather than recursing
+     */
+    pub fn resolve<'a>(&'a self, mut term: &'a Term) -> &'a Term {
+        while let Term::Var(name) = term {
+            match self.get(name) {
+                Some(sub) => term = sub,
+                None => break,
+            }
+        }
+        term
+    }
+
+    /* Replaces the variables in the term with whatever they have been unified with */
+    pub fn apply(&self, term: &Term) -> Term {
+        match term {
+            Term::Var(_) => match self.resolve(term) {
+                Term::Var(_) => self.resolve(term).clone(),
+                sub => self.apply(sub),
+            },
+            Term::Constant(_) => term.clone(),
+            Term::Type(name, args) => Term::Type(
+                name.clone(),
+                args.iter().map(|arg| self.apply(arg)).collect(),
+            ),
+            Term::Function(from, to) => {
+                Term::Function(Box::new(self.apply(from)), Box::new(self.apply(to)))
+            }
+            Term::Record(fields) => Term::Record(
+                fields
+                    .iter()
+                    .map(|(name, term)| (name.clone(), self.apply(term)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/* The innermost parts of two terms that couldn't be unified, eg. 'Int' and 'String' when unifying
+ * 'List Int' with 'List String', along with the pairs of terms that contained them, outermost
+ * first. Variables that had already been settled are substituted in.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clash {
+    pub left: Term,
+    pub right: Term,
+    pub within: Vec<(Term, Term)>,
+}
+
+// Shows constrained variables by their constraint, eg. 'number' rather than 'number-3'
+fn readable(term: &Term) -> Term {
+    match term {
+        Term::Var(name) if is_number_var(name) => Term::Var("number".to_string()),
+        Term::Var(name) if is_comparable_var(name) => Term::Var("comparable".to_string()),
+        Term::Var(_) | Term::Constant(_) => term.clone(),
+        Term::Type(name, args) => Term::Type(name.clone(), args.iter().map(readable).collect()),
+        Term::Function(from, to) => {
+            Term::Function(Box::new(readable(from)), Box::new(readable(to)))
+        }
+        Term::Record(fields) => Term::Record(
+            fields
+                .iter()
+                .map(|(name, term)| (name.clone(), readable(term)))
+                .collect(),
+        ),
+    }
+}
+
+impl std::fmt::Display for Clash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} doesn't match {}",
+            readable(&self.left),
+            readable(&self.right)
+        )?;
+        if let Some((left, right)) = self.within.first() {
+            write!(
+                f,
+                "\n\nin:\n\n    {}\n\nand:\n\n    {}",
+                readable(left),
+                readable(right)
+            )?;
+        }
+        Ok(())
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
@@ -11,8 +126,17 @@ pub enum Error {
 }
 
 pub fn unify(x: &Term, y: &Term, subs: &Substitutions) -> Result<Substitutions, Error> {
+    let (x, y) = (subs.resolve(x), subs.resolve(y));
     if x == y {
         Ok(subs.clone())
+    } else if let (Term::Var(x_name), Term::Var(y_name)) = (x, y) {
+        // Bind the less constrained variable to the more constrained one so that we don't lose
+        // the constraint
+        if constraint_rank(x_name) > constraint_rank(y_name) {
+            unify_variable(y_name, y, x, subs)
+        } else {
+            unify_variable(x_name, x, y, subs)
+        }
     } else if let Term::Var(name) = x {
         unify_variable(name, x, y, subs)
     } else if let Term::Var(name) = y {
@@ -39,11 +163,27 @@ pub fn unify(x: &Term, y: &Term, subs: &Substitutions) -> Result<Substitutions,
                     Ok(subs)
                 }
             }
+            (Term::Record(fields_1), Term::Record(fields_2)) if same_fields(fields_1, fields_2) => {
+                let mut subs = subs.clone();
+                for ((_, x_1), (_, y_1)) in fields_1.iter().zip(fields_2.iter()) {
+                    subs = unify(x_1, y_1, &subs)?;
+                }
+                Ok(subs)
+            }
             _ => Err(Error::FailedToUnify(format!("{:?}", x), format!("{:?}", y))),
         }
     }
 }
 
+/* Records only unify when they have exactly the same fields */
+fn same_fields(fields_1: &[(String, Term)], fields_2: &[(String, Term)]) -> bool {
+    fields_1.len() == fields_2.len()
+        && fields_1
+            .iter()
+            .zip(fields_2.iter())
+            .all(|((name_1, _), (name_2, _))| name_1 == name_2)
+}
+
 fn unify_variable(
     v_name: &str,
     v: &Term,
@@ -60,29 +200,186 @@ fn unify_variable(
         }
     }
 
+    if is_number_var(v_name) {
+        match x {
+            Term::Constant(Value::Integer) | Term::Constant(Value::Float) => {}
+            Term::Type(x_name, args)
+                if args.is_empty() && is_rigid(x_name) && is_number_var(x_name) => {}
+            Term::Var(x_name) if !is_number_var(x_name) => {
+                return Ok(subs.update(x_name.to_string(), v.clone()))
+            }
+            Term::Var(_) => {}
+            _ => {
+                return Err(Error::FailedToUnify(
+                    "number".to_string(),
+                    format!("{:?}", x),
+                ))
+            }
+        }
+    }
+
+    if is_comparable_var(v_name) {
+        match x {
+            Term::Var(x_name) if constraint_rank(x_name) == 0 => {
+                return Ok(subs.update(x_name.to_string(), v.clone()))
+            }
+            _ if is_comparable(x) => {}
+            _ => {
+                return Err(Error::FailedToUnify(
+                    "comparable".to_string(),
+                    format!("{:?}", x),
+                ))
+            }
+        }
+    }
+
     Ok(subs.update(v_name.to_string(), x.clone()))
 }
 
+/* Numbers can only be Ints or Floats so they are more constrained than comparables, which can be
+ * any of the types that compare can order
+ */
+fn constraint_rank(name: &str) -> u8 {
+    if is_number_var(name) {
+        2
+    } else if is_comparable_var(name) {
+        1
+    } else {
+        0
+    }
+}
+
+/* Variables named 'number' stand for either an Int or a Float, like the constrained type variables
+ * in Elm. Integer literals are given one of these so that they can be used as Floats.
+ */
+pub fn is_number_var(name: &str) -> bool {
+    name.starts_with("number")
+}
+
+/* Variables named 'comparable' stand for the types that can be ordered: Ints, Floats, Chars,
+ * Strings and Lists or Tuples of them.
+ */
+pub fn is_comparable_var(name: &str) -> bool {
+    name.starts_with("comparable")
+}
+
+/* The variables of an annotation are rigid while the annotated body is checked, as the body has to
+ * work for whatever type they stand for. They're kept as types named after the variable, which
+ * can't clash with other types as those start with a capital, so that they only match themselves.
+ */
+pub fn is_rigid(name: &str) -> bool {
+    name.starts_with(|char: char| char.is_lowercase())
+}
+
+/* Whether the term can be ordered. Variables inside Lists & Tuples aren't constrained any further
+ * so a list of something still unknown is accepted
+ */
+fn is_comparable(term: &Term) -> bool {
+    match term {
+        Term::Constant(Value::Bool) => false,
+        Term::Constant(_) | Term::Var(_) => true,
+        Term::Type(name, args) if args.is_empty() && is_rigid(name) => {
+            is_number_var(name) || is_comparable_var(name)
+        }
+        Term::Type(name, args) if name == "List" || name == "Tuple" => {
+            args.iter().all(is_comparable)
+        }
+        Term::Type(_, _) | Term::Function(_, _) | Term::Record(_) => false,
+    }
+}
+
+/* Unifies the terms like 'unify' but on failure works out which parts of them clashed so that
+ * errors and editors can point at the actual difference rather than the whole terms
+ */
+pub fn explain(x: &Term, y: &Term, subs: &Substitutions) -> Result<Substitutions, Clash> {
+    unify(x, y, subs).map_err(|_| find_clash(x, y, subs, vec![]))
+}
+
+fn find_clash(x: &Term, y: &Term, subs: &Substitutions, mut within: Vec<(Term, Term)>) -> Clash {
+    let x = subs.apply(x);
+    let y = subs.apply(y);
+
+    let parts = match (&x, &y) {
+        (Term::Function(x_from, x_to), Term::Function(y_from, y_to)) => vec![
+            ((**x_from).clone(), (**y_from).clone()),
+            ((**x_to).clone(), (**y_to).clone()),
+        ],
+        (Term::Type(x_name, x_args), Term::Type(y_name, y_args))
+            if x_name == y_name && x_args.len() == y_args.len() =>
+        {
+            x_args.iter().cloned().zip(y_args.iter().cloned()).collect()
+        }
+        (Term::Record(x_fields), Term::Record(y_fields)) if same_fields(x_fields, y_fields) => {
+            x_fields
+                .iter()
+                .zip(y_fields.iter())
+                .map(|((_, x_term), (_, y_term))| (x_term.clone(), y_term.clone()))
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    // Unify the parts in order, as 'unify' does, and look inside the first one that fails
+    let mut subs = subs.clone();
+    for (x_part, y_part) in parts {
+        match unify(&x_part, &y_part, &subs) {
+            Ok(next) => subs = next,
+            Err(_) => {
+                within.push((x, y));
+                return find_clash(&x_part, &y_part, &subs, within);
+            }
+        }
+    }
+
+    Clash {
+        left: x,
+        right: y,
+        within,
+    }
+}
+
+/* Integer literals are Ints unless something makes them Floats, so any number variables that are
+ * still unresolved once we've seen everything become Ints
+ */
+pub fn default_numbers(term: &Term) -> Term {
+    match term {
+        Term::Var(name) if is_number_var(name) => Term::Constant(Value::Integer),
+        Term::Var(_) | Term::Constant(_) => term.clone(),
+        Term::Type(name, args) => {
+            Term::Type(name.clone(), args.iter().map(default_numbers).collect())
+        }
+        Term::Function(from, to) => Term::Function(
+            Box::new(default_numbers(from)),
+            Box::new(default_numbers(to)),
+        ),
+        Term::Record(fields) => Term::Record(
+            fields
+                .iter()
+                .map(|(name, term)| (name.clone(), default_numbers(term)))
+                .collect(),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::super::term::Value;
     use super::*;
 
-    fn test_unification<'a, 'src>(
+    fn test_unification<'src>(
         x: &'src Term,
         y: &'src Term,
-        mut subs: &'a Substitutions,
+        subs: &Substitutions,
     ) -> Result<Substitutions, Error> {
-        unify(&x, &y, &mut subs)
+        unify(x, y, subs)
     }
 
     #[test]
     fn conflicting_constants() {
-        let mut subs = Substitutions::new();
+        let subs = Substitutions::new();
         let result = test_unification(
             &Term::Constant(Value::String),
             &Term::Constant(Value::Integer),
-            &mut subs,
+            &subs,
         );
 
         assert_eq!(
@@ -97,8 +394,8 @@ mod test {
     #[test]
     fn constant_and_var() {
         let var = Term::Var("a".to_string());
-        let mut subs = Substitutions::new();
-        let result = test_unification(&Term::Constant(Value::String), &var, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&Term::Constant(Value::String), &var, &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("a".to_string(), Term::Constant(Value::String));
@@ -108,8 +405,8 @@ mod test {
     #[test]
     fn var_and_constant() {
         let var = Term::Var("a".to_string());
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var, &Term::Constant(Value::String), &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var, &Term::Constant(Value::String), &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("a".to_string(), Term::Constant(Value::String));
@@ -120,8 +417,8 @@ mod test {
     fn var_and_var() {
         let var_a = Term::Var("a".to_string());
         let var_b = Term::Var("b".to_string());
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("a".to_string(), var_b);
@@ -135,8 +432,8 @@ mod test {
             Box::new(Term::Constant(Value::String)),
             Box::new(Term::Constant(Value::String)),
         );
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("a".to_string(), var_b);
@@ -155,8 +452,8 @@ mod test {
             Box::new(Term::Constant(Value::Integer)),
         );
 
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let expected_subs = Substitutions::new();
         assert_eq!(result, Ok(expected_subs));
@@ -174,8 +471,8 @@ mod test {
             Box::new(Term::Constant(Value::String)),
         );
 
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("a".to_string(), Term::Constant(Value::String));
@@ -187,8 +484,8 @@ mod test {
         let var_a = Term::Type("List".to_string(), vec![Term::Constant(Value::String)]);
         let var_b = Term::Type("List".to_string(), vec![Term::Constant(Value::String)]);
 
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let expected_subs = Substitutions::new();
         assert_eq!(result, Ok(expected_subs));
@@ -202,8 +499,8 @@ mod test {
             vec![Term::Constant(Value::String), Term::Constant(Value::String)],
         );
 
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         assert_eq!(
             result,
@@ -219,11 +516,131 @@ mod test {
         let var_a = Term::Type("List".to_string(), vec![Term::Constant(Value::String)]);
         let var_b = Term::Type("List".to_string(), vec![Term::Var("var-1".to_string())]);
 
-        let mut subs = Substitutions::new();
-        let result = test_unification(&var_a, &var_b, &mut subs);
+        let subs = Substitutions::new();
+        let result = test_unification(&var_a, &var_b, &subs);
 
         let mut expected_subs = Substitutions::new();
         expected_subs.insert("var-1".to_string(), Term::Constant(Value::String));
         assert_eq!(result, Ok(expected_subs));
     }
+
+    #[test]
+    fn substitute_chained_vars() {
+        let term = Term::Function(
+            Box::new(Term::Var("a".to_string())),
+            Box::new(Term::Type(
+                "List".to_string(),
+                vec![Term::Var("b".to_string())],
+            )),
+        );
+
+        let mut subs = Substitutions::new();
+        subs.insert("a".to_string(), Term::Var("b".to_string()));
+        subs.insert("b".to_string(), Term::Constant(Value::Integer));
+
+        assert_eq!(
+            subs.apply(&term),
+            Term::Function(
+                Box::new(Term::Constant(Value::Integer)),
+                Box::new(Term::Type(
+                    "List".to_string(),
+                    vec![Term::Constant(Value::Integer)]
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn number_var_and_float() {
+        let var = Term::Var("number-1".to_string());
+        let subs = Substitutions::new();
+        let result = test_unification(&var, &Term::Constant(Value::Float), &subs);
+
+        let mut expected_subs = Substitutions::new();
+        expected_subs.insert("number-1".to_string(), Term::Constant(Value::Float));
+        assert_eq!(result, Ok(expected_subs));
+    }
+
+    #[test]
+    fn number_var_and_string() {
+        let var = Term::Var("number-1".to_string());
+        let subs = Substitutions::new();
+        let result = test_unification(&var, &Term::Constant(Value::String), &subs);
+
+        assert_eq!(
+            result,
+            Err(Error::FailedToUnify(
+                "number".to_string(),
+                "Constant(String)".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn number_var_keeps_constraint() {
+        let var_a = Term::Var("a".to_string());
+        let number = Term::Var("number-1".to_string());
+        let subs = Substitutions::new();
+        let subs = test_unification(&number, &var_a, &subs).unwrap();
+        let result = test_unification(&var_a, &Term::Constant(Value::String), &subs);
+
+        assert_eq!(
+            result,
+            Err(Error::FailedToUnify(
+                "number".to_string(),
+                "Constant(String)".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn comparable_var_and_bool() {
+        let var = Term::Var("comparable-1".to_string());
+        let subs = Substitutions::new();
+        let result = test_unification(&var, &Term::Constant(Value::Bool), &subs);
+
+        assert_eq!(
+            result,
+            Err(Error::FailedToUnify(
+                "comparable".to_string(),
+                "Constant(Bool)".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn comparable_var_and_list_of_strings() {
+        let var = Term::Var("comparable-1".to_string());
+        let list = Term::Type("List".to_string(), vec![Term::Constant(Value::String)]);
+        let subs = Substitutions::new();
+        let result = test_unification(&var, &list, &subs);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn explain_finds_the_clashing_parts() {
+        let list_of = |value| Term::Type("List".to_string(), vec![Term::Constant(value)]);
+        let x = Term::Function(
+            Box::new(list_of(Value::Integer)),
+            Box::new(Term::Var("a".to_string())),
+        );
+        let y = Term::Function(
+            Box::new(list_of(Value::String)),
+            Box::new(Term::Constant(Value::Bool)),
+        );
+        let result = explain(&x, &y, &Substitutions::new());
+
+        assert_eq!(
+            result,
+            Err(Clash {
+                left: Term::Constant(Value::Integer),
+                right: Term::Constant(Value::String),
+                within: vec![
+                    (x.clone(), y.clone()),
+                    (list_of(Value::Integer), list_of(Value::String))
+                ],
+            })
+        );
+    }
 }
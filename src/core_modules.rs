@@ -0,0 +1,29 @@
+/* The core modules compiled into the library for builds without filesystem access, like the
+ * WebAssembly build. Keyed by the module name with '/' separators to match the paths in core/.
+ */
+const MODULES: [(&str, &str); 17] = [
+    ("Basics", include_str!("../core/Basics.elm")),
+    ("Bytes", include_str!("../core/Bytes.elm")),
+    ("Csv", include_str!("../core/Csv.elm")),
+    ("Debug", include_str!("../core/Debug.elm")),
+    ("Env", include_str!("../core/Env.elm")),
+    ("Expect", include_str!("../core/Expect.elm")),
+    ("File", include_str!("../core/File.elm")),
+    ("Http", include_str!("../core/Http.elm")),
+    ("List", include_str!("../core/List.elm")),
+    ("Maybe", include_str!("../core/Maybe.elm")),
+    ("Platform", include_str!("../core/Platform.elm")),
+    ("Process", include_str!("../core/Process.elm")),
+    ("Random", include_str!("../core/Random.elm")),
+    ("Result", include_str!("../core/Result.elm")),
+    ("String", include_str!("../core/String.elm")),
+    ("Test", include_str!("../core/Test.elm")),
+    ("Time", include_str!("../core/Time.elm")),
+];
+
+pub fn source(name: &str) -> Option<&'static str> {
+    MODULES
+        .iter()
+        .find(|(module_name, _)| *module_name == name)
+        .map(|(_, source)| *source)
+}
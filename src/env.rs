@@ -1,15 +1,23 @@
-use std::collections::HashMap;
-use std::io::Read;
-use std::path::PathBuf;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::Arc;
 
 use im::vector;
 use logos::Logos;
+#[cfg(feature = "fs")]
+use rayon::prelude::*;
 
-use super::ast::{self, Associativity, Module, Stmt};
+use super::ast::{self, Arena, Associativity, Module, Stmt};
 use super::bindings::Binding;
 use super::builtins;
-use super::lexer::Token;
+use super::checker::resolve;
+use super::evaluator::values;
+use super::lexer::{Range, Token};
+use super::optimize;
+#[cfg(feature = "fs")]
+use super::parse_cache;
 use super::parser;
 use super::project;
 
@@ -22,42 +30,226 @@ pub struct Operator {
     pub binding: Binding,
 }
 
+/* A constructor of a custom type along with the details of the type it belongs to so that the
+ * checker can work out the type of the values it creates
+ */
+#[derive(Debug, Clone)]
+pub struct Constructor {
+    pub name: String,
+    pub type_name: String,
+    pub type_args: Vec<ast::LowerName>,
+    pub args: Vec<ast::Type>,
+}
+
 pub type Bindings = HashMap<ast::QualifiedLowerName, Binding>;
 type Operators = HashMap<String, Operator>;
+type Constructors = HashMap<String, Constructor>;
 
 #[derive(Debug, Clone)]
 pub struct ModuleImport {
     pub module_scope: Rc<ModuleScope>,
+    // The name given with 'import ... as ...' which then replaces the module name in qualified names
+    pub alias: Option<String>,
     pub exposing: Option<ast::Exposing>,
 }
 
 impl ModuleImport {
-    pub fn get_binding(&self, target_name: &ast::QualifiedLowerName) -> Option<Binding> {
+    /* The module name that qualified references to the import's names have to use */
+    pub fn qualifier(&self) -> ast::ModuleName {
+        match &self.alias {
+            Some(alias) => vec![alias.clone()],
+            None => self.module_scope.name.clone(),
+        }
+    }
+
+    /* Whether the import makes the value name available without the module name, either by
+      listing it or with 'exposing (..)'
+    */
+    pub fn exposes_unqualified(&self, name: &str) -> bool {
+        match &self.exposing {
+            Some(ast::Exposing::All) => true,
+            Some(ast::Exposing::List(details)) => details.iter().any(
+                |detail| matches!(detail, ast::ExposingDetail::Name(exposed) if exposed == name),
+            ),
+            None => false,
+        }
+    }
+
+    pub fn get_operator(&self, target_name: &str) -> Option<(Operator, Environment)> {
         log::trace!(
-            "ModuleImport:get_binding: {:?} from {:?}",
+            "ModuleImport:get_operator: {} from {:?}",
             &target_name,
             &self.module_scope.name
         );
 
-        if target_name.modules == self.module_scope.name {
-            // TODO: Check that target name is in exposing
-            self.module_scope.get_binding(&target_name.without_module())
-        } else if target_name.modules.is_empty() {
-            // TODO: Check that target name is in exposing
-            self.module_scope.get_binding(target_name)
+        // Operators can't be qualified so they are only available when the import exposes them
+        let exposed = match &self.exposing {
+            Some(ast::Exposing::All) => true,
+            Some(ast::Exposing::List(details)) => details.iter().any(|detail| {
+                matches!(detail, ast::ExposingDetail::Operator(exposed) if exposed == target_name)
+            }),
+            None => false,
+        };
+
+        if exposed {
+            self.module_scope.get_operator(target_name)
         } else {
             None
         }
     }
 
-    pub fn get_operator(&self, target_name: &str) -> Option<Operator> {
+    /* Qualified constructors have to use the import's qualifier and the module has to expose the
+      constructor's type with '(..)'. Unqualified constructors also need the import to expose the
+      type with '(..)'
+    */
+    pub fn get_constructor(
+        &self,
+        target_name: &ast::QualifiedUpperName,
+    ) -> Result<Constructor, GetBindingError> {
         log::trace!(
-            "ModuleImport:get_operator: {} from {:?}",
+            "ModuleImport:get_constructor: {:?} from {:?}",
             &target_name,
             &self.module_scope.name
         );
 
-        self.module_scope.get_operator(target_name)
+        let qualified = !target_name.modules.is_empty();
+        if qualified && target_name.modules != self.qualifier() {
+            return Err(GetBindingError::Unknown);
+        }
+
+        let constructor = self
+            .module_scope
+            .local_scope
+            .constructors
+            .get(&target_name.access)
+            .ok_or(GetBindingError::Unknown)?;
+
+        if !self.module_scope.exposes_constructor(constructor) {
+            return Err(if qualified {
+                GetBindingError::NotExposed {
+                    module: self.module_scope.name.join("."),
+                    name: target_name.access.clone(),
+                }
+            } else {
+                GetBindingError::Unknown
+            });
+        }
+
+        let imported = qualified
+            || match &self.exposing {
+                Some(ast::Exposing::All) => true,
+                Some(ast::Exposing::List(details)) => details.iter().any(|detail| {
+                    matches!(
+                        detail,
+                        ast::ExposingDetail::Type(name, ast::TypeState::Open)
+                            if name.0 == constructor.type_name
+                    )
+                }),
+                None => false,
+            };
+
+        if imported {
+            Ok(constructor.clone())
+        } else {
+            Err(GetBindingError::Unknown)
+        }
+    }
+}
+
+/* Every name that a module can reach through its imports, both qualified & unqualified, along with
+ * the scope of the module that defines it. Built once with the module's scope so that looking up a
+ * name doesn't search through each of the imports every time.
+ */
+#[derive(Debug, Default)]
+pub struct ImportedNames {
+    exposed: HashMap<ast::QualifiedLowerName, (Binding, Rc<ModuleScope>)>,
+    // The names that an imported module defines but doesn't expose, with the name of that module
+    hidden: HashMap<ast::QualifiedLowerName, String>,
+    // The functions that the host program embedding erm has registered in the settings
+    host_functions: project::HostFunctions,
+}
+
+impl ImportedNames {
+    /* Earlier imports take precedence over later ones for unqualified names, and a name that one
+      module hides is still found if another module exposes it
+    */
+    fn from_imports(
+        module_imports: &im::Vector<ModuleImport>,
+        host_functions: &project::HostFunctions,
+    ) -> Self {
+        log::trace!("ImportedNames::from_imports");
+        let mut imported_names = Self {
+            host_functions: host_functions.clone(),
+            ..Self::default()
+        };
+
+        for module_import in module_imports {
+            let module_scope = &module_import.module_scope;
+            for (name, binding) in &module_scope.local_scope.bindings {
+                let qualified = ast::QualifiedLowerName {
+                    modules: module_import.qualifier(),
+                    access: name.access.clone(),
+                };
+
+                let (exposed, unqualified) = match name.access.first() {
+                    Some(access) => (
+                        module_scope.exposes(access),
+                        module_import.exposes_unqualified(access),
+                    ),
+                    None => (false, false),
+                };
+
+                // Names are always available qualified with the module name and only available
+                // unqualified when the import exposes them
+                let names = std::iter::once(qualified).chain(unqualified.then(|| name.clone()));
+
+                for name in names {
+                    if exposed {
+                        imported_names
+                            .exposed
+                            .entry(name)
+                            .or_insert_with(|| (binding.clone(), module_scope.clone()));
+                    } else {
+                        imported_names
+                            .hidden
+                            .entry(name)
+                            .or_insert_with(|| module_scope.name.join("."));
+                    }
+                }
+            }
+        }
+
+        imported_names
+    }
+
+    /* The exposed names along with the binding & the name of the module that defines it */
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (&ast::QualifiedLowerName, &Binding, &ast::ModuleName)> {
+        self.exposed
+            .iter()
+            .map(|(name, (binding, module_scope))| (name, binding, &module_scope.name))
+    }
+
+    pub fn get(
+        &self,
+        target_name: &ast::QualifiedLowerName,
+    ) -> Result<FoundBinding, GetBindingError> {
+        log::trace!("ImportedNames::get {:?}", target_name);
+        if let Some((binding, module_scope)) = self.exposed.get(target_name) {
+            return Ok(FoundBinding::WithEnv(
+                binding.clone(),
+                Box::new(module_scope.environment()),
+            ));
+        }
+
+        match (self.hidden.get(target_name), target_name.access.first()) {
+            (Some(module), Some(name)) => Err(GetBindingError::NotExposed {
+                module: module.clone(),
+                name: name.clone(),
+            }),
+            _ => Err(GetBindingError::Unknown),
+        }
     }
 }
 
@@ -66,91 +258,183 @@ pub enum Error {
     UnableToFindModule(String),
     FailedToRead(PathBuf),
     FailedToParse(PathBuf, parser::Error),
+    FailedToWrite(PathBuf),
+    // Modules that import each other, in the order that the imports lead from one to the next and
+    // back to the first
+    ImportCycle(Vec<String>),
 }
 
 #[derive(Debug)]
 pub struct Scope {
     pub bindings: Bindings,
+    // Local bindings in the order that the resolver assigned slots to them so that resolved
+    // references can be looked up by index
+    pub slots: Vec<Binding>,
     pub operators: Operators,
+    pub constructors: Constructors,
+    // Values of the bindings in this scope that have already been evaluated. Bindings without
+    // arguments are pure so we only need to evaluate each one once
+    pub evaluated: RefCell<HashMap<ast::QualifiedLowerName, values::Value>>,
 }
 
 impl Scope {
-    pub fn from_bindings(bindings: Bindings) -> Self {
-        log::trace!("from_bindings");
+    pub fn from_slots(slots: Vec<(String, Binding)>) -> Self {
+        log::trace!("from_slots");
+        let bindings = slots
+            .iter()
+            .map(|(name, binding)| {
+                (
+                    ast::QualifiedLowerName::simple(name.to_string()),
+                    binding.clone(),
+                )
+            })
+            .collect();
+
         Scope {
             bindings,
+            slots: slots.into_iter().map(|(_, binding)| binding).collect(),
             operators: HashMap::new(),
+            constructors: HashMap::new(),
+            evaluated: RefCell::new(HashMap::new()),
         }
     }
 }
 
+/* Where a top level value, type or constructor is defined. The range is of the name in the source
+ * of the defining module, when the parser recorded one
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub name: String,
+    pub kind: ast::DefinitionKind,
+    pub module: ast::ModuleName,
+    pub range: Option<Range>,
+}
+
 #[derive(Debug)]
 pub struct ModuleScope {
     pub name: ast::ModuleName,
     pub module_imports: im::Vector<ModuleImport>,
+    pub imported_names: Rc<ImportedNames>,
     pub local_scope: Rc<Scope>,
     pub exposing: ast::Exposing,
+    pub arena: Arc<Arena>,
+    // The names declared by the module itself
+    pub definitions: Vec<Definition>,
 }
 
 impl ModuleScope {
-    pub fn get_binding(&self, target_name: &ast::QualifiedLowerName) -> Option<Binding> {
-        log::trace!(
-            "ModuleScope:get_binding: {:?} from {:?}",
-            &target_name,
-            &self.name
-        );
+    /* Whether the module's exposing list includes the given value name */
+    pub fn exposes(&self, name: &str) -> bool {
+        match &self.exposing {
+            ast::Exposing::All => true,
+            ast::Exposing::List(details) => details.iter().any(
+                |detail| matches!(detail, ast::ExposingDetail::Name(exposed) if exposed == name),
+            ),
+        }
+    }
 
-        // If there is no module part of the lower name then we look in the main scope for the
-        // target name
-        if target_name.modules.is_empty() {
-            if let Some(value) = self.local_scope.bindings.get(target_name) {
-                return Some(value.clone());
-            }
-        } else {
-            // If there is a module part then we find the corresponding module and then look in the
-            // main scope of that module for just the 'access' part of the lower name
-            if let Some(import) = &self
-                .module_imports
-                .iter()
-                .find(|import| import.module_scope.name == target_name.modules)
-            {
-                if let Some(value) = &import
-                    .module_scope
-                    .local_scope
-                    .bindings
-                    .get(&target_name.without_module())
-                {
-                    return Some((*value).clone());
-                }
-            }
+    /* Whether the module's exposing list makes the definition available to the modules that import
+      it. Constructors are only exposed along with their type with 'Type(..)'
+    */
+    fn exposes_definition(&self, definition: &Definition) -> bool {
+        let details = match &self.exposing {
+            ast::Exposing::All => return true,
+            ast::Exposing::List(details) => details,
+        };
+
+        match definition.kind {
+            ast::DefinitionKind::Value => self.exposes(&definition.name),
+            ast::DefinitionKind::Type => details.iter().any(|detail| {
+                matches!(detail, ast::ExposingDetail::Type(name, _) if name.0 == definition.name)
+            }),
+            ast::DefinitionKind::Constructor => self
+                .local_scope
+                .constructors
+                .get(&definition.name)
+                .is_some_and(|constructor| self.exposes_constructor(constructor)),
         }
+    }
 
-        // TODO: Filter by exposing
+    /* Constructors are exposed along with their type by listing it as 'Type(..)' */
+    pub fn exposes_constructor(&self, constructor: &Constructor) -> bool {
+        match &self.exposing {
+            ast::Exposing::All => true,
+            ast::Exposing::List(details) => details.iter().any(|detail| {
+                matches!(
+                    detail,
+                    ast::ExposingDetail::Type(name, ast::TypeState::Open)
+                        if name.0 == constructor.type_name
+                )
+            }),
+        }
+    }
+
+    /* Every top level name that the module can refer to and where it is defined. The module's own
+      declarations come first, followed by the names exposed by each of its imports in the order
+      that they are imported. This is the table behind 'erm defs' for editors to jump to a
+      definition
+    */
+    pub fn definitions_in_scope(&self) -> Vec<Definition> {
+        log::trace!("definitions_in_scope: {:?}", &self.name);
+        let mut definitions = self.definitions.clone();
+        let mut seen_modules = HashSet::new();
+
+        for module_import in &self.module_imports {
+            let module_scope = &module_import.module_scope;
+            // The prelude imports can be repeated by the module's own imports
+            if !seen_modules.insert(module_scope.name.clone()) {
+                continue;
+            }
+
+            definitions.extend(
+                module_scope
+                    .definitions
+                    .iter()
+                    .filter(|definition| module_scope.exposes_definition(definition))
+                    .cloned(),
+            );
+        }
 
-        None
+        definitions
     }
 
-    pub fn get_operator(&self, target_name: &str) -> Option<Operator> {
+    /* Returns the operator, if the module defines & exposes it, along with the environment of the
+      module so that the operator's binding can be checked & evaluated against the right arena.
+      Operators from the module's own imports aren't passed on as Elm modules can't re-export them
+    */
+    pub fn get_operator(&self, target_name: &str) -> Option<(Operator, Environment)> {
         log::trace!(
             "ModuleScope:get_operator: {} from {:?}",
             &target_name,
             &self.name
         );
 
-        // TODO: Filter by exposing
-        if let Some(value) = self.local_scope.operators.get(target_name) {
-            return Some(value.clone());
-        }
+        let exposed = match &self.exposing {
+            ast::Exposing::All => true,
+            ast::Exposing::List(details) => details.iter().any(|detail| {
+                matches!(detail, ast::ExposingDetail::Operator(exposed) if exposed == target_name)
+            }),
+        };
 
-        // Backwards through list to check lowest imports first
-        for import in self.module_imports.iter().rev() {
-            // TODO: Filter by exposing
-            if let Some(value) = &import.module_scope.get_operator(target_name) {
-                return Some(value.clone());
-            }
+        if !exposed {
+            return None;
         }
 
-        None
+        self.local_scope
+            .operators
+            .get(target_name)
+            .map(|value| (value.clone(), self.environment()))
+    }
+
+    /* Returns an environment for evaluating the module's own bindings */
+    pub fn environment(&self) -> Environment {
+        Environment {
+            module_imports: self.module_imports.clone(),
+            imported_names: self.imported_names.clone(),
+            local_scopes: vector![self.local_scope.clone()],
+            arena: self.arena.clone(),
+        }
     }
 
     pub fn from_module(
@@ -158,55 +442,71 @@ impl ModuleScope {
         settings: &project::Settings,
     ) -> Result<ModuleScope, Error> {
         log::trace!("from_module {:?}", &module.name);
+        let loaded_modules = load_imports(module, settings)?;
+        let mut built = ScopeBuild::default();
+        Self::from_loaded_module(
+            &resolve::resolve_module(module),
+            &loaded_modules,
+            &settings.host_functions,
+            &mut built,
+        )
+    }
+
+    /* Builds the scope for the resolved module using the already parsed & resolved modules for
+      its imports. This step is single threaded as the scopes are shared via Rc. Each imported
+      module's scope is built once and then shared by every module that imports it.
+    */
+    fn from_loaded_module(
+        module: &Module,
+        loaded_modules: &LoadedModules,
+        host_functions: &project::HostFunctions,
+        built: &mut ScopeBuild,
+    ) -> Result<ModuleScope, Error> {
+        log::trace!("from_loaded_module {:?}", &module.name);
+        built.enter(&module.name)?;
+
         let module_imports: im::Vector<ModuleImport> = module
             .imports
             .iter()
             .map(|import| {
-                // Read & parse import.name
-                let mut filenames: Vec<(PathBuf, bool)> = settings
-                    .source_directories
-                    .iter()
-                    .map(|dir| {
-                        let mut path = dir.clone();
-                        path.push(format!("{}.elm", &import.module_name.join("/")));
-                        (path, false)
-                    })
-                    .collect();
-
-                let mut core_module_path = PathBuf::new();
-                core_module_path.push("core");
-                core_module_path.push(format!("{}.elm", &import.module_name.join("/")));
-                filenames.push((core_module_path, true));
-
-                let (filename, mut file, is_core) = filenames
-                    .into_iter()
-                    .find_map(|(path, is_core)| {
-                        std::fs::File::open(&path).ok().map(|f| (path, f, is_core))
-                    })
-                    .ok_or_else(|| Error::UnableToFindModule(import.module_name.join(".")))?;
-
-                let mut source = String::new();
-                file.read_to_string(&mut source)
-                    .map_err(|_| Error::FailedToRead(filename.clone()))?;
-
-                let tokens = Token::lexer(&source);
-                let mut iter = tokens.spanned().peekable();
-                let mut module = parser::parse(&mut iter)
-                    .map_err(|err| Error::FailedToParse(filename.clone(), err))?;
-
-                // See readme for how Elm determines when to include prelude
-                if !is_core {
-                    module = ast::with_default_imports(&module);
-                }
+                let module_scope = match built.scopes.get(&import.module_name) {
+                    Some(module_scope) => module_scope.clone(),
+                    None => {
+                        let imported_module =
+                            loaded_modules.get(&import.module_name).ok_or_else(|| {
+                                Error::UnableToFindModule(import.module_name.join("."))
+                            })?;
+                        let module_scope = Rc::new(Self::from_loaded_module(
+                            imported_module,
+                            loaded_modules,
+                            host_functions,
+                            built,
+                        )?);
+                        built
+                            .scopes
+                            .insert(import.module_name.clone(), module_scope.clone());
+                        module_scope
+                    }
+                };
 
-                // Create a scope from it maybe?
-                Self::from_module(&module, settings).map(|module_scope| ModuleImport {
-                    module_scope: Rc::new(module_scope),
+                Ok(ModuleImport {
+                    module_scope,
+                    alias: import.alias.clone(),
                     exposing: import.exposing.clone(),
                 })
             })
-            .collect::<Result<_, _>>()?;
+            .collect::<Result<_, Error>>()?;
 
+        built.leave();
+        Ok(Self::with_imports(module, module_imports, host_functions))
+    }
+
+    /* Builds the scope from the resolved module once the scopes of its imports are available */
+    fn with_imports(
+        module: &Module,
+        module_imports: im::Vector<ModuleImport>,
+        host_functions: &project::HostFunctions,
+    ) -> ModuleScope {
         let bindings: Bindings = module
             .statements
             .iter()
@@ -220,11 +520,15 @@ impl ModuleScope {
                         modules: Vec::new(),
                         access: vec![name.to_string()],
                     },
-                    Binding::UserBinding(expr.clone()),
+                    Binding::UserBinding(*expr),
                 )),
                 Stmt::Function {
                     name: ast::LowerName(name),
                     ..
+                }
+                | Stmt::Port {
+                    name: ast::LowerName(name),
+                    ..
                 } => Some((
                     ast::QualifiedLowerName {
                         modules: Vec::new(),
@@ -250,7 +554,7 @@ impl ModuleScope {
                         operator_name.to_string(),
                         Operator {
                             operator_name: operator_name.clone(),
-                            associativity: associativity.clone(),
+                            associativity: *associativity,
                             precedence: *precedence,
                             function_name: function_name.clone(),
                             // Store the binding for the operator's function along with the
@@ -264,124 +568,783 @@ impl ModuleScope {
             })
             .collect();
 
-        Ok(ModuleScope {
+        let constructors = module
+            .statements
+            .iter()
+            .flat_map(|entry| match &**entry {
+                Stmt::Type {
+                    name,
+                    args: type_args,
+                    constructors,
+                } => constructors
+                    .iter()
+                    .filter_map(|constructor| match constructor {
+                        ast::Type::UserDefined {
+                            name: constructor_name,
+                            args,
+                        } => Some((
+                            constructor_name.access.clone(),
+                            Constructor {
+                                name: constructor_name.access.clone(),
+                                type_name: name.0.clone(),
+                                type_args: type_args.clone(),
+                                args: args.clone(),
+                            },
+                        )),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            })
+            .collect();
+
+        let definitions = module
+            .statements
+            .iter()
+            .flat_map(|entry| match &**entry {
+                Stmt::Binding { name, .. }
+                | Stmt::Function { name, .. }
+                | Stmt::Port { name, .. } => vec![(ast::DefinitionKind::Value, name.0.clone())],
+                Stmt::Type {
+                    name, constructors, ..
+                } => std::iter::once((ast::DefinitionKind::Type, name.0.clone()))
+                    .chain(
+                        constructors
+                            .iter()
+                            .filter_map(|constructor| match constructor {
+                                ast::Type::UserDefined { name, .. } => {
+                                    Some((ast::DefinitionKind::Constructor, name.access.clone()))
+                                }
+                                _ => None,
+                            }),
+                    )
+                    .collect(),
+                Stmt::Infix { .. } => vec![],
+            })
+            .map(|(kind, name)| Definition {
+                range: module.arena.definition(kind, &name).cloned(),
+                name,
+                kind,
+                module: module.name.clone(),
+            })
+            .collect();
+
+        ModuleScope {
             name: module.name.clone(),
+            imported_names: Rc::new(ImportedNames::from_imports(&module_imports, host_functions)),
             module_imports,
             local_scope: Rc::new(Scope {
                 bindings,
+                slots: Vec::new(),
                 operators,
+                constructors,
+                evaluated: RefCell::new(HashMap::new()),
             }),
             exposing: module.exposing.clone(),
+            arena: module.arena.clone(),
+            definitions,
+        }
+    }
+
+    /* Like from_module but reuses the scopes of imported modules from the cache where their source
+     * hasn't changed since they were last built.
+     */
+    pub fn from_module_cached(
+        module: &Module,
+        settings: &project::Settings,
+        cache: &mut ModuleCache,
+    ) -> Result<ModuleScope, Error> {
+        log::trace!("from_module_cached {:?}", &module.name);
+        let module = &resolve::resolve_module(module);
+        let mut built = ScopeBuild::default();
+        built.enter(&module.name)?;
+        let module_imports = cache.module_imports(module, settings, &mut built)?;
+        Ok(Self::with_imports(
+            module,
+            module_imports,
+            &settings.host_functions,
+        ))
+    }
+}
+
+/* The scopes built so far while building the scope of a module, so that a module imported along
+ * several paths is only built once, along with the modules whose imports are still being built so
+ * that an import cycle is reported rather than followed forever.
+ */
+#[derive(Default)]
+struct ScopeBuild {
+    scopes: HashMap<ast::ModuleName, Rc<ModuleScope>>,
+    in_progress: Vec<ast::ModuleName>,
+}
+
+impl ScopeBuild {
+    fn enter(&mut self, module_name: &ast::ModuleName) -> Result<(), Error> {
+        if let Some(start) = self.in_progress.iter().position(|name| name == module_name) {
+            let cycle = self.in_progress[start..]
+                .iter()
+                .chain(std::iter::once(module_name))
+                .map(|name| name.join("."))
+                .collect();
+            return Err(Error::ImportCycle(cycle));
+        }
+
+        self.in_progress.push(module_name.clone());
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.in_progress.pop();
+    }
+}
+
+/* Module scopes kept between runs of the same program, for watch mode & the repl. A module's scope
+ * is only rebuilt when its source has changed or when one of the modules it imports has been
+ * rebuilt, so an edit to one module leaves the rest of the import graph alone.
+ */
+#[derive(Default)]
+pub struct ModuleCache {
+    modules: HashMap<ast::ModuleName, CachedModule>,
+    // The last source read for each module, including modules that failed to parse, so that we
+    // can tell when they've been edited
+    sources: HashMap<ast::ModuleName, String>,
+}
+
+struct CachedModule {
+    source: String,
+    module: Module,
+    scope: Rc<ModuleScope>,
+}
+
+impl ModuleCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Whether the source of any of the cached modules is different from the source on disk */
+    pub fn has_changed(&self, settings: &project::Settings) -> bool {
+        log::trace!("has_changed");
+        self.sources.iter().any(|(module_name, last_source)| {
+            read_module(module_name, settings)
+                .map_or(true, |(_filename, source, _is_core)| source != *last_source)
         })
     }
+
+    fn module_imports(
+        &mut self,
+        module: &Module,
+        settings: &project::Settings,
+        built: &mut ScopeBuild,
+    ) -> Result<im::Vector<ModuleImport>, Error> {
+        module
+            .imports
+            .iter()
+            .map(|import| {
+                self.module_scope(&import.module_name, settings, built)
+                    .map(|module_scope| ModuleImport {
+                        module_scope,
+                        alias: import.alias.clone(),
+                        exposing: import.exposing.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /* The scope of the module, read & checked against the cache at most once per build */
+    fn module_scope(
+        &mut self,
+        module_name: &ast::ModuleName,
+        settings: &project::Settings,
+        built: &mut ScopeBuild,
+    ) -> Result<Rc<ModuleScope>, Error> {
+        log::trace!("module_scope {:?}", module_name);
+        if let Some(scope) = built.scopes.get(module_name) {
+            return Ok(scope.clone());
+        }
+
+        built.enter(module_name)?;
+        let scope = self.build_module_scope(module_name, settings, built)?;
+        built.leave();
+
+        built.scopes.insert(module_name.clone(), scope.clone());
+        Ok(scope)
+    }
+
+    fn build_module_scope(
+        &mut self,
+        module_name: &ast::ModuleName,
+        settings: &project::Settings,
+        built: &mut ScopeBuild,
+    ) -> Result<Rc<ModuleScope>, Error> {
+        let (filename, source, is_core) = read_module(module_name, settings)?;
+        self.sources.insert(module_name.clone(), source.clone());
+
+        let module = match self.modules.get(module_name) {
+            Some(cached) if cached.source == source => cached.module.clone(),
+            _ => parse_module(&filename, &source, is_core, settings)?,
+        };
+
+        let module_imports = self.module_imports(&module, settings, built)?;
+
+        // The scopes of the imports are shared so the scope can be reused when they're all the same
+        if let Some(cached) = self.modules.get(module_name) {
+            let same_imports = cached.scope.module_imports.len() == module_imports.len()
+                && cached
+                    .scope
+                    .module_imports
+                    .iter()
+                    .zip(module_imports.iter())
+                    .all(|(cached, import)| Rc::ptr_eq(&cached.module_scope, &import.module_scope));
+
+            if cached.source == source && same_imports {
+                return Ok(cached.scope.clone());
+            }
+        }
+
+        let scope = Rc::new(ModuleScope::with_imports(
+            &module,
+            module_imports,
+            &settings.host_functions,
+        ));
+        self.modules.insert(
+            module_name.clone(),
+            CachedModule {
+                source,
+                module,
+                scope: scope.clone(),
+            },
+        );
+
+        Ok(scope)
+    }
 }
 
-#[derive(Debug)]
+/* Returns the path to the source file for the module along with whether or not it is one of the
+ * core modules. The project's modules are found through the module roots and then the source
+ * directories. The module order in the settings decides whether they are checked before or after
+ * the core modules. Within the core modules, the core path from the
+ * settings is checked before the core modules that come with erm. A module that is found in both
+ * the source directories & the core modules is reported in the warnings.
+ */
+#[cfg(feature = "fs")]
+pub fn find_module_path(
+    module_name: &ast::ModuleName,
+    settings: &project::Settings,
+) -> Option<(PathBuf, bool)> {
+    log::trace!("find_module_path {:?}", module_name);
+    let filename = format!("{}.elm", &module_name.join("/"));
+
+    let source_path = module_root_paths(module_name, settings)
+        .into_iter()
+        .chain(
+            settings
+                .source_directories
+                .iter()
+                .map(|dir| dir.join(&filename)),
+        )
+        .find(|path| path.is_file());
+
+    let core_path = settings
+        .core_path
+        .iter()
+        .map(|dir| dir.join(&filename))
+        .chain(std::iter::once(PathBuf::from("core").join(&filename)))
+        .find(|path| path.is_file());
+
+    match (source_path, core_path) {
+        (Some(source_path), Some(core_path)) => {
+            let (used, shadowed, is_core) = match settings.module_order {
+                project::ModuleOrder::SourcesFirst => (source_path, core_path, false),
+                project::ModuleOrder::CoreFirst => (core_path, source_path, true),
+            };
+            settings.warnings.add(project::Warning::ShadowedModule {
+                module: module_name.join("."),
+                used: used.clone(),
+                shadowed,
+            });
+            Some((used, is_core))
+        }
+        (Some(source_path), None) => Some((source_path, false)),
+        (None, Some(core_path)) => Some((core_path, true)),
+        (None, None) => None,
+    }
+}
+
+/* The paths that the module roots in the settings give for the module, with the longest matching
+ * prefix first
+ */
+#[cfg(feature = "fs")]
+fn module_root_paths(module_name: &ast::ModuleName, settings: &project::Settings) -> Vec<PathBuf> {
+    let mut roots: Vec<(usize, PathBuf)> = settings
+        .module_roots
+        .iter()
+        .filter_map(|(prefix, dir)| {
+            let prefix: Vec<&str> = prefix.split('.').collect();
+            let matches = module_name.len() > prefix.len()
+                && module_name.iter().zip(&prefix).all(|(a, b)| a == b);
+
+            matches.then(|| {
+                let rest = module_name[prefix.len()..].join("/");
+                (prefix.len(), dir.join(format!("{}.elm", rest)))
+            })
+        })
+        .collect();
+
+    roots.sort_by(|(a, _), (b, _)| b.cmp(a));
+    roots.into_iter().map(|(_, path)| path).collect()
+}
+
+type LoadedModules = HashMap<ast::ModuleName, Module>;
+
+/* Finds, reads & parses every module in the import graph of the given module. The modules at each
+ * level of the graph don't depend on each other at this stage so we parse them in parallel and
+ * then move on to the imports that they introduce.
+ */
+fn load_imports(module: &Module, settings: &project::Settings) -> Result<LoadedModules, Error> {
+    log::trace!("load_imports {:?}", &module.name);
+    let mut loaded_modules = LoadedModules::new();
+    let mut pending: Vec<ast::ModuleName> = module
+        .imports
+        .iter()
+        .map(|import| import.module_name.clone())
+        .collect();
+
+    while !pending.is_empty() {
+        pending.sort();
+        pending.dedup();
+        pending.retain(|module_name| !loaded_modules.contains_key(module_name));
+
+        // Without the filesystem we're likely running in a browser where we can't spawn threads
+        #[cfg(feature = "fs")]
+        let iter = pending.par_iter();
+        #[cfg(not(feature = "fs"))]
+        let iter = pending.iter();
+
+        let modules = iter
+            .map(|module_name| {
+                load_module(module_name, settings).map(|module| (module_name.clone(), module))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        pending = modules
+            .iter()
+            .flat_map(|(_, module)| module.imports.iter())
+            .map(|import| import.module_name.clone())
+            .collect();
+
+        loaded_modules.extend(modules);
+    }
+
+    Ok(loaded_modules)
+}
+
+fn load_module(
+    module_name: &ast::ModuleName,
+    settings: &project::Settings,
+) -> Result<Module, Error> {
+    log::trace!("load_module {:?}", module_name);
+    let (filename, source, is_core) = read_module(module_name, settings)?;
+    parse_module(&filename, &source, is_core, settings)
+}
+
+fn parse_module(
+    filename: &Path,
+    source: &str,
+    is_core: bool,
+    settings: &project::Settings,
+) -> Result<Module, Error> {
+    let module = parse_cached(source, settings)
+        .map_err(|err| Error::FailedToParse(filename.to_path_buf(), err))?;
+
+    // See readme for how Elm determines when to include prelude
+    if is_core {
+        Ok(module)
+    } else {
+        Ok(ast::with_default_imports(&module))
+    }
+}
+
+/* Parses & resolves the source, ready for its scope to be built. When the settings have a cache
+ * directory the resolved module from an earlier run is reused if neither the source nor the
+ * operators available to it have changed since, which skips both the parse & the resolve.
+ */
+#[cfg(feature = "fs")]
+fn parse_cached(source: &str, settings: &project::Settings) -> parser::ParseResult {
+    let imported = |import: &ast::Import| imported_operators(import, settings);
+    let directory = match &settings.cache_directory {
+        Some(directory) => directory,
+        None => {
+            return parser::parse_source(source, imported).map(|module| prepared(module, settings))
+        }
+    };
+
+    let operators = parser::source_operators(source, imported)?;
+    let key = parse_cache::key(source, &operators, settings.optimize);
+    if let Some(module) = parse_cache::load(directory, &key) {
+        return Ok(module);
+    }
+
+    let module = prepared(
+        parser::parse_source_with_operators(source, operators)?,
+        settings,
+    );
+    parse_cache::store(directory, &key, &module);
+    Ok(module)
+}
+
+#[cfg(not(feature = "fs"))]
+fn parse_cached(source: &str, settings: &project::Settings) -> parser::ParseResult {
+    parser::parse_source(source, |import| imported_operators(import, settings))
+        .map(|module| prepared(module, settings))
+}
+
+/* The freshly parsed module in the form that its scope is built from */
+fn prepared(module: Module, settings: &project::Settings) -> Module {
+    resolve::resolve_module(&optimized(module, settings))
+}
+
+/* The module with its constants worked out when the settings ask for it */
+pub fn optimized(module: Module, settings: &project::Settings) -> Module {
+    if settings.optimize {
+        optimize::fold_constants(&module)
+    } else {
+        module
+    }
+}
+
+/* The operators declared by an imported module so that the importing module can be parsed with
+ * them. Modules that can't be read have no operators, the error is reported when we load them.
+ */
+pub fn imported_operators(import: &ast::Import, settings: &project::Settings) -> parser::Operators {
+    log::trace!("imported_operators {:?}", import.module_name);
+    read_module(&import.module_name, settings)
+        .ok()
+        .and_then(|(_filename, source, _is_core)| {
+            let mut iter = Token::lexer(&source).spanned().peekable();
+            parser::operators::declared_operators(&mut iter).ok()
+        })
+        .unwrap_or_default()
+}
+
+/* Returns the path, source and whether the module is a core module */
+#[cfg(feature = "fs")]
+fn read_module(
+    module_name: &ast::ModuleName,
+    settings: &project::Settings,
+) -> Result<(PathBuf, String, bool), Error> {
+    let (filename, is_core) = find_module_path(module_name, settings)
+        .ok_or_else(|| Error::UnableToFindModule(module_name.join(".")))?;
+
+    let source =
+        std::fs::read_to_string(&filename).map_err(|_| Error::FailedToRead(filename.clone()))?;
+
+    Ok((filename, source, is_core))
+}
+
+/* Where 'vendor' puts the core modules within the directory that it copies modules into. Module
+ * names start with a capital so this can't clash with a project module.
+ */
+#[cfg(feature = "fs")]
+pub fn vendored_core_path(directory: &Path) -> PathBuf {
+    directory.join("core")
+}
+
+/* Copies the source of every module in the import graph of the given module, including the
+ * prelude, into the directory, laid out by module name, so that the directory can be used as the
+ * only source directory for the module. Core modules are read from the working directory or
+ * ERM_CORE which won't be there once the script is moved, so they are copied too and go in the
+ * vendored_core_path to be used as the core path. Returns the paths of the copied files in module
+ * name order, the core modules first.
+ */
+#[cfg(feature = "fs")]
+pub fn vendor(
+    module: &Module,
+    settings: &project::Settings,
+    directory: &Path,
+) -> Result<Vec<PathBuf>, Error> {
+    log::trace!("vendor {:?}", &module.name);
+    let mut copied = HashMap::new();
+    let mut pending: Vec<ast::ModuleName> = ast::with_default_imports(module)
+        .imports
+        .iter()
+        .map(|import| import.module_name.clone())
+        .collect();
+
+    while let Some(module_name) = pending.pop() {
+        if copied.contains_key(&module_name) {
+            continue;
+        }
+
+        let (filename, source, is_core) = read_module(&module_name, settings)?;
+        let imported = parse_module(&filename, &source, is_core, settings)?;
+        pending.extend(
+            imported
+                .imports
+                .iter()
+                .map(|import| import.module_name.clone()),
+        );
+
+        let root = if is_core {
+            vendored_core_path(directory)
+        } else {
+            directory.to_path_buf()
+        };
+        let target = root.join(format!("{}.elm", module_name.join("/")));
+        target
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&target, &source))
+            .map_err(|_| Error::FailedToWrite(target.clone()))?;
+
+        copied.insert(module_name, (!is_core, target));
+    }
+
+    let mut copied: Vec<_> = copied
+        .into_iter()
+        .map(|(module_name, (is_project, path))| (is_project, module_name, path))
+        .collect();
+    copied.sort();
+    Ok(copied.into_iter().map(|(_, _, path)| path).collect())
+}
+
+/* Without the filesystem only the core modules, which are compiled into the library, are
+ * available so the module roots, source directories & core path in the settings are ignored.
+ */
+#[cfg(not(feature = "fs"))]
+fn read_module(
+    module_name: &ast::ModuleName,
+    _settings: &project::Settings,
+) -> Result<(PathBuf, String, bool), Error> {
+    let name = module_name.join("/");
+    let source = super::core_modules::source(&name)
+        .ok_or_else(|| Error::UnableToFindModule(module_name.join(".")))?;
+
+    let mut filename = PathBuf::new();
+    filename.push("core");
+    filename.push(format!("{}.elm", name));
+
+    Ok((filename, source.to_string(), true))
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Environment {
     pub module_imports: im::Vector<ModuleImport>,
+    pub imported_names: Rc<ImportedNames>,
     pub local_scopes: im::Vector<Rc<Scope>>,
+    // The arena holding the expressions of the module that this environment belongs to
+    pub arena: Arc<Arena>,
 }
 
 impl Environment {
     pub fn from_module_scope(module_scope: ModuleScope) -> Environment {
         Environment {
             module_imports: module_scope.module_imports,
+            imported_names: module_scope.imported_names,
             local_scopes: vector![module_scope.local_scope],
+            arena: module_scope.arena,
         }
     }
 
+    /* The functions registered by the host program that the environment's module can call */
+    pub fn host_functions(&self) -> &project::HostFunctions {
+        &self.imported_names.host_functions
+    }
+
     /* Returns the binding for the target name and the environment in which that binding should be
       evaluated.
     */
     pub fn get_binding(
-        self: &Self,
+        &self,
         target_name: &ast::QualifiedLowerName,
     ) -> Result<FoundBinding, GetBindingError> {
         let full_name = target_name.as_string();
         log::trace!("get_binding: {:?}", full_name);
-        match full_name.as_str() {
-            // core/Basics
-            "Elm.Kernel.Basics.add" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            "Elm.Kernel.Basics.sub" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            "Elm.Kernel.Basics.mul" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            "Elm.Kernel.Basics.gt" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            "Elm.Kernel.Basics.lt" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            "Elm.Kernel.Basics.append" => {
-                return Ok(FoundBinding::BuiltInFunc(target_name.clone()))
-            }
-            // core/String
-            "Elm.Kernel.String.fromInt" => {
-                return Ok(FoundBinding::BuiltInFunc(target_name.clone()))
-            }
-            "Elm.Kernel.String.join" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            // core/List
-            "Elm.Kernel.List.sum" => return Ok(FoundBinding::BuiltInFunc(target_name.clone())),
-            _ => {}
+        if builtins::is_built_in(&full_name) || self.host_functions().contains(&full_name) {
+            return Ok(FoundBinding::BuiltInFunc(target_name.clone()));
         }
 
         // TODO: Only check local scope if there is not module section to the LowerName
         for (i, scope) in self.local_scopes.iter().enumerate() {
             if let Some(value) = scope.bindings.get(target_name) {
-                let env = Environment {
-                    module_imports: self.module_imports.clone(),
-                    local_scopes: self.local_scopes.iter().skip(i).cloned().collect(),
-                };
-                return Ok(FoundBinding::WithEnv(value.clone(), env));
+                return Ok(FoundBinding::WithEnv(
+                    value.clone(),
+                    Box::new(self.pop_scopes(i)),
+                ));
+            }
+        }
+
+        self.imported_names.get(target_name)
+    }
+
+    /* Returns the environment with the scope as its innermost local scope. The environment itself
+      is left as it is so the scope only lasts as long as the environment that is returned and can't
+      leak into the code that carries on with this one
+    */
+    pub fn push_scope(&self, scope: Scope) -> Environment {
+        log::trace!("push_scope");
+        let mut local_scopes = self.local_scopes.clone();
+        local_scopes.push_front(Rc::new(scope));
+
+        Environment {
+            local_scopes,
+            ..self.clone()
+        }
+    }
+
+    /* Returns the environment as it was before the innermost 'count' local scopes were pushed */
+    pub fn pop_scopes(&self, count: usize) -> Environment {
+        log::trace!("pop_scopes: {}", count);
+        Environment {
+            local_scopes: self.local_scopes.iter().skip(count).cloned().collect(),
+            ..self.clone()
+        }
+    }
+
+    /* The number of local scopes, including the module's own top level scope */
+    pub fn scope_depth(&self) -> usize {
+        self.local_scopes.len()
+    }
+
+    /* Every binding that get_binding can find, for inspecting what a module can see. Names from
+      the local scopes hide the same names in outer scopes & imports, as they do with get_binding.
+      The module is None for bindings from the environment's own scopes.
+    */
+    pub fn resolvable_bindings(&self) -> Vec<ResolvableBinding> {
+        log::trace!("resolvable_bindings");
+        let mut seen = HashSet::new();
+        let mut bindings = Vec::new();
+
+        for scope in &self.local_scopes {
+            for (name, binding) in &scope.bindings {
+                if seen.insert(name.clone()) {
+                    bindings.push(ResolvableBinding {
+                        name: name.clone(),
+                        binding: binding.clone(),
+                        module: None,
+                    });
+                }
+            }
+        }
+
+        for (name, binding, module) in self.imported_names.iter() {
+            if seen.insert(name.clone()) {
+                bindings.push(ResolvableBinding {
+                    name: name.clone(),
+                    binding: binding.clone(),
+                    module: Some(module.clone()),
+                });
+            }
+        }
+
+        bindings.sort_by_key(|resolvable| resolvable.name.as_string());
+        bindings
+    }
+
+    /* Every operator that get_operator can find along with the module that defines it, or None
+      for operators from the environment's own scopes
+    */
+    pub fn resolvable_operators(&self) -> Vec<(Operator, Option<ast::ModuleName>)> {
+        log::trace!("resolvable_operators");
+        let mut seen = HashSet::new();
+        let mut operators = Vec::new();
+
+        for scope in &self.local_scopes {
+            for (name, operator) in &scope.operators {
+                if seen.insert(name.clone()) {
+                    operators.push((operator.clone(), None));
+                }
             }
         }
 
-        // TODO: Iterate in reverse through imports so later ones override earlier ones?
         for module_import in &self.module_imports {
-            if let Some(value) = module_import.get_binding(target_name) {
-                let env = Environment {
-                    module_imports: module_import.module_scope.module_imports.clone(),
-                    local_scopes: vector![module_import.module_scope.local_scope.clone()],
-                };
-                return Ok(FoundBinding::WithEnv(value, env));
+            let module_scope = &module_import.module_scope;
+            for name in module_scope.local_scope.operators.keys() {
+                if seen.contains(name) {
+                    continue;
+                }
+                if let Some((operator, _env)) = module_import.get_operator(name) {
+                    seen.insert(name.clone());
+                    operators.push((operator, Some(module_scope.name.clone())));
+                }
             }
         }
 
-        Err(GetBindingError::Unknown)
+        operators.sort_by(|(a, _), (b, _)| a.operator_name.cmp(&b.operator_name));
+        operators
+    }
+
+    /* Returns the binding in the given slot of the local scope at the given depth, as assigned by
+      the resolver. Depth 0 is the innermost scope.
+    */
+    pub fn get_local(&self, depth: usize, slot: usize) -> Option<Binding> {
+        log::trace!("get_local: {} {}", depth, slot);
+        self.local_scopes
+            .get(depth)
+            .and_then(|scope| scope.slots.get(slot))
+            .cloned()
     }
+
+    /* Returns the cached value of a binding from the innermost scope. Intended for use with the
+      environment returned alongside the binding by get_binding.
+    */
+    pub fn get_evaluated(&self, target_name: &ast::QualifiedLowerName) -> Option<values::Value> {
+        log::trace!("get_evaluated: {:?}", target_name);
+        self.local_scopes.front().and_then(|scope| {
+            scope
+                .evaluated
+                .borrow()
+                .get(&target_name.without_module())
+                .cloned()
+        })
+    }
+
+    pub fn set_evaluated(&self, target_name: &ast::QualifiedLowerName, value: values::Value) {
+        log::trace!("set_evaluated: {:?}", target_name);
+        if let Some(scope) = self.local_scopes.front() {
+            scope
+                .evaluated
+                .borrow_mut()
+                .insert(target_name.without_module(), value);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ResolvableBinding {
+    pub name: ast::QualifiedLowerName,
+    pub binding: Binding,
+    pub module: Option<ast::ModuleName>,
 }
 
 #[derive(Debug)]
 pub enum FoundBinding {
     BuiltInFunc(ast::QualifiedLowerName),
-    WithEnv(Binding, Environment),
+    WithEnv(Binding, Box<Environment>),
 }
 
 #[derive(Debug, PartialEq)]
 pub enum GetBindingError {
     Unknown,
+    // The module defines the name but doesn't include it in its exposing list
+    NotExposed { module: String, name: String },
 }
 
-pub fn get_built_in(target_name: &ast::QualifiedLowerName) -> Option<Rc<dyn builtins::Func>> {
-    let full_name = target_name.as_string();
-    match full_name.as_str() {
-        // core/Basics
-        "Elm.Kernel.Basics.add" => return Some(Rc::new(builtins::Add {})),
-        "Elm.Kernel.Basics.sub" => return Some(Rc::new(builtins::Sub {})),
-        "Elm.Kernel.Basics.mul" => return Some(Rc::new(builtins::Mul {})),
-        "Elm.Kernel.Basics.gt" => return Some(Rc::new(builtins::Gt {})),
-        "Elm.Kernel.Basics.lt" => return Some(Rc::new(builtins::Lt {})),
-        "Elm.Kernel.Basics.append" => return Some(Rc::new(builtins::Append {})),
-        // core/String
-        "Elm.Kernel.String.fromInt" => return Some(Rc::new(builtins::StringFromInt {})),
-        "Elm.Kernel.String.join" => return Some(Rc::new(builtins::StringJoin {})),
-        // core/List
-        "Elm.Kernel.List.sum" => return Some(Rc::new(builtins::ListSum {})),
-        _ => {}
-    }
-
-    None
+/* The builtin or host function with the name. Host functions can't share a name with a builtin */
+pub fn get_built_in(
+    target_name: &ast::QualifiedLowerName,
+    host_functions: &project::HostFunctions,
+) -> Option<Rc<dyn builtins::Func>> {
+    let name = target_name.as_string();
+    builtins::get(&name).or_else(|| host_functions.get(&name))
 }
 
-pub fn get_operator(environment: &Environment, target_name: &str) -> Option<Operator> {
+pub fn get_operator(
+    environment: &Environment,
+    target_name: &str,
+) -> Option<(Operator, Environment)> {
     log::trace!("get_operator: {}", &target_name);
-    for scope in &environment.local_scopes {
+    for (i, scope) in environment.local_scopes.iter().enumerate() {
         if let Some(value) = scope.operators.get(target_name) {
-            return Some(value.clone());
+            return Some((value.clone(), environment.pop_scopes(i)));
         }
     }
 
@@ -394,21 +1357,110 @@ pub fn get_operator(environment: &Environment, target_name: &str) -> Option<Oper
     None
 }
 
-pub fn add_local_scope(environment: &Environment, new_scope: Scope) -> Environment {
-    log::trace!("add_local_scope");
-    let mut new_scopes = environment.local_scopes.clone();
-    new_scopes.push_front(Rc::new(new_scope));
+/* Finds the constructor in the environment. Constructors are only defined at the top level of
+ * modules so we check the outermost scope and then the imports.
+ */
+pub fn get_constructor(
+    environment: &Environment,
+    target_name: &ast::QualifiedUpperName,
+) -> Result<Constructor, GetBindingError> {
+    log::trace!("get_constructor: {:?}", &target_name);
+    if target_name.modules.is_empty() {
+        if let Some(constructor) = environment
+            .local_scopes
+            .back()
+            .and_then(|scope| scope.constructors.get(&target_name.access))
+        {
+            return Ok(constructor.clone());
+        }
+    }
 
-    Environment {
-        module_imports: environment.module_imports.clone(),
-        local_scopes: new_scopes,
+    // Report a constructor that a module hides over one that no import knows about
+    let mut error = GetBindingError::Unknown;
+    for module_import in &environment.module_imports {
+        match module_import.get_constructor(target_name) {
+            Ok(constructor) => return Ok(constructor),
+            Err(GetBindingError::Unknown) => {}
+            Err(not_exposed) => error = not_exposed,
+        }
     }
+
+    Err(error)
 }
 
-pub fn new_local_scope(environment: &Environment, new_scope: Scope) -> Environment {
-    log::trace!("new_local_scope");
-    Environment {
-        module_imports: environment.module_imports.clone(),
-        local_scopes: im::vector![Rc::new(new_scope)],
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn value_scope(name: &str, value: i64) -> Scope {
+        Scope::from_slots(vec![(
+            name.to_string(),
+            Binding::Value(values::Value::Integer(value)),
+        )])
+    }
+
+    fn integer(environment: &Environment, name: &str) -> Option<i64> {
+        match environment.get_binding(&ast::QualifiedLowerName::simple(name.to_string())) {
+            Ok(FoundBinding::WithEnv(Binding::Value(values::Value::Integer(value)), _env)) => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn pushed_scope_shadows_outer_scope() {
+        let outer = Environment::default().push_scope(value_scope("x", 1));
+        let inner = outer.push_scope(value_scope("x", 2));
+
+        assert_eq!(integer(&inner, "x"), Some(2));
+        assert_eq!(inner.scope_depth(), 2);
+        // The outer environment doesn't see the scope pushed onto it
+        assert_eq!(integer(&outer, "x"), Some(1));
+        assert_eq!(outer.scope_depth(), 1);
+    }
+
+    #[test]
+    fn popped_scopes_are_gone() {
+        let environment = Environment::default()
+            .push_scope(value_scope("x", 1))
+            .push_scope(value_scope("y", 2));
+
+        let popped = environment.pop_scopes(1);
+        assert_eq!(integer(&popped, "x"), Some(1));
+        assert_eq!(integer(&popped, "y"), None);
+        assert_eq!(environment.pop_scopes(2).scope_depth(), 0);
+    }
+
+    #[test]
+    fn binding_comes_with_the_scopes_it_was_defined_in() {
+        let environment = Environment::default()
+            .push_scope(value_scope("x", 1))
+            .push_scope(value_scope("y", 2));
+
+        match environment.get_binding(&ast::QualifiedLowerName::simple("x".to_string())) {
+            Ok(FoundBinding::WithEnv(_, env)) => assert_eq!(env.scope_depth(), 1),
+            _ => panic!("Failed to find x"),
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn cached_scopes_report_import_cycles() {
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules/cycle")],
+            ..project::Settings::new()
+        };
+        let module = parser::parse_source(
+            "module Main exposing (..)\nimport Cycle.B\nmain args =\n    Cycle.B.b\n",
+            |import| imported_operators(import, &settings),
+        )
+        .expect("Failed to parse");
+
+        let result = ModuleScope::from_module_cached(&module, &settings, &mut ModuleCache::new());
+        assert!(matches!(
+            result,
+            Err(Error::ImportCycle(modules)) if modules == ["Cycle.B", "Cycle.A", "Cycle.B"]
+        ));
     }
 }
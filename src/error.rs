@@ -3,21 +3,37 @@ use codespan_reporting::files::SimpleFiles;
 use codespan_reporting::term;
 use codespan_reporting::term::termcolor::Buffer;
 
+use crate::builtins;
 use crate::checker;
 use crate::env;
 use crate::evaluator;
 use crate::lexer::Range;
 use crate::parser;
+use crate::project;
 
 #[derive(Debug)]
 pub enum Error {
     FileError,
     ParserError(parser::Error, String),
     CheckError(checker::Error),
-    EvaluateError(evaluator::Error),
+    EvaluateError(evaluator::Error, String),
     ScopeError(env::Error),
 }
 
+/* Elm 0.19 removed some functions that older code and tutorials still use so we point at their
+ * replacements rather than only saying that the name is unknown
+ */
+fn with_removed_name_hint(message: String, name: &str) -> String {
+    match name {
+        "toString" | "Basics.toString" => format!(
+            "{}\n\ntoString was removed from Basics in Elm 0.19. Use String.fromInt, \
+             String.fromBool or Debug.toString instead.",
+            message
+        ),
+        _ => message,
+    }
+}
+
 pub fn to_user_output(error: Error) -> String {
     match error {
         Error::FileError => "File error".to_string(),
@@ -34,7 +50,7 @@ pub fn to_user_output(error: Error) -> String {
                 explain_with_source("Unexpected indentation.", source, range)
             }
             parser::Error::UnderscoreExpression(range) => explain_with_source(
-                "You cannot use an underscore as a value or expression. You can only assign to it.",
+                "An underscore is a pattern, not an expression. It can match a value that you want to ignore but it can't be used as one.",
                 source,
                 range,
             ),
@@ -43,6 +59,38 @@ pub fn to_user_output(error: Error) -> String {
                 source,
                 range,
             ),
+            parser::Error::NonAssociativeOperators {
+                first,
+                second,
+                range,
+            } => explain_with_source(
+                &format!(
+                    "The ({}) and ({}) operators cannot be used together without parentheses.",
+                    first, second
+                ),
+                source,
+                range,
+            ),
+            parser::Error::PortType(range) => explain_with_source(
+                "Ports need a type like `String -> Cmd msg` to send values to the host or `(String -> msg) -> Sub msg` to receive them.",
+                source,
+                range,
+            ),
+            parser::Error::TooDeeplyNested(range) => explain_with_source(
+                "This expression is too deeply nested.",
+                source,
+                range,
+            ),
+            parser::Error::TooManyOperators(range) => explain_with_source(
+                "This chain of operators is too long. Try splitting it up with some let bindings.",
+                source,
+                range,
+            ),
+            parser::Error::DuplicateField { name, range } => explain_with_source(
+                &format!("This record gives the `{}` field more than once.", name),
+                source,
+                range,
+            ),
             parser::Error::TokensRemaining(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
@@ -80,30 +128,67 @@ pub fn to_user_output(error: Error) -> String {
             checker::Error::UnhandledExpression(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
-            checker::Error::UnifyError(unify_error) => format!(
+            checker::Error::UnifyError {
+                error: unify_error, ..
+            } => format!(
                 r#"Type error:
 
 {:#?}"#,
                 unify_error
             ),
+            checker::Error::TypeMismatch { clash, .. } => format!("Type mismatch: {}", clash),
             checker::Error::UnknownFunction(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
-            checker::Error::UnknownOperator(_) => {
-                format!("Error text not written ({}) {:?}", line!(), error)
+            checker::Error::UnknownOperator(operator) => {
+                format!("Unknown operator: ({})", operator)
             }
-            checker::Error::UnknownVarName(_) => {
-                format!("Error text not written ({}) {:?}", line!(), error)
+            checker::Error::UnknownVarName(name) => {
+                with_removed_name_hint(format!("Unknown name: {}", name), &name)
             }
             checker::Error::UnknownPattern(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
-            checker::Error::ArgumentMismatch(_) => {
-                format!("Error text not written ({}) {:?}", line!(), error)
-            }
-            checker::Error::TooManyArguments => {
-                format!("Error text not written ({}) {:?}", line!(), error)
+            checker::Error::UnknownConstructor(name) => format!("Unknown constructor: {}", name),
+            checker::Error::NotExposed { name, module } => {
+                format!("The {} module does not expose {}", module, name)
             }
+            checker::Error::AnnotationMismatch {
+                name,
+                expected,
+                found,
+                range: _,
+            } => format!(
+                "Type error: the annotation for {} expects {} but found {}",
+                name, expected, found
+            ),
+            checker::Error::ArgumentMismatch {
+                name,
+                expected,
+                supplied,
+                range: _,
+            } => format!(
+                "Type error: {} needs {} but it has {}",
+                name,
+                count_arguments(expected),
+                count_arguments(supplied)
+            ),
+            checker::Error::TooManyArguments {
+                name,
+                expected,
+                supplied,
+                range: _,
+            } => format!(
+                "Type error: {} takes {} but it is given {}",
+                name,
+                count_arguments(expected),
+                count_arguments(supplied)
+            ),
+            checker::Error::CyclicDefinition { names, range: _ } => format!(
+                "The {} value depends on itself: {}",
+                names.first().map_or("", |name| name.as_str()),
+                names.join(" -> ")
+            ),
             checker::Error::Broken(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
@@ -116,14 +201,36 @@ pub fn to_user_output(error: Error) -> String {
             checker::Error::ImpossiblyEmptyCase => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
+            checker::Error::MissingField { field, record, .. } => format!(
+                "This pattern needs a field called {} but the record only has:\n\n    {}",
+                field, record
+            ),
+            checker::Error::MissingCaseFallback(_) => {
+                "This case matches literal values but has no _ branch for the others".to_string()
+            }
             checker::Error::Unknown => format!("Error text not written ({}) {:?}", line!(), error),
         },
-        Error::EvaluateError(error) => match error {
+        Error::EvaluateError(error, source) => match error {
             evaluator::Error::UnsupportedOperation => {
                 format!("Error text not written ({})", line!())
             }
             evaluator::Error::UnknownFunction => "Unable to find function".to_string(),
-            evaluator::Error::UnknownBinding(name) => format!("Unknown binding: {}", name),
+            evaluator::Error::OperatorNotExposed(operator) => {
+                format!("The ({}) operator isn't exposed by any of the imports", operator)
+            }
+            evaluator::Error::UnknownBinding(name) => {
+                with_removed_name_hint(format!("Unknown binding: {}", name), &name)
+            }
+            evaluator::Error::NotExposed { name, module } => {
+                format!("The {} module does not expose {}", module, name)
+            }
+            evaluator::Error::FunctionError(builtins::Error::IntegerOverflow) => {
+                "Integer overflow: the result is too large to fit in an Int".to_string()
+            }
+            evaluator::Error::FunctionError(builtins::Error::TooLarge) => {
+                "The program was stopped as it tried to build a value too large to fit in memory."
+                    .to_string()
+            }
             evaluator::Error::FunctionError(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
@@ -139,12 +246,80 @@ pub fn to_user_output(error: Error) -> String {
             evaluator::Error::UnexpectedBinding(_) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
-            evaluator::Error::UnsupportedArgumentPattern(_) => {
-                format!("Error text not written ({}) {:?}", line!(), error)
+            evaluator::Error::UnmatchedArgumentPattern(pattern) => {
+                format!("The argument doesn't match the pattern {} of the function", pattern)
             }
-            evaluator::Error::NoMatchingCase => {
-                format!("Error text not written ({}) {:?}", line!(), error)
+            evaluator::Error::UnknownConstructor(name) => format!("Unknown constructor: {}", name),
+            evaluator::Error::NoMatchingCase {
+                value,
+                patterns,
+                range,
+            } => {
+                let text = format!(
+                    r#"None of the branches of this case expression match the value:
+
+    {}
+
+The patterns that were tried are:
+
+    {}"#,
+                    value,
+                    patterns.join("\n    ")
+                );
+
+                // The case might be in an imported module in which case we don't have the right
+                // source to show it
+                if range.end <= source.len() {
+                    explain_with_source(&text, source, range)
+                } else {
+                    text
+                }
             }
+            evaluator::Error::UnmatchedLetPattern => {
+                "The value doesn't match the pattern of the let binding".to_string()
+            }
+            evaluator::Error::ResourceLimit { resource, limit } => {
+                let description = match resource {
+                    evaluator::limits::Resource::ListLength => "a list longer than",
+                    evaluator::limits::Resource::StringLength => "a string longer than",
+                    evaluator::limits::Resource::Values => "more values than",
+                };
+                format!(
+                    "The program was stopped as it built {} the limit of {}.",
+                    description, limit
+                )
+            }
+            evaluator::Error::NotAllowed(capability) => {
+                let (description, flag) = match capability {
+                    builtins::Capability::Fs => ("read files", "--allow-fs"),
+                    builtins::Capability::Net => ("make network requests", "--allow-net"),
+                    builtins::Capability::Env => ("read environment variables", "--allow-env"),
+                    builtins::Capability::Process => ("run other programs", "--allow-process"),
+                    builtins::Capability::Time => ("read the time", "--allow-time"),
+                };
+                format!(
+                    "The program needs permission to {}. Run it with {}, or --allow-all, to allow it.",
+                    description, flag
+                )
+            }
+            evaluator::Error::PortError(evaluator::ports::PortError::NoListener(port)) => {
+                format!(
+                    "The program sent a value to the '{}' port but nothing is listening to it.",
+                    port
+                )
+            }
+            evaluator::Error::PortError(evaluator::ports::PortError::Json(port, error)) => {
+                format!("Unable to pass a value through the '{}' port: {}", port, error)
+            }
+            evaluator::Error::Timeout { trace } if trace.is_empty() => {
+                "The program was stopped as it ran for longer than it was allowed to.".to_string()
+            }
+            evaluator::Error::Timeout { trace } => format!(
+                r#"The program was stopped as it ran for longer than it was allowed to. It was evaluating:
+
+    {}"#,
+                collapse_repeats(&trace).join("\n    ")
+            ),
         },
         Error::ScopeError(error) => match error {
             env::Error::UnableToFindModule(module) => format!(
@@ -157,10 +332,41 @@ pub fn to_user_output(error: Error) -> String {
             env::Error::FailedToParse(_, _) => {
                 format!("Error text not written ({}) {:?}", line!(), error)
             }
+            env::Error::FailedToWrite(path) => format!(
+                "Unable to write file:
+
+{}",
+                path.display()
+            ),
+            env::Error::ImportCycle(modules) => format!(
+                "These modules import each other in a cycle:
+
+{}",
+                modules.join(" -> ")
+            ),
         },
     }
 }
 
+pub fn warning_to_user_output(warning: &project::Warning) -> String {
+    match warning {
+        project::Warning::ShadowedModule {
+            module,
+            used,
+            shadowed,
+        } => format!(
+            "The {} module at {} is used in place of the one at {}",
+            module,
+            used.display(),
+            shadowed.display()
+        ),
+        project::Warning::UnreachableBranch { .. } => {
+            "This case branch can never match as an earlier branch matches the same value"
+                .to_string()
+        }
+    }
+}
+
 fn explain_with_source(text: &str, source: String, range: Range) -> String {
     format!(
         r#"{}
@@ -185,3 +391,29 @@ pub fn pretty_print(source: String, range: Range) -> String {
         .unwrap_or("Failure")
         .to_string()
 }
+
+fn count_arguments(count: usize) -> String {
+    match count {
+        1 => "1 argument".to_string(),
+        count => format!("{} arguments", count),
+    }
+}
+
+/* Joins runs of the same name, like those from a recursive function, into a single line */
+fn collapse_repeats(names: &[String]) -> Vec<String> {
+    let mut lines: Vec<(String, usize)> = vec![];
+    for name in names {
+        match lines.last_mut() {
+            Some((last, count)) if last == name => *count += 1,
+            _ => lines.push((name.clone(), 1)),
+        }
+    }
+
+    lines
+        .into_iter()
+        .map(|(name, count)| match count {
+            1 => name,
+            count => format!("{} ({} times)", name, count),
+        })
+        .collect()
+}
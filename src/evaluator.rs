@@ -1,116 +1,326 @@
 use std::cmp::Ordering;
 use std::rc::Rc;
+use std::sync::Arc;
 
 use log;
 
+use self::limits::Usage;
+use self::observer::EvalObserver;
 use self::values::{Func, Value};
-use super::ast::{self, Expr, Module, Pattern, Stmt};
+use super::ast::{self, Expr, ExprId, LetBinding, Module, Pattern, Stmt};
 use super::bindings::Binding;
 use super::builtins;
-use super::env::{self, Bindings, FoundBinding};
+use super::env::{self, FoundBinding, GetBindingError};
+use super::lexer::Range;
+use super::printer;
 use super::project;
 
+pub mod coverage;
+pub mod limits;
+pub mod observer;
+pub mod platform;
+pub mod ports;
+pub mod testing;
 pub mod values;
 
 #[derive(Debug, PartialEq)]
 pub enum Error {
     UnsupportedOperation,
     UnknownFunction,
+    // The operator was parsed but none of the imports in scope expose it
+    OperatorNotExposed(String),
     UnknownBinding(String),
+    // The name is defined by the module but the module doesn't expose it
+    NotExposed {
+        name: String,
+        module: String,
+    },
     UnexpectedBinding(String),
     FunctionError(builtins::Error),
     WrongArity,
     TooManyArguments,
     ScopeError(env::Error),
-    UnsupportedArgumentPattern(String),
-    NoMatchingCase,
+    // An argument doesn't match the pattern, given as it is printed, that the function has for it
+    UnmatchedArgumentPattern(String),
+    UnknownConstructor(String),
+    NoMatchingCase {
+        value: String,
+        patterns: Vec<String>,
+        range: Range,
+    },
+    UnmatchedLetPattern,
+    // The names of the user functions that were being evaluated, innermost first
+    Timeout {
+        trace: Vec<String>,
+    },
+    ResourceLimit {
+        resource: limits::Resource,
+        limit: usize,
+    },
+    NotAllowed(builtins::Capability),
+    PortError(ports::PortError),
 }
 
 pub fn evaluate(
     _module: &Module,
     args: Vec<String>,
     environment: &env::Environment,
-    _settings: &project::Settings,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
     log::trace!("evaluate");
 
-    let call_main = Expr::Call {
-        function: Rc::new(ast::Expr::VarName(ast::QualifiedLowerName::simple(
-            "main".to_string(),
-        ))),
-        args: vec![Rc::new(Expr::List(
-            args.iter()
-                .map(|entry| Rc::new(Expr::String(String::from(entry))))
-                .collect(),
-        ))],
-    };
+    let main_name = ast::QualifiedLowerName::simple("main".to_string());
+    let main = evaluate_var_name(&main_name, environment, settings, usage)?;
+
+    // Main is either a function of the command line arguments or a plain value
+    match main {
+        Value::PartiallyAppliedFunc { .. } => {
+            let args = Value::List(
+                args.into_iter()
+                    .map(|arg| Value::String(Arc::from(arg)))
+                    .collect(),
+            );
+            apply_function(main, vec![args], settings, usage)
+        }
+        value => Ok(value),
+    }
+}
 
-    evaluate_expression(&call_main, environment)
+/* Evaluates a single top level binding rather than running main, for tools like the repl that
+ * want to show the value of an expression.
+ */
+pub fn evaluate_name(
+    name: &str,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_name: {}", name);
+    let name = ast::QualifiedLowerName::simple(name.to_string());
+    evaluate_var_name(&name, environment, settings, usage)
 }
 
-fn evaluate_expression(expr: &Expr, environment: &env::Environment) -> Result<Value, Error> {
+/* Turns a runtime error into a RuntimeError value, with the message that the caller gives for it,
+ * when the settings ask to recover from errors. Other errors are returned as they are.
+ */
+pub fn recover(
+    result: Result<Value, Error>,
+    settings: &project::Settings,
+    message: impl FnOnce(Error) -> String,
+) -> Result<Value, Error> {
+    match result {
+        Err(error) if settings.recover_errors => Ok(Value::RuntimeError(Rc::from(message(error)))),
+        result => result,
+    }
+}
+
+fn evaluate_expression(
+    expr_id: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
     log::trace!("evaluate_expression");
-    match expr {
+    if usage.step(environment.scope_depth()) {
+        return Err(Error::Timeout { trace: vec![] });
+    }
+
+    match &environment.arena[expr_id] {
         Expr::Bool(bool) => Ok(Value::Bool(*bool)),
         Expr::Integer(int) => Ok(Value::Integer(*int)),
         Expr::Float(float) => Ok(Value::Float(*float)),
-        Expr::String(string) => Ok(Value::String(string.to_string())),
-        Expr::BinOp {
-            operator,
-            left,
-            right,
-        } => evaluate_binary_expression(operator, left, right, environment),
+        Expr::String(string) => Ok(Value::String(string.clone())),
+        Expr::Char(char) => Ok(Value::Char(*char)),
+        Expr::Unit => Ok(Value::Unit),
+        Expr::BinOp { .. } => evaluate_binary_expression(expr_id, environment, settings, usage),
         Expr::If {
             condition,
             then_branch,
             else_branch,
-        } => evaluate_if_expression(condition, then_branch, else_branch, environment),
-        Expr::Case { expr, branches } => evaluate_case_expression(expr, branches, environment),
+        } => evaluate_if_expression(
+            *condition,
+            *then_branch,
+            *else_branch,
+            environment,
+            settings,
+            usage,
+        ),
+        Expr::Case {
+            expr,
+            branches,
+            range,
+        } => evaluate_case_expression(*expr, branches, range, environment, settings, usage),
+        Expr::Let { bindings, body } => {
+            evaluate_let_expression(bindings, *body, environment, settings, usage)
+        }
+        // Lambdas keep the environment that they're written in so that their bodies can use the
+        // local names around them
+        Expr::Lambda { args, body } => Ok(Value::PartiallyAppliedFunc {
+            func: Func::UserFunc {
+                name: "<lambda>".to_string(),
+                args: args.clone(),
+                expr: *body,
+                environment: environment.clone(),
+            },
+            values: vec![],
+        }),
         Expr::List(items) => {
             let value_items = items
                 .iter()
-                .map(|expr| evaluate_expression(expr, environment))
+                .map(|expr| evaluate_expression(*expr, environment, settings, usage))
+                .collect::<Result<Vec<Value>, Error>>()?;
+            checked(Value::List(value_items), settings, usage)
+        }
+        Expr::Tuple(items) => {
+            let value_items = items
+                .iter()
+                .map(|expr| evaluate_expression(*expr, environment, settings, usage))
                 .collect::<Result<Vec<Value>, Error>>()?;
-            Ok(Value::List(value_items))
+            checked(Value::Tuple(value_items), settings, usage)
+        }
+        Expr::Record(fields) => {
+            let mut value_fields = fields
+                .iter()
+                .map(|(name, field)| {
+                    evaluate_expression(*field, environment, settings, usage)
+                        .map(|value| (name.clone(), value))
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+            value_fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            checked(Value::Record(value_fields), settings, usage)
         }
-        Expr::Call { function, args } => evaluate_function_call(function, args, environment),
-        Expr::VarName(name) => environment
-            .get_binding(name)
-            .map_err(|_| {
+        Expr::Constructor(name) => evaluate_constructor(name, environment),
+        Expr::Call { function, args } => {
+            evaluate_function_call(*function, args, environment, settings, usage)
+        }
+        Expr::VarName(name) => evaluate_var_name(name, environment, settings, usage),
+        Expr::LocalVar { name, depth, slot } => match environment.get_local(*depth, *slot) {
+            Some(Binding::Value(value)) => Ok(value),
+            Some(Binding::UserPattern(pattern, expr)) => evaluate_let_binding(
+                name,
+                &pattern,
+                expr,
+                &environment.pop_scopes(*depth),
+                settings,
+                usage,
+            ),
+            result => {
+                log::error!(
+                    "Error::UnknownBinding {:?} Found: {:?}\n\n{:#?}",
+                    name,
+                    result,
+                    environment
+                );
+                Err(Error::UnknownBinding(name.to_string()))
+            }
+        },
+    }
+}
+
+fn evaluate_var_name(
+    name: &ast::QualifiedLowerName,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_var_name: {:?}", name);
+    environment
+        .get_binding(name)
+        .map_err(|error| match error {
+            GetBindingError::NotExposed { module, name } => Error::NotExposed { name, module },
+            GetBindingError::Unknown => {
                 log::error!("Error::UnknownBinding {:?}\n\n{:#?}", name, environment);
                 Error::UnknownBinding(name.as_string())
-            })
-            .and_then(|binding| match binding {
-                FoundBinding::WithEnv(Binding::UserBinding(expr), env) => {
-                    evaluate_expression(&expr, &env)
-                }
-                FoundBinding::WithEnv(Binding::UserFunc(stmt), env) => {
-                    evaluate_statement(&stmt, &env)
-                }
-                FoundBinding::WithEnv(Binding::Value(value), _env) => Ok(value),
-                FoundBinding::BuiltInFunc(name) => Ok(Value::PartiallyAppliedFunc {
-                    func: Func::BuiltInFunc(name),
-                    values: vec![],
-                }),
-                result => {
-                    log::error!(
-                        "Error::UnknownBinding {:?} Found: {:?}\n\n{:#?}",
-                        name,
-                        result,
-                        environment
-                    );
-                    Err(Error::UnknownBinding(name.as_string()))
-                }
+            }
+        })
+        .and_then(|binding| match binding {
+            FoundBinding::WithEnv(Binding::UserBinding(expr), env) => {
+                evaluate_binding(name, expr, &env, settings, usage)
+            }
+            FoundBinding::WithEnv(Binding::UserFunc(stmt), env) => evaluate_statement(&stmt, &env),
+            FoundBinding::WithEnv(Binding::Value(value), _env) => Ok(value),
+            FoundBinding::BuiltInFunc(name) => Ok(Value::PartiallyAppliedFunc {
+                func: Func::BuiltInFunc(name),
+                values: vec![],
             }),
+            result => {
+                log::error!(
+                    "Error::UnknownBinding {:?} Found: {:?}\n\n{:#?}",
+                    name,
+                    result,
+                    environment
+                );
+                Err(Error::UnknownBinding(name.as_string()))
+            }
+        })
+}
+
+/* Constructors without arguments are values in their own right, otherwise they are functions that
+ * build the value once they have been given all their arguments
+ */
+fn evaluate_constructor(
+    name: &ast::QualifiedUpperName,
+    environment: &env::Environment,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_constructor: {:?}", name);
+    let constructor = env::get_constructor(environment, name).map_err(|error| match error {
+        GetBindingError::NotExposed { module, name } => Error::NotExposed { name, module },
+        GetBindingError::Unknown => Error::UnknownConstructor(name.as_string()),
+    })?;
+
+    if constructor.args.is_empty() {
+        Ok(Value::Custom {
+            name: constructor.name,
+            args: vec![],
+        })
+    } else {
+        Ok(Value::PartiallyAppliedFunc {
+            func: Func::Constructor {
+                name: constructor.name,
+                arity: constructor.args.len(),
+            },
+            values: vec![],
+        })
     }
 }
 
-fn evaluate_statement(stmt: &Stmt, _environment: &env::Environment) -> Result<Value, Error> {
+fn evaluate_binding(
+    name: &ast::QualifiedLowerName,
+    expr: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_binding: {:?}", name);
+    if let Some(value) = environment.get_evaluated(name) {
+        return Ok(value);
+    }
+
+    let value = evaluate_expression(expr, environment, settings, usage)?;
+    environment.set_evaluated(name, value.clone());
+    Ok(value)
+}
+
+fn evaluate_statement(stmt: &Stmt, environment: &env::Environment) -> Result<Value, Error> {
     match stmt {
-        Stmt::Function { args, expr, .. } => Ok(Value::PartiallyAppliedFunc {
+        Stmt::Function {
+            name, args, expr, ..
+        } => Ok(Value::PartiallyAppliedFunc {
             func: Func::UserFunc {
+                name: name.0.clone(),
                 args: args.clone(),
-                expr: expr.clone(),
+                expr: *expr,
+                environment: environment.clone(),
+            },
+            values: vec![],
+        }),
+        Stmt::Port {
+            name, direction, ..
+        } => Ok(Value::PartiallyAppliedFunc {
+            func: Func::Port {
+                name: name.0.clone(),
+                direction: *direction,
             },
             values: vec![],
         }),
@@ -119,232 +329,522 @@ fn evaluate_statement(stmt: &Stmt, _environment: &env::Environment) -> Result<Va
 }
 
 fn evaluate_function_call(
-    function_expr: &Rc<Expr>,
-    arg_exprs: &[Rc<Expr>],
+    function_expr: ExprId,
+    arg_exprs: &[ExprId],
     environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
     log::trace!("evaluate_function_call");
-    let func = evaluate_expression(function_expr, environment)?;
+    let func = evaluate_expression(function_expr, environment, settings, usage)?;
 
+    // TODO: Don't evaluate in advance here but rather on demand when used then we don't have to
+    // store values in the Scope/Bindings which is a bit out of place at the moment. Could
+    // potentially have another cache for evaluated expressions/values
+    let arg_values = arg_exprs
+        .iter()
+        .map(|expr| evaluate_expression(*expr, environment, settings, usage))
+        .collect::<Result<Vec<Value>, Error>>()?;
+
+    apply_function(func, arg_values, settings, usage)
+}
+
+/* Applies the argument values to the function value. User functions are evaluated in the
+ * environment in which they were defined as their bodies live in the arena of that module.
+ */
+fn apply_function(
+    func: Value,
+    arg_values: Vec<Value>,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("apply_function");
     match func {
         Value::PartiallyAppliedFunc { func, values } => {
             match func {
-                Func::UserFunc { ref args, ref expr } => {
+                Func::UserFunc {
+                    ref name,
+                    ref args,
+                    expr,
+                    ref environment,
+                } => {
                     // If there are enough entries in values (the already applied values) and
-                    // arg_exprs (the arguments provided at this call site) then we can evaluate
+                    // arg_values (the arguments provided at this call site) then we can evaluate
                     // the function, otherwise we want to return a PartiallyAppliedFunc with the
-                    // args_exprs evaulated and inserted into the values array
-                    match (values.len() + arg_exprs.len()).cmp(&args.len()) {
+                    // arg_values inserted into the values array
+                    match (values.len() + arg_values.len()).cmp(&args.len()) {
                         Ordering::Greater => {
                             // TODO Evaluate the function and see if it returns another function to apply
                             // the args to? Or maybe that isn't how Elm syntax works
                             Err(Error::TooManyArguments)
                         }
                         Ordering::Equal => {
-                            // Filter the args down to the valid Pattern::Name entries and error if
-                            // we encounter anything else
-                            let filtered_args: Vec<String> = args
-                                .iter()
-                                .map(|pattern| match pattern {
-                                    Pattern::Name(name) => Ok(name.clone()),
-                                    _ => Err(Error::UnsupportedArgumentPattern(format!(
-                                        "{:?}",
-                                        pattern
-                                    ))),
-                                })
-                                .collect::<Result<_, _>>()?;
-
-                            // TODO: Don't evaluate in advance here but rather on demand when
-                            // used then we don't have to store values in the Scope/Bindings
-                            // which is a bit out of place at the moment. Could potentially
-                            // have another cache for evaluated expressions/values
-                            let arg_expr_values: Vec<Value> = arg_exprs
-                                .iter()
-                                .map(|expr| evaluate_expression(expr, environment))
-                                .collect::<Result<_, _>>()?;
+                            let all_values: Vec<Value> =
+                                values.into_iter().chain(arg_values).collect();
+                            usage.call();
+                            notify(settings, |observer| observer.on_call(&func, &all_values));
+
+                            // Match each argument against its pattern. The values bound by the
+                            // patterns make up the scope for the function's body, in the same
+                            // order as the names that the resolver gave slots to
+                            let mut bound_values = Vec::new();
+                            for (pattern, value) in args.iter().zip(&all_values) {
+                                if !pattern_matches_values(pattern, value, &mut bound_values) {
+                                    return Err(Error::UnmatchedArgumentPattern(
+                                        printer::print_pattern(pattern),
+                                    ));
+                                }
+                            }
 
-                            // Evaluate each argument to the function call and create a map from argument
-                            // value to argument name to use as a scope within the function evaluation
-                            let pairs = filtered_args
+                            let slots = args
                                 .iter()
-                                .zip(values.iter().chain(arg_expr_values.iter()))
-                                .map(|(name, value)| {
-                                    (
-                                        ast::QualifiedLowerName::simple(name.to_string()),
-                                        Binding::Value(value.clone()),
-                                    )
-                                })
-                                .collect::<Bindings>();
-
-                            let arg_scope = env::Scope::from_bindings(pairs);
-
-                            let environment = env::add_local_scope(environment, arg_scope);
+                                .flat_map(|arg| arg.names())
+                                .zip(bound_values)
+                                .map(|(name, value)| (name, Binding::Value(value)))
+                                .collect();
+
+                            let arg_scope = env::Scope::from_slots(slots);
+
+                            let environment = environment.push_scope(arg_scope);
                             // println!("Environment: {:#?}", environment);
-                            evaluate_expression(expr, &environment)
-                        }
-                        Ordering::Less => {
-                            let arg_expr_values: Vec<Value> = arg_exprs
-                                .iter()
-                                .map(|expr| evaluate_expression(expr, environment))
-                                .collect::<Result<_, _>>()?;
-
-                            Ok(Value::PartiallyAppliedFunc {
-                                func: func.clone(),
-                                values: values
-                                    .iter()
-                                    .chain(arg_expr_values.iter())
-                                    .cloned()
-                                    .collect(),
-                            })
+                            let value = evaluate_expression(expr, &environment, settings, usage)
+                                .map_err(|error| add_to_trace(error, name))?;
+                            notify(settings, |observer| observer.on_return(&func, &value));
+                            Ok(value)
                         }
+                        Ordering::Less => Ok(Value::PartiallyAppliedFunc {
+                            func: func.clone(),
+                            values: values.into_iter().chain(arg_values).collect(),
+                        }),
                     }
                 }
                 Func::BuiltInFunc(name) => {
-                    // Something
-                    // Err(Error::UnknownFunction)
-                    let built_in_func = env::get_built_in(&name).ok_or(Error::UnknownFunction)?;
-
-                    let arg_values = arg_exprs
-                        .iter()
-                        .map(|expr| evaluate_expression(expr, environment))
-                        .collect::<Result<Vec<Value>, Error>>()?;
+                    let built_in_func = env::get_built_in(&name, &settings.host_functions)
+                        .ok_or(Error::UnknownFunction)?;
+                    if let Some(capability) = built_in_func.capability() {
+                        if !is_allowed(capability, settings) {
+                            return Err(Error::NotAllowed(capability));
+                        }
+                    }
 
-                    built_in_func.call(arg_values).map_err(Error::FunctionError)
+                    let all_values: Vec<Value> = values.into_iter().chain(arg_values).collect();
+                    let func = Func::BuiltInFunc(name);
+                    usage.call();
+                    notify(settings, |observer| observer.on_call(&func, &all_values));
+                    let apply = |func: Value, args: Vec<Value>| {
+                        apply_function(func, args, settings, usage)
+                            .map_err(|error| builtins::Error::Callback(Box::new(error)))
+                    };
+                    let value = built_in_func
+                        .call_with_apply(all_values, settings, &apply)
+                        .map_err(|error| match error {
+                            // Errors from the program's own functions are reported as they are
+                            builtins::Error::Callback(error) => *error,
+                            // As are limits that the builtin hit before building its value
+                            builtins::Error::ResourceLimit { resource, limit } => {
+                                Error::ResourceLimit { resource, limit }
+                            }
+                            error => Error::FunctionError(error),
+                        })?;
+                    limits::check(&value, settings, usage)?;
+                    notify(settings, |observer| observer.on_return(&func, &value));
+                    Ok(value)
+                }
+                Func::Port {
+                    ref name,
+                    direction,
+                } => {
+                    let mut all_values = values.into_iter().chain(arg_values);
+                    match (all_values.next(), all_values.next()) {
+                        (Some(arg), None) => ports::call(name, direction, arg, settings),
+                        (Some(_), Some(_)) => Err(Error::TooManyArguments),
+                        (None, _) => Ok(Value::PartiallyAppliedFunc {
+                            func: func.clone(),
+                            values: vec![],
+                        }),
+                    }
+                }
+                Func::Constructor { ref name, arity } => {
+                    let all_values: Vec<Value> = values.into_iter().chain(arg_values).collect();
+                    match all_values.len().cmp(&arity) {
+                        Ordering::Greater => Err(Error::TooManyArguments),
+                        Ordering::Equal => checked(
+                            Value::Custom {
+                                name: name.clone(),
+                                args: all_values,
+                            },
+                            settings,
+                            usage,
+                        ),
+                        Ordering::Less => Ok(Value::PartiallyAppliedFunc {
+                            func: func.clone(),
+                            values: all_values,
+                        }),
+                    }
                 }
             }
         }
         _ => Err(Error::UnknownFunction),
     }
+}
 
-    // log::error!("{:?}", function_expr);
-    // log::error!("{:?}", _func);
-    // Err(Error::UnknownFunction)
-    /*
-    match env::get_binding(&environment, name) {
-        Ok(FoundBinding::BuiltInFunc(func)) => {
-            let arg_values = arg_exprs
-                .iter()
-                .map(|expr| evaluate_expression(&expr, &environment))
-                .collect::<Result<Vec<Value>, Error>>()?;
-
-            func.call(arg_values).map_err(Error::FunctionError)
-        }
-        Ok(FoundBinding::WithEnv(Binding::UserFunc(stmt_rc), _env)) =>
-        match &*stmt_rc {
-            Stmt::Function { args, expr, .. } => {
-                if arg_exprs.len() != args.len() {
-                    Err(Error::WrongArity)
-                } else {
-                    // Evaluate each argument to the function call and create a map from argument
-                    // value to argument name to use as a scope within the function evaluation
-                    let pairs = args
-                        .iter()
-                        .zip(arg_exprs.iter())
-                        .map(|(Pattern::Name(name), expr)| {
-                            evaluate_expression(expr, &environment).map(|value| {
-                                (
-                                    ast::LowerName::simple(name.to_string()),
-                                    Binding::Value(value),
-                                )
-                            })
-                        })
-                        .collect::<Result<_, _>>()?;
-
-                    let arg_scope = env::Scope::from_bindings(pairs);
-
-                    let environment = env::add_local_scope(environment, arg_scope);
-                    // println!("Environment: {:#?}", environment);
-                    evaluate_expression(&expr, &environment)
-                }
-            }
-            _ => Err(Error::UnknownFunction(name.to_string())),
-        },
-        Ok(FoundBinding::WithEnv(Binding::UserBinding(expr), _env)) => {
-            // println!("expr {:#?}", expr);
-            match &*expr {
-                Expr::VarName(lower_name) => {
-                    evaluate_function_call(lower_name, arg_exprs, environment)
+/* Evaluates a tree of binary operators, walking it in order rather than recursing for each
+ * operator as a chain of them can be very deep
+ */
+fn evaluate_binary_expression(
+    expr_id: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_binary_expression");
+    let mut values = Vec::new();
+    for id in environment.arena.operator_tree(expr_id) {
+        match &environment.arena[id] {
+            Expr::BinOp { operator, .. } => {
+                // The operators below the top one count as steps as they would if we recursed
+                if id != expr_id && usage.step(environment.scope_depth()) {
+                    return Err(Error::Timeout { trace: vec![] });
                 }
-                _ => Err(Error::UnknownFunction(name.to_string())),
+                let right_value = values.pop().ok_or(Error::UnsupportedOperation)?;
+                let left_value = values.pop().ok_or(Error::UnsupportedOperation)?;
+                values.push(apply_operator(
+                    operator,
+                    left_value,
+                    right_value,
+                    environment,
+                    settings,
+                    usage,
+                )?);
             }
-        }
-        entry => {
-            log::error!("{:?} {} {:#?}", entry, name.to_string(), environment);
-            Err(Error::UnknownFunction(name.to_string()))
+            _ => values.push(evaluate_expression(id, environment, settings, usage)?),
         }
     }
-    */
+
+    values.pop().ok_or(Error::UnsupportedOperation)
 }
 
-fn evaluate_binary_expression(
+fn apply_operator(
     operator: &str,
-    left: &Expr,
-    right: &Expr,
+    left_value: Value,
+    right_value: Value,
     environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
-    log::trace!("evaluate_binary_expression");
-    let left_value = evaluate_expression(left, environment)?;
-    let right_value = evaluate_expression(right, environment)?;
-
-    match (operator, left_value, right_value) {
-        ("+", Value::Integer(l), Value::Integer(r)) => Ok(Value::Integer(l + r)),
-        ("+", Value::Float(l), Value::Float(r)) => Ok(Value::Float(l + r)),
-        ("-", Value::Integer(l), Value::Integer(r)) => Ok(Value::Integer(l - r)),
-        ("-", Value::Float(l), Value::Float(r)) => Ok(Value::Float(l - r)),
-        ("*", Value::Integer(l), Value::Integer(r)) => Ok(Value::Integer(l * r)),
-        ("*", Value::Float(l), Value::Float(r)) => Ok(Value::Float(l * r)),
-        ("++", Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
-        (">", Value::Integer(l), Value::Integer(r)) => Ok(Value::Bool(l > r)),
-        (">", Value::Float(l), Value::Float(r)) => Ok(Value::Bool(l > r)),
-        (">=", Value::Integer(l), Value::Integer(r)) => Ok(Value::Bool(l >= r)),
-        (">=", Value::Float(l), Value::Float(r)) => Ok(Value::Bool(l >= r)),
-        ("<", Value::Integer(l), Value::Integer(r)) => Ok(Value::Bool(l < r)),
-        ("<", Value::Float(l), Value::Float(r)) => Ok(Value::Bool(l < r)),
-        ("<=", Value::Integer(l), Value::Integer(r)) => Ok(Value::Bool(l <= r)),
-        ("<=", Value::Float(l), Value::Float(r)) => Ok(Value::Bool(l <= r)),
-        _ => Err(Error::UnsupportedOperation),
+    // Fast path for arithmetic & comparisons on numbers which are by far the most common binary
+    // expressions and don't need to go via the operator table and built-in dispatch
+    match (operator, &left_value, &right_value) {
+        ("+", Value::Integer(l), Value::Integer(r)) => {
+            return builtins::int_arithmetic(
+                i64::checked_add,
+                i64::wrapping_add,
+                *l,
+                *r,
+                settings.integer_overflow,
+            )
+            .map(Value::Integer)
+            .map_err(Error::FunctionError)
+        }
+        ("+", Value::Float(l), Value::Float(r)) => return Ok(Value::Float(l + r)),
+        ("-", Value::Integer(l), Value::Integer(r)) => {
+            return builtins::int_arithmetic(
+                i64::checked_sub,
+                i64::wrapping_sub,
+                *l,
+                *r,
+                settings.integer_overflow,
+            )
+            .map(Value::Integer)
+            .map_err(Error::FunctionError)
+        }
+        ("-", Value::Float(l), Value::Float(r)) => return Ok(Value::Float(l - r)),
+        ("*", Value::Integer(l), Value::Integer(r)) => {
+            return builtins::int_arithmetic(
+                i64::checked_mul,
+                i64::wrapping_mul,
+                *l,
+                *r,
+                settings.integer_overflow,
+            )
+            .map(Value::Integer)
+            .map_err(Error::FunctionError)
+        }
+        ("*", Value::Float(l), Value::Float(r)) => return Ok(Value::Float(l * r)),
+        (">", Value::Integer(l), Value::Integer(r)) => return Ok(Value::Bool(l > r)),
+        (">", Value::Float(l), Value::Float(r)) => return Ok(Value::Bool(l > r)),
+        ("<", Value::Integer(l), Value::Integer(r)) => return Ok(Value::Bool(l < r)),
+        ("<", Value::Float(l), Value::Float(r)) => return Ok(Value::Bool(l < r)),
+        _ => {}
     }
+
+    // Otherwise we find the function for the operator and apply it to the two values
+    let (operator, operator_env) = env::get_operator(environment, operator)
+        .ok_or_else(|| Error::OperatorNotExposed(operator.to_string()))?;
+
+    let func = match operator.binding {
+        Binding::UserBinding(expr) => evaluate_binding(
+            &operator.function_name,
+            expr,
+            &operator_env,
+            settings,
+            usage,
+        ),
+        Binding::UserFunc(stmt) => evaluate_statement(&stmt, &operator_env),
+        _ => Err(Error::UnsupportedOperation),
+    }?;
+
+    apply_function(func, vec![left_value, right_value], settings, usage)
 }
 
 fn evaluate_if_expression(
-    condition: &Expr,
-    then_branch: &Expr,
-    else_branch: &Expr,
+    condition: ExprId,
+    then_branch: ExprId,
+    else_branch: ExprId,
     environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
     log::trace!("evaluate_if_expression");
-    let condition_value = evaluate_expression(condition, environment)?;
+    let condition_value = evaluate_expression(condition, environment, settings, usage)?;
 
-    match condition_value {
-        Value::Bool(true) => evaluate_expression(then_branch, environment),
-        Value::Bool(false) => evaluate_expression(else_branch, environment),
-        _ => Err(Error::UnsupportedOperation),
-    }
+    let branch = match condition_value {
+        Value::Bool(true) => then_branch,
+        Value::Bool(false) => else_branch,
+        _ => return Err(Error::UnsupportedOperation),
+    };
+
+    record_branch(branch, environment, settings);
+    evaluate_expression(branch, environment, settings, usage)
 }
 
 fn evaluate_case_expression(
-    expr: &Expr,
-    branches: &[(Pattern, Expr)],
+    expr: ExprId,
+    branches: &[(Pattern, ExprId)],
+    range: &Range,
     environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
     log::trace!("evaluate_case_expression");
-    let expr_value = evaluate_expression(expr, environment)?;
+    let expr_value = evaluate_expression(expr, environment, settings, usage)?;
 
     for (pattern, branch_expr) in branches {
-        if pattern_matches_values(&pattern, &expr_value) {
-            return evaluate_expression(branch_expr, environment);
+        let mut bound_values = Vec::new();
+        if pattern_matches_values(pattern, &expr_value, &mut bound_values) {
+            // The names bound by the pattern make up the local scope of the branch
+            let slots = pattern
+                .names()
+                .into_iter()
+                .zip(bound_values)
+                .map(|(name, value)| (name, Binding::Value(value)))
+                .collect();
+            let environment = environment.push_scope(env::Scope::from_slots(slots));
+
+            record_branch(*branch_expr, &environment, settings);
+            return evaluate_expression(*branch_expr, &environment, settings, usage);
         }
     }
 
     log::error!("No matching case");
-    Err(Error::NoMatchingCase)
+    Err(Error::NoMatchingCase {
+        value: expr_value.to_string(),
+        patterns: branches
+            .iter()
+            .map(|(pattern, _)| printer::print_pattern(pattern))
+            .collect(),
+        range: range.clone(),
+    })
+}
+
+fn evaluate_let_expression(
+    bindings: &[LetBinding],
+    body: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_let_expression");
+    // All of the names share one scope so that each binding can refer to itself & the others. The
+    // bindings are evaluated when they are first needed
+    let slots = bindings
+        .iter()
+        .flat_map(|LetBinding { pattern, expr, .. }| {
+            pattern
+                .names()
+                .into_iter()
+                .map(move |name| (name, Binding::UserPattern(pattern.clone(), *expr)))
+        })
+        .collect();
+    let environment = environment.push_scope(env::Scope::from_slots(slots));
+
+    // Then we evaluate the rest in order so that patterns that don't match are reported even when
+    // none of their names are used. Lambdas can't fail so we leave them until they are used
+    for LetBinding { pattern, expr, .. } in bindings {
+        let evaluated = pattern.names().first().is_some_and(|name| {
+            environment
+                .get_evaluated(&ast::QualifiedLowerName::simple(name.clone()))
+                .is_some()
+        });
+        if !evaluated && !matches!(environment.arena[*expr], Expr::Lambda { .. }) {
+            bind_let_pattern(pattern, *expr, &environment, settings, usage)?;
+        }
+    }
+
+    evaluate_expression(body, &environment, settings, usage)
 }
 
-fn pattern_matches_values(pattern: &Pattern, value: &Value) -> bool {
+/* Returns the value of a name bound by a let binding from the environment of the let expression,
+ * evaluating the binding if it hasn't been already
+ */
+fn evaluate_let_binding(
+    name: &str,
+    pattern: &Pattern,
+    expr: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Value, Error> {
+    log::trace!("evaluate_let_binding: {}", name);
+    if let Some(value) =
+        environment.get_evaluated(&ast::QualifiedLowerName::simple(name.to_string()))
+    {
+        return Ok(value);
+    }
+
+    bind_let_pattern(pattern, expr, environment, settings, usage)?
+        .into_iter()
+        .find_map(|(bound_name, value)| (bound_name == name).then_some(value))
+        .ok_or_else(|| Error::UnknownBinding(name.to_string()))
+}
+
+/* Matches the pattern of a let binding against the value of its expression and returns the names
+ * that it binds with their values. The values are kept in the scope of the let expression so that
+ * the binding is only evaluated once, except for lambdas as the scope would then hold a closure
+ * over itself and never be freed
+ */
+fn bind_let_pattern(
+    pattern: &Pattern,
+    expr: ExprId,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Vec<(String, Value)>, Error> {
+    let value = evaluate_expression(expr, environment, settings, usage)?;
+
+    let mut bound_values = Vec::new();
+    if !pattern_matches_values(pattern, &value, &mut bound_values) {
+        return Err(Error::UnmatchedLetPattern);
+    }
+
+    let bound: Vec<(String, Value)> = pattern.names().into_iter().zip(bound_values).collect();
+    if !matches!(environment.arena[expr], Expr::Lambda { .. }) {
+        for (name, value) in &bound {
+            environment.set_evaluated(
+                &ast::QualifiedLowerName::simple(name.clone()),
+                value.clone(),
+            );
+        }
+    }
+    Ok(bound)
+}
+
+fn is_allowed(capability: builtins::Capability, settings: &project::Settings) -> bool {
+    let capabilities = &settings.capabilities;
+    match capability {
+        builtins::Capability::Fs => capabilities.fs,
+        builtins::Capability::Net => capabilities.net,
+        builtins::Capability::Env => capabilities.env,
+        builtins::Capability::Process => capabilities.process,
+        builtins::Capability::Time => capabilities.time,
+    }
+}
+
+fn checked(value: Value, settings: &project::Settings, usage: &Usage) -> Result<Value, Error> {
+    limits::check(&value, settings, usage)?;
+    Ok(value)
+}
+
+/* Records the function that a timeout passed through so that the user can see what the program
+ * was doing when it was stopped
+ */
+fn add_to_trace(error: Error, name: &str) -> Error {
+    match error {
+        Error::Timeout { mut trace } => {
+            trace.push(name.to_string());
+            Error::Timeout { trace }
+        }
+        error => error,
+    }
+}
+
+fn record_branch(branch: ExprId, environment: &env::Environment, settings: &project::Settings) {
+    notify(settings, |observer| {
+        observer.on_branch(&environment.arena, branch)
+    });
+}
+
+fn notify(settings: &project::Settings, event: impl Fn(&dyn EvalObserver)) {
+    for observer in &settings.observers {
+        event(observer.as_ref());
+    }
+}
+
+/* Checks whether the value matches the pattern. The values matched by names in the pattern are
+ * added to bound_values in the same order as the names are returned by Pattern::names
+ */
+fn pattern_matches_values(pattern: &Pattern, value: &Value, bound_values: &mut Vec<Value>) -> bool {
     match (pattern, value) {
         (Pattern::Bool(p_bool), Value::Bool(v_bool)) => p_bool == v_bool,
         (Pattern::Integer(p_int), Value::Integer(v_int)) => p_int == v_int,
+        (Pattern::String(p_string), Value::String(v_string)) => p_string.as_str() == &**v_string,
+        (Pattern::Char(p_char), Value::Char(v_char)) => p_char == v_char,
         (Pattern::Anything, _) => true,
+        (Pattern::Name(_), _) => {
+            bound_values.push(value.clone());
+            true
+        }
+        (Pattern::Tuple(patterns), Value::Tuple(values))
+        | (Pattern::List(patterns), Value::List(values)) => {
+            all_patterns_match(patterns, values, bound_values)
+        }
+        (Pattern::Cons(head, tail), Value::List(values)) => match values.split_first() {
+            Some((first, rest)) => {
+                pattern_matches_values(head, first, bound_values)
+                    && pattern_matches_values(tail, &Value::List(rest.to_vec()), bound_values)
+            }
+            None => false,
+        },
+        (
+            Pattern::Constructor(p_name, patterns),
+            Value::Custom {
+                name: v_name,
+                args: values,
+            },
+        ) => p_name.access == *v_name && all_patterns_match(patterns, values, bound_values),
+        (Pattern::Record(names), Value::Record(fields)) => {
+            names.iter().all(
+                |name| match fields.iter().find(|(field, _value)| field == name) {
+                    Some((_field, value)) => {
+                        bound_values.push(value.clone());
+                        true
+                    }
+                    None => false,
+                },
+            )
+        }
         _ => false,
     }
 }
+
+fn all_patterns_match(
+    patterns: &[Pattern],
+    values: &[Value],
+    bound_values: &mut Vec<Value>,
+) -> bool {
+    patterns.len() == values.len()
+        && patterns
+            .iter()
+            .zip(values)
+            .all(|(pattern, value)| pattern_matches_values(pattern, value, bound_values))
+}
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use super::observer::EvalObserver;
+use crate::ast::{Arena, ExprId};
+
+/* Records which branches of if & case expressions are executed. Expression ids are only unique
+ * within an arena so hits are keyed by the address of the arena as well. The arenas are kept alive
+ * by the environments for the length of the run so the addresses stay valid.
+ */
+#[derive(Debug, Default)]
+pub struct Coverage {
+    hits: Mutex<HashMap<(usize, ExprId), usize>>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Self {
+            hits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record(&self, arena: &Arena, branch: ExprId) {
+        if let Ok(mut hits) = self.hits.lock() {
+            *hits.entry((arena_key(arena), branch)).or_insert(0) += 1;
+        }
+    }
+
+    /* Lists every branch in the arena, which should be the one for the given source, alongside the
+     * line that it starts on and how many times it was executed.
+     */
+    pub fn report(&self, arena: &Arena, source: &str) -> String {
+        let hits = match self.hits.lock() {
+            Ok(hits) => hits,
+            Err(_) => return String::new(),
+        };
+
+        let mut branches: Vec<_> = arena
+            .ranges()
+            .map(|(branch, range)| {
+                let count = hits.get(&(arena_key(arena), *branch)).copied().unwrap_or(0);
+                (range.start, count)
+            })
+            .collect();
+        branches.sort_unstable();
+
+        let executed = branches.iter().filter(|(_, count)| *count > 0).count();
+        let mut output = format!(
+            "Branch coverage: {} of {} branches executed\n",
+            executed,
+            branches.len()
+        );
+
+        if !branches.is_empty() {
+            output.push('\n');
+        }
+
+        for (start, count) in branches {
+            let line_number = source[..start].matches('\n').count() + 1;
+            let line = source.lines().nth(line_number - 1).unwrap_or("");
+            let note = match count {
+                0 => "never executed".to_string(),
+                1 => "executed 1 time".to_string(),
+                count => format!("executed {} times", count),
+            };
+
+            output.push_str(&format!("{:>4} | {:<40} -- {}\n", line_number, line, note));
+        }
+
+        output
+    }
+}
+
+impl EvalObserver for Coverage {
+    fn on_branch(&self, arena: &Arena, branch: ExprId) {
+        self.record(arena, branch);
+    }
+}
+
+fn arena_key(arena: &Arena) -> usize {
+    arena as *const Arena as usize
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use logos::Logos;
+
+    use super::*;
+    use crate::ast;
+    use crate::env;
+    use crate::evaluator;
+    use crate::evaluator::limits::Usage;
+    use crate::lexer::Token;
+    use crate::parser;
+    use crate::project;
+
+    #[test]
+    fn reports_branches_that_were_not_executed() {
+        let source = "module Main exposing (main)
+main args =
+    if True then
+        \"yes\"
+    else
+        \"no\"
+";
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        let module = ast::with_default_imports(&parser::parse(&mut iter).expect("Failed to parse"));
+
+        let coverage = Arc::new(Coverage::new());
+        let settings = project::Settings {
+            observers: vec![coverage.clone()],
+            ..project::Settings::new()
+        };
+
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+        evaluator::evaluate(&module, vec![], &environment, &settings, &Usage::default())
+            .expect("Failed to evaluate");
+
+        assert_eq!(
+            coverage.report(&environment.arena, source),
+            "Branch coverage: 1 of 2 branches executed
+
+   3 |     if True then                         -- executed 1 time
+   5 |     else                                 -- never executed
+"
+        );
+    }
+}
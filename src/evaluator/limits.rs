@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use super::values::Value;
+use super::Error;
+use crate::project;
+
+// Reading the clock is slow compared to evaluating an expression so we only check it this often
+const STEPS_PER_CHECK: usize = 100;
+
+/* The resources used by a single evaluation so that it can be stopped once it goes over the
+ * limits in the settings. Each evaluation has its own, passed alongside the settings, so that
+ * evaluations which share the settings don't reset or add to each other's counts.
+ */
+#[derive(Debug, Default)]
+pub struct Usage {
+    max_duration: Option<Duration>,
+    // Set by the first step so that loading the program doesn't count towards the duration
+    deadline: OnceLock<Instant>,
+    steps: AtomicUsize,
+    values: AtomicUsize,
+    calls: AtomicUsize,
+    lists: AtomicUsize,
+    strings: AtomicUsize,
+    max_scope_depth: AtomicUsize,
+}
+
+/* Counts of the work done by the last evaluation so that the cost of a program can be compared
+ * between versions of it, or of erm
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Stats {
+    pub expressions: usize,
+    pub calls: usize,
+    // Lists & strings built while evaluating, including those returned by builtins. Strings
+    // written in the source aren't counted
+    pub lists: usize,
+    pub strings: usize,
+    // The most local scopes that were in effect at once, including the module's top level
+    pub max_scope_depth: usize,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Resource {
+    ListLength,
+    StringLength,
+    Values,
+}
+
+impl Usage {
+    /* The usage for a new evaluation. The clock is only read when there is a duration to enforce
+     * as it isn't available in every environment, eg. in the browser.
+     */
+    pub fn new(max_duration: Option<Duration>) -> Self {
+        Self {
+            max_duration,
+            ..Self::default()
+        }
+    }
+
+    /* Records an evaluation step in an environment with the given number of local scopes and
+     * returns whether the deadline has passed
+     */
+    pub fn step(&self, scope_depth: usize) -> bool {
+        self.max_scope_depth
+            .fetch_max(scope_depth, Ordering::Relaxed);
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+        let Some(max_duration) = self.max_duration else {
+            return false;
+        };
+
+        let deadline = *self.deadline.get_or_init(|| Instant::now() + max_duration);
+        steps.is_multiple_of(STEPS_PER_CHECK) && Instant::now() >= deadline
+    }
+
+    /* Records a call of a user defined or builtin function */
+    pub fn call(&self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> Stats {
+        Stats {
+            expressions: self.steps.load(Ordering::Relaxed),
+            calls: self.calls.load(Ordering::Relaxed),
+            lists: self.lists.load(Ordering::Relaxed),
+            strings: self.strings.load(Ordering::Relaxed),
+            max_scope_depth: self.max_scope_depth.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/* Checks a value that the evaluator has just built against the size limits in the settings and
+ * adds it to the counts of values allocated so far. Only the outer value is checked as the values
+ * inside it were checked when they were built. String lengths are measured in bytes.
+ */
+pub fn check(value: &Value, settings: &project::Settings, usage: &Usage) -> Result<(), Error> {
+    let (length, limit, resource) = match value {
+        Value::List(items) => (items.len(), settings.max_list_length, Resource::ListLength),
+        Value::String(string) => (
+            string.len(),
+            settings.max_string_length,
+            Resource::StringLength,
+        ),
+        Value::Tuple(items) => (items.len(), None, Resource::ListLength),
+        Value::Record(fields) => (fields.len(), None, Resource::ListLength),
+        Value::Custom { args, .. } => (args.len(), None, Resource::ListLength),
+        _ => return Ok(()),
+    };
+
+    if let Some(limit) = limit.filter(|limit| length > *limit) {
+        return Err(Error::ResourceLimit { resource, limit });
+    }
+
+    match value {
+        Value::List(_) => usage.lists.fetch_add(1, Ordering::Relaxed),
+        Value::String(_) => usage.strings.fetch_add(1, Ordering::Relaxed),
+        _ => 0,
+    };
+
+    // The value itself along with the items that it holds
+    let values = usage.values.fetch_add(length + 1, Ordering::Relaxed) + length + 1;
+    match settings.max_values {
+        Some(limit) if values > limit => Err(Error::ResourceLimit {
+            resource: Resource::Values,
+            limit,
+        }),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,92 @@
+use super::values::{Func, Value};
+use crate::ast::{Arena, ExprId};
+
+/* Hooks for embedders to follow evaluation, for example to profile, debug or measure coverage.
+ * Observers are registered in the project settings and every method defaults to doing nothing so
+ * implementations only need to provide the ones that they care about.
+ */
+pub trait EvalObserver: Send + Sync {
+    // Called when a function has all of its arguments and is about to be evaluated
+    fn on_call(&self, _func: &Func, _args: &[Value]) {}
+
+    // Called when a function has successfully returned a value
+    fn on_return(&self, _func: &Func, _value: &Value) {}
+
+    // Called when evaluation enters a branch of an if or case expression. The branch is an
+    // expression in the given arena which has a source range recorded for it
+    fn on_branch(&self, _arena: &Arena, _branch: ExprId) {}
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{Arc, Mutex};
+
+    use logos::Logos;
+
+    use super::*;
+    use crate::ast;
+    use crate::env;
+    use crate::evaluator;
+    use crate::evaluator::limits::Usage;
+    use crate::lexer::Token;
+    use crate::parser;
+    use crate::project;
+
+    #[derive(Default)]
+    struct Recorder {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl EvalObserver for Recorder {
+        fn on_call(&self, func: &Func, args: &[Value]) {
+            if let Func::UserFunc { name, .. } = func {
+                if let Ok(mut events) = self.events.lock() {
+                    events.push(format!("call {} {:?}", name, args));
+                }
+            }
+        }
+
+        fn on_return(&self, func: &Func, value: &Value) {
+            if let Func::UserFunc { name, .. } = func {
+                if let Ok(mut events) = self.events.lock() {
+                    events.push(format!("return {} {:?}", name, value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn observes_user_function_calls() {
+        let source = "module Main exposing (main)
+double x =
+    x * 2
+main args =
+    double 3
+";
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        let module = ast::with_default_imports(&parser::parse(&mut iter).expect("Failed to parse"));
+
+        let recorder = Arc::new(Recorder::default());
+        let settings = project::Settings {
+            observers: vec![recorder.clone()],
+            ..project::Settings::new()
+        };
+
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+        evaluator::evaluate(&module, vec![], &environment, &settings, &Usage::default())
+            .expect("Failed to evaluate");
+
+        let events = recorder.events.lock().map(|events| events.clone());
+        assert_eq!(
+            events.unwrap_or_default(),
+            vec![
+                "call main [List([])]",
+                "call double [Integer(3)]",
+                "return double Integer(6)",
+                "return main Integer(6)",
+            ]
+        );
+    }
+}
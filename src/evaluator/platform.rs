@@ -0,0 +1,44 @@
+use super::limits::Usage;
+use super::values::{Program, Value};
+use super::{apply_function, Error};
+use crate::project;
+
+/* Runs a program built with Platform.worker. The model is kept between messages so the program
+ * can live for as long as the host keeps sending them, eg. for each line of standard input.
+ */
+pub struct Worker {
+    update: Value,
+    model: Value,
+}
+
+impl Worker {
+    pub fn new(program: &Program) -> Self {
+        Self {
+            update: program.update.clone(),
+            model: program.init.clone(),
+        }
+    }
+
+    /* Passes the message to the program's update function. The limits in the settings apply to
+     * each message separately, with its own usage, as the program is expected to keep running.
+     */
+    pub fn update(
+        &mut self,
+        msg: Value,
+        settings: &project::Settings,
+        usage: &Usage,
+    ) -> Result<&Value, Error> {
+        log::trace!("Worker::update");
+        self.model = apply_function(
+            self.update.clone(),
+            vec![msg, self.model.clone()],
+            settings,
+            usage,
+        )?;
+        Ok(&self.model)
+    }
+
+    pub fn model(&self) -> &Value {
+        &self.model
+    }
+}
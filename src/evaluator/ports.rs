@@ -0,0 +1,130 @@
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use std::sync::Mutex;
+
+use super::limits::Usage;
+use super::values::{JsonError, Sub, Value};
+use super::{apply_function, Error};
+use crate::ast::PortDirection;
+use crate::project;
+
+/* The connection between the program's ports and the Rust program embedding erm. Values cross as
+ * JSON so that they can be sent between threads. Outgoing values are delivered to the channel
+ * registered with 'listen' and incoming values wait in a queue for each port until the host asks
+ * for the messages of a subscription with 'receive'.
+ */
+#[derive(Debug, Default)]
+pub struct Ports {
+    listener: Mutex<Option<Sender<PortMessage>>>,
+    incoming: Mutex<HashMap<String, VecDeque<serde_json::Value>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PortMessage {
+    pub port: String,
+    pub value: serde_json::Value,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PortError {
+    // The program sent a value but the host isn't listening for them
+    NoListener(String),
+    Json(String, JsonError),
+}
+
+impl Ports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* Values sent to outgoing ports are delivered to the channel from now on */
+    pub fn listen(&self, sender: Sender<PortMessage>) {
+        if let Ok(mut listener) = self.listener.lock() {
+            *listener = Some(sender);
+        }
+    }
+
+    /* Queues a value from the host for the named incoming port */
+    pub fn inject(&self, port: &str, value: serde_json::Value) {
+        if let Ok(mut incoming) = self.incoming.lock() {
+            incoming
+                .entry(port.to_string())
+                .or_default()
+                .push_back(value);
+        }
+    }
+
+    fn send(&self, port: &str, value: &Value) -> Result<(), PortError> {
+        let value = value
+            .to_json()
+            .map_err(|error| PortError::Json(port.to_string(), error))?;
+
+        let listener = self.listener.lock().ok();
+        listener
+            .as_ref()
+            .and_then(|listener| listener.as_ref())
+            .and_then(|sender| {
+                sender
+                    .send(PortMessage {
+                        port: port.to_string(),
+                        value,
+                    })
+                    .ok()
+            })
+            .ok_or_else(|| PortError::NoListener(port.to_string()))
+    }
+
+    fn take(&self, port: &str) -> Vec<serde_json::Value> {
+        self.incoming
+            .lock()
+            .ok()
+            .and_then(|mut incoming| incoming.remove(port))
+            .map(Vec::from)
+            .unwrap_or_default()
+    }
+}
+
+/* Calls the port with its argument. Outgoing ports hand the value to the host straight away as
+ * there is no runtime to carry out commands, so the command itself carries nothing. Incoming ports
+ * return a subscription which the host can later pass to 'receive'.
+ */
+pub fn call(
+    name: &str,
+    direction: PortDirection,
+    arg: Value,
+    settings: &project::Settings,
+) -> Result<Value, Error> {
+    log::trace!("ports::call {}", name);
+    match direction {
+        PortDirection::Outgoing => {
+            settings.ports.send(name, &arg).map_err(Error::PortError)?;
+            Ok(Value::Unit)
+        }
+        PortDirection::Incoming => Ok(Value::Sub(Rc::new(Sub {
+            port: name.to_string(),
+            tagger: arg,
+        }))),
+    }
+}
+
+/* Takes the values that the host has sent to the subscription's port and turns each of them into
+ * a message for the program with the subscription's tagger.
+ */
+pub fn receive(
+    sub: &Sub,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Vec<Value>, Error> {
+    log::trace!("ports::receive {}", sub.port);
+    settings
+        .ports
+        .take(&sub.port)
+        .iter()
+        .map(|json| {
+            let value = Value::from_json(json)
+                .map_err(|error| Error::PortError(PortError::Json(sub.port.clone(), error)))?;
+            apply_function(sub.tagger.clone(), vec![value], settings, usage)
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+use std::rc::Rc;
+
+use super::limits::Usage;
+use super::values::{Expectation, Test, Value};
+use super::{apply_function, evaluate_var_name, recover, Error};
+use crate::ast::{self, Exposing, ExposingDetail, Module, Stmt};
+use crate::env;
+use crate::project;
+
+/* Runs the test suites written with the Test & Expect core modules in the style of elm-test. Every
+ * exposed top level value of the module which evaluates to a Test is run.
+ */
+
+#[derive(Debug, PartialEq)]
+pub struct TestResult {
+    // The names of the enclosing describe blocks followed by the name of the test
+    pub path: Vec<String>,
+    pub outcome: Expectation,
+}
+
+pub fn run_tests(
+    module: &Module,
+    environment: &env::Environment,
+    settings: &project::Settings,
+    usage: &Usage,
+) -> Result<Vec<TestResult>, Error> {
+    log::trace!("run_tests");
+    let mut results = vec![];
+
+    for stmt in &module.statements {
+        let name = match &**stmt {
+            Stmt::Binding { name, .. } if is_exposed(&module.exposing, &name.0) => &name.0,
+            _ => continue,
+        };
+
+        let qualified_name = ast::QualifiedLowerName::simple(name.to_string());
+        let value = evaluate_var_name(&qualified_name, environment, settings, usage);
+        match recover(value, settings, |error| {
+            format!("Failed to evaluate test: {:?}", error)
+        })? {
+            Value::Test(test) => run_test(&test, &mut vec![], &mut results, settings, usage),
+            // The suite couldn't be built so we report it as a single failure
+            Value::RuntimeError(message) => results.push(TestResult {
+                path: vec![name.to_string()],
+                outcome: Expectation::Fail(message.to_string()),
+            }),
+            _ => {}
+        }
+    }
+
+    Ok(results)
+}
+
+fn is_exposed(exposing: &Exposing, name: &str) -> bool {
+    match exposing {
+        Exposing::All => true,
+        Exposing::List(details) => details
+            .iter()
+            .any(|detail| matches!(detail, ExposingDetail::Name(exposed) if exposed == name)),
+    }
+}
+
+fn run_test(
+    test: &Rc<Test>,
+    path: &mut Vec<String>,
+    results: &mut Vec<TestResult>,
+    settings: &project::Settings,
+    usage: &Usage,
+) {
+    match &**test {
+        Test::Test { name, body } => {
+            let outcome = match apply_function(body.clone(), vec![Value::Unit], settings, usage) {
+                Ok(Value::Expectation(expectation)) => expectation,
+                Ok(value) => {
+                    Expectation::Fail(format!("Expected an Expectation but got {}", value))
+                }
+                Err(error) => Expectation::Fail(format!("Failed to evaluate test: {:?}", error)),
+            };
+
+            let mut path = path.clone();
+            path.push(name.clone());
+            results.push(TestResult { path, outcome });
+        }
+        Test::Describe { name, tests } => {
+            path.push(name.clone());
+            for test in tests {
+                run_test(test, path, results, settings, usage);
+            }
+            path.pop();
+        }
+    }
+}
+
+/* Formats the results similarly to elm-test, listing the failures followed by a summary */
+pub fn report(results: &[TestResult]) -> String {
+    let mut output = String::new();
+
+    let failures: Vec<_> = results
+        .iter()
+        .filter_map(|result| match &result.outcome {
+            Expectation::Pass => None,
+            Expectation::Fail(message) => Some((&result.path, message)),
+        })
+        .collect();
+
+    for (path, message) in &failures {
+        if let Some((name, describes)) = path.split_last() {
+            for describe in describes {
+                output.push_str(&format!("↓ {}\n", describe));
+            }
+            output.push_str(&format!("✗ {}\n\n", name));
+        }
+
+        for line in message.lines() {
+            output.push_str(&format!("    {}\n", line));
+        }
+        output.push('\n');
+    }
+
+    let status = if failures.is_empty() && !results.is_empty() {
+        "PASSED"
+    } else {
+        "FAILED"
+    };
+
+    output.push_str(&format!(
+        "TEST RUN {}\n\nPassed: {}\nFailed: {}\n",
+        status,
+        results.len() - failures.len(),
+        failures.len()
+    ));
+
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+
+    #[test]
+    fn runs_nested_tests() {
+        let source = "module Tests exposing (suite)
+import Expect
+import Test exposing (describe, test)
+
+adds _ =
+    Expect.equal 2 (1 + 1)
+
+subtracts _ =
+    Expect.equal 1 (3 - 1)
+
+isBig _ =
+    Expect.true \"Expected a big number\" (10 > 5)
+
+suite =
+    describe \"Math\"
+        [ test \"adds\" adds
+        , describe \"More\" [ test \"subtracts\" subtracts, test \"is big\" isBig ]
+        ]
+";
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        let module = ast::with_default_imports(&parser::parse(&mut iter).expect("Failed to parse"));
+
+        let settings = project::Settings::new();
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+
+        let results =
+            run_tests(&module, &environment, &settings, &Usage::default()).expect("Failed to run");
+        assert_eq!(
+            report(&results),
+            "↓ Math
+↓ More
+✗ subtracts
+
+    2
+    ╷
+    │ Expect.equal
+    ╵
+    1
+
+TEST RUN FAILED
+
+Passed: 2
+Failed: 1
+"
+        );
+    }
+
+    #[test]
+    fn suites_that_fail_to_build_are_failures_when_recovering() {
+        let source = "module Tests exposing (broken, suite)
+import Expect
+import Test exposing (test)
+
+adds _ =
+    Expect.equal 2 (1 + 1)
+
+broken =
+    if 9223372036854775807 + 1 > 0 then
+        test \"big\" adds
+    else
+        test \"small\" adds
+
+suite =
+    test \"adds\" adds
+";
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        let module = ast::with_default_imports(&parser::parse(&mut iter).expect("Failed to parse"));
+
+        let settings = project::Settings {
+            recover_errors: true,
+            ..project::Settings::new()
+        };
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+
+        let results =
+            run_tests(&module, &environment, &settings, &Usage::default()).expect("Failed to run");
+        let outcomes: Vec<_> = results
+            .iter()
+            .map(|result| (result.path.join("/"), result.outcome == Expectation::Pass))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![("broken".to_string(), false), ("adds".to_string(), true)]
+        );
+    }
+}
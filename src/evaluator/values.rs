@@ -1,14 +1,59 @@
 use crate::ast;
+use crate::env;
 
+use std::fmt;
 use std::rc::Rc;
+use std::sync::Arc;
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub enum Func {
     UserFunc {
+        name: String,
         args: Vec<ast::Pattern>,
-        expr: Rc<ast::Expr>,
+        expr: ast::ExprId,
+        // The environment the function was defined in which gives access to the arena holding
+        // the function body
+        environment: env::Environment,
     },
     BuiltInFunc(ast::QualifiedLowerName),
+    // A constructor of a custom type which builds the value once it has all its arguments
+    Constructor {
+        name: String,
+        arity: usize,
+    },
+    // A port declared by the module which passes values to or from the host
+    Port {
+        name: String,
+        direction: ast::PortDirection,
+    },
+}
+
+// Manual implementation as the environment can contain cached values which might contain this
+// function and so lead to an endless loop
+impl fmt::Debug for Func {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Func::UserFunc {
+                name, args, expr, ..
+            } => f
+                .debug_struct("UserFunc")
+                .field("name", name)
+                .field("args", args)
+                .field("expr", expr)
+                .finish(),
+            Func::BuiltInFunc(name) => f.debug_tuple("BuiltInFunc").field(name).finish(),
+            Func::Constructor { name, arity } => f
+                .debug_struct("Constructor")
+                .field("name", name)
+                .field("arity", arity)
+                .finish(),
+            Func::Port { name, direction } => f
+                .debug_struct("Port")
+                .field("name", name)
+                .field("direction", direction)
+                .finish(),
+        }
+    }
 }
 
 // TODO: Unsure about making this 'Clone'. Done so that we can have the Value binding without too
@@ -16,9 +61,433 @@ pub enum Func {
 #[derive(Debug, Clone)]
 pub enum Value {
     Bool(bool),
-    Integer(i32),
+    Integer(i64),
     Float(f32),
-    String(String),
+    Char(char),
+    // Strings are shared as they are cloned whenever a bound string is used. They are shared with
+    // the string literals in the arena, which have to be sendable between the threads that parse
+    // modules, hence Arc rather than Rc
+    String(Arc<str>),
     List(Vec<Value>),
+    Tuple(Vec<Value>),
+    // The fields of a record sorted by name
+    Record(Vec<(String, Value)>),
+    // A value built by one of the constructors of a custom type
+    Custom { name: String, args: Vec<Value> },
+    // Passed to functions that take no meaningful argument, like the bodies of tests
+    Unit,
     PartiallyAppliedFunc { func: Func, values: Vec<Value> },
+    Expectation(Expectation),
+    Test(Rc<Test>),
+    Seed(Seed),
+    Generator(Rc<Generator>),
+    // Binary data from the Bytes module
+    Bytes(Rc<[u8]>),
+    Sub(Rc<Sub>),
+    Program(Rc<Program>),
+    // The message of a runtime error that ended the evaluation of a test suite or repl input.
+    // Only produced when the settings ask to recover from errors, so that the rest of the
+    // session can carry on
+    RuntimeError(Rc<str>),
+}
+
+// The result of an assertion from the Expect module
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expectation {
+    Pass,
+    Fail(String),
+}
+
+// Tree of tests built by the Test module. Test bodies are functions which are only evaluated when
+// the test is run
+#[derive(Debug)]
+pub enum Test {
+    Test { name: String, body: Value },
+    Describe { name: String, tests: Vec<Rc<Test>> },
+}
+
+// The state of the random number generator from the Random module, which is passed explicitly
+// from one step to the next so that generating values stays pure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Seed {
+    pub state: u32,
+    pub increment: u32,
+}
+
+// A subscription to the messages that the host sends to an incoming port. The tagger is the
+// function which turns each value from the host into a message for the program
+#[derive(Debug)]
+pub struct Sub {
+    pub port: String,
+    pub tagger: Value,
+}
+
+// A long running program built by Platform.worker. The update function is given each message
+// along with the current model and returns the next model
+#[derive(Debug)]
+pub struct Program {
+    pub init: Value,
+    pub update: Value,
+}
+
+// Describes how to build a random value from a seed. Generators are only run by Random.step
+#[derive(Debug)]
+pub enum Generator {
+    Int(i64, i64),
+    Float(f32, f32),
+    List(usize, Rc<Generator>),
+}
+
+/* Structural equality for values. Functions are never equal to anything as we have no way to
+ * compare them.
+ */
+pub fn equal(left: &Value, right: &Value) -> bool {
+    match (left, right) {
+        (Value::Bool(left), Value::Bool(right)) => left == right,
+        (Value::Integer(left), Value::Integer(right)) => left == right,
+        (Value::Float(left), Value::Float(right)) => left == right,
+        (Value::Char(left), Value::Char(right)) => left == right,
+        (Value::String(left), Value::String(right)) => left == right,
+        (Value::List(left), Value::List(right)) | (Value::Tuple(left), Value::Tuple(right)) => {
+            all_equal(left, right)
+        }
+        (
+            Value::Custom {
+                name: left_name,
+                args: left_args,
+            },
+            Value::Custom {
+                name: right_name,
+                args: right_args,
+            },
+        ) => left_name == right_name && all_equal(left_args, right_args),
+        (Value::Record(left), Value::Record(right)) => {
+            left.len() == right.len()
+                && left
+                    .iter()
+                    .zip(right.iter())
+                    .all(|((left_name, left), (right_name, right))| {
+                        left_name == right_name && equal(left, right)
+                    })
+        }
+        (Value::Unit, Value::Unit) => true,
+        (Value::Expectation(left), Value::Expectation(right)) => left == right,
+        (Value::Seed(left), Value::Seed(right)) => left == right,
+        (Value::Bytes(left), Value::Bytes(right)) => left == right,
+        _ => false,
+    }
+}
+
+fn all_equal(left: &[Value], right: &[Value]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right.iter())
+            .all(|(left, right)| equal(left, right))
+}
+
+// Displays the value as Elm source where possible, for use in messages to the user. This matches
+// the output of Debug.toString. The output only depends on the value so that it is the same on
+// every run, which snapshot tests rely on. Items are printed in the order that the value holds them
+// and anything without an order of its own, like record fields or Dict entries, is to be printed
+// sorted by field name or key
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Bool(true) => write!(f, "True"),
+            Value::Bool(false) => write!(f, "False"),
+            Value::Integer(int) => write!(f, "{}", int),
+            Value::Float(float) => write!(f, "{:?}", float),
+            Value::Char(char) => match char {
+                '\'' => write!(f, "'\\''"),
+                '"' => write!(f, "'\"'"),
+                char => write!(f, "'{}'", escape(&char.to_string())),
+            },
+            Value::String(string) => write!(f, "\"{}\"", escape(string)),
+            Value::List(items) => write_items(f, "[", items, "]"),
+            Value::Tuple(items) => write_items(f, "(", items, ")"),
+            Value::Record(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Value::Record(fields) => {
+                write!(f, "{{ ")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{} = {}", name, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Custom { name, args } => {
+                write!(f, "{}", name)?;
+                for arg in args {
+                    match arg {
+                        Value::Custom { args, .. } if !args.is_empty() => write!(f, " ({})", arg)?,
+                        _ => write!(f, " {}", arg)?,
+                    }
+                }
+                Ok(())
+            }
+            Value::Unit => write!(f, "()"),
+            Value::PartiallyAppliedFunc { .. } => write!(f, "<function>"),
+            Value::Seed(seed) => write!(f, "Seed {} {}", seed.state, seed.increment),
+            Value::Bytes(bytes) => write!(f, "<{} bytes>", bytes.len()),
+            Value::RuntimeError(_) => write!(f, "<runtime error>"),
+            Value::Expectation(_)
+            | Value::Test(_)
+            | Value::Generator(_)
+            | Value::Sub(_)
+            | Value::Program(_) => {
+                write!(f, "<internals>")
+            }
+        }
+    }
+}
+
+fn write_items(
+    f: &mut fmt::Formatter<'_>,
+    open: &str,
+    items: &[Value],
+    close: &str,
+) -> fmt::Result {
+    write!(f, "{}", open)?;
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, ",")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    write!(f, "{}", close)
+}
+
+// Escapes the string so that it reads as an Elm string literal
+fn escape(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for char in string.chars() {
+        match char {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            char => escaped.push(char),
+        }
+    }
+    escaped
+}
+
+// Reasons that a value can't be converted to or from JSON
+#[derive(Debug, PartialEq)]
+pub enum JsonError {
+    // Functions, tests and the like only have meaning inside the interpreter
+    NotData(String),
+    // JSON has no way to write NaN or infinity
+    NonFiniteFloat(f32),
+    // Whole numbers in JSON which don't fit in an Int
+    IntegerOutOfRange(String),
+    // Objects are only understood when they describe a custom type value
+    MalformedCustom(String),
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonError::NotData(value) => write!(f, "{} can't be represented as JSON", value),
+            JsonError::NonFiniteFloat(float) => {
+                write!(f, "{:?} can't be represented as a JSON number", float)
+            }
+            JsonError::IntegerOutOfRange(number) => write!(f, "{} is too large for an Int", number),
+            JsonError::MalformedCustom(object) => write!(
+                f,
+                "{} needs a constructor name in \"$\" and a list of \"args\" to be read as a custom type value",
+                object
+            ),
+        }
+    }
+}
+
+impl Value {
+    /* Converts the value to JSON so that it can be handed to the embedding program. Lists and
+     * tuples become arrays, unit becomes null, records become objects with the same fields and
+     * custom type values become objects with the constructor name in "$" and the arguments in
+     * "args", eg. {"$": "Just", "args": [1]}. No record field can be called "$" so the two can't
+     * be confused when they are read back
+     */
+    pub fn to_json(&self) -> Result<serde_json::Value, JsonError> {
+        log::trace!("to_json");
+        use serde_json::Value as Json;
+
+        match self {
+            Value::Bool(bool) => Ok(Json::Bool(*bool)),
+            Value::Integer(int) => Ok(Json::from(*int)),
+            Value::Float(float) => serde_json::Number::from_f64(*float as f64)
+                .map(Json::Number)
+                .ok_or(JsonError::NonFiniteFloat(*float)),
+            Value::Char(char) => Ok(Json::String(char.to_string())),
+            Value::String(string) => Ok(Json::String(string.to_string())),
+            Value::List(items) | Value::Tuple(items) => items
+                .iter()
+                .map(Value::to_json)
+                .collect::<Result<_, _>>()
+                .map(Json::Array),
+            Value::Record(fields) => fields
+                .iter()
+                .map(|(name, value)| value.to_json().map(|json| (name.clone(), json)))
+                .collect::<Result<_, _>>()
+                .map(Json::Object),
+            Value::Custom { name, args } => {
+                let args = args.iter().map(Value::to_json).collect::<Result<_, _>>()?;
+                Ok(serde_json::json!({ "$": name, "args": Json::Array(args) }))
+            }
+            Value::Unit => Ok(Json::Null),
+            Value::PartiallyAppliedFunc { .. }
+            | Value::Expectation(_)
+            | Value::Test(_)
+            | Value::Seed(_)
+            | Value::Generator(_)
+            | Value::Bytes(_)
+            | Value::Sub(_)
+            | Value::Program(_)
+            | Value::RuntimeError(_) => Err(JsonError::NotData(self.to_string())),
+        }
+    }
+
+    /* Builds a value from JSON using the same layout as to_json. JSON can't tell us when a string
+     * was a Char or an array was a tuple so those come back as strings and lists. Whole numbers
+     * become Ints and anything with a fraction or exponent becomes a Float.
+     */
+    pub fn from_json(json: &serde_json::Value) -> Result<Value, JsonError> {
+        log::trace!("from_json");
+        use serde_json::Value as Json;
+
+        match json {
+            Json::Null => Ok(Value::Unit),
+            Json::Bool(bool) => Ok(Value::Bool(*bool)),
+            Json::Number(number) if number.is_i64() || number.is_u64() => number
+                .as_i64()
+                .map(Value::Integer)
+                .ok_or_else(|| JsonError::IntegerOutOfRange(number.to_string())),
+            Json::Number(number) => Ok(Value::Float(number.as_f64().unwrap_or(f64::NAN) as f32)),
+            Json::String(string) => Ok(Value::String(Arc::from(string.as_str()))),
+            Json::Array(items) => items
+                .iter()
+                .map(Value::from_json)
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            Json::Object(object) if object.contains_key("$") => {
+                let name = match object.get("$") {
+                    Some(Json::String(name)) => name.clone(),
+                    _ => return Err(JsonError::MalformedCustom(json.to_string())),
+                };
+                let args = match object.get("args") {
+                    None => vec![],
+                    Some(Json::Array(args)) => args
+                        .iter()
+                        .map(Value::from_json)
+                        .collect::<Result<_, _>>()?,
+                    Some(_) => return Err(JsonError::MalformedCustom(json.to_string())),
+                };
+                Ok(Value::Custom { name, args })
+            }
+            Json::Object(object) => {
+                // Record fields are kept in name order as they are when the record is evaluated
+                let mut fields = object
+                    .iter()
+                    .map(|(name, value)| Value::from_json(value).map(|value| (name.clone(), value)))
+                    .collect::<Result<Vec<_>, _>>()?;
+                fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+                Ok(Value::Record(fields))
+            }
+        }
+    }
+}
+
+/* Joins two strings. We only need to allocate a new string when both sides have some content,
+ * otherwise we can hand back the existing one.
+ */
+pub fn append(left: &Arc<str>, right: &Arc<str>) -> Arc<str> {
+    if right.is_empty() {
+        left.clone()
+    } else if left.is_empty() {
+        right.clone()
+    } else {
+        let mut joined = String::with_capacity(left.len() + right.len());
+        joined.push_str(left);
+        joined.push_str(right);
+        Arc::from(joined)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn converts_values_to_json() {
+        let value = Value::List(vec![
+            Value::Custom {
+                name: "Just".to_string(),
+                args: vec![Value::Tuple(vec![
+                    Value::Integer(1),
+                    Value::String(Arc::from("one")),
+                ])],
+            },
+            Value::Custom {
+                name: "Nothing".to_string(),
+                args: vec![],
+            },
+        ]);
+
+        assert_eq!(
+            value.to_json(),
+            Ok(serde_json::json!([
+                { "$": "Just", "args": [[1, "one"]] },
+                { "$": "Nothing", "args": [] }
+            ]))
+        );
+    }
+
+    #[test]
+    fn converts_json_to_values() {
+        let json = serde_json::json!([{ "$": "Ok", "args": [2.5] }, { "$": "Nothing" }, 3, null]);
+        let value = Value::from_json(&json).expect("Failed to convert");
+
+        assert_eq!(value.to_string(), "[Ok 2.5,Nothing,3,()]");
+    }
+
+    #[test]
+    fn rejects_values_without_json_form() {
+        assert_eq!(
+            Value::Float(f32::INFINITY).to_json(),
+            Err(JsonError::NonFiniteFloat(f32::INFINITY))
+        );
+        assert_eq!(
+            Value::from_json(&serde_json::json!(10_000_000_000_000_000_000u64))
+                .map(|value| value.to_string()),
+            Err(JsonError::IntegerOutOfRange(
+                "10000000000000000000".to_string()
+            ))
+        );
+        assert_eq!(
+            Value::from_json(&serde_json::json!({ "$": 1 })).map(|value| value.to_string()),
+            Err(JsonError::MalformedCustom("{\"$\":1}".to_string()))
+        );
+    }
+
+    #[test]
+    fn records_round_trip_through_json() {
+        let value = Value::List(vec![
+            Value::Record(vec![
+                ("args".to_string(), Value::List(vec![Value::Integer(1)])),
+                ("tag".to_string(), Value::String(Arc::from("Just"))),
+            ]),
+            Value::Custom {
+                name: "Just".to_string(),
+                args: vec![Value::Record(vec![("tag".to_string(), Value::Unit)])],
+            },
+        ]);
+
+        let json = value.to_json().expect("Failed to convert");
+        let round_tripped = Value::from_json(&json).expect("Failed to convert back");
+
+        assert_eq!(round_tripped.to_string(), value.to_string());
+    }
 }
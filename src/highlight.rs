@@ -0,0 +1,125 @@
+use crate::lexer::{self, Range, Token};
+
+/* Token kinds for syntax highlighting, derived directly from the lexer so that editors can
+ * highlight files without needing a full language server.
+ */
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Keyword,
+    Operator,
+    Symbol,
+    UpperName,
+    LowerName,
+    String,
+    Char,
+    Number,
+    Comment,
+    Error,
+}
+
+impl Kind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Kind::Keyword => "keyword",
+            Kind::Operator => "operator",
+            Kind::Symbol => "symbol",
+            Kind::UpperName => "upper-name",
+            Kind::LowerName => "lower-name",
+            Kind::String => "string",
+            Kind::Char => "char",
+            Kind::Number => "number",
+            Kind::Comment => "comment",
+            Kind::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Span {
+    pub kind: Kind,
+    pub range: Range,
+    // Zero based line & column of the start of the range. The column is in bytes
+    pub line: usize,
+    pub column: usize,
+}
+
+pub fn highlight(source: &str) -> Vec<Span> {
+    log::trace!("highlight");
+    lexer::lex(source)
+        .into_iter()
+        .filter_map(|located| {
+            let kind = match located.token {
+                Token::NewLine | Token::Space(_) => return None,
+                Token::Module
+                | Token::Port
+                | Token::Type
+                | Token::Alias
+                | Token::Exposing
+                | Token::As
+                | Token::Import
+                | Token::Case
+                | Token::Of
+                | Token::Let
+                | Token::In
+                | Token::If
+                | Token::Then
+                | Token::Else
+                | Token::Infix => Kind::Keyword,
+                Token::Operator(_) | Token::Equals | Token::RightArrow | Token::BackSlash => {
+                    Kind::Operator
+                }
+                Token::OpenParen
+                | Token::CloseParen
+                | Token::OpenBracket
+                | Token::CloseBracket
+                | Token::OpenBrace
+                | Token::CloseBrace
+                | Token::Bar
+                | Token::Comma
+                | Token::Point
+                | Token::Ellipsis
+                | Token::Colon
+                | Token::Underscore => Kind::Symbol,
+                Token::UpperName(_) | Token::UpperPath(_) => Kind::UpperName,
+                Token::LowerName(_) | Token::LowerPath(_) => Kind::LowerName,
+                Token::LiteralString(_) | Token::WebGL(_) => Kind::String,
+                Token::LiteralChar(_) => Kind::Char,
+                Token::LiteralInteger(_) | Token::LiteralFloat(_) => Kind::Number,
+                Token::SingleLineComment(_) | Token::MultiLineComment(_) => Kind::Comment,
+                Token::Error => Kind::Error,
+            };
+
+            Some(Span {
+                kind,
+                range: located.range,
+                line: located.line,
+                column: located.column,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tracks_lines_after_multi_line_comments() {
+        let spans = highlight("{- a\n  b -} x = \"s\"\nmain");
+        let kinds: Vec<_> = spans
+            .iter()
+            .map(|span| (span.kind, span.line, span.column))
+            .collect();
+
+        assert_eq!(
+            kinds,
+            vec![
+                (Kind::Comment, 0, 0),
+                (Kind::LowerName, 1, 7),
+                (Kind::Operator, 1, 9),
+                (Kind::String, 1, 11),
+                (Kind::LowerName, 2, 0),
+            ]
+        );
+    }
+}
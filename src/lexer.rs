@@ -1,12 +1,71 @@
+use std::io::{self, Read};
+
 use logos::{Lexer, Logos};
 
 pub type Range = std::ops::Range<usize>;
 
-#[allow(dead_code)]
 pub type SrcToken<'src> = (Token<'src>, Range);
 
-#[allow(dead_code)]
-pub type TokenIter<'src> = std::iter::Peekable<logos::SpannedIter<'src, Token<'src>>>;
+/* Where the parser gets its tokens from. The parser only needs to take the next token and look at
+ * the one after it so the tokens can come from the logos lexer, as they do when parsing source,
+ * or from anything else that produces them, like a TokenBuffer built by a tool or a test.
+ */
+pub trait TokenSource<'src>: Iterator<Item = SrcToken<'src>> {
+    fn peek(&mut self) -> Option<&SrcToken<'src>>;
+}
+
+// What the parser functions take so that they don't depend on a particular source of tokens
+pub type TokenIter<'a, 'src> = dyn TokenSource<'src> + 'a;
+
+impl<'src> TokenSource<'src> for std::iter::Peekable<logos::SpannedIter<'src, Token<'src>>> {
+    fn peek(&mut self) -> Option<&SrcToken<'src>> {
+        std::iter::Peekable::peek(self)
+    }
+}
+
+/* Tokens held in a vector that can be read again from the start with 'rewind', for tools & tests
+ * that want to build or edit a token stream before parsing it, or parse the same one more than once.
+ */
+#[derive(Debug, Clone, Default)]
+pub struct TokenBuffer<'src> {
+    tokens: Vec<SrcToken<'src>>,
+    position: usize,
+}
+
+impl<'src> TokenBuffer<'src> {
+    pub fn new(tokens: Vec<SrcToken<'src>>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+        }
+    }
+
+    pub fn from_source(source: &'src str) -> Self {
+        Self::new(Token::lexer(source).spanned().collect())
+    }
+
+    pub fn rewind(&mut self) {
+        self.position = 0;
+    }
+}
+
+impl<'src> Iterator for TokenBuffer<'src> {
+    type Item = SrcToken<'src>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+}
+
+impl<'src> TokenSource<'src> for TokenBuffer<'src> {
+    fn peek(&mut self) -> Option<&SrcToken<'src>> {
+        self.tokens.get(self.position)
+    }
+}
 
 #[derive(Logos, Debug, PartialEq, Clone)]
 pub enum Token<'src> {
@@ -124,8 +183,8 @@ pub enum Token<'src> {
     #[regex(r#"\[glsl\|(?:[^|]|\|[^]])*\|]"#)]
     WebGL(&'src str),
 
-    #[regex("-?[0-9]+", |lex| lex.slice().parse::<i32>(), priority = 2)]
-    LiteralInteger(i32),
+    #[regex("-?[0-9]+", |lex| lex.slice().parse::<i64>(), priority = 2)]
+    LiteralInteger(i64),
 
     #[regex("[0-9]*\\.[0-9]+([eE][+-]?[0-9]+)?|[0-9]+[eE][+-]?[0-9]+", |lex| lex.slice().parse::<f32>())]
     LiteralFloat(f32),
@@ -146,8 +205,285 @@ fn string_contents<'src>(lex: &mut Lexer<'src, Token<'src>>) -> Option<&'src str
     Some(&slice[1..slice.len() - 1])
 }
 
+impl<'src> Token<'src> {
+    /* The name of the kind of token without any of its contents, eg. 'LowerName' */
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Token::Module => "Module",
+            Token::Port => "Port",
+            Token::Type => "Type",
+            Token::Alias => "Alias",
+            Token::Exposing => "Exposing",
+            Token::As => "As",
+            Token::Import => "Import",
+            Token::Case => "Case",
+            Token::Of => "Of",
+            Token::Let => "Let",
+            Token::In => "In",
+            Token::If => "If",
+            Token::Then => "Then",
+            Token::Else => "Else",
+            Token::Infix => "Infix",
+            Token::OpenParen => "OpenParen",
+            Token::CloseParen => "CloseParen",
+            Token::OpenBracket => "OpenBracket",
+            Token::CloseBracket => "CloseBracket",
+            Token::OpenBrace => "OpenBrace",
+            Token::CloseBrace => "CloseBrace",
+            Token::Space(_) => "Space",
+            Token::NewLine => "NewLine",
+            Token::Bar => "Bar",
+            Token::Comma => "Comma",
+            Token::Point => "Point",
+            Token::Ellipsis => "Ellipsis",
+            Token::Equals => "Equals",
+            Token::Colon => "Colon",
+            Token::BackSlash => "BackSlash",
+            Token::RightArrow => "RightArrow",
+            Token::Underscore => "Underscore",
+            Token::UpperPath(_) => "UpperPath",
+            Token::UpperName(_) => "UpperName",
+            Token::LowerPath(_) => "LowerPath",
+            Token::LowerName(_) => "LowerName",
+            Token::Operator(_) => "Operator",
+            Token::SingleLineComment(_) => "SingleLineComment",
+            Token::MultiLineComment(_) => "MultiLineComment",
+            Token::WebGL(_) => "WebGL",
+            Token::LiteralInteger(_) => "LiteralInteger",
+            Token::LiteralFloat(_) => "LiteralFloat",
+            Token::LiteralString(_) => "LiteralString",
+            Token::LiteralChar(_) => "LiteralChar",
+            Token::Error => "Error",
+        }
+    }
+}
+
+/* A token along with where it is in the source, for tools that work with the tokens directly */
+#[derive(Debug, PartialEq)]
+pub struct Located<'src> {
+    pub token: Token<'src>,
+    pub range: Range,
+    // Zero based line & column of the start of the range. The column is in bytes
+    pub line: usize,
+    pub column: usize,
+}
+
+/* Splits the source into tokens, including the whitespace & comments that the parser mostly
+ * skips, and works out the line & column where each one starts.
+ */
+pub fn lex(source: &str) -> Vec<Located<'_>> {
+    log::trace!("lex");
+    let mut position = Position::default();
+
+    Token::lexer(source)
+        .spanned()
+        .map(|(token, range)| position.locate(token, range.clone(), &source[range]))
+        .collect()
+}
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/* Splits the input into tokens like 'lex' but reads it a chunk at a time, passing each token to
+ * the callback along with its text. Only the unlexed part of the input is held in memory so large
+ * generated modules can be worked through without reading them into a single String. Ranges,
+ * lines & columns are for the whole input. Lexing stops at the first error from the callback,
+ * eg. when the output it writes the tokens to has been closed, and returns it.
+ */
+pub fn lex_reader<R: Read>(
+    reader: R,
+    on_token: impl FnMut(Located<'_>, &str) -> io::Result<()>,
+) -> io::Result<()> {
+    log::trace!("lex_reader");
+    lex_chunks(reader, CHUNK_SIZE, on_token)
+}
+
+fn lex_chunks<R: Read>(
+    mut reader: R,
+    chunk_size: usize,
+    mut on_token: impl FnMut(Located<'_>, &str) -> io::Result<()>,
+) -> io::Result<()> {
+    let mut pending: Vec<u8> = vec![];
+    let mut chunk = vec![0; chunk_size];
+    // Where the pending bytes start in the input
+    let mut offset = 0;
+    let mut position = Position::default();
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(read) => read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error),
+        };
+        pending.extend_from_slice(&chunk[..read]);
+        let finished = read == 0;
+
+        // A chunk can end part way through a character, which is completed by the next one
+        let text = match std::str::from_utf8(&pending) {
+            Ok(text) => text,
+            Err(error) if !finished && error.error_len().is_none() => {
+                std::str::from_utf8(&pending[..error.valid_up_to()]).unwrap_or_default()
+            }
+            Err(error) => return Err(io::Error::new(io::ErrorKind::InvalidData, error)),
+        };
+
+        let tokens: Vec<_> = Token::lexer(text).spanned().collect();
+        let count = if finished {
+            tokens.len()
+        } else {
+            settled_tokens(text, &tokens)
+        };
+
+        let mut end = 0;
+        for (token, range) in tokens.into_iter().take(count) {
+            end = range.end;
+            let slice = &text[range.clone()];
+            let range = range.start + offset..range.end + offset;
+            on_token(position.locate(token, range, slice), slice)?;
+        }
+
+        if finished {
+            return Ok(());
+        }
+
+        // Nothing settled means a comment or string goes on past what we've read, so read more at
+        // a time rather than lexing the same text over and over
+        if end == 0 {
+            chunk.resize(chunk.len() * 2, 0);
+        }
+
+        pending.drain(..end);
+        offset += end;
+    }
+}
+
+/* The number of tokens at the start of partly read input that won't change when more of it is
+ * read. Only comments, strings & glsl blocks span lines so everything up to a new line is settled
+ * unless one of them has been started but not finished. Those show up as an error token or as a
+ * bracket followed by the rest of the opening, as the lexer doesn't see the closing.
+ */
+fn settled_tokens(text: &str, tokens: &[(Token, Range)]) -> usize {
+    let mut count = 0;
+
+    for (index, (token, range)) in tokens.iter().enumerate() {
+        let rest = &text[range.end..];
+        match token {
+            Token::Error => break,
+            Token::OpenBrace if rest.starts_with('-') => break,
+            Token::OpenBracket if rest.starts_with("glsl|") => break,
+            Token::NewLine => count = index + 1,
+            _ => {}
+        }
+    }
+
+    count
+}
+
+/* Tracks the line that the lexer has reached so that each token can be given its line & column */
+#[derive(Default)]
+struct Position {
+    line: usize,
+    line_start: usize,
+}
+
+impl Position {
+    fn locate<'src>(&mut self, token: Token<'src>, range: Range, slice: &str) -> Located<'src> {
+        let located = Located {
+            token,
+            line: self.line,
+            column: range.start - self.line_start,
+            range: range.clone(),
+        };
+
+        // New lines, along with multi-line tokens like comments & strings, move us on to later lines
+        let newlines = slice.matches('\n').count();
+        if newlines > 0 {
+            self.line += newlines;
+            self.line_start = range.start + slice.rfind('\n').unwrap_or(0) + 1;
+        }
+
+        located
+    }
+}
+
 impl<'src> std::fmt::Display for Token<'src> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn lex_tracks_lines_across_multi_line_tokens() {
+        let source = "x =\n  {- a\nb -} 1";
+        let tokens: Vec<_> = lex(source)
+            .into_iter()
+            .map(|located| (located.token.kind(), located.line, located.column))
+            .collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                ("LowerName", 0, 0),
+                ("Space", 0, 1),
+                ("Equals", 0, 2),
+                ("NewLine", 0, 3),
+                ("Space", 1, 0),
+                ("MultiLineComment", 1, 2),
+                ("Space", 2, 4),
+                ("LiteralInteger", 2, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_reader_matches_lex_whatever_the_chunk_size() {
+        let source = "module Main exposing (..)\n\n{- a\ncomment -}\nx =\n    \"a\nstring\" ++ \"é\"\n\nshader =\n    [glsl|\nvoid main () {}\n|]\n-- end";
+        let expected: Vec<_> = lex(source)
+            .into_iter()
+            .map(|located| {
+                let slice = source[located.range.clone()].to_string();
+                (
+                    located.token.kind(),
+                    slice,
+                    located.range,
+                    located.line,
+                    located.column,
+                )
+            })
+            .collect();
+
+        for chunk_size in 1..source.len() + 1 {
+            let mut tokens = vec![];
+            lex_chunks(source.as_bytes(), chunk_size, |located, slice| {
+                tokens.push((
+                    located.token.kind(),
+                    slice.to_string(),
+                    located.range,
+                    located.line,
+                    located.column,
+                ));
+                Ok(())
+            })
+            .expect("Failed to lex");
+
+            assert_eq!(tokens, expected, "chunk size {}", chunk_size);
+        }
+    }
+
+    #[test]
+    fn token_buffer_parses_like_the_lexer_and_can_be_replayed() {
+        let source = "module Main exposing (..)\nmain args =\n    [ 1, 2 ]\n";
+        let mut iter = Token::lexer(source).spanned().peekable();
+        let expected = crate::printer::print_module(&crate::parser::parse(&mut iter).unwrap());
+
+        let mut buffer = TokenBuffer::from_source(source);
+        for _ in 0..2 {
+            let module = crate::parser::parse(&mut buffer).expect("Failed to parse");
+            assert_eq!(crate::printer::print_module(&module), expected);
+            buffer.rewind();
+        }
+    }
+}
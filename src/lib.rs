@@ -1,10 +1,23 @@
+pub mod analysis;
 pub mod ast;
 pub mod bindings;
 pub mod builtins;
 pub mod checker;
+#[cfg(not(feature = "fs"))]
+mod core_modules;
 pub mod env;
 pub mod error;
 pub mod evaluator;
+pub mod highlight;
 pub mod lexer;
+pub mod optimize;
+#[cfg(feature = "fs")]
+mod parse_cache;
 pub mod parser;
+pub mod printer;
 pub mod project;
+pub mod repl;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use analysis::{diagnostics, Diagnostic, Severity};
@@ -0,0 +1,148 @@
+use std::sync::Arc;
+
+use crate::ast::{Arena, Expr, ExprId, Module, Stmt};
+
+/* Works out the values of the top-level bindings that only use literals, eg.
+ * 'secondsPerDay = 60 * 60 * 24', 'names = [ "erm" ++ ".elm" ]' or 'limits = { size = 2 * 1024 }',
+ * and stores them in place of the expressions so that they aren't worked out again each time the
+ * program runs. Bindings that use anything else are left alone. So are Int results that don't fit
+ * in 64 bits, so that the overflow is still reported, or wrapped, at run time.
+ *
+ * Like the evaluator's fast path for numbers, this relies on the core operators meaning what
+ * they do in core/Basics. The statements keep their expression ids, we just rewrite the entries in
+ * a copy of the arena.
+ */
+pub fn fold_constants(module: &Module) -> Module {
+    log::trace!("fold_constants: {:?}", module.name);
+    let mut arena = (*module.arena).clone();
+
+    for stmt in &module.statements {
+        if let Stmt::Binding { expr, .. } = &**stmt {
+            if is_constant(*expr, &arena) {
+                fold_expression(*expr, &mut arena);
+            }
+        }
+    }
+
+    Module {
+        name: module.name.clone(),
+        exposing: module.exposing.clone(),
+        imports: module.imports.clone(),
+        statements: module.statements.clone(),
+        arena: Arc::new(arena),
+    }
+}
+
+fn is_constant(id: ExprId, arena: &Arena) -> bool {
+    match &arena[id] {
+        Expr::Bool(_)
+        | Expr::Integer(_)
+        | Expr::Float(_)
+        | Expr::String(_)
+        | Expr::Char(_)
+        | Expr::Unit => true,
+        Expr::List(items) | Expr::Tuple(items) => {
+            items.iter().all(|item| is_constant(*item, arena))
+        }
+        Expr::Record(fields) => fields
+            .iter()
+            .all(|(_name, field)| is_constant(*field, arena)),
+        Expr::BinOp {
+            operator,
+            left,
+            right,
+        } => {
+            matches!(operator.as_str(), "+" | "-" | "*" | "++")
+                && is_constant(*left, arena)
+                && is_constant(*right, arena)
+        }
+        _ => false,
+    }
+}
+
+fn fold_expression(id: ExprId, arena: &mut Arena) {
+    match arena[id].clone() {
+        Expr::List(items) | Expr::Tuple(items) => {
+            for item in items {
+                fold_expression(item, arena);
+            }
+        }
+        Expr::Record(fields) => {
+            for (_name, field) in fields {
+                fold_expression(field, arena);
+            }
+        }
+        Expr::BinOp { .. } => {
+            // The operands come before the operators that combine them so each operator sees
+            // its operands once they have been folded
+            for operand in arena.operator_tree(id) {
+                match arena[operand].clone() {
+                    Expr::BinOp {
+                        operator,
+                        left,
+                        right,
+                    } => {
+                        if let Some(expr) = fold_operator(&operator, &arena[left], &arena[right]) {
+                            arena.replace(operand, expr);
+                        }
+                    }
+                    _ => fold_expression(operand, arena),
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn fold_operator(operator: &str, left: &Expr, right: &Expr) -> Option<Expr> {
+    match (operator, left, right) {
+        ("+", Expr::Integer(l), Expr::Integer(r)) => l.checked_add(*r).map(Expr::Integer),
+        ("+", Expr::Float(l), Expr::Float(r)) => Some(Expr::Float(l + r)),
+        ("-", Expr::Integer(l), Expr::Integer(r)) => l.checked_sub(*r).map(Expr::Integer),
+        ("-", Expr::Float(l), Expr::Float(r)) => Some(Expr::Float(l - r)),
+        ("*", Expr::Integer(l), Expr::Integer(r)) => l.checked_mul(*r).map(Expr::Integer),
+        ("*", Expr::Float(l), Expr::Float(r)) => Some(Expr::Float(l * r)),
+        ("++", Expr::String(l), Expr::String(r)) => {
+            Some(Expr::String(Arc::from(format!("{}{}", l, r))))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn folds_bindings_that_only_use_literals() {
+        let source = "module Constants exposing (..)
+day = 60 * 60 * 24
+name = \"erm\" ++ \".elm\"
+sizes = [ 1 + 1, 2 * 2 ]
+tooBig = 9223372036854775807 + 1
+double n = n * 2
+twice = double 2 + 1
+";
+        let mut iter = Token::lexer(source).spanned().peekable();
+        let module = parser::parse(&mut iter).expect("Failed to parse");
+
+        insta::assert_snapshot!(printer::print_module(&fold_constants(&module)));
+    }
+
+    #[test]
+    fn folds_records_of_literals() {
+        let source = "module Constants exposing (..)
+limits = { size = 2 * 1024, name = \"erm\" ++ \".elm\", pair = ( 1 + 1, [ 3 * 3 ] ) }
+named n = { size = n * 2, count = 1 + 1 }
+";
+        let mut iter = Token::lexer(source).spanned().peekable();
+        let module = parser::parse(&mut iter).expect("Failed to parse");
+
+        insta::assert_snapshot!(printer::print_module(&fold_constants(&module)));
+    }
+}
@@ -0,0 +1,123 @@
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+use crate::ast::Module;
+use crate::parser::Operators;
+
+// Changed whenever the form of the stored modules changes without a change to the version of erm
+const FORMAT: &str = "resolved";
+
+/* Parsed & resolved modules kept on disk so that later runs can skip parsing & resolving the
+ * modules that haven't changed. Entries are keyed by a hash of everything that decides how a
+ * module parses: its source, the operators available to it, whether its constants have been worked
+ * out and the version of erm that parsed it. Entries that can't be read are ignored and the module
+ * is parsed again.
+ */
+pub fn key(source: &str, operators: &Operators, optimized: bool) -> String {
+    let mut hasher = StableHasher::new();
+    env!("CARGO_PKG_VERSION").hash(&mut hasher);
+    FORMAT.hash(&mut hasher);
+    source.hash(&mut hasher);
+    operators.hash(&mut hasher);
+    optimized.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/* FNV-1a, which unlike the standard library's hasher gives the same hash for the same input in
+ * every run & every build so that the keys written by one run are found by the next
+ */
+struct StableHasher(u64);
+
+impl StableHasher {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+pub fn load(directory: &Path, key: &str) -> Option<Module> {
+    log::trace!("parse_cache::load {}", key);
+    let contents = std::fs::read(entry_path(directory, key)).ok()?;
+    serde_json::from_slice(&contents).ok()
+}
+
+/* Failing to write to the cache only means that the module is parsed again next time */
+pub fn store(directory: &Path, key: &str, module: &Module) {
+    log::trace!("parse_cache::store {}", key);
+    if let Ok(contents) = serde_json::to_vec(module) {
+        let _ = std::fs::create_dir_all(directory)
+            .and_then(|_| std::fs::write(entry_path(directory, key), contents));
+    }
+}
+
+fn entry_path(directory: &Path, key: &str) -> PathBuf {
+    directory.join(format!("{}.json", key))
+}
+
+#[cfg(test)]
+mod test {
+    use logos::Logos;
+
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+    use crate::printer;
+
+    #[test]
+    fn stored_modules_load_again() {
+        let source = "module Cached exposing (..)
+double n = n * 2
+main args = case double 2 of
+    4 -> \"four\"
+    _ -> \"other\"
+";
+        let mut iter = Token::lexer(source).spanned().peekable();
+        let module = parser::parse(&mut iter).expect("Failed to parse");
+
+        let directory =
+            std::env::temp_dir().join(format!("erm-parse-cache-{}", std::process::id()));
+        let key = key(source, &Operators::core(), false);
+        store(&directory, &key, &module);
+        let loaded = load(&directory, &key).expect("Failed to load");
+        let _ = std::fs::remove_dir_all(&directory);
+
+        assert_eq!(
+            printer::print_module(&loaded),
+            printer::print_module(&module)
+        );
+    }
+
+    #[test]
+    fn key_is_stable_between_runs() {
+        let source = "module Cached exposing (..)";
+        assert_eq!(
+            key(source, &Operators::new(), false),
+            key(source, &Operators::new(), false)
+        );
+
+        let mut hasher = StableHasher::new();
+        hasher.write(b"erm");
+        assert_eq!(hasher.finish(), 0xc2fd_1c18_f05e_04e1);
+    }
+
+    #[test]
+    fn key_depends_on_operators() {
+        let source = "module Cached exposing (..)";
+        assert_ne!(
+            key(source, &Operators::core(), false),
+            key(source, &Operators::new(), false)
+        );
+    }
+}
@@ -1,28 +1,107 @@
+mod depth;
 mod error;
 mod extract;
+pub mod incremental;
 mod indent;
 mod mtch;
+pub mod operators;
 mod types;
 
 use std::convert::TryFrom;
-use std::rc::Rc;
+use std::sync::Arc;
+
+use logos::Logos;
 
 use super::ast::*;
-use super::lexer::{SrcToken, Token, TokenIter};
+use super::lexer::{Range, SrcToken, Token, TokenIter};
 
 pub use self::error::Error;
 use self::mtch::matches;
+pub use self::operators::Operators;
 
 pub type ParseResult = Result<Module, Error>;
 
 pub fn parse(iter: &mut TokenIter) -> ParseResult {
+    parse_with_operators(iter, Operators::core())
+}
+
+/* Parses the source with all the operators that are available to the module. Infix declarations
+ * can come after the expressions that use them so we scan the module for them before parsing it
+ * properly. The callback provides the operators declared by an imported module.
+ */
+pub fn parse_source<F>(source: &str, imported_operators: F) -> ParseResult
+where
+    F: Fn(&Import) -> Operators,
+{
+    log::trace!("parse_source");
+    let operators = source_operators(source, imported_operators)?;
+    parse_source_with_operators(source, operators)
+}
+
+/* The operators available to the module, from the core operators, its imports & its own infix
+ * declarations. Together with the source these decide how the module parses.
+ */
+pub fn source_operators<F>(source: &str, imported_operators: F) -> Result<Operators, Error>
+where
+    F: Fn(&Import) -> Operators,
+{
+    log::trace!("source_operators");
+    let mut iter = Token::lexer(source).spanned().peekable();
+    let (_name, _exposing, imports) = parse_header(&mut iter)?;
+
+    let mut operators = Operators::core();
+    for import in &imports {
+        operators.extend(imported_operators(import).exposed_by(import));
+    }
+    operators.extend(operators::declared_operators(&mut iter)?);
+
+    Ok(operators)
+}
+
+pub fn parse_source_with_operators(source: &str, operators: Operators) -> ParseResult {
+    let mut iter = Token::lexer(source).spanned().peekable();
+    parse_with_operators(&mut iter, operators)
+}
+
+/* Without scanning ahead, operators declared in the module are only available to the statements
+ * that follow their declarations.
+ */
+fn parse_with_operators(iter: &mut TokenIter, mut operators: Operators) -> ParseResult {
     log::trace!("parse");
 
     // Uncomment to print out whole token stream
     // println!("{:?}", iter.collect::<Vec<_>>());
 
+    let (name, exposing, imports) = parse_header(iter)?;
+
+    let mut arena = Arena::new();
+    let statements = parse_statements(iter, &mut arena, &mut operators)?;
+
+    if iter.peek().is_none() {
+        Ok(Module {
+            name,
+            exposing,
+            imports,
+            statements,
+            arena: Arc::new(arena),
+        })
+    } else {
+        let tokens = iter.map(|token| format!("{:?}", token)).collect();
+        Err(Error::TokensRemaining(tokens))
+    }
+}
+
+/* Parses the module line & imports, leaving the iterator at the start of the first statement */
+fn parse_header(iter: &mut TokenIter) -> Result<(ModuleName, Exposing, Vec<Import>), Error> {
+    log::trace!("parse_header");
     let base_indent = indent::Indentation::new();
 
+    // Modules that declare ports are written 'port module'
+    if let Some((Token::Port, _range)) = iter.peek() {
+        iter.next();
+        base_indent.must_consume_to_indented(iter)?;
+    }
+
     matches(&iter.next(), Token::Module)?;
     base_indent.must_consume_to_indented(iter)?;
 
@@ -37,19 +116,7 @@ pub fn parse(iter: &mut TokenIter) -> ParseResult {
     let imports = parse_imports(iter)?;
     base_indent.must_consume_to_line_start(iter)?;
 
-    let statements = parse_statements(iter)?;
-
-    if iter.peek() == None {
-        Ok(Module {
-            name,
-            exposing,
-            imports,
-            statements,
-        })
-    } else {
-        let tokens = iter.map(|token| format!("{:?}", token)).collect();
-        Err(Error::TokensRemaining(tokens))
-    }
+    Ok((name, exposing, imports))
 }
 
 fn parse_exposing(iter: &mut TokenIter) -> Result<Exposing, Error> {
@@ -71,6 +138,7 @@ fn parse_exposing(iter: &mut TokenIter) -> Result<Exposing, Error> {
         token => Err(Error::UnknownExposing(format!("{:?}", token))),
     }?;
 
+    base_indent.must_consume_to_indented(iter)?;
     matches(&iter.next(), Token::CloseParen)?;
 
     Ok(exposing)
@@ -96,6 +164,8 @@ fn parse_exposing_details(iter: &mut TokenIter) -> Result<Vec<ExposingDetail>, E
             Some((Token::UpperName(name), _range)) => {
                 let name = name.to_string();
                 iter.next();
+                // Types can expose their constructors with '(..)', eg. 'Msg(..)' or 'Msg (..)'
+                base_indent.consume(iter);
                 let mut type_state = TypeState::Closed;
                 if let Some((Token::OpenParen, _range)) = iter.peek() {
                     iter.next();
@@ -108,6 +178,9 @@ fn parse_exposing_details(iter: &mut TokenIter) -> Result<Vec<ExposingDetail>, E
             token => return Err(Error::UnknownExposing(format!("{:?}", token))),
         }
 
+        // Lists can be spread over several lines in the elm-format style with leading commas
+        base_indent.must_consume_to_indented(iter)?;
+
         if let Some((Token::CloseParen, _range)) = iter.peek() {
             // Break without consuming the CloseParen
             break;
@@ -137,9 +210,23 @@ fn parse_imports(iter: &mut TokenIter) -> Result<Vec<Import>, Error> {
 
         let module_name = extract::extract_module_name(&iter.next())?;
 
+        let mut alias = None;
         let mut exposing = None;
 
-        let next_token_indent = base_indent.consume(iter);
+        let mut next_token_indent = base_indent.consume(iter);
+
+        if let Some((Token::As, range)) = iter.peek() {
+            if next_token_indent.at_line_start() {
+                return Err(Error::Indent {
+                    range: range.clone(),
+                });
+            }
+
+            iter.next();
+            base_indent.must_consume_to_indented(iter)?;
+            alias = Some(extract::extract_upper_name(&iter.next())?.0);
+            next_token_indent = base_indent.consume(iter);
+        }
 
         match iter.peek() {
             Some((Token::Exposing, range)) => {
@@ -153,16 +240,15 @@ fn parse_imports(iter: &mut TokenIter) -> Result<Vec<Import>, Error> {
                 exposing = Some(parse_exposing(iter)?);
                 base_indent.must_consume_to_line_start(iter)?;
             }
-            Some((_, range)) => {
-                if !next_token_indent.at_line_start() {
-                    return Err(Error::TokenNotAtLineStart(range.clone()));
-                }
+            Some((_, range)) if !next_token_indent.at_line_start() => {
+                return Err(Error::TokenNotAtLineStart(range.clone()));
             }
-            None => {}
+            _ => {}
         }
 
         imports.push(Import {
             module_name,
+            alias,
             exposing,
         });
     }
@@ -171,7 +257,11 @@ fn parse_imports(iter: &mut TokenIter) -> Result<Vec<Import>, Error> {
 }
 
 // Statements
-fn parse_statements(iter: &mut TokenIter) -> Result<Vec<Rc<Stmt>>, Error> {
+fn parse_statements(
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &mut Operators,
+) -> Result<Vec<Arc<Stmt>>, Error> {
     log::trace!("parse_statements: {:?}", iter.peek());
 
     // Fresh indentation as statement lines only need to be indented from zero
@@ -182,15 +272,22 @@ fn parse_statements(iter: &mut TokenIter) -> Result<Vec<Rc<Stmt>>, Error> {
 
     loop {
         match iter.peek() {
-            Some((Token::LowerName(_), _range)) => {
+            Some((Token::LowerName(_), range)) => {
+                let mut name_range = range.clone();
                 // Get the name
                 let name = extract::extract_lower_name(&iter.next())?;
                 base_indent.must_consume_to_indented(iter)?;
 
                 let statement = if matches!(iter.peek(), Some((Token::Colon, _range))) {
-                    let type_annotation = parse_type_annotation(iter, name.clone(), &base_indent)?;
+                    let (type_annotation, holes) =
+                        parse_type_annotation(iter, name.clone(), &base_indent)?;
+                    for range in holes {
+                        arena.add_hole(&name.0, range);
+                    }
                     base_indent.must_consume_to_line_start(iter)?;
 
+                    // The definition is the line after the annotation
+                    name_range = next_range(iter);
                     let function_name = extract::extract_lower_name(&iter.next())?;
                     base_indent.must_consume_to_indented(iter)?;
 
@@ -200,23 +297,33 @@ fn parse_statements(iter: &mut TokenIter) -> Result<Vec<Rc<Stmt>>, Error> {
 
                     parse_function_or_binding(
                         iter,
+                        arena,
+                        operators,
                         function_name,
                         Some(type_annotation),
                         &base_indent,
                     )?
                 } else {
-                    parse_function_or_binding(iter, name, None, &base_indent)?
+                    parse_function_or_binding(iter, arena, operators, name, None, &base_indent)?
                 };
 
-                statements.push(Rc::new(statement));
+                if let Stmt::Binding { name, .. } | Stmt::Function { name, .. } = &statement {
+                    arena.add_definition(DefinitionKind::Value, &name.0, name_range);
+                }
+                statements.push(Arc::new(statement));
             }
             Some((Token::Type, _range)) => {
-                let statement = types::parse_type_declaration(iter, &base_indent)?;
-                statements.push(Rc::new(statement));
+                let statement = types::parse_type_declaration(iter, arena, &base_indent)?;
+                statements.push(Arc::new(statement));
+            }
+            Some((Token::Port, _range)) => {
+                let statement = parse_port(iter, arena, &base_indent)?;
+                statements.push(Arc::new(statement));
             }
             Some((Token::Infix, _range)) => {
                 let statement = parse_infix(iter, &base_indent)?;
-                statements.push(Rc::new(statement));
+                operators.add_declaration(&statement);
+                statements.push(Arc::new(statement));
             }
             Some((token, range)) => {
                 log::error!("UnexpectedToken");
@@ -282,26 +389,63 @@ fn extract_precendence(stream_token: &Option<SrcToken>) -> Result<usize, Error>
     }
 }
 
-// Type annotations
-fn parse_type_annotation(
+// Ports
+//
+// Matches:
+//
+//   port sendThing : String -> Cmd msg
+//
+fn parse_port(
     iter: &mut TokenIter,
-    name: LowerName,
+    arena: &mut Arena,
     base_indent: &indent::Indentation,
-) -> Result<TypeAnnotation, Error> {
-    log::trace!("parse_type_annotation: {:?}", name);
+) -> Result<Stmt, Error> {
+    log::trace!("parse_port: {:?}", iter.peek());
+    matches(&iter.next(), Token::Port)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    let range = next_range(iter);
+    let name = extract::extract_lower_name(&iter.next())?;
+    arena.add_definition(DefinitionKind::Value, &name.0, range.clone());
+    base_indent.must_consume_to_indented(iter)?;
+
     matches(&iter.next(), Token::Colon)?;
     base_indent.must_consume_to_indented(iter)?;
 
     let type_ = types::parse_type(iter, base_indent)?;
-    base_indent.must_consume_to_indented(iter)?;
+    let direction = PortDirection::from_type(&type_).ok_or(Error::PortType(range))?;
 
-    Ok(TypeAnnotation {
-        // TODO: Don't use lower name for this stuff
+    Ok(Stmt::Port {
         name,
         type_,
+        direction,
     })
 }
 
+/* Parses a type annotation and returns it along with the ranges of the holes in it */
+fn parse_type_annotation(
+    iter: &mut TokenIter,
+    name: LowerName,
+    base_indent: &indent::Indentation,
+) -> Result<(TypeAnnotation, Vec<Range>), Error> {
+    log::trace!("parse_type_annotation: {:?}", name);
+    matches(&iter.next(), Token::Colon)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    let mut holes = vec![];
+    let type_ = types::parse_type_with_holes(iter, base_indent, &mut holes)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    Ok((
+        TypeAnnotation {
+            // TODO: Don't use lower name for this stuff
+            name,
+            type_,
+        },
+        holes,
+    ))
+}
+
 // Functions & bindings
 //
 // Matches:
@@ -311,6 +455,8 @@ fn parse_type_annotation(
 //
 fn parse_function_or_binding(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     name: LowerName,
     type_annotation: Option<TypeAnnotation>,
     base_indent: &indent::Indentation,
@@ -335,20 +481,20 @@ fn parse_function_or_binding(
 
     base_indent.must_consume_to_indented(iter)?;
 
-    let (expr, _) = parse_expression(iter, base_indent)?;
+    let (expr, _) = parse_expression(iter, arena, operators, base_indent)?;
 
     if args.is_empty() {
         Ok(Stmt::Binding {
             type_annotation,
             name,
-            expr: Rc::new(expr),
+            expr,
         })
     } else {
         Ok(Stmt::Function {
             type_annotation,
             name,
             args,
-            expr: Rc::new(expr),
+            expr,
         })
     }
 }
@@ -356,14 +502,21 @@ fn parse_function_or_binding(
 // Expressions
 //
 fn parse_expression(
-    mut iter: &mut TokenIter,
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_expression: {:?}", iter.peek());
+    let _depth = depth::Guard::enter(iter)?;
     match iter.peek() {
-        Some((Token::If, _range)) => parse_if_expression(&mut iter, base_indent),
-        Some((Token::Case, _range)) => parse_case_expression(&mut iter, base_indent),
-        Some(_) => parse_binary_expression(&mut iter, base_indent),
+        Some((Token::If, _range)) => parse_if_expression(iter, arena, operators, base_indent),
+        Some((Token::Case, _range)) => parse_case_expression(iter, arena, operators, base_indent),
+        Some((Token::Let, _range)) => parse_let_expression(iter, arena, operators, base_indent),
+        Some((Token::BackSlash, _range)) => {
+            parse_lambda_expression(iter, arena, operators, base_indent)
+        }
+        Some(_) => parse_binary_expression(iter, arena, operators, base_indent),
         None => Err(Error::UnexpectedEnd),
     }
 }
@@ -375,116 +528,176 @@ fn parse_expression(
 //   - http://www.engr.mun.ca/~theo/Misc/exp_parsing.htm
 //
 fn parse_binary_expression(
-    mut iter: &mut TokenIter,
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_binary_expression: {:?}", iter.peek());
-    let (expr, next_token_indent) = parse_var_or_call(&mut iter, base_indent)?;
+    let (expr, next_token_indent) = parse_var_or_call(iter, arena, operators, base_indent)?;
 
     // We have to keep parsing to look for more parts to this expression but if we find a change in
     // indentation that indicates the end of the scope for this expression then we just want to
     // return the expression we've found so far and allow the level up to deal with the change in
     // scope.
-    if !next_token_indent.indented_from(&base_indent) {
+    if !next_token_indent.indented_from(base_indent) {
         log::trace!("exiting parse_binary_expression: {:?}", iter.peek());
         return Ok((expr, next_token_indent));
     }
 
     let mut operator_stack = Vec::new();
-    let mut operand_stack = vec![expr];
+    // Each operand is kept with the depth of the tree of operators that it is made from. A flat
+    // chain like '1 + 1 + 1' builds a tree as deep as it is long so we hold an operator guard for
+    // each level of the deepest tree so far
+    let mut operand_stack = vec![(expr, 0)];
+    let mut levels = Vec::new();
 
     let next_token_indent = loop {
         if !matches!(iter.peek(), Some((Token::Operator(_), _range))) {
             break next_token_indent;
         }
 
+        let range = next_range(iter);
         let operator = extract::extract_operator(&iter.next())?;
         base_indent.must_consume_to_indented(iter)?;
 
-        process_stacks(operator, &mut operator_stack, &mut operand_stack)?;
+        process_stacks(
+            operator,
+            &range,
+            operators,
+            &mut operator_stack,
+            &mut operand_stack,
+            &mut levels,
+            arena,
+        )?;
+
+        // An if, case, let or lambda runs on for as far as it can, as in Elm, so it is always the
+        // last operand, eg. 'total + if big then 100 else 1'
+        if matches!(
+            iter.peek(),
+            Some((Token::If, _))
+                | Some((Token::Case, _))
+                | Some((Token::Let, _))
+                | Some((Token::BackSlash, _))
+        ) {
+            let (right_hand_expr, next_token_indent) =
+                parse_expression(iter, arena, operators, base_indent)?;
+            operand_stack.push((right_hand_expr, 0));
+            break next_token_indent;
+        }
 
-        let (right_hand_expr, next_token_indent) = parse_var_or_call(&mut iter, base_indent)?;
-        operand_stack.push(right_hand_expr);
+        let (right_hand_expr, next_token_indent) =
+            parse_var_or_call(iter, arena, operators, base_indent)?;
+        operand_stack.push((right_hand_expr, 0));
 
         // Similar to above, we consume the expression on the right hand side of the operator and
         // then any whitespace afterwards (to reach the next operator if there is one) but if we
         // find that we're no longer in the indentation scope of the expression then we assume
         // we've reached the end of it and continue with processing what we've got so far
-        if !next_token_indent.indented_from(&base_indent) {
+        if !next_token_indent.indented_from(base_indent) {
             log::trace!("exiting parse_binary_expression: {:?}", iter.peek());
             break next_token_indent;
         }
     };
 
-    while !operator_stack.is_empty() {
-        let operator = operator_stack.pop().ok_or(Error::NoOperator)?;
-        let right_hand_expr = operand_stack.pop().ok_or(Error::NoOperand)?;
-        let left_hand_expr = operand_stack.pop().ok_or(Error::NoOperand)?;
-
-        operand_stack.push(Expr::BinOp {
-            operator,
-            left: Rc::new(left_hand_expr),
-            right: Rc::new(right_hand_expr),
-        })
+    while let Some(operator) = operator_stack.pop() {
+        reduce(operator, &mut operand_stack, &mut levels, arena)?;
     }
 
     assert!(operand_stack.len() == 1);
     operand_stack
         .pop()
         .ok_or(Error::NoOperand)
-        .map(|expr| (expr, next_token_indent))
+        .map(|(expr, _depth)| (expr, next_token_indent))
+}
+
+/* Combines the top two operands with the operator and enters a depth level if the result is
+ * deeper than any operator tree so far
+ */
+fn reduce(
+    operator: String,
+    operand_stack: &mut Vec<(ExprId, usize)>,
+    levels: &mut Vec<depth::OperatorGuard>,
+    arena: &mut Arena,
+) -> Result<(), Error> {
+    let (right_hand_expr, right_depth) = operand_stack.pop().ok_or(Error::NoOperand)?;
+    let (left_hand_expr, left_depth) = operand_stack.pop().ok_or(Error::NoOperand)?;
+
+    let span = arena.span_between(left_hand_expr, right_hand_expr);
+    let tree_depth = left_depth.max(right_depth) + 1;
+    if tree_depth > levels.len() {
+        levels.push(depth::OperatorGuard::enter_at(&span)?);
+    }
+
+    operand_stack.push((
+        arena.alloc_at(
+            Expr::BinOp {
+                operator,
+                left: left_hand_expr,
+                right: right_hand_expr,
+            },
+            span,
+        ),
+        tree_depth,
+    ));
+    Ok(())
 }
 
 fn process_stacks(
     operator: &str,
-    mut operator_stack: &mut Vec<String>,
-    mut operand_stack: &mut Vec<Expr>,
+    range: &Range,
+    operators: &Operators,
+    operator_stack: &mut Vec<String>,
+    operand_stack: &mut Vec<(ExprId, usize)>,
+    levels: &mut Vec<depth::OperatorGuard>,
+    arena: &mut Arena,
 ) -> Result<(), Error> {
-    if has_greater_precedence(operator, operator_stack)? {
-        operator_stack.push(operator.to_string());
-    } else {
-        let right_hand_expr = operand_stack.pop().ok_or(Error::NoOperand)?;
-        let left_hand_expr = operand_stack.pop().ok_or(Error::NoOperand)?;
+    // Reduce the operators that bind at least as tightly as the new one before it goes on the stack
+    while !should_shift(operator, range, operators, operator_stack)? {
         let stored_operator = operator_stack.pop().ok_or(Error::NoOperator)?;
+        reduce(stored_operator, operand_stack, levels, arena)?;
+    }
 
-        operand_stack.push(Expr::BinOp {
-            operator: stored_operator,
-            left: Rc::new(left_hand_expr),
-            right: Rc::new(right_hand_expr),
-        });
+    operator_stack.push(operator.to_string());
 
-        process_stacks(operator, &mut operator_stack, &mut operand_stack)?;
-    };
+    // Each operator waiting on the stack will be the right hand side of the one below it
+    if operator_stack.len() > levels.len() {
+        levels.push(depth::OperatorGuard::enter_at(range)?);
+    }
 
     Ok(())
 }
 
-fn has_greater_precedence(operator_a: &str, operator_stack: &[String]) -> Result<bool, Error> {
-    if operator_stack.is_empty() {
-        Ok(true)
-    } else {
-        let precedence_a = precedence(operator_a)?;
-        let precedence_b = operator_stack
-            .last()
-            .ok_or(Error::EmptyOperatorStack)
-            .and_then(|op| precedence(op))?;
+/* Decides whether the new operator should go on the stack, so that it binds more tightly than the
+ * operator below it, or whether the operator below it should be reduced first. Operators with the
+ * same precedence are grouped according to their associativity.
+ */
+fn should_shift(
+    operator_a: &str,
+    range: &Range,
+    operators: &Operators,
+    operator_stack: &[String],
+) -> Result<bool, Error> {
+    let operator_b = match operator_stack.last() {
+        Some(operator_b) => operator_b,
+        None => return Ok(true),
+    };
 
-        Ok(precedence_a > precedence_b)
+    let fixity_a = operators.get(operator_a)?;
+    let fixity_b = operators.get(operator_b)?;
+
+    if fixity_a.precedence != fixity_b.precedence {
+        return Ok(fixity_a.precedence > fixity_b.precedence);
     }
-}
 
-// Based on:
-//
-//   - http://faq.elm-community.org/operators.html
-//   - https://github.com/elm-lang/core/blob/master/src/Basics.elm#L72-L90
-//
-fn precedence(operator: &str) -> Result<usize, Error> {
-    match operator {
-        "*" | "/" => Ok(7),
-        "+" | "-" => Ok(6),
-        "++" | "::" => Ok(5),
-        "==" | "/=" | ">" | "<" | "<=" | ">=" => Ok(4),
-        _ => Err(Error::UnknownOperator(operator.to_string())),
+    match (fixity_a.associativity, fixity_b.associativity) {
+        (Associativity::Left, Associativity::Left) => Ok(false),
+        (Associativity::Right, Associativity::Right) => Ok(true),
+        _ => Err(Error::NonAssociativeOperators {
+            first: operator_b.to_string(),
+            second: operator_a.to_string(),
+            range: range.clone(),
+        }),
     }
 }
 
@@ -493,63 +706,114 @@ fn precedence(operator: &str) -> Result<usize, Error> {
  */
 fn parse_singular_expression(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_singular_expression: {:?}", iter.peek());
     let expr = match iter.peek() {
-        Some((Token::OpenParen, _range)) => {
+        Some((Token::OpenParen, range)) => {
+            let start = range.start;
             matches(&iter.next(), Token::OpenParen)?;
-            let (expr, _) = parse_expression(iter, base_indent)?;
             base_indent.must_consume_to_indented(iter)?;
 
+            if let Some((Token::CloseParen, range)) = iter.peek() {
+                let span = start..range.end;
+                iter.next();
+                let expr = arena.alloc_at(Expr::Unit, span);
+                let next_token_indent = base_indent.consume(iter);
+                return Ok((expr, next_token_indent));
+            }
+
+            let (expr, _) = parse_expression(iter, arena, operators, base_indent)?;
+            base_indent.must_consume_to_indented(iter)?;
+
+            // A comma after the first expression means that we have a tuple rather than just an
+            // expression in parens
+            let mut items = vec![expr];
+            while let Some((Token::Comma, _range)) = iter.peek() {
+                matches(&iter.next(), Token::Comma)?;
+                base_indent.must_consume_to_indented(iter)?;
+
+                let (item, _) = parse_expression(iter, arena, operators, base_indent)?;
+                items.push(item);
+                base_indent.must_consume_to_indented(iter)?;
+            }
+
+            let span = start..next_range(iter).end;
             matches(&iter.next(), Token::CloseParen)?;
 
-            Ok(expr)
+            if items.len() == 1 {
+                Ok(expr)
+            } else {
+                Ok(arena.alloc_at(Expr::Tuple(items), span))
+            }
+        }
+        Some((Token::OpenBracket, _range)) => {
+            parse_list_literal(iter, arena, operators, base_indent)
+        }
+        Some((Token::OpenBrace, _range)) => {
+            parse_record_literal(iter, arena, operators, base_indent)
         }
-        Some((Token::OpenBracket, _range)) => parse_list_literal(iter, base_indent),
         None => Err(Error::UnexpectedEnd),
-        _ => parse_contained_expression(iter),
+        _ => parse_contained_expression(iter, arena),
     }?;
 
     let next_token_indent = base_indent.consume(iter);
     Ok((expr, next_token_indent))
 }
 
-fn parse_contained_expression(iter: &mut TokenIter) -> Result<Expr, Error> {
+fn parse_contained_expression(iter: &mut TokenIter, arena: &mut Arena) -> Result<ExprId, Error> {
     log::trace!("parse_contained_expression: {:?}", iter.peek());
     match iter.peek() {
-        Some((Token::LiteralInteger(int), _range)) => {
-            let result = Ok(Expr::Integer(*int));
+        Some((Token::LiteralInteger(int), range)) => {
+            let result = Ok(arena.alloc_at(Expr::Integer(*int), range.clone()));
             iter.next();
             result
         }
-        Some((Token::LiteralFloat(float), _range)) => {
-            let result = Ok(Expr::Float(*float));
+        Some((Token::LiteralFloat(float), range)) => {
+            let result = Ok(arena.alloc_at(Expr::Float(*float), range.clone()));
             iter.next();
             result
         }
-        Some((Token::LiteralString(string), _range)) => {
-            let result = Ok(Expr::String(string.to_string()));
+        Some((Token::LiteralString(string), range)) => {
+            let result = Ok(arena.alloc_at(Expr::String(Arc::from(*string)), range.clone()));
             iter.next();
             result
         }
-        Some((Token::UpperName("True"), _range)) => {
-            let result = Ok(Expr::Bool(true));
+        Some((Token::UpperName("True"), range)) => {
+            let result = Ok(arena.alloc_at(Expr::Bool(true), range.clone()));
             iter.next();
             result
         }
-        Some((Token::UpperName("False"), _range)) => {
-            let result = Ok(Expr::Bool(false));
+        Some((Token::UpperName("False"), range)) => {
+            let result = Ok(arena.alloc_at(Expr::Bool(false), range.clone()));
             iter.next();
             result
         }
-        Some((Token::LowerName(name), _range)) => {
-            let result = Ok(Expr::VarName(QualifiedLowerName::from(name.to_string())));
+        Some((Token::LiteralChar(_), range)) => {
+            let range = range.clone();
+            let char = extract::extract_char(&iter.next())?;
+            Ok(arena.alloc_at(Expr::Char(char), range))
+        }
+        Some((Token::UpperName(_), range)) | Some((Token::UpperPath(_), range)) => {
+            let range = range.clone();
+            let name = extract::extract_qualified_upper_name(&iter.next())?;
+            Ok(arena.alloc_at(Expr::Constructor(name), range))
+        }
+        Some((Token::LowerName(name), range)) => {
+            let result = Ok(arena.alloc_at(
+                Expr::VarName(QualifiedLowerName::from(name.to_string())),
+                range.clone(),
+            ));
             iter.next();
             result
         }
-        Some((Token::LowerPath(name), _range)) => {
-            let result = Ok(Expr::VarName(QualifiedLowerName::from(name.to_string())));
+        Some((Token::LowerPath(name), range)) => {
+            let result = Ok(arena.alloc_at(
+                Expr::VarName(QualifiedLowerName::from(name.to_string())),
+                range.clone(),
+            ));
             iter.next();
             result
         }
@@ -569,9 +833,12 @@ fn parse_contained_expression(iter: &mut TokenIter) -> Result<Expr, Error> {
 /* Parse the contents between [ and ] */
 fn parse_list_literal(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<Expr, Error> {
+) -> Result<ExprId, Error> {
     log::trace!("parse_list_literal: {:?}", iter.peek());
+    let start = next_range(iter).start;
     matches(&iter.next(), Token::OpenBracket)?;
 
     let mut expressions = Vec::new();
@@ -583,8 +850,8 @@ fn parse_list_literal(
             break;
         }
 
-        let (expr, _) = parse_expression(iter, base_indent)?;
-        expressions.push(Rc::new(expr));
+        let (expr, _) = parse_expression(iter, arena, operators, base_indent)?;
+        expressions.push(expr);
 
         base_indent.must_consume_to_indented(iter)?;
 
@@ -605,9 +872,76 @@ fn parse_list_literal(
         }
     }
 
+    let span = start..next_range(iter).end;
     matches(&iter.next(), Token::CloseBracket)?;
 
-    Ok(Expr::List(expressions))
+    Ok(arena.alloc_at(Expr::List(expressions), span))
+}
+
+/* Parse the fields between { and }. Each field is a name, '=' and an expression and, as with
+ * lists, the fields can be spread over several lines as long as they are indented from the
+ * expression that contains the record.
+ */
+fn parse_record_literal(
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
+    base_indent: &indent::Indentation,
+) -> Result<ExprId, Error> {
+    log::trace!("parse_record_literal: {:?}", iter.peek());
+    let start = next_range(iter).start;
+    matches(&iter.next(), Token::OpenBrace)?;
+
+    let mut fields: Vec<(String, ExprId)> = Vec::new();
+
+    loop {
+        base_indent.must_consume_to_indented(iter)?;
+
+        if fields.is_empty() {
+            if let Some((Token::CloseBrace, _range)) = iter.peek() {
+                break;
+            }
+        }
+
+        let range = next_range(iter);
+        let name = extract::extract_lower_name(&iter.next())?;
+        if fields.iter().any(|(field, _)| *field == name.0) {
+            return Err(Error::DuplicateField {
+                name: name.0,
+                range,
+            });
+        }
+
+        base_indent.must_consume_to_indented(iter)?;
+        matches(&iter.next(), Token::Equals)?;
+        base_indent.must_consume_to_indented(iter)?;
+
+        let (expr, _) = parse_expression(iter, arena, operators, base_indent)?;
+        fields.push((name.0, expr));
+
+        base_indent.must_consume_to_indented(iter)?;
+
+        match iter.peek() {
+            Some((Token::CloseBrace, _range)) => break,
+            Some((Token::Comma, _range)) => {
+                matches(&iter.next(), Token::Comma)?;
+            }
+            Some((token, range)) => {
+                log::error!("UnexpectedToken");
+                return Err(Error::UnexpectedToken {
+                    found: token.to_string(),
+                    expected: ", or }".to_string(),
+                    range: range.clone(),
+                });
+            }
+            None => return Err(Error::UnexpectedEnd),
+        }
+    }
+
+    let span = start..next_range(iter).end;
+    matches(&iter.next(), Token::CloseBrace)?;
+
+    Ok(arena.alloc_at(Expr::Record(fields), span))
 }
 
 /* A single value or a call site with some kind of single token or expression that we assume
@@ -615,14 +949,18 @@ fn parse_list_literal(
  */
 fn parse_var_or_call(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_var_or_call: {:?}", iter.peek());
-    let (var_or_func_expr, mut next_token_indent) = parse_singular_expression(iter, base_indent)?;
+    let function_range = next_range(iter);
+    let (var_or_func_expr, mut next_token_indent) =
+        parse_singular_expression(iter, arena, operators, base_indent)?;
 
     // If the next token is within our base indent then we assume we have more of the expression to
     // parse but if it is at a shallower indent then we assume it is a separate entity
-    if !next_token_indent.indented_from(&base_indent) {
+    if !next_token_indent.indented_from(base_indent) {
         log::trace!("exiting parse_var_or_call: {:?}", iter.peek());
         return Ok((var_or_func_expr, next_token_indent));
     }
@@ -634,10 +972,12 @@ fn parse_var_or_call(
             Some((Token::Operator(_), _))
             | Some((Token::CloseParen, _))
             | Some((Token::CloseBracket, _))
+            | Some((Token::CloseBrace, _))
             | Some((Token::Comma, _))
             | Some((Token::Then, _))
             | Some((Token::Else, _))
             | Some((Token::Of, _))
+            | Some((Token::In, _))
             | Some((Token::RightArrow, _))
             | None => {
                 // On certain tokens we know we've finish this 'var or call' and so we can exit and
@@ -647,14 +987,15 @@ fn parse_var_or_call(
             _ => {}
         }
 
-        let (argument_expr, next_token_indent) = parse_singular_expression(iter, base_indent)?;
-        args.push(Rc::new(argument_expr));
+        let (argument_expr, next_token_indent) =
+            parse_singular_expression(iter, arena, operators, base_indent)?;
+        args.push(argument_expr);
 
         // Similar to above, we consume the expression on the right hand side of the operator and
         // then any whitespace afterwards (to reach the next operator if there is one) but if we
         // find that we're no longer in the indentation scope of the expression then we assume
         // we've reached the end of it and continue with processing what we've got so far
-        if !next_token_indent.indented_from(&base_indent) {
+        if !next_token_indent.indented_from(base_indent) {
             break next_token_indent;
         }
     };
@@ -662,99 +1003,335 @@ fn parse_var_or_call(
     if args.is_empty() {
         Ok((var_or_func_expr, next_token_indent))
     } else {
-        Ok((
+        let span = args.last().map_or(function_range.clone(), |last| {
+            arena.span_between(var_or_func_expr, *last)
+        });
+        let call = arena.alloc_at(
             Expr::Call {
-                function: Rc::new(var_or_func_expr),
+                function: var_or_func_expr,
                 args,
             },
-            next_token_indent,
-        ))
+            span,
+        );
+        arena.set_call_range(call, function_range);
+        Ok((call, next_token_indent))
     }
 }
 
+/* Like Elm, the keywords & branches of an if expression only need to be indented from the
+ * expression that contains it, given by base_indent, rather than from the 'if' itself. So branches
+ * can sit at a shallower indent than the 'if' as long as they don't return to the level of the
+ * enclosing definition, let binding or case branch.
+ */
 fn parse_if_expression(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_if_expression: {:?}", iter.peek());
+    let start = next_range(iter).start;
     matches(&iter.next(), Token::If)?;
     base_indent.must_consume_to_indented(iter)?;
 
-    let (condition, _) = parse_expression(iter, base_indent)?;
+    let (condition, _) = parse_expression(iter, arena, operators, base_indent)?;
     base_indent.must_consume_to_indented(iter)?;
 
+    let then_range = next_range(iter);
     matches(&iter.next(), Token::Then)?;
     base_indent.must_consume_to_indented(iter)?;
 
-    let (then_branch, _) = parse_expression(iter, base_indent)?;
+    let (then_branch, _) = parse_expression(iter, arena, operators, base_indent)?;
+    arena.set_range(then_branch, then_range);
     base_indent.must_consume_to_indented(iter)?;
 
+    let else_range = next_range(iter);
     matches(&iter.next(), Token::Else)?;
     base_indent.must_consume_to_indented(iter)?;
 
-    let (else_branch, next_token_indent) = parse_expression(iter, base_indent)?;
+    let (else_branch, next_token_indent) = parse_expression(iter, arena, operators, base_indent)?;
+    arena.set_range(else_branch, else_range);
 
+    let span = arena.span_from(start, else_branch);
     Ok((
-        Expr::If {
-            condition: Rc::new(condition),
-            then_branch: Rc::new(then_branch),
-            else_branch: Rc::new(else_branch),
-        },
+        arena.alloc_at(
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            },
+            span,
+        ),
         next_token_indent,
     ))
 }
 
 fn parse_case_expression(
     iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
     base_indent: &indent::Indentation,
-) -> Result<(Expr, indent::Indentation), Error> {
+) -> Result<(ExprId, indent::Indentation), Error> {
     log::trace!("parse_case_expression: {:?}", iter.peek());
+    let case_range = next_range(iter);
     matches(&iter.next(), Token::Case)?;
     base_indent.must_consume_to_indented(iter)?;
 
-    let (expr, _) = parse_expression(iter, base_indent)?;
+    let (expr, _) = parse_expression(iter, arena, operators, base_indent)?;
     base_indent.must_consume_to_indented(iter)?;
 
+    let of_range = next_range(iter);
     matches(&iter.next(), Token::Of)?;
     let branch_indent = base_indent.must_consume_to_indented(iter)?;
 
     let mut branches = vec![];
 
     let next_token_indent = loop {
-        match iter.peek() {
-            None => {
-                // If there are no more tokens then we've finished parsing the possible cases
-                break branch_indent;
-            }
-            _ => {}
+        if iter.peek().is_none() {
+            // If there are no more tokens then we've finished parsing the possible cases
+            break branch_indent;
         }
 
-        let pattern = parse_pattern(iter)?;
+        let pattern_range = next_range(iter);
+        let pattern = parse_pattern(iter, &branch_indent)?;
         branch_indent.must_consume_to_indented(iter)?;
 
         matches(&iter.next(), Token::RightArrow)?;
         branch_indent.must_consume_to_indented(iter)?;
 
-        let (expr, next_token_indent) = parse_expression(iter, &branch_indent)?;
+        let (expr, next_token_indent) = parse_expression(iter, arena, operators, &branch_indent)?;
+        arena.set_range(expr, pattern_range);
         branches.push((pattern, expr));
 
-        if next_token_indent.matches(&branch_indent) {
+        // A closing bracket or comma straight after the branch isn't on a new line so its
+        // indentation says nothing about whether another branch follows
+        if next_token_indent.matches(&branch_indent) && starts_pattern(iter.peek()) {
             continue;
         } else {
             break next_token_indent;
         }
     };
 
+    let span = match branches.last() {
+        Some((_pattern, last)) => arena.span_from(case_range.start, *last),
+        None => case_range.start..of_range.end,
+    };
     Ok((
-        Expr::Case {
-            expr: Rc::new(expr),
-            branches,
-        },
+        arena.alloc_at(
+            Expr::Case {
+                expr,
+                branches,
+                range: case_range.start..of_range.end,
+            },
+            span,
+        ),
+        next_token_indent,
+    ))
+}
+
+/* Parses an anonymous function, '\x y -> x + y'. As with the arguments of top level functions, each
+ * argument is a name or an underscore. The body runs on for as far as it can.
+ */
+fn parse_lambda_expression(
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
+    base_indent: &indent::Indentation,
+) -> Result<(ExprId, indent::Indentation), Error> {
+    log::trace!("parse_lambda_expression: {:?}", iter.peek());
+    let start = next_range(iter).start;
+    matches(&iter.next(), Token::BackSlash)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    // Each argument is a single pattern, so constructors with arguments need parens as they do in
+    // function arguments, eg. '\( a, b ) (Just c) -> ...'
+    let mut args = Vec::new();
+    while !matches!(iter.peek(), Some((Token::RightArrow, _range))) {
+        args.push(parse_single_pattern(iter, base_indent)?);
+        base_indent.must_consume_to_indented(iter)?;
+    }
+
+    if args.is_empty() {
+        return Err(Error::UnexpectedToken {
+            found: Token::RightArrow.to_string(),
+            expected: "Lambda argument".to_string(),
+            range: next_range(iter),
+        });
+    }
+
+    matches(&iter.next(), Token::RightArrow)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    let (body, next_token_indent) = parse_expression(iter, arena, operators, base_indent)?;
+
+    let span = arena.span_from(start, body);
+    Ok((
+        arena.alloc_at(Expr::Lambda { args, body }, span),
+        next_token_indent,
+    ))
+}
+
+/* Parses 'let' followed by bindings, each of which has a pattern on the left hand side, and then
+ * 'in' and the body. The bindings have to line up with each other. A name followed by arguments,
+ * 'f x = ...', defines a local function which is stored as a lambda bound to the name.
+ */
+fn parse_let_expression(
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    operators: &Operators,
+    base_indent: &indent::Indentation,
+) -> Result<(ExprId, indent::Indentation), Error> {
+    log::trace!("parse_let_expression: {:?}", iter.peek());
+    let start = next_range(iter).start;
+    matches(&iter.next(), Token::Let)?;
+    let binding_indent = base_indent.must_consume_to_indented(iter)?;
+
+    let mut bindings = vec![];
+
+    loop {
+        let binding_start = next_range(iter).start;
+        let mut pattern = parse_pattern(iter, &binding_indent)?;
+        binding_indent.must_consume_to_indented(iter)?;
+
+        // A single name can have an annotation on the line above its definition
+        let mut type_annotation = None;
+        if let (Pattern::Name(name), Some((Token::Colon, _range))) = (&pattern, iter.peek()) {
+            let name = LowerName(name.clone());
+            // Holes are only reported for top level annotations so we don't keep their ranges
+            let (annotation, _holes) = parse_type_annotation(iter, name.clone(), &binding_indent)?;
+            type_annotation = Some(annotation);
+
+            pattern = parse_pattern(iter, &binding_indent)?;
+            binding_indent.must_consume_to_indented(iter)?;
+
+            if !matches!(&pattern, Pattern::Name(binding_name) if *binding_name == name.0) {
+                return Err(Error::NameMismatch);
+            }
+        }
+
+        let mut args = Vec::new();
+        if let Pattern::Name(_) = &pattern {
+            while !matches!(iter.peek(), Some((Token::Equals, _range))) {
+                args.push(extract::extract_pattern(&iter.next())?);
+                binding_indent.must_consume_to_indented(iter)?;
+            }
+        }
+
+        matches(&iter.next(), Token::Equals)?;
+        binding_indent.must_consume_to_indented(iter)?;
+
+        let (mut expr, next_token_indent) =
+            parse_expression(iter, arena, operators, &binding_indent)?;
+        if !args.is_empty() {
+            let span = arena.span_from(binding_start, expr);
+            expr = arena.alloc_at(Expr::Lambda { args, body: expr }, span);
+        }
+
+        bindings.push(LetBinding {
+            type_annotation,
+            pattern,
+            expr,
+        });
+
+        match iter.peek() {
+            Some((Token::In, _range)) => break,
+            Some((_token, _range)) if next_token_indent.matches(&binding_indent) => continue,
+            Some((token, range)) => {
+                log::error!("UnexpectedToken");
+                return Err(Error::UnexpectedToken {
+                    found: token.to_string(),
+                    expected: "in".to_string(),
+                    range: range.clone(),
+                });
+            }
+            None => return Err(Error::UnexpectedEnd),
+        }
+    }
+
+    matches(&iter.next(), Token::In)?;
+    base_indent.must_consume_to_indented(iter)?;
+
+    let (body, next_token_indent) = parse_expression(iter, arena, operators, base_indent)?;
+
+    let span = arena.span_from(start, body);
+    Ok((
+        arena.alloc_at(Expr::Let { bindings, body }, span),
         next_token_indent,
     ))
 }
 
-fn parse_pattern(iter: &mut TokenIter) -> Result<Pattern, Error> {
+// The range of the next token or an empty range at the start if there are no more tokens
+fn next_range(iter: &mut TokenIter) -> Range {
+    iter.peek()
+        .map(|(_token, range)| range.clone())
+        .unwrap_or(0..0)
+}
+
+/* Parses a full pattern as found at the start of a case branch. Constructors can take arguments
+ * at this level and patterns can be joined with '::' to match the head & tail of a list.
+ */
+fn parse_pattern(
+    iter: &mut TokenIter,
+    base_indent: &indent::Indentation,
+) -> Result<Pattern, Error> {
+    log::trace!("parse_pattern: {:?}", iter.peek());
+    let _depth = depth::Guard::enter(iter)?;
+    let pattern = match iter.peek() {
+        Some((Token::UpperName(name), _range)) | Some((Token::UpperPath(name), _range))
+            if *name != "True" && *name != "False" =>
+        {
+            let name = extract::extract_qualified_upper_name(&iter.next())?;
+            let mut args = vec![];
+            loop {
+                base_indent.consume(iter);
+                if !starts_pattern(iter.peek()) {
+                    break;
+                }
+                args.push(parse_single_pattern(iter, base_indent)?);
+            }
+            Pattern::Constructor(name, args)
+        }
+        _ => parse_single_pattern(iter, base_indent)?,
+    };
+
+    base_indent.consume(iter);
+
+    if let Some((Token::Operator("::"), _range)) = iter.peek() {
+        iter.next();
+        base_indent.must_consume_to_indented(iter)?;
+
+        // Cons patterns are right associative so the rest of the pattern is the tail
+        let tail = parse_pattern(iter, base_indent)?;
+        Ok(Pattern::Cons(Box::new(pattern), Box::new(tail)))
+    } else {
+        Ok(pattern)
+    }
+}
+
+fn starts_pattern(token: Option<&SrcToken>) -> bool {
+    matches!(
+        token,
+        Some((Token::LowerName(_), _))
+            | Some((Token::Underscore, _))
+            | Some((Token::LiteralInteger(_), _))
+            | Some((Token::LiteralString(_), _))
+            | Some((Token::LiteralChar(_), _))
+            | Some((Token::UpperName(_), _))
+            | Some((Token::UpperPath(_), _))
+            | Some((Token::OpenParen, _))
+            | Some((Token::OpenBracket, _))
+            | Some((Token::OpenBrace, _))
+    )
+}
+
+/* Parses a pattern that can appear as an argument to a constructor pattern. ie. nothing with
+ * arguments unless it is wrapped in parens.
+ */
+fn parse_single_pattern(
+    iter: &mut TokenIter,
+    base_indent: &indent::Indentation,
+) -> Result<Pattern, Error> {
+    log::trace!("parse_single_pattern: {:?}", iter.peek());
     match iter.peek() {
         Some((Token::UpperName("True"), _range)) => {
             let result = Ok(Pattern::Bool(true));
@@ -766,6 +1343,10 @@ fn parse_pattern(iter: &mut TokenIter) -> Result<Pattern, Error> {
             iter.next();
             result
         }
+        Some((Token::UpperName(_), _range)) | Some((Token::UpperPath(_), _range)) => {
+            let name = extract::extract_qualified_upper_name(&iter.next())?;
+            Ok(Pattern::Constructor(name, vec![]))
+        }
         Some((Token::LowerName(name), _range)) => {
             let result = Ok(Pattern::Name(name.to_string()));
             iter.next();
@@ -781,12 +1362,91 @@ fn parse_pattern(iter: &mut TokenIter) -> Result<Pattern, Error> {
             iter.next();
             result
         }
+        Some((Token::LiteralString(string), _range)) => {
+            let result = Ok(Pattern::String(string.to_string()));
+            iter.next();
+            result
+        }
+        Some((Token::LiteralChar(_), _range)) => {
+            let char = extract::extract_char(&iter.next())?;
+            Ok(Pattern::Char(char))
+        }
         Some((Token::LiteralFloat(_), range)) => Err(Error::FloatPattern(range.clone())),
+        Some((Token::OpenParen, _range)) => {
+            matches(&iter.next(), Token::OpenParen)?;
+            base_indent.consume(iter);
+
+            let mut patterns = vec![parse_pattern(iter, base_indent)?];
+            while let Some((Token::Comma, _range)) = iter.peek() {
+                matches(&iter.next(), Token::Comma)?;
+                base_indent.consume(iter);
+                patterns.push(parse_pattern(iter, base_indent)?);
+            }
+
+            matches(&iter.next(), Token::CloseParen)?;
+
+            // A single pattern in parens is just that pattern, more than one is a tuple
+            if patterns.len() == 1 {
+                Ok(patterns.remove(0))
+            } else {
+                Ok(Pattern::Tuple(patterns))
+            }
+        }
+        Some((Token::OpenBracket, _range)) => {
+            matches(&iter.next(), Token::OpenBracket)?;
+            base_indent.consume(iter);
+
+            let mut patterns = vec![];
+            if !matches!(iter.peek(), Some((Token::CloseBracket, _range))) {
+                patterns.push(parse_pattern(iter, base_indent)?);
+                while let Some((Token::Comma, _range)) = iter.peek() {
+                    matches(&iter.next(), Token::Comma)?;
+                    base_indent.consume(iter);
+                    patterns.push(parse_pattern(iter, base_indent)?);
+                }
+            }
+
+            matches(&iter.next(), Token::CloseBracket)?;
+            Ok(Pattern::List(patterns))
+        }
+        Some((Token::OpenBrace, _range)) => {
+            matches(&iter.next(), Token::OpenBrace)?;
+
+            let mut fields: Vec<String> = vec![];
+            loop {
+                base_indent.consume(iter);
+                let range = next_range(iter);
+                let name = extract::extract_lower_name(&iter.next())?;
+                if fields.contains(&name.0) {
+                    return Err(Error::DuplicateField {
+                        name: name.0,
+                        range,
+                    });
+                }
+                fields.push(name.0);
+
+                base_indent.consume(iter);
+                match iter.next() {
+                    Some((Token::CloseBrace, _range)) => break,
+                    Some((Token::Comma, _range)) => {}
+                    Some((token, range)) => {
+                        return Err(Error::UnexpectedToken {
+                            found: token.to_string(),
+                            expected: ", or }".to_string(),
+                            range,
+                        });
+                    }
+                    None => return Err(Error::UnexpectedEnd),
+                }
+            }
+
+            Ok(Pattern::Record(fields))
+        }
         Some((token, range)) => {
             log::error!("UnexpectedToken");
             Err(Error::UnexpectedToken {
                 found: token.to_string(),
-                expected: "Expression token".to_string(),
+                expected: "Pattern".to_string(),
                 range: range.clone(),
             })
         }
@@ -0,0 +1,72 @@
+use std::cell::Cell;
+
+use super::error::Error;
+use crate::lexer::{Range, TokenIter};
+
+// Deep enough for any hand written code while leaving room on the stack for the checker & the
+// evaluator, which walk the same nesting, even on threads with smaller stacks than main
+const MAX_DEPTH: usize = 100;
+
+// Chains of operators, like '1 + 2 + 3', make trees as deep as they are long but the later passes
+// walk those trees in a loop so they have a much larger budget of their own
+const MAX_OPERATOR_DEPTH: usize = 10_000;
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    static OPERATOR_DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/* Marks that the parser has gone one level deeper into nested expressions, patterns or types. The
+ * parse functions are mutually recursive so pathological input, like thousands of parentheses,
+ * would otherwise overflow the stack. The level is left when the guard is dropped, including when
+ * parsing fails part way through.
+ */
+pub struct Guard;
+
+impl Guard {
+    pub fn enter(iter: &mut TokenIter) -> Result<Self, Error> {
+        let range = iter
+            .peek()
+            .map(|(_token, range)| range.clone())
+            .unwrap_or(0..0);
+
+        DEPTH.with(|depth| {
+            if depth.get() >= MAX_DEPTH {
+                Err(Error::TooDeeplyNested(range))
+            } else {
+                depth.set(depth.get() + 1);
+                Ok(Guard)
+            }
+        })
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
+
+/* Marks one level of a tree of operators, which is reported at the given range if the tree is
+ * too deep. Like Guard, the level is left when the guard is dropped.
+ */
+pub struct OperatorGuard;
+
+impl OperatorGuard {
+    pub fn enter_at(range: &Range) -> Result<Self, Error> {
+        OPERATOR_DEPTH.with(|depth| {
+            if depth.get() >= MAX_OPERATOR_DEPTH {
+                Err(Error::TooManyOperators(range.clone()))
+            } else {
+                depth.set(depth.get() + 1);
+                Ok(OperatorGuard)
+            }
+        })
+    }
+}
+
+impl Drop for OperatorGuard {
+    fn drop(&mut self) {
+        OPERATOR_DEPTH.with(|depth| depth.set(depth.get() - 1));
+    }
+}
@@ -14,6 +14,23 @@ pub enum Error {
     },
     UnderscoreExpression(Range),
     FloatPattern(Range),
+    // Operators like '<' can't be chained together without parens as they don't group either way
+    NonAssociativeOperators {
+        first: String,
+        second: String,
+        range: Range,
+    },
+    // Ports have to either send values out or receive them
+    PortType(Range),
+    // Expressions, patterns or types nested deeper than the parser is willing to go
+    TooDeeplyNested(Range),
+    // A chain of operators longer than the parser is willing to go
+    TooManyOperators(Range),
+    // A record literal that gives the same field more than once
+    DuplicateField {
+        name: String,
+        range: Range,
+    },
 
     //
     TokensRemaining(Vec<String>),
@@ -26,3 +43,22 @@ pub enum Error {
     NameMismatch,
     Unknown,
 }
+
+impl Error {
+    /* The location in the source of the error, if we know it */
+    pub fn range(&self) -> Option<Range> {
+        match self {
+            Error::UnexpectedToken { range, .. } => Some(range.clone()),
+            Error::TokenNotAtLineStart(range) => Some(range.clone()),
+            Error::Indent { range } => Some(range.clone()),
+            Error::UnderscoreExpression(range) => Some(range.clone()),
+            Error::FloatPattern(range) => Some(range.clone()),
+            Error::NonAssociativeOperators { range, .. } => Some(range.clone()),
+            Error::PortType(range) => Some(range.clone()),
+            Error::TooDeeplyNested(range) => Some(range.clone()),
+            Error::TooManyOperators(range) => Some(range.clone()),
+            Error::DuplicateField { range, .. } => Some(range.clone()),
+            _ => None,
+        }
+    }
+}
@@ -132,6 +132,31 @@ pub fn extract_pattern(stream_token: &Option<SrcToken>) -> Result<Pattern, Error
     }
 }
 
+pub fn extract_char(stream_token: &Option<SrcToken>) -> Result<char, Error> {
+    log::trace!("extract_char: {:?}", stream_token);
+    match stream_token {
+        Some((Token::LiteralChar(contents), range)) => {
+            contents
+                .chars()
+                .next()
+                .ok_or_else(|| Error::UnexpectedToken {
+                    found: format!("'{}'", contents),
+                    expected: "a single character".to_string(),
+                    range: range.clone(),
+                })
+        }
+        Some((token, range)) => {
+            log::error!("UnexpectedToken");
+            Err(Error::UnexpectedToken {
+                found: token.to_string(),
+                expected: Token::LiteralChar("").to_string(),
+                range: range.clone(),
+            })
+        }
+        None => Err(Error::UnexpectedEnd),
+    }
+}
+
 pub fn extract_associativity(stream_token: &Option<SrcToken>) -> Result<Associativity, Error> {
     log::trace!("extract_associativity: {:?}", stream_token);
     match stream_token {
@@ -0,0 +1,325 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use logos::Logos;
+
+use super::error::Error;
+use super::operators::{declared_operators, Operators};
+use super::{indent, parse_header, parse_statements, ParseResult};
+use crate::ast::{Arena, DefinitionKind, Import, Module, Stmt};
+use crate::lexer::{Range, Token};
+
+/* Holds the results of previous parses of a file so that re-parsing it after an edit only has to
+ * parse the top level declarations whose source has changed. Intended to be kept alive for the
+ * length of a watch or editor session.
+ *
+ * Declarations are keyed by a hash of their source text. The statements for cached declarations
+ * refer to expressions in the cache's arena which we only ever append to so that the existing
+ * expression ids stay valid. When a reused declaration has moved in the source, the ranges of its
+ * expressions are moved with it. The arena is shared with the modules that we return and is only
+ * copied if one of them is still alive when the next edit is parsed.
+ */
+#[derive(Default)]
+pub struct Cache {
+    source_hash: Option<u64>,
+    module: Option<Module>,
+    declarations: HashMap<u64, Declaration>,
+    arena: Arc<Arena>,
+    // The declarations are parsed with these operators so we have to start again if they change
+    operators: Operators,
+}
+
+struct Declaration {
+    statements: Vec<Arc<Stmt>>,
+    // Where the declaration started in the source when its ranges were last set
+    start: usize,
+    // The ids of the expressions that the declaration allocated in the arena
+    exprs: std::ops::Range<usize>,
+    definitions: Vec<(DefinitionKind, String, Range)>,
+    holes: Vec<(String, Range)>,
+}
+
+impl Cache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* The callback provides the operators declared by an imported module, as for parse_source */
+    pub fn parse<F>(&mut self, source: &str, imported_operators: F) -> ParseResult
+    where
+        F: Fn(&Import) -> Operators,
+    {
+        log::trace!("Cache:parse");
+        let source_hash = hash(source);
+        if let (Some(cached_hash), Some(module)) = (self.source_hash, &self.module) {
+            if cached_hash == source_hash {
+                return Ok(module.clone());
+            }
+        }
+
+        // Let go of our copy of the last module so that the arena isn't shared unnecessarily
+        self.module = None;
+
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        let (name, exposing, imports) = parse_header(&mut iter)?;
+
+        let statements_start = match iter.peek() {
+            Some((_token, range)) => range.start,
+            None => source.len(),
+        };
+
+        let mut operators = Operators::core();
+        for import in &imports {
+            operators.extend(imported_operators(import).exposed_by(import));
+        }
+        operators.extend(declared_operators(&mut iter)?);
+
+        if operators != self.operators {
+            self.declarations.clear();
+            self.operators = operators;
+        }
+
+        let mut statements = vec![];
+        let mut definitions = vec![];
+        let mut holes = vec![];
+        let mut live_declarations = HashSet::new();
+        let arena = Arc::make_mut(&mut self.arena);
+
+        for range in declaration_ranges(source, statements_start) {
+            let declaration_hash = hash(&source[range.clone()]);
+            live_declarations.insert(declaration_hash);
+
+            if let Some(cached) = self.declarations.get_mut(&declaration_hash) {
+                if cached.start != range.start {
+                    let offset = range.start as isize - cached.start as isize;
+                    arena.shift_ranges(cached.exprs.clone(), offset);
+                    let definition_ranges =
+                        cached.definitions.iter_mut().map(|(_, _, range)| range);
+                    let hole_ranges = cached.holes.iter_mut().map(|(_, range)| range);
+                    for range in definition_ranges.chain(hole_ranges) {
+                        range.start = range.start.saturating_add_signed(offset);
+                        range.end = range.end.saturating_add_signed(offset);
+                    }
+                    cached.start = range.start;
+                }
+
+                statements.extend(cached.statements.iter().cloned());
+                definitions.extend(cached.definitions.iter().cloned());
+                holes.extend(cached.holes.iter().cloned());
+                continue;
+            }
+
+            let first_expr = arena.len();
+            let first_definition = arena.definitions_len();
+            let first_hole = arena.holes_len();
+            let parsed = parse_declaration(source, range.clone(), arena, &mut self.operators)?;
+            let declaration = Declaration {
+                statements: parsed,
+                start: range.start,
+                exprs: first_expr..arena.len(),
+                definitions: arena.split_definitions(first_definition),
+                holes: arena.split_holes(first_hole),
+            };
+
+            statements.extend(declaration.statements.iter().cloned());
+            definitions.extend(declaration.definitions.iter().cloned());
+            holes.extend(declaration.holes.iter().cloned());
+            self.declarations.insert(declaration_hash, declaration);
+        }
+
+        arena.set_definitions(definitions);
+        arena.set_holes(holes);
+
+        // Forget declarations that are no longer in the file. Their expressions stay in the arena
+        // until the cache is dropped
+        self.declarations
+            .retain(|declaration_hash, _| live_declarations.contains(declaration_hash));
+
+        let module = Module {
+            name,
+            exposing,
+            imports,
+            statements,
+            arena: self.arena.clone(),
+        };
+
+        self.source_hash = Some(source_hash);
+        self.module = Some(module.clone());
+
+        Ok(module)
+    }
+}
+
+fn hash(source: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    source.hash(&mut hasher);
+    hasher.finish()
+}
+
+/* Splits the source after the header into the ranges of the top level declarations. A declaration
+ * starts with a token at the start of a line, except that a type annotation and the definition
+ * that follows it are kept together. We work from the tokens rather than the lines so that
+ * multi-line comments & strings aren't split up.
+ */
+fn declaration_ranges(source: &str, start: usize) -> Vec<Range> {
+    log::trace!("declaration_ranges");
+    let mut starts = vec![];
+    let mut at_line_start = true;
+    let mut in_annotation = false;
+    let mut previous_was_declaration_start = false;
+
+    for (token, range) in Token::lexer(source).spanned() {
+        match token {
+            Token::NewLine => {
+                at_line_start = true;
+                continue;
+            }
+            Token::Space(_) | Token::SingleLineComment(_) | Token::MultiLineComment(_) => {
+                at_line_start = false;
+                continue;
+            }
+            Token::Colon if previous_was_declaration_start => {
+                in_annotation = true;
+            }
+            _ if at_line_start && range.start >= start => {
+                if in_annotation {
+                    in_annotation = false;
+                } else {
+                    starts.push(range.start);
+                }
+                previous_was_declaration_start = true;
+                at_line_start = false;
+                continue;
+            }
+            _ => {}
+        }
+
+        at_line_start = false;
+        previous_was_declaration_start = false;
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(index, declaration_start)| {
+            let end = starts.get(index + 1).copied().unwrap_or(source.len());
+            *declaration_start..end
+        })
+        .collect()
+}
+
+/* Parses the statements in the given range of the source. We lex from the start of the source
+ * but skip ahead to the range so that the token ranges in any errors match the full file.
+ */
+fn parse_declaration(
+    source: &str,
+    range: Range,
+    arena: &mut Arena,
+    operators: &mut Operators,
+) -> Result<Vec<Arc<Stmt>>, Error> {
+    log::trace!("parse_declaration: {:?}", range);
+    let mut lexer = Token::lexer(&source[..range.end]);
+    lexer.bump(range.start);
+
+    let mut iter = lexer.spanned().peekable();
+    let base_indent = indent::Indentation::new();
+    base_indent.must_consume_to_line_start(&mut iter)?;
+
+    parse_statements(&mut iter, arena, operators)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn find_statement(module: &Module, target_name: &str) -> Arc<Stmt> {
+        module
+            .statements
+            .iter()
+            .find(|stmt| match &***stmt {
+                Stmt::Binding { name, .. } | Stmt::Function { name, .. } => name.0 == target_name,
+                _ => false,
+            })
+            .cloned()
+            .expect("Failed to find statement")
+    }
+
+    #[test]
+    fn only_changed_declarations_are_reparsed() {
+        let mut cache = Cache::new();
+        let first = cache
+            .parse(
+                "module Main exposing (..)\nx : Int\nx = 1\n\ny = 2\n",
+                |_| Operators::new(),
+            )
+            .expect("Failed to parse");
+        let second = cache
+            .parse(
+                "module Main exposing (..)\nx : Int\nx = 1\n\ny = 3\n",
+                |_| Operators::new(),
+            )
+            .expect("Failed to parse");
+
+        assert_eq!(second.statements.len(), 2);
+        assert!(Arc::ptr_eq(
+            &find_statement(&first, "x"),
+            &find_statement(&second, "x")
+        ));
+        assert!(!Arc::ptr_eq(
+            &find_statement(&first, "y"),
+            &find_statement(&second, "y")
+        ));
+    }
+
+    #[test]
+    fn errors_refer_to_the_full_source() {
+        let mut cache = Cache::new();
+        let source = "module Main exposing (..)\nx = 1\n\ny = )\n";
+        let result = cache.parse(source, |_| Operators::new());
+
+        assert!(matches!(
+            result,
+            Err(Error::UnexpectedToken { range, .. }) if range.start == source.rfind(')').unwrap_or(0)
+        ));
+    }
+
+    #[test]
+    fn reused_declarations_move_their_ranges() {
+        let mut cache = Cache::new();
+        let before = "module Main exposing (..)\nx = 1\n\ny : _\ny =\n    if True then 1 else 2\n";
+        let after =
+            "module Main exposing (..)\nx = 12345\n\ny : _\ny =\n    if True then 1 else 2\n";
+
+        let first = cache
+            .parse(before, |_| Operators::new())
+            .expect("Failed to parse");
+        let then_range = |module: &Module| {
+            let mut ranges: Vec<Range> = module
+                .arena
+                .ranges()
+                .map(|(_, range)| range.clone())
+                .collect();
+            ranges.sort_by_key(|range| range.start);
+            ranges.first().cloned()
+        };
+        assert_eq!(&before[then_range(&first).unwrap_or(0..0)], "then");
+        drop(first);
+
+        let second = cache
+            .parse(after, |_| Operators::new())
+            .expect("Failed to parse");
+        assert_eq!(&after[then_range(&second).unwrap_or(0..0)], "then");
+
+        let y = after.find("y =").unwrap_or(0);
+        assert_eq!(
+            second.arena.definition(DefinitionKind::Value, "y"),
+            Some(&(y..y + 1))
+        );
+
+        let hole = after.find('_').unwrap_or(0);
+        assert_eq!(second.arena.holes("y").next(), Some(&(hole..hole + 1)));
+    }
+}
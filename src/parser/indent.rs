@@ -48,6 +48,10 @@ impl Indentation {
                     new.spaces += count;
                     iter.next();
                 }
+                // Comments can go anywhere that whitespace can
+                Token::SingleLineComment(_) | Token::MultiLineComment(_) => {
+                    iter.next();
+                }
                 _ => {
                     return new;
                 }
@@ -107,6 +111,9 @@ impl Indentation {
                     new.spaces += count;
                     iter.next();
                 }
+                Token::SingleLineComment(_) | Token::MultiLineComment(_) => {
+                    iter.next();
+                }
                 _ => {
                     return if new.indented_from(self) {
                         Ok(new)
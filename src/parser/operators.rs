@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use super::{indent, parse_infix, Error};
+use crate::ast::{Associativity, Exposing, ExposingDetail, Import, Stmt};
+use crate::lexer::{Token, TokenIter};
+
+/* The precedence & associativity of the operators that are available to a module. These come from
+ * the infix declarations in the module itself and in the modules that it imports, so we collect
+ * them before parsing any expressions.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Hash)]
+pub struct Fixity {
+    pub precedence: usize,
+    pub associativity: Associativity,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Operators {
+    fixities: HashMap<String, Fixity>,
+}
+
+// Hashed in a fixed order so that the same operators always give the same hash, for keying cached
+// parses of the modules that use them
+impl Hash for Operators {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut fixities: Vec<_> = self.fixities.iter().collect();
+        fixities.sort_by_key(|(operator, _)| *operator);
+        fixities.hash(state);
+    }
+}
+
+// Based on:
+//
+//   - http://faq.elm-community.org/operators.html
+//   - https://github.com/elm-lang/core/blob/master/src/Basics.elm#L72-L90
+//
+const CORE: [(&str, usize, Associativity); 20] = [
+    ("<|", 0, Associativity::Right),
+    ("|>", 0, Associativity::Left),
+    ("||", 2, Associativity::Right),
+    ("&&", 3, Associativity::Right),
+    ("==", 4, Associativity::Non),
+    ("/=", 4, Associativity::Non),
+    ("<", 4, Associativity::Non),
+    (">", 4, Associativity::Non),
+    ("<=", 4, Associativity::Non),
+    (">=", 4, Associativity::Non),
+    ("++", 5, Associativity::Right),
+    ("::", 5, Associativity::Right),
+    ("+", 6, Associativity::Left),
+    ("-", 6, Associativity::Left),
+    ("*", 7, Associativity::Left),
+    ("/", 7, Associativity::Left),
+    ("//", 7, Associativity::Left),
+    ("^", 8, Associativity::Right),
+    ("<<", 9, Associativity::Left),
+    (">>", 9, Associativity::Right),
+];
+
+impl Operators {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /* The operators declared by Elm's Basics module which every module imports. Our Basics module
+     * doesn't declare all of them yet so we don't rely on reading it.
+     */
+    pub fn core() -> Self {
+        let mut operators = Self::new();
+        for (operator, precedence, associativity) in CORE.iter() {
+            operators.insert(
+                operator,
+                Fixity {
+                    precedence: *precedence,
+                    associativity: *associativity,
+                },
+            );
+        }
+        operators
+    }
+
+    pub fn insert(&mut self, operator: &str, fixity: Fixity) {
+        self.fixities.insert(operator.to_string(), fixity);
+    }
+
+    pub fn extend(&mut self, other: Operators) {
+        self.fixities.extend(other.fixities);
+    }
+
+    pub fn get(&self, operator: &str) -> Result<Fixity, Error> {
+        self.fixities
+            .get(operator)
+            .copied()
+            .ok_or_else(|| Error::UnknownOperator(operator.to_string()))
+    }
+
+    pub fn add_declaration(&mut self, stmt: &Stmt) {
+        if let Stmt::Infix {
+            operator_name,
+            associativity,
+            precedence,
+            ..
+        } = stmt
+        {
+            self.insert(
+                operator_name,
+                Fixity {
+                    precedence: *precedence,
+                    associativity: *associativity,
+                },
+            );
+        }
+    }
+
+    /* Only the operators that the import exposes are available in the importing module */
+    pub fn exposed_by(&self, import: &Import) -> Self {
+        let fixities = self
+            .fixities
+            .iter()
+            .filter(|(operator, _)| {
+                match &import.exposing {
+                Some(Exposing::All) => true,
+                Some(Exposing::List(details)) => details.iter().any(|detail| {
+                    matches!(detail, ExposingDetail::Operator(exposed) if exposed == *operator)
+                }),
+                None => false,
+            }
+            })
+            .map(|(operator, fixity)| (operator.clone(), *fixity))
+            .collect();
+
+        Self { fixities }
+    }
+}
+
+/* Finds the infix declarations in the token stream without parsing the rest of the module. Infix
+ * is a keyword so it only appears at the start of a declaration.
+ */
+pub fn declared_operators(iter: &mut TokenIter) -> Result<Operators, Error> {
+    log::trace!("declared_operators");
+    let mut operators = Operators::new();
+
+    while let Some((token, _range)) = iter.peek() {
+        if *token == Token::Infix {
+            let stmt = parse_infix(iter, &indent::Indentation::new())?;
+            operators.add_declaration(&stmt);
+        } else {
+            iter.next();
+        }
+    }
+
+    Ok(operators)
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::parse_source;
+    use super::*;
+    use crate::ast::{Expr, Module};
+
+    fn value_expr(module: &Module) -> &Expr {
+        let expr = module
+            .statements
+            .iter()
+            .find_map(|stmt| match &**stmt {
+                Stmt::Binding { expr, .. } => Some(*expr),
+                _ => None,
+            })
+            .expect("Failed to find binding");
+        &module.arena[expr]
+    }
+
+    #[test]
+    fn uses_declarations_after_the_expression() {
+        let source = "module Main exposing (..)
+value = 1 |+| 2 |+| 3
+infix right 5 (|+|) = add
+";
+        let module = parse_source(source, |_import| Operators::new()).expect("Failed to parse");
+
+        match value_expr(&module) {
+            Expr::BinOp { right, .. } => {
+                assert!(matches!(module.arena[*right], Expr::BinOp { .. }))
+            }
+            expr => panic!("Unexpected expression: {:?}", expr),
+        }
+    }
+
+    #[test]
+    fn uses_operators_exposed_by_imports() {
+        let source = "module Main exposing (..)
+import Ops exposing ((|+|))
+value = 1 |+| 2 * 3
+";
+        let imported_operators = |_import: &Import| {
+            let mut operators = Operators::new();
+            let fixity = Fixity {
+                precedence: 9,
+                associativity: Associativity::Left,
+            };
+            operators.insert("|+|", fixity);
+            operators.insert("|-|", fixity);
+            operators
+        };
+        let module = parse_source(source, imported_operators).expect("Failed to parse");
+
+        // The imported operator binds more tightly than multiplication
+        match value_expr(&module) {
+            Expr::BinOp { operator, left, .. } => {
+                assert_eq!(operator, "*");
+                assert!(matches!(module.arena[*left], Expr::BinOp { .. }))
+            }
+            expr => panic!("Unexpected expression: {:?}", expr),
+        }
+    }
+}
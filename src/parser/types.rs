@@ -1,20 +1,25 @@
 use crate::ast::*;
-use crate::lexer::{Token, TokenIter};
+use crate::lexer::{Range, Token, TokenIter};
 
+use super::depth;
 use super::error::Error;
 use super::extract;
 use super::indent;
 use super::mtch::matches;
+use super::next_range;
 
 pub fn parse_type_declaration(
     iter: &mut TokenIter,
+    arena: &mut Arena,
     base_indent: &indent::Indentation,
 ) -> Result<Stmt, Error> {
     log::trace!("parse_type_declaration: {:?}", iter.peek());
     matches(&iter.next(), Token::Type)?;
     base_indent.must_consume_to_indented(iter)?;
 
+    let range = next_range(iter);
     let name = extract::extract_upper_name(&iter.next())?;
+    arena.add_definition(DefinitionKind::Type, &name.0, range);
     base_indent.must_consume_to_indented(iter)?;
 
     let mut args = vec![];
@@ -32,8 +37,8 @@ pub fn parse_type_declaration(
     matches(&iter.next(), Token::Equals)?;
     base_indent.must_consume_to_indented(iter)?;
 
-    let first_constructor = parse_type(iter, &base_indent)?;
-    base_indent.must_consume_to_indented(iter)?;
+    let first_constructor = parse_constructor(iter, arena, base_indent)?;
+    base_indent.consume(iter);
 
     let mut constructors = vec![first_constructor];
 
@@ -44,9 +49,9 @@ pub fn parse_type_declaration(
         matches(&iter.next(), Token::Bar)?;
         base_indent.must_consume_to_indented(iter)?;
 
-        let constructor = parse_type(iter, &base_indent)?;
+        let constructor = parse_constructor(iter, arena, base_indent)?;
         constructors.push(constructor);
-        base_indent.must_consume_to_indented(iter)?;
+        base_indent.consume(iter);
     }
 
     Ok(Stmt::Type {
@@ -56,50 +61,74 @@ pub fn parse_type_declaration(
     })
 }
 
+fn parse_constructor(
+    iter: &mut TokenIter,
+    arena: &mut Arena,
+    base_indent: &indent::Indentation,
+) -> Result<Type, Error> {
+    let range = next_range(iter);
+    let constructor = parse_type(iter, base_indent)?;
+    if let Type::UserDefined { name, .. } = &constructor {
+        arena.add_definition(DefinitionKind::Constructor, &name.access, range);
+    }
+    Ok(constructor)
+}
+
 pub fn parse_type(iter: &mut TokenIter, base_indent: &indent::Indentation) -> Result<Type, Error> {
-    log::trace!("parse_type: {:?}", iter.peek());
-    let mut type_ = parse_single_type(iter, &base_indent)?;
-    base_indent.must_consume_to_indented(iter)?;
+    parse_type_with_holes(iter, base_indent, &mut vec![])
+}
 
-    loop {
-        match iter.peek() {
-            Some((Token::RightArrow, _range)) => {
-                matches(&iter.next(), Token::RightArrow)?;
-                base_indent.must_consume_to_indented(iter)?;
-
-                let next_type = parse_single_type(iter, &base_indent)?;
-                base_indent.must_consume_to_indented(iter)?;
-                type_ = Type::Function {
-                    from: Box::new(type_),
-                    to: Box::new(next_type),
-                }
-            }
-            Some((Token::CloseParen, _range)) => {
-                break;
-            }
-            _ => {
-                break;
-            }
+/* Parses a type and adds the range of each '_' in it to holes, in the order that they appear, so
+ * that the checker's findings for them can be reported where they are
+ */
+pub fn parse_type_with_holes(
+    iter: &mut TokenIter,
+    base_indent: &indent::Indentation,
+    holes: &mut Vec<Range>,
+) -> Result<Type, Error> {
+    log::trace!("parse_type_with_holes: {:?}", iter.peek());
+    let _depth = depth::Guard::enter(iter)?;
+    let type_ = parse_single_type(iter, base_indent, holes)?;
+    // The type might be the last thing in the file so we don't insist on finding another token
+    base_indent.consume(iter);
+
+    match iter.peek() {
+        // Arrows group to the right so 'a -> b -> c' is a function returning a function
+        Some((Token::RightArrow, _range)) => {
+            matches(&iter.next(), Token::RightArrow)?;
+            base_indent.must_consume_to_indented(iter)?;
+
+            let rest = parse_type_with_holes(iter, base_indent, holes)?;
+            Ok(Type::Function {
+                from: Box::new(type_),
+                to: Box::new(rest),
+            })
         }
+        _ => Ok(type_),
     }
-
-    Ok(type_)
 }
 
 // Parse up to the next "->" (RightArrow)
 fn parse_single_type(
     iter: &mut TokenIter,
     base_indent: &indent::Indentation,
+    holes: &mut Vec<Range>,
 ) -> Result<Type, Error> {
     log::trace!("parse_single_type: {:?}", iter.peek());
 
     match iter.peek() {
-        Some((Token::UpperName(_), _range)) => parse_explicit_type(iter, &base_indent),
-        Some((Token::UpperPath(_), _range)) => parse_explicit_type(iter, &base_indent),
+        Some((Token::UpperName(_), _range)) => parse_explicit_type(iter, base_indent, holes),
+        Some((Token::UpperPath(_), _range)) => parse_explicit_type(iter, base_indent, holes),
         Some((Token::LowerName(_), _range)) => {
             let name = extract::extract_lower_name(&iter.next())?;
             Ok(Type::Var(name))
         }
+        Some((Token::Underscore, range)) => {
+            holes.push(range.clone());
+            matches(&iter.next(), Token::Underscore)?;
+            Ok(Type::Hole)
+        }
+        Some((Token::OpenParen, _range)) => parse_parenthesised_type(iter, base_indent, holes),
         Some((token, range)) => Err(Error::UnexpectedToken {
             expected: "Not sure".to_string(),
             found: token.to_string(),
@@ -109,14 +138,57 @@ fn parse_single_type(
     }
 }
 
+/* Parentheses hold the unit type, a tuple of types or a single type that they group, eg. a
+ * function passed as an argument or a type with arguments of its own used as an argument
+ */
+fn parse_parenthesised_type(
+    iter: &mut TokenIter,
+    base_indent: &indent::Indentation,
+    holes: &mut Vec<Range>,
+) -> Result<Type, Error> {
+    log::trace!("parse_parenthesised_type: {:?}", iter.peek());
+    matches(&iter.next(), Token::OpenParen)?;
+    base_indent.consume(iter);
+
+    if let Some((Token::CloseParen, _range)) = iter.peek() {
+        iter.next();
+        return Ok(Type::Unit);
+    }
+
+    let mut types = vec![parse_type_with_holes(iter, base_indent, holes)?];
+    loop {
+        base_indent.consume(iter);
+        match iter.peek() {
+            Some((Token::Comma, _range)) => {
+                iter.next();
+                base_indent.consume(iter);
+                types.push(parse_type_with_holes(iter, base_indent, holes)?);
+            }
+            _ => break,
+        }
+    }
+
+    matches(&iter.next(), Token::CloseParen)?;
+
+    match types.pop() {
+        Some(type_) if types.is_empty() => Ok(type_),
+        Some(last) => {
+            types.push(last);
+            Ok(Type::Tuple(types))
+        }
+        None => Err(Error::UnexpectedEnd),
+    }
+}
+
 fn parse_explicit_type(
     iter: &mut TokenIter,
     base_indent: &indent::Indentation,
+    holes: &mut Vec<Range>,
 ) -> Result<Type, Error> {
     log::trace!("parse_explicit_type: {:?}", iter.peek());
     let name = extract::extract_qualified_upper_name(&iter.next())?;
     let indent = base_indent.consume(iter);
-    if indent.indented_from(&base_indent) {
+    if indent.indented_from(base_indent) {
         // current = indent.extract();
     } else {
         return convert_name_to_type(name, vec![]);
@@ -126,15 +198,18 @@ fn parse_explicit_type(
     loop {
         if matches!(
             iter.peek(),
-            Some((Token::CloseParen, _range))
-                | Some((Token::RightArrow, _range))
-                | Some((Token::Bar, _range))
+            Some((Token::CloseParen, _))
+                | Some((Token::Comma, _))
+                | Some((Token::RightArrow, _))
+                | Some((Token::Bar, _))
+                | None
         ) {
             break;
         }
 
+        // Arguments are single names unless they are in parentheses
         let arg_type = match iter.peek() {
-            Some((Token::UpperName(_), _range)) => {
+            Some((Token::UpperName(_), _range)) | Some((Token::UpperPath(_), _range)) => {
                 let name = extract::extract_qualified_upper_name(&iter.next())?;
                 convert_name_to_type(name, vec![])
             }
@@ -142,12 +217,7 @@ fn parse_explicit_type(
                 let name = extract::extract_lower_name(&iter.next())?;
                 Ok(Type::Var(name))
             }
-            Some((Token::OpenParen, _range)) => {
-                matches(&iter.next(), Token::OpenParen)?;
-                let type_ = parse_type(iter, &base_indent)?;
-                matches(&iter.next(), Token::CloseParen)?;
-                Ok(type_)
-            }
+            Some((Token::OpenParen, _range)) => parse_parenthesised_type(iter, base_indent, holes),
             Some((token, range)) => Err(Error::UnexpectedToken {
                 expected: "Not sure".to_string(),
                 found: token.to_string(),
@@ -159,7 +229,7 @@ fn parse_explicit_type(
         args.push(arg_type);
 
         let next_indent = base_indent.consume(iter);
-        if next_indent.indented_from(&base_indent) {
+        if next_indent.indented_from(base_indent) {
             // current = indent.extract();
         } else {
             break;
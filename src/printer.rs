@@ -0,0 +1,724 @@
+use crate::ast::*;
+use crate::parser::Operators;
+
+/* Renders modules back to Elm source. The original formatting and comments aren't preserved but
+ * the output parses back to the same module. The layout loosely follows elm-format.
+ */
+
+const INDENT: usize = 4;
+
+pub fn print_module(module: &Module) -> String {
+    log::trace!("print_module {:?}", &module.name);
+    let is_port_module = module
+        .statements
+        .iter()
+        .any(|stmt| matches!(**stmt, Stmt::Port { .. }));
+    let mut output = format!(
+        "{}module {} exposing {}\n",
+        if is_port_module { "port " } else { "" },
+        module.name.join("."),
+        print_exposing(&module.exposing)
+    );
+
+    if !module.imports.is_empty() {
+        output.push('\n');
+    }
+
+    for import in &module.imports {
+        output.push_str(&format!("import {}", import.module_name.join(".")));
+        if let Some(alias) = &import.alias {
+            output.push_str(&format!(" as {}", alias));
+        }
+        if let Some(exposing) = &import.exposing {
+            output.push_str(&format!(" exposing {}", print_exposing(exposing)));
+        }
+        output.push('\n');
+    }
+
+    for stmt in &module.statements {
+        output.push_str("\n\n");
+        output.push_str(&print_statement(stmt, &module.arena));
+        output.push('\n');
+    }
+
+    output
+}
+
+fn print_exposing(exposing: &Exposing) -> String {
+    match exposing {
+        Exposing::All => "(..)".to_string(),
+        Exposing::List(details) => {
+            let details: Vec<String> = details
+                .iter()
+                .map(|detail| match detail {
+                    ExposingDetail::Type(UpperName(name), TypeState::Open) => {
+                        format!("{}(..)", name)
+                    }
+                    ExposingDetail::Type(UpperName(name), TypeState::Closed) => name.clone(),
+                    ExposingDetail::Operator(operator) => format!("({})", operator),
+                    ExposingDetail::Name(name) => name.clone(),
+                })
+                .collect();
+
+            format!("({})", details.join(", "))
+        }
+    }
+}
+
+pub fn print_statement(stmt: &Stmt, arena: &Arena) -> String {
+    match stmt {
+        Stmt::Binding {
+            type_annotation,
+            name,
+            expr,
+        } => print_definition(type_annotation, name, &[], *expr, arena),
+        Stmt::Function {
+            type_annotation,
+            name,
+            args,
+            expr,
+        } => print_definition(type_annotation, name, args, *expr, arena),
+        Stmt::Infix {
+            operator_name,
+            associativity,
+            precedence,
+            function_name,
+        } => {
+            let associativity = match associativity {
+                Associativity::Left => "left",
+                Associativity::Right => "right",
+                Associativity::Non => "non",
+            };
+
+            format!(
+                "infix {} {} ({}) = {}",
+                associativity,
+                precedence,
+                operator_name,
+                function_name.as_string()
+            )
+        }
+        Stmt::Port { name, type_, .. } => format!("port {} : {}", name.0, print_type(type_)),
+        Stmt::Type {
+            name,
+            args,
+            constructors,
+        } => {
+            let mut output = format!("type {}", name.0);
+            for arg in args {
+                output.push_str(&format!(" {}", arg.0));
+            }
+
+            for (index, constructor) in constructors.iter().enumerate() {
+                let separator = if index == 0 { "=" } else { "|" };
+                output.push_str(&format!(
+                    "\n{}{} {}",
+                    spaces(INDENT),
+                    separator,
+                    print_type(constructor)
+                ));
+            }
+
+            output
+        }
+    }
+}
+
+fn print_definition(
+    type_annotation: &Option<TypeAnnotation>,
+    name: &LowerName,
+    args: &[Pattern],
+    expr: ExprId,
+    arena: &Arena,
+) -> String {
+    let mut output = String::new();
+
+    if let Some(annotation) = type_annotation {
+        output.push_str(&format!("{} : {}\n", name.0, print_type(&annotation.type_)));
+    }
+
+    output.push_str(&name.0);
+    for arg in args {
+        output.push_str(&format!(" {}", print_pattern(arg)));
+    }
+
+    output.push_str(&format!(
+        " =\n{}{}",
+        spaces(INDENT),
+        print_expr_at(expr, arena, INDENT)
+    ));
+
+    output
+}
+
+pub fn print_type(type_: &Type) -> String {
+    match type_ {
+        Type::Var(name) => name.0.clone(),
+        Type::Bool => "Bool".to_string(),
+        Type::Int => "Int".to_string(),
+        Type::Float => "Float".to_string(),
+        Type::Char => "Char".to_string(),
+        Type::String => "String".to_string(),
+        Type::Unit => "()".to_string(),
+        Type::Hole => "_".to_string(),
+        Type::List(arg) => format!("List {}", print_type_arg(arg)),
+        Type::Tuple(items) => {
+            let items: Vec<String> = items.iter().map(print_type).collect();
+            format!("( {} )", items.join(", "))
+        }
+        Type::Function { from, to } => match **from {
+            Type::Function { .. } => format!("({}) -> {}", print_type(from), print_type(to)),
+            _ => format!("{} -> {}", print_type(from), print_type(to)),
+        },
+        Type::UserDefined { name, args } => {
+            let mut output = name.as_string();
+            for arg in args {
+                output.push_str(&format!(" {}", print_type_arg(arg)));
+            }
+            output
+        }
+    }
+}
+
+// Types used as arguments to other types need parens if they have arguments of their own
+fn print_type_arg(type_: &Type) -> String {
+    match type_ {
+        Type::List(_) | Type::Function { .. } => format!("({})", print_type(type_)),
+        Type::UserDefined { args, .. } if !args.is_empty() => format!("({})", print_type(type_)),
+        _ => print_type(type_),
+    }
+}
+
+pub fn print_pattern(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Anything => "_".to_string(),
+        Pattern::Bool(true) => "True".to_string(),
+        Pattern::Bool(false) => "False".to_string(),
+        Pattern::Integer(int) => int.to_string(),
+        Pattern::String(string) => format!("\"{}\"", string),
+        Pattern::Char(char) => format!("'{}'", char),
+        Pattern::Name(name) => name.clone(),
+        Pattern::Record(fields) => format!("{{ {} }}", fields.join(", ")),
+        Pattern::Tuple(patterns) => {
+            let patterns: Vec<String> = patterns.iter().map(print_pattern).collect();
+            format!("( {} )", patterns.join(", "))
+        }
+        Pattern::List(patterns) => {
+            if patterns.is_empty() {
+                "[]".to_string()
+            } else {
+                let patterns: Vec<String> = patterns.iter().map(print_pattern).collect();
+                format!("[ {} ]", patterns.join(", "))
+            }
+        }
+        Pattern::Cons(head, tail) => {
+            let head = match **head {
+                Pattern::Cons(..) => format!("({})", print_pattern(head)),
+                _ => print_pattern_arg(head),
+            };
+            format!("{} :: {}", head, print_pattern(tail))
+        }
+        Pattern::Constructor(name, args) => {
+            let mut output = name.as_string();
+            for arg in args {
+                output.push_str(&format!(" {}", print_pattern_arg(arg)));
+            }
+            output
+        }
+    }
+}
+
+// Patterns used as arguments to constructors need parens if they have arguments of their own
+fn print_pattern_arg(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Constructor(_, args) if !args.is_empty() => {
+            format!("({})", print_pattern(pattern))
+        }
+        Pattern::Cons(..) => format!("({})", print_pattern(pattern)),
+        _ => print_pattern(pattern),
+    }
+}
+
+pub fn print_expr(expr: ExprId, arena: &Arena) -> String {
+    print_expr_at(expr, arena, 0)
+}
+
+/* Prints the expression assuming that it starts on a line indented by the given amount. The first
+ * line has no leading spaces but any following lines are fully indented.
+ */
+fn print_expr_at(expr: ExprId, arena: &Arena, indent: usize) -> String {
+    match &arena[expr] {
+        Expr::Bool(true) => "True".to_string(),
+        Expr::Bool(false) => "False".to_string(),
+        Expr::Integer(int) => int.to_string(),
+        // Debug formatting keeps the decimal point on whole numbers so they aren't read as ints
+        Expr::Float(float) => format!("{:?}", float),
+        Expr::String(string) => format!("\"{}\"", string),
+        Expr::Char(char) => format!("'{}'", char),
+        Expr::Unit => "()".to_string(),
+        Expr::Tuple(items) => {
+            let items: Vec<String> = items
+                .iter()
+                .map(|item| print_enclosed(*item, arena, indent))
+                .collect();
+            format!("( {} )", items.join(", "))
+        }
+        Expr::Record(fields) => {
+            if fields.is_empty() {
+                "{}".to_string()
+            } else {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, field)| {
+                        format!("{} = {}", name, print_enclosed(*field, arena, indent))
+                    })
+                    .collect();
+                format!("{{ {} }}", fields.join(", "))
+            }
+        }
+        Expr::Constructor(name) => name.as_string(),
+        Expr::List(items) => {
+            if items.is_empty() {
+                "[]".to_string()
+            } else {
+                let items: Vec<String> = items
+                    .iter()
+                    .map(|item| print_enclosed(*item, arena, indent))
+                    .collect();
+                format!("[ {} ]", items.join(", "))
+            }
+        }
+        Expr::BinOp {
+            operator,
+            left,
+            right,
+        } => {
+            // Operators of the same precedence group according to their associativity so only
+            // the other side needs parens when they match
+            let fixity = Operators::core().get(operator).ok();
+            let precedence = fixity.map(|fixity| fixity.precedence);
+            let (left_precedence, right_precedence) = match fixity.map(|f| f.associativity) {
+                Some(Associativity::Left) => (precedence, precedence.map(|p| p + 1)),
+                Some(Associativity::Right) => (precedence.map(|p| p + 1), precedence),
+                _ => (precedence.map(|p| p + 1), precedence.map(|p| p + 1)),
+            };
+            let left = print_operand(*left, arena, indent, left_precedence);
+            let right = print_operand(*right, arena, indent, right_precedence);
+            format!("{} {} {}", left, operator, right)
+        }
+        Expr::If {
+            condition,
+            then_branch,
+            else_branch,
+        } => {
+            let inner = indent + INDENT;
+            let else_branch = match &arena[*else_branch] {
+                // Chains of 'else if' stay at the same indentation
+                Expr::If { .. } => format!(" {}", print_expr_at(*else_branch, arena, indent)),
+                _ => format!(
+                    "\n{}{}",
+                    spaces(inner),
+                    print_expr_at(*else_branch, arena, inner)
+                ),
+            };
+
+            format!(
+                "if {} then\n{}{}\n\n{}else{}",
+                print_enclosed(*condition, arena, indent),
+                spaces(inner),
+                print_expr_at(*then_branch, arena, inner),
+                spaces(indent),
+                else_branch
+            )
+        }
+        Expr::Case { expr, branches, .. } => {
+            let branch_indent = indent + INDENT;
+            let expr_indent = branch_indent + INDENT;
+            let branches: Vec<String> = branches
+                .iter()
+                .map(|(pattern, expr)| {
+                    format!(
+                        "{}{} ->\n{}{}",
+                        spaces(branch_indent),
+                        print_pattern(pattern),
+                        spaces(expr_indent),
+                        print_expr_at(*expr, arena, expr_indent)
+                    )
+                })
+                .collect();
+
+            format!(
+                "case {} of\n{}",
+                print_enclosed(*expr, arena, indent),
+                branches.join("\n\n")
+            )
+        }
+        Expr::Lambda { args, body } => {
+            let args: Vec<String> = args.iter().map(print_pattern).collect();
+            format!(
+                "\\{} -> {}",
+                args.join(" "),
+                print_enclosed(*body, arena, indent)
+            )
+        }
+        Expr::Let { bindings, body } => {
+            let binding_indent = indent + INDENT;
+            let expr_indent = binding_indent + INDENT;
+            let bindings: Vec<String> = bindings
+                .iter()
+                .map(|binding| {
+                    let annotation = match &binding.type_annotation {
+                        Some(annotation) => format!(
+                            "{}{} : {}\n",
+                            spaces(binding_indent),
+                            annotation.name.0,
+                            print_type(&annotation.type_)
+                        ),
+                        None => String::new(),
+                    };
+                    format!(
+                        "{}{}{} =\n{}{}",
+                        annotation,
+                        spaces(binding_indent),
+                        print_pattern(&binding.pattern),
+                        spaces(expr_indent),
+                        print_expr_at(binding.expr, arena, expr_indent)
+                    )
+                })
+                .collect();
+
+            format!(
+                "let\n{}\n{}in\n{}{}",
+                bindings.join("\n\n"),
+                spaces(indent),
+                spaces(indent),
+                print_expr_at(*body, arena, indent)
+            )
+        }
+        Expr::Call { function, args } => {
+            let mut output = print_argument(*function, arena, indent);
+            for arg in args {
+                output.push(' ');
+                output.push_str(&print_argument(*arg, arena, indent));
+            }
+            output
+        }
+        Expr::VarName(name) => name.as_string(),
+        Expr::LocalVar { name, .. } => name.clone(),
+    }
+}
+
+/* Prints one side of a binary expression. Nested binary expressions need parens unless their
+ * operator has at least the given precedence. We always use parens if we don't know either of the
+ * precedences.
+ */
+fn print_operand(
+    expr: ExprId,
+    arena: &Arena,
+    indent: usize,
+    min_precedence: Option<usize>,
+) -> String {
+    match &arena[expr] {
+        Expr::BinOp { operator, .. } => match (Operators::core().get(operator), min_precedence) {
+            (Ok(fixity), Some(min_precedence)) if fixity.precedence >= min_precedence => {
+                print_expr_at(expr, arena, indent)
+            }
+            _ => format!("({})", print_expr_at(expr, arena, indent)),
+        },
+        // The body of a lambda would run on into the rest of the expression
+        Expr::Lambda { .. } => format!("({})", print_expr_at(expr, arena, indent)),
+        Expr::If { .. } | Expr::Case { .. } | Expr::Let { .. } => {
+            print_block_in_parens(expr, arena, indent)
+        }
+        _ => print_expr_at(expr, arena, indent),
+    }
+}
+
+fn print_argument(expr: ExprId, arena: &Arena, indent: usize) -> String {
+    match &arena[expr] {
+        Expr::BinOp { .. } | Expr::Call { .. } | Expr::Lambda { .. } => {
+            format!("({})", print_expr_at(expr, arena, indent))
+        }
+        Expr::Integer(int) if *int < 0 => format!("({})", int),
+        Expr::Float(float) if *float < 0.0 => format!("({:?})", float),
+        Expr::If { .. } | Expr::Case { .. } | Expr::Let { .. } => {
+            print_block_in_parens(expr, arena, indent)
+        }
+        _ => print_expr_at(expr, arena, indent),
+    }
+}
+
+/* Prints expressions that appear between other syntax, like list items or case subjects, which
+ * only need parens when they span multiple lines.
+ */
+fn print_enclosed(expr: ExprId, arena: &Arena, indent: usize) -> String {
+    match &arena[expr] {
+        Expr::If { .. } | Expr::Case { .. } | Expr::Let { .. } => {
+            print_block_in_parens(expr, arena, indent)
+        }
+        _ => print_expr_at(expr, arena, indent),
+    }
+}
+
+// The closing paren goes on its own line so that it isn't mistaken for part of the last branch
+fn print_block_in_parens(expr: ExprId, arena: &Arena, indent: usize) -> String {
+    format!(
+        "({}\n{})",
+        print_expr_at(expr, arena, indent),
+        spaces(indent)
+    )
+}
+
+fn spaces(count: usize) -> String {
+    " ".repeat(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::lexer::Token;
+    use crate::parser;
+    use logos::Logos;
+
+    fn parse(source: &str) -> Module {
+        let tokens = Token::lexer(source);
+        let mut iter = tokens.spanned().peekable();
+        parser::parse(&mut iter).expect("Failed to parse")
+    }
+
+    /* Parses the printed module and checks that it matches the original, both as text and as a
+     * tree with the expression ids and source ranges left out
+     */
+    fn assert_round_trips(module: &Module) -> String {
+        let printed = print_module(module);
+        let reparsed = parse(&printed);
+        assert_eq!(print_module(&reparsed), printed);
+        assert_eq!(tree(&reparsed), tree(module));
+        printed
+    }
+
+    fn tree(module: &Module) -> String {
+        let arena = &module.arena;
+        let statements: Vec<String> = module
+            .statements
+            .iter()
+            .map(|stmt| match &**stmt {
+                Stmt::Binding {
+                    type_annotation,
+                    name,
+                    expr,
+                } => format!(
+                    "Binding {:?} {:?} {}",
+                    type_annotation,
+                    name,
+                    expr_tree(*expr, arena)
+                ),
+                Stmt::Function {
+                    type_annotation,
+                    name,
+                    args,
+                    expr,
+                } => format!(
+                    "Function {:?} {:?} {:?} {}",
+                    type_annotation,
+                    name,
+                    args,
+                    expr_tree(*expr, arena)
+                ),
+                stmt => format!("{:?}", stmt),
+            })
+            .collect();
+        format!(
+            "{:?} {:?} {:?}\n{}",
+            module.name,
+            module.exposing,
+            module.imports,
+            statements.join("\n")
+        )
+    }
+
+    fn expr_tree(expr: ExprId, arena: &Arena) -> String {
+        let all = |exprs: &[ExprId]| -> Vec<String> {
+            exprs.iter().map(|expr| expr_tree(*expr, arena)).collect()
+        };
+        match &arena[expr] {
+            Expr::List(items) => format!("List {:?}", all(items)),
+            Expr::Tuple(items) => format!("Tuple {:?}", all(items)),
+            Expr::Record(fields) => {
+                let fields: Vec<(&String, String)> = fields
+                    .iter()
+                    .map(|(name, expr)| (name, expr_tree(*expr, arena)))
+                    .collect();
+                format!("Record {:?}", fields)
+            }
+            Expr::BinOp {
+                operator,
+                left,
+                right,
+            } => format!(
+                "BinOp {} ({}) ({})",
+                operator,
+                expr_tree(*left, arena),
+                expr_tree(*right, arena)
+            ),
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => format!("If {:?}", all(&[*condition, *then_branch, *else_branch])),
+            Expr::Case { expr, branches, .. } => {
+                let branches: Vec<(&Pattern, String)> = branches
+                    .iter()
+                    .map(|(pattern, expr)| (pattern, expr_tree(*expr, arena)))
+                    .collect();
+                format!("Case ({}) {:?}", expr_tree(*expr, arena), branches)
+            }
+            Expr::Lambda { args, body } => {
+                format!("Lambda {:?} ({})", args, expr_tree(*body, arena))
+            }
+            Expr::Let { bindings, body } => {
+                let bindings: Vec<String> = bindings
+                    .iter()
+                    .map(|binding| {
+                        format!(
+                            "{:?} {:?} {}",
+                            binding.type_annotation,
+                            binding.pattern,
+                            expr_tree(binding.expr, arena)
+                        )
+                    })
+                    .collect();
+                format!("Let {:?} ({})", bindings, expr_tree(*body, arena))
+            }
+            Expr::Call { function, args } => {
+                format!("Call ({}) {:?}", expr_tree(*function, arena), all(args))
+            }
+            expr => format!("{:?}", expr),
+        }
+    }
+
+    #[test]
+    fn prints_module() {
+        let source = "module Main exposing (main, Shape(..))
+import Maybe exposing (Maybe(..), (+))
+type Shape a = Circle Int | Square (List a)
+count : Int -> Bool
+count n = case n of
+    0 -> True
+    _ -> if n > 1 then False else (n - 1) * 2 < 0
+main args = String.join \"x\" (List.map (count 1.0) [ 1 + 2 * 3, (1 + 2) * 3 ])
+";
+
+        insta::assert_snapshot!(print_module(&parse(source)));
+    }
+
+    #[test]
+    fn round_trips() {
+        let source = "module Main exposing (..)
+infix left 6 (+) = add
+value = 1 - (2 - 3) - 4 ++ \"a\" ++ (\"b\" ++ \"c\")
+main args =
+    List.map f [ case 1 of
+        1 -> -2
+        _ -> if True then f (-3) else g 4.5, f (case x of
+            _ -> 1
+          ) 2 ]
+";
+        assert_round_trips(&parse(source));
+    }
+
+    #[test]
+    fn groups_by_associativity() {
+        let source = "module Main exposing (..)
+value = (\"a\" ++ \"b\") ++ \"c\" ++ (\"d\" ++ \"e\")
+";
+        let printed = print_module(&parse(source));
+        assert!(printed.contains("(\"a\" ++ \"b\") ++ \"c\" ++ \"d\" ++ \"e\""));
+    }
+
+    #[test]
+    fn prints_type_arguments() {
+        let source = "module Main exposing (..)
+main : Program () Model (Maybe (List ( Int, String )))
+main =
+    program
+results : Dict.Dict String (Result (List String) Int) -> (a -> b) -> List ( a, () )
+results x y =
+    x
+";
+        let printed = print_module(&parse(source));
+        assert!(printed.contains("main : Program () Model (Maybe (List ( Int, String )))"));
+        assert!(printed.contains(
+            "results : Dict.Dict String (Result (List String) Int) -> (a -> b) -> List ( a, () )"
+        ));
+    }
+
+    #[test]
+    fn round_trips_let() {
+        let source = "module Main exposing (..)
+main args =
+    let
+        ( a, b ) = pair
+        c : Int
+        c = f (let d = 1 in d)
+    in
+    a
+";
+        assert_round_trips(&parse(source));
+    }
+
+    #[test]
+    fn round_trips_lambda() {
+        let source = "module Main exposing (..)
+main args =
+    List.map (\\x _ -> x + 1) [ 1 ] ++ apply (\\y -> y) 2
+";
+        let printed = assert_round_trips(&parse(source));
+        assert!(printed.contains("List.map (\\x _ -> x + 1) [ 1 ] ++ apply (\\y -> y) 2"));
+    }
+
+    #[test]
+    fn round_trips_lambda_with_block_body() {
+        let source = "module Main exposing (..)
+main args =
+    [ \\x -> if x then 1 else 2, \\y -> case y of
+        _ -> 3, \\z -> let w = z in w ]
+";
+        let printed = assert_round_trips(&parse(source));
+        assert!(printed.contains("[ \\x -> (if x then"));
+        assert!(printed.contains("3\n    ), \\z -> (let"));
+    }
+
+    #[test]
+    fn round_trips_import_alias() {
+        let source = "module Main exposing (..)
+import Dict as D exposing (Dict)
+main args =
+    D.empty
+";
+        let printed = assert_round_trips(&parse(source));
+        assert!(printed.contains("import Dict as D exposing (Dict)"));
+    }
+
+    #[test]
+    fn round_trips_record() {
+        let source = "module Main exposing (..)
+main args =
+    [ { name = \"x\", age = 1 + 2 }, {} ]
+";
+        let printed = assert_round_trips(&parse(source));
+        assert!(printed.contains("[ { name = \"x\", age = 1 + 2 }, {} ]"));
+    }
+
+    #[test]
+    fn round_trips_record_pattern() {
+        let source = "module Main exposing (..)
+main args =
+    case { name = \"x\", age = 1 } of
+        { name, age } ->
+            name
+";
+        let printed = assert_round_trips(&parse(source));
+        assert!(printed.contains("{ name, age } ->"));
+    }
+}
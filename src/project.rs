@@ -1,13 +1,94 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
 use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use logos::Logos;
+
+use crate::builtins::{self, Func};
+use crate::evaluator::observer::EvalObserver;
+use crate::evaluator::ports::Ports;
+use crate::lexer::{Range, Token};
 
 pub struct Settings {
     pub source_directories: Vec<PathBuf>,
+    // Modules whose names start with one of these prefixes, eg. "Shared" for "Shared.Strings", are
+    // read from the matching directory without the prefix, eg. "/home/me/elm-lib/Strings.elm", so
+    // that helper modules can be shared between projects. Checked before the source directories
+    pub module_roots: HashMap<String, PathBuf>,
+    // Which of the source directories & the core modules wins when both have a module of the same
+    // name. The losing module is reported in the warnings
+    pub module_order: ModuleOrder,
+    // Core modules are read from here before the ones that come with erm, so that changes to them
+    // can be tried out without rebuilding. Defaults to the ERM_CORE environment variable
+    pub core_path: Option<PathBuf>,
+    // Imported modules are parsed once and kept here for later runs. Ignored without the filesystem
+    pub cache_directory: Option<PathBuf>,
+    // Top-level bindings that only use literals are worked out as their modules are loaded, and
+    // kept in the cache directory in that form. See optimize::fold_constants
+    pub optimize: bool,
+    // Notified as the evaluator calls functions and takes branches
+    pub observers: Vec<Arc<dyn EvalObserver>>,
+    // Evaluation stops with a timeout error once it has run for longer than this
+    pub max_duration: Option<Duration>,
+    // Evaluation stops with a resource limit error when it builds lists or strings longer than
+    // these or allocates more values in total
+    pub max_list_length: Option<usize>,
+    pub max_string_length: Option<usize>,
+    pub max_values: Option<usize>,
+    // A runtime error while evaluating a test suite or repl input gives a Value::RuntimeError
+    // rather than ending the whole run, so that the other suites & later inputs still run
+    pub recover_errors: bool,
+    // What the builtins that reach outside of the program are allowed to do. Nothing by default
+    pub capabilities: Capabilities,
+    // What the String functions count as a character. See StringUnits
+    pub string_units: StringUnits,
+    // What happens when Int arithmetic gives a result that doesn't fit in 64 bits
+    pub integer_overflow: IntegerOverflow,
+    // Functions provided by the program embedding erm. See HostFunctions
+    pub host_functions: HostFunctions,
+    // Where Debug.log writes. Standard error unless another writer is given
+    pub output: Output,
+    // Carries values between the program's ports and the host
+    pub ports: Ports,
+    // Problems found while loading the program that don't stop it from running
+    pub warnings: Warnings,
 }
 
 impl Settings {
+    /* Starts from the same defaults as 'new'. Settings made with the builder have their paths & host
+     * functions checked when they're built rather than when a module fails to load.
+     */
+    pub fn builder() -> SettingsBuilder {
+        SettingsBuilder {
+            settings: Self::new(),
+        }
+    }
+
     pub fn new() -> Self {
         Self {
             source_directories: vec![],
+            module_roots: HashMap::new(),
+            module_order: ModuleOrder::default(),
+            core_path: std::env::var_os("ERM_CORE").map(PathBuf::from),
+            cache_directory: None,
+            optimize: false,
+            observers: vec![],
+            max_duration: None,
+            max_list_length: None,
+            max_string_length: None,
+            max_values: None,
+            recover_errors: false,
+            capabilities: Capabilities::default(),
+            string_units: StringUnits::default(),
+            integer_overflow: IntegerOverflow::default(),
+            host_functions: HostFunctions::default(),
+            output: Output::default(),
+            ports: Ports::new(),
+            warnings: Warnings::default(),
         }
     }
 }
@@ -17,3 +98,306 @@ impl Default for Settings {
         Self::new()
     }
 }
+
+pub struct SettingsBuilder {
+    settings: Settings,
+}
+
+impl SettingsBuilder {
+    pub fn source_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.settings.source_directories.push(directory.into());
+        self
+    }
+
+    pub fn module_root(mut self, prefix: &str, directory: impl Into<PathBuf>) -> Self {
+        self.settings
+            .module_roots
+            .insert(prefix.to_string(), directory.into());
+        self
+    }
+
+    pub fn core_path(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.settings.core_path = Some(directory.into());
+        self
+    }
+
+    // The directory is created when the first module is cached so it doesn't need to exist yet
+    pub fn cache_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.settings.cache_directory = Some(directory.into());
+        self
+    }
+
+    pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+        self.settings.capabilities = capabilities;
+        self
+    }
+
+    pub fn max_duration(mut self, duration: Duration) -> Self {
+        self.settings.max_duration = Some(duration);
+        self
+    }
+
+    pub fn max_list_length(mut self, length: usize) -> Self {
+        self.settings.max_list_length = Some(length);
+        self
+    }
+
+    pub fn max_string_length(mut self, length: usize) -> Self {
+        self.settings.max_string_length = Some(length);
+        self
+    }
+
+    pub fn max_values(mut self, count: usize) -> Self {
+        self.settings.max_values = Some(count);
+        self
+    }
+
+    pub fn host_function(
+        mut self,
+        name: &str,
+        constructor: impl Fn() -> Rc<dyn Func> + Send + Sync + 'static,
+    ) -> Self {
+        self.settings
+            .host_functions
+            .insert(name, Arc::new(constructor));
+        self
+    }
+
+    pub fn output(mut self, writer: impl Write + Send + 'static) -> Self {
+        self.settings.output = Output(Some(Arc::new(Mutex::new(Box::new(writer)))));
+        self
+    }
+
+    /* Checks that the directories exist and that the host functions have names that Elm code can
+     * call them by, eg. "Host.greet", which don't hide a builtin.
+     */
+    pub fn build(self) -> Result<Settings, SettingsError> {
+        let settings = self.settings;
+
+        let directories = settings
+            .source_directories
+            .iter()
+            .map(|directory| ("source directory", directory))
+            .chain(
+                settings
+                    .module_roots
+                    .values()
+                    .map(|directory| ("module root", directory)),
+            )
+            .chain(
+                settings
+                    .core_path
+                    .iter()
+                    .map(|directory| ("core path", directory)),
+            );
+
+        for (setting, directory) in directories {
+            if !directory.is_dir() {
+                return Err(SettingsError::MissingDirectory {
+                    setting,
+                    path: directory.clone(),
+                });
+            }
+        }
+
+        for name in settings.host_functions.0.keys() {
+            let mut tokens = Token::lexer(name);
+            if !matches!(
+                (tokens.next(), tokens.next()),
+                (Some(Token::LowerPath(_)), None)
+            ) {
+                return Err(SettingsError::InvalidHostFunctionName(name.clone()));
+            }
+            if builtins::is_built_in(name) {
+                return Err(SettingsError::HostFunctionIsBuiltIn(name.clone()));
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SettingsError {
+    MissingDirectory {
+        setting: &'static str,
+        path: PathBuf,
+    },
+    // Host functions need a qualified name like "Host.greet"
+    InvalidHostFunctionName(String),
+    HostFunctionIsBuiltIn(String),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::MissingDirectory { setting, path } => {
+                write!(f, "The {} {} isn't a directory", setting, path.display())
+            }
+            SettingsError::InvalidHostFunctionName(name) => write!(
+                f,
+                "The host function name '{}' needs to be a module name followed by a function name, eg. Host.greet",
+                name
+            ),
+            SettingsError::HostFunctionIsBuiltIn(name) => {
+                write!(f, "The host function '{}' has the same name as a builtin", name)
+            }
+        }
+    }
+}
+
+/* Functions that the program embedding erm provides to the Elm code, keyed by the qualified name
+ * that the code calls them by, eg. "Host.greet". They are checked & called like the builtins and,
+ * like the builtins, each one is made by a constructor when it is needed.
+ */
+#[derive(Clone, Default)]
+pub struct HostFunctions(Arc<HashMap<String, HostFunction>>);
+
+pub type HostFunction = Arc<dyn Fn() -> Rc<dyn Func> + Send + Sync>;
+
+impl HostFunctions {
+    pub fn insert(&mut self, name: &str, constructor: HostFunction) {
+        Arc::make_mut(&mut self.0).insert(name.to_string(), constructor);
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<Rc<dyn Func>> {
+        self.0.get(name).map(|constructor| constructor())
+    }
+}
+
+impl fmt::Debug for HostFunctions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.0.keys().collect();
+        names.sort();
+        f.debug_tuple("HostFunctions").field(&names).finish()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Output(Option<Arc<Mutex<Box<dyn Write + Send>>>>);
+
+impl Output {
+    pub fn write_line(&self, line: &str) {
+        match &self.0 {
+            Some(writer) => {
+                if let Ok(mut writer) = writer.lock() {
+                    // Like printing to standard error, failing to write doesn't stop the program
+                    let _ = writeln!(writer, "{}", line);
+                }
+            }
+            None => eprintln!("{}", line),
+        }
+    }
+}
+
+/* The access to the world outside of the program that the user has allowed. Builtins that need one
+ * of these fail with an error naming it when it isn't set.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Capabilities {
+    // Reading files
+    pub fs: bool,
+    // Making network requests
+    pub net: bool,
+    // Reading environment variables
+    pub env: bool,
+    // Running other programs
+    pub process: bool,
+    // Reading the clock
+    pub time: bool,
+}
+
+impl Capabilities {
+    pub fn all() -> Self {
+        Self {
+            fs: true,
+            net: true,
+            env: true,
+            process: true,
+            time: true,
+        }
+    }
+}
+
+/* Ints are 64 bit. A result that doesn't fit is an error by default so that summing a large list
+ * can't quietly give the wrong answer
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IntegerOverflow {
+    #[default]
+    Error,
+    // Wrap around in two's complement, as Rust's wrapping arithmetic does
+    Wrap,
+}
+
+/* The units that String.length, String.reverse, String.left & String.right work in */
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum StringUnits {
+    // What a reader would see as a single character, eg. "é" written as an "e" followed by a
+    // combining accent, or an emoji made up of several code points
+    #[default]
+    Graphemes,
+    // Unicode code points. Cheaper to work out but a combining accent counts as a character of its
+    // own, so reversing a string moves it onto a different letter
+    CodePoints,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ModuleOrder {
+    // The project's modules are used in place of core modules with the same name
+    #[default]
+    SourcesFirst,
+    CoreFirst,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Warning {
+    // The module was found in more than one place and the file that is used hides the other
+    ShadowedModule {
+        module: String,
+        used: PathBuf,
+        shadowed: PathBuf,
+    },
+    // The case branch at the range can never match as an earlier branch has the same literal
+    UnreachableBranch {
+        range: Range,
+    },
+}
+
+impl Warning {
+    /* The part of the checked module's source that the warning is about, if it is about one */
+    pub fn range(&self) -> Option<Range> {
+        match self {
+            Warning::ShadowedModule { .. } => None,
+            Warning::UnreachableBranch { range } => Some(range.clone()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Warnings(Mutex<Vec<Warning>>);
+
+impl Warnings {
+    /* Records the warning unless it has already been recorded, as modules are looked up each time
+     * that they're imported
+     */
+    pub fn add(&self, warning: Warning) {
+        if let Ok(mut warnings) = self.0.lock() {
+            if !warnings.contains(&warning) {
+                warnings.push(warning);
+            }
+        }
+    }
+
+    /* Returns the warnings recorded so far and clears them */
+    pub fn take(&self) -> Vec<Warning> {
+        self.0
+            .lock()
+            .map(|mut warnings| std::mem::take(&mut *warnings))
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,564 @@
+use std::cell::RefCell;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+
+use logos::Logos;
+
+use crate::ast;
+use crate::checker::{self, term::Term};
+use crate::env;
+use crate::error::{self, Error};
+use crate::evaluator::{self, limits::Usage, values::Value};
+use crate::lexer::Token;
+use crate::parser;
+use crate::project;
+
+/* An interactive session. Imports & declarations entered at the prompt are kept as source and
+ * replayed into a generated module each time so that later inputs can refer to them.
+ */
+pub struct Session {
+    imports: Vec<String>,
+    // Keyed by the name being declared so that redefining a name replaces the earlier version
+    declarations: Vec<(String, String)>,
+    settings: project::Settings,
+    // Imported modules are only rebuilt when their files change so edits show up in the session
+    cache: RefCell<env::ModuleCache>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Input {
+    Import,
+    Declaration(String),
+    Expression,
+}
+
+// Name of the binding that holds the expression being evaluated in the generated module
+const RESULT_NAME: &str = "erm_repl_result";
+
+impl Session {
+    pub fn new(settings: project::Settings) -> Self {
+        Self {
+            imports: vec![],
+            declarations: vec![],
+            settings,
+            cache: RefCell::new(env::ModuleCache::new()),
+        }
+    }
+
+    /* Evaluates a complete input, returning nothing for imports & type declarations as they have no
+     * value to show. Imports & declarations are only kept in the session if the module they
+     * produce can be loaded, so a typo doesn't break every later input. When the settings recover
+     * from errors, a runtime error is returned as a RuntimeError value and the declaration is kept.
+     */
+    pub fn eval(&mut self, input: &str) -> Result<Option<Value>, Error> {
+        log::trace!("eval: {}", input);
+        match classify(input) {
+            Input::Import => {
+                let mut imports = self.imports.clone();
+                imports.push(input.trim().to_string());
+                self.load(&imports, &self.declarations, None)?;
+                self.imports = imports;
+                Ok(None)
+            }
+            Input::Declaration(name) => {
+                let mut declarations = self.declarations.clone();
+                declarations.retain(|(existing, _)| *existing != name);
+                declarations.push((name.clone(), input.trim_end().to_string()));
+
+                // Bindings show their value like an expression would, types have no value
+                let value = if starts_lowercase(&name) {
+                    self.load(&self.imports, &declarations, Some(&name))?
+                } else {
+                    self.load(&self.imports, &declarations, None)?;
+                    None
+                };
+
+                self.declarations = declarations;
+                Ok(value)
+            }
+            Input::Expression => {
+                let declarations = self.with_result(input);
+                self.load(&self.imports, &declarations, Some(RESULT_NAME))
+            }
+        }
+    }
+
+    /* Infers the type of the expression without evaluating it */
+    pub fn type_of(&self, input: &str) -> Result<Term, Error> {
+        log::trace!("type_of: {}", input);
+        let declarations = self.with_result(input);
+        let (environment, _source) = self.environment(&self.imports, &declarations)?;
+
+        let name = ast::QualifiedLowerName::simple(RESULT_NAME.to_string());
+        checker::infer(&name, &environment).map_err(Error::CheckError)
+    }
+
+    /* Imports every binding from the module in the file. The file's source directory is added to
+     * the search path so that the module can be found by name like any other import.
+     */
+    #[cfg(feature = "fs")]
+    pub fn load_file(&mut self, path: &str) -> Result<(), Error> {
+        log::trace!("load_file: {}", path);
+        let contents = std::fs::read_to_string(path).map_err(|_| Error::FileError)?;
+        let settings = &self.settings;
+        let module = parser::parse_source(&contents, |import| {
+            env::imported_operators(import, settings)
+        })
+        .map_err(|err| Error::ParserError(err, contents.clone()))?;
+
+        // Nested module names live in nested directories below the source directory
+        let mut directory = PathBuf::from(path);
+        for _ in 0..module.name.len() {
+            directory.pop();
+        }
+        if !self.settings.source_directories.contains(&directory) {
+            self.settings.source_directories.push(directory);
+        }
+
+        self.eval(&format!("import {} exposing (..)", module.name.join(".")))
+            .map(|_| ())
+    }
+
+    /* Forgets every import & declaration from the session */
+    pub fn reset(&mut self) {
+        log::trace!("reset");
+        self.imports.clear();
+        self.declarations.clear();
+    }
+
+    /* Names that the next input can refer to, both unqualified & qualified by their module, for
+     * completing names at the prompt
+     */
+    pub fn names(&self) -> Vec<String> {
+        log::trace!("names");
+        let mut names = vec![];
+        let (environment, _source) = match self.environment(&self.imports, &self.declarations) {
+            Ok(loaded) => loaded,
+            Err(_) => return names,
+        };
+
+        names.extend(
+            environment
+                .resolvable_bindings()
+                .iter()
+                .map(|resolvable| resolvable.name.as_string())
+                .filter(|name| name != RESULT_NAME),
+        );
+
+        for scope in environment.local_scopes.iter() {
+            names.extend(constructor_names(scope, ""));
+        }
+
+        for import in environment.module_imports.iter() {
+            let module_name = import.module_scope.name.join(".");
+            names.extend(constructor_names(&import.module_scope.local_scope, ""));
+            names.extend(constructor_names(
+                &import.module_scope.local_scope,
+                &format!("{}.", module_name),
+            ));
+            names.push(module_name);
+        }
+
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /* Finds the names that could complete the word that ends at the cursor. Returns where the word
+     * starts so that the completion can replace it.
+     */
+    pub fn complete(&self, line: &str, cursor: usize) -> (usize, Vec<String>) {
+        log::trace!("complete: {} {}", line, cursor);
+        let start = line[..cursor]
+            .rfind(|char: char| !(char.is_alphanumeric() || char == '_' || char == '.'))
+            .map_or(0, |index| index + 1);
+        let word = &line[start..cursor];
+
+        if word.is_empty() {
+            return (start, vec![]);
+        }
+
+        let names = self
+            .names()
+            .into_iter()
+            .filter(|name| name.starts_with(word))
+            .collect();
+        (start, names)
+    }
+
+    /* The session's declarations along with a binding that holds the value of the expression */
+    fn with_result(&self, input: &str) -> Vec<(String, String)> {
+        let mut declarations = self.declarations.clone();
+        declarations.push((
+            RESULT_NAME.to_string(),
+            format!("{} =\n{}", RESULT_NAME, indent(input)),
+        ));
+        declarations
+    }
+
+    fn load(
+        &self,
+        imports: &[String],
+        declarations: &[(String, String)],
+        evaluate: Option<&str>,
+    ) -> Result<Option<Value>, Error> {
+        let (environment, source) = self.environment(imports, declarations)?;
+
+        evaluate
+            .map(|name| {
+                let usage = Usage::new(self.settings.max_duration);
+                let value = evaluator::evaluate_name(name, &environment, &self.settings, &usage);
+                evaluator::recover(value, &self.settings, |err| {
+                    error::to_user_output(Error::EvaluateError(err, source.clone()))
+                })
+                .map_err(|err| Error::EvaluateError(err, source.clone()))
+            })
+            .transpose()
+    }
+
+    fn environment(
+        &self,
+        imports: &[String],
+        declarations: &[(String, String)],
+    ) -> Result<(env::Environment, String), Error> {
+        let source = module_source(imports, declarations);
+        let settings = &self.settings;
+
+        let module =
+            parser::parse_source(&source, |import| env::imported_operators(import, settings))
+                .map_err(|err| Error::ParserError(err, source.clone()))?;
+
+        let module = ast::with_default_imports(&module);
+        let scope =
+            env::ModuleScope::from_module_cached(&module, settings, &mut self.cache.borrow_mut())
+                .map_err(Error::ScopeError)?;
+        checker::check_cycles(&module).map_err(Error::CheckError)?;
+        Ok((env::Environment::from_module_scope(scope), source))
+    }
+}
+
+fn constructor_names(scope: &env::Scope, prefix: &str) -> Vec<String> {
+    scope
+        .constructors
+        .keys()
+        .map(|name| format!("{}{}", prefix, name))
+        .collect()
+}
+
+fn module_source(imports: &[String], declarations: &[(String, String)]) -> String {
+    let mut source = String::from("module Repl exposing (..)\n\n");
+    for import in imports {
+        source.push_str(import);
+        source.push('\n');
+    }
+
+    for (_name, declaration) in declarations {
+        source.push('\n');
+        source.push_str(declaration);
+        source.push('\n');
+    }
+
+    source
+}
+
+fn indent(input: &str) -> String {
+    input
+        .trim_end()
+        .lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn starts_lowercase(name: &str) -> bool {
+    name.chars().next().is_some_and(|char| !char.is_uppercase())
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Command {
+    Type(String),
+    Load(String),
+    Reset,
+    Unknown(String),
+}
+
+/* Reads a colon command like ':type x', returning None when the input is Elm code */
+pub fn parse_command(input: &str) -> Option<Command> {
+    let input = input.trim().strip_prefix(':')?;
+    let (name, argument) = input.split_once(' ').unwrap_or((input, ""));
+    let argument = argument.trim().to_string();
+
+    Some(match name {
+        "type" | "t" => Command::Type(argument),
+        "load" | "l" => Command::Load(argument),
+        "reset" => Command::Reset,
+        _ => Command::Unknown(name.to_string()),
+    })
+}
+
+/* Works out whether the input is an import, a declaration to keep in the session or an expression
+ * to evaluate. A declaration is a name followed by argument patterns & an equals sign, or by a
+ * type annotation.
+ */
+pub fn classify(input: &str) -> Input {
+    let tokens = significant_tokens(input);
+
+    match tokens.as_slice() {
+        [Token::Import, ..] => Input::Import,
+        [Token::Type, Token::Alias, Token::UpperName(name), ..]
+        | [Token::Type, Token::UpperName(name), ..] => Input::Declaration(name.to_string()),
+        [Token::LowerName(name), Token::Colon, ..] => Input::Declaration(name.to_string()),
+        [Token::LowerName(name), rest @ ..] => {
+            let equals = rest.iter().position(|token| {
+                !matches!(
+                    token,
+                    Token::LowerName(_)
+                        | Token::UpperName(_)
+                        | Token::UpperPath(_)
+                        | Token::Underscore
+                        | Token::OpenParen
+                        | Token::CloseParen
+                        | Token::OpenBracket
+                        | Token::CloseBracket
+                        | Token::Comma
+                        | Token::LiteralInteger(_)
+                        | Token::LiteralString(_)
+                        | Token::LiteralChar(_)
+                )
+            });
+
+            match equals.map(|index| (&rest[index], rest.get(index + 1))) {
+                // A second equals sign means a comparison rather than a definition
+                Some((Token::Equals, next)) if next != Some(&Token::Equals) => {
+                    Input::Declaration(name.to_string())
+                }
+                _ => Input::Expression,
+            }
+        }
+        _ => Input::Expression,
+    }
+}
+
+/* Whether the input can be evaluated or whether we should keep reading lines. Input is incomplete
+ * while brackets are left open or the last line ends in a way that needs more to follow. Once the
+ * input spans several lines an indented last line only ends with a blank line, as the next line
+ * might be indented to continue it.
+ */
+pub fn is_complete(input: &str) -> bool {
+    log::trace!("is_complete");
+    let tokens = significant_tokens(input);
+
+    let depth = tokens.iter().fold(0, |depth, token| match token {
+        Token::OpenParen | Token::OpenBracket | Token::OpenBrace => depth + 1,
+        Token::CloseParen | Token::CloseBracket | Token::CloseBrace => depth - 1,
+        _ => depth,
+    });
+    if depth > 0 {
+        return false;
+    }
+
+    let continues = matches!(
+        tokens.iter().rev().find(|token| **token != Token::NewLine),
+        Some(
+            Token::Equals
+                | Token::Colon
+                | Token::RightArrow
+                | Token::Operator(_)
+                | Token::Bar
+                | Token::Comma
+                | Token::Of
+                | Token::Let
+                | Token::In
+                | Token::If
+                | Token::Then
+                | Token::Else
+        )
+    );
+    if continues {
+        return false;
+    }
+
+    // An indented last line might be followed by more of the same block
+    let mut lines = input.trim_end_matches('\n').lines();
+    let last_line = lines.next_back().unwrap_or("");
+    lines.next().is_none() || !last_line.starts_with(' ') || input.ends_with("\n\n")
+}
+
+fn significant_tokens(input: &str) -> Vec<Token<'_>> {
+    Token::lexer(input)
+        .filter(|token| {
+            !matches!(
+                token,
+                Token::Space(_) | Token::SingleLineComment(_) | Token::MultiLineComment(_)
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn eval(session: &mut Session, input: &str) -> String {
+        match session.eval(input) {
+            Ok(Some(value)) => value.to_string(),
+            Ok(None) => "loaded".to_string(),
+            Err(error) => format!("{:?}", error),
+        }
+    }
+
+    #[test]
+    fn classifies_inputs() {
+        assert_eq!(classify("import List"), Input::Import);
+        assert_eq!(classify("x = 1"), Input::Declaration("x".to_string()));
+        assert_eq!(
+            classify("add (a, b) = a + b"),
+            Input::Declaration("add".to_string())
+        );
+        assert_eq!(
+            classify("type Colour = Red"),
+            Input::Declaration("Colour".to_string())
+        );
+        assert_eq!(classify("add 1 2"), Input::Expression);
+        assert_eq!(classify("x == 1"), Input::Expression);
+    }
+
+    #[test]
+    fn incomplete_input() {
+        assert!(is_complete("1 + 2\n"));
+        assert!(!is_complete("(1 +\n"));
+        assert!(!is_complete("f x =\n"));
+        assert!(!is_complete("case x of\n"));
+        assert!(!is_complete("f x =\n    x + 1\n"));
+        assert!(is_complete("f x =\n    x + 1\n\n"));
+        assert!(is_complete("[ 1\n, 2\n]\n"));
+        assert!(!is_complete("( 1\n  , 2 )\n"));
+    }
+
+    #[test]
+    fn declarations_persist() {
+        let mut session = Session::new(project::Settings::new());
+
+        assert_eq!(eval(&mut session, "x = 41\n"), "41");
+        assert_eq!(eval(&mut session, "inc n =\n    n + 1\n\n"), "<function>");
+        assert_eq!(eval(&mut session, "inc x\n"), "42");
+        assert_eq!(eval(&mut session, "x = 1\n"), "1");
+        assert_eq!(eval(&mut session, "inc x\n"), "2");
+    }
+
+    #[test]
+    fn runtime_errors_are_values_when_recovering() {
+        let mut session = Session::new(project::Settings {
+            recover_errors: true,
+            ..project::Settings::new()
+        });
+
+        let value = session.eval("big = 9223372036854775807 + 1\n");
+        assert!(
+            matches!(&value, Ok(Some(Value::RuntimeError(message))) if message.contains("Integer overflow")),
+            "{:?}",
+            value
+        );
+        assert_eq!(eval(&mut session, "1 + 1\n"), "2");
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            parse_command(":type inc 1"),
+            Some(Command::Type("inc 1".to_string()))
+        );
+        assert_eq!(parse_command(":reset\n"), Some(Command::Reset));
+        assert_eq!(
+            parse_command(":quit"),
+            Some(Command::Unknown("quit".to_string()))
+        );
+        assert_eq!(parse_command("x = 1"), None);
+    }
+
+    #[test]
+    fn types_of_expressions() {
+        let mut session = Session::new(project::Settings::new());
+
+        assert_eq!(
+            eval(&mut session, "greet name = \"Hi \" ++ name\n"),
+            "<function>"
+        );
+        assert_eq!(
+            session.type_of("greet").ok().map(|term| term.to_string()),
+            Some("String -> String".to_string())
+        );
+        assert_eq!(
+            session
+                .type_of("[ 1, 2 ]")
+                .ok()
+                .map(|term| term.to_string()),
+            Some("List Int".to_string())
+        );
+    }
+
+    #[test]
+    fn completes_names_in_scope() {
+        let mut session = Session::new(project::Settings::new());
+        assert_eq!(eval(&mut session, "total = 3\n"), "3");
+
+        assert_eq!(
+            session.complete("1 + tot", 7),
+            (4, vec!["total".to_string()])
+        );
+        assert_eq!(
+            session.complete("String.fromI", 12),
+            (0, vec!["String.fromInt".to_string()])
+        );
+        // Names from modules imported without exposing them need the module name
+        assert_eq!(session.complete("fromI", 5), (0, vec![]));
+    }
+
+    #[test]
+    fn reset_forgets_declarations() {
+        let mut session = Session::new(project::Settings::new());
+
+        assert_eq!(eval(&mut session, "x = 1\n"), "1");
+        session.reset();
+        assert!(session.eval("x\n").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn loaded_modules_reload_when_changed() {
+        let directory = std::env::temp_dir().join("erm-repl-reload");
+        let _ = std::fs::create_dir_all(&directory);
+        let path = directory.join("Reload.elm");
+        let path_string = path.to_string_lossy().to_string();
+
+        let mut session = Session::new(project::Settings::new());
+        let _ = std::fs::write(&path, "module Reload exposing (..)\n\nvalue =\n    1\n");
+        assert!(session.load_file(&path_string).is_ok());
+        assert_eq!(eval(&mut session, "offset = 10\n"), "10");
+        assert_eq!(eval(&mut session, "value + offset\n"), "11");
+
+        let _ = std::fs::write(&path, "module Reload exposing (..)\n\nvalue =\n    2\n");
+        assert_eq!(eval(&mut session, "value + offset\n"), "12");
+    }
+
+    #[test]
+    fn failed_declarations_are_dropped() {
+        let mut session = Session::new(project::Settings::new());
+
+        assert_eq!(eval(&mut session, "x = 1\n"), "1");
+        assert!(session.eval("y = (\n").is_err());
+        assert_eq!(eval(&mut session, "x\n"), "1");
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn imports_persist() {
+        let mut session = Session::new(project::Settings::new());
+
+        assert_eq!(
+            eval(&mut session, "import String exposing (fromInt)\n"),
+            "loaded"
+        );
+        assert_eq!(eval(&mut session, "fromInt 3\n"), "\"3\"");
+    }
+}
@@ -0,0 +1,39 @@
+use wasm_bindgen::prelude::*;
+
+use crate::ast;
+use crate::checker;
+use crate::env;
+use crate::error::{self, Error};
+use crate::evaluator::{self, limits::Usage, values::Value};
+use crate::parser;
+use crate::project;
+
+/* Runs the source with the given program arguments and returns the output. Errors are returned as
+ * the same text that the command line tool prints so that they can be shown as they are.
+ */
+#[wasm_bindgen]
+pub fn run(source: &str, args: Vec<String>) -> Result<String, String> {
+    log::trace!("run");
+    let settings = project::Settings::new();
+
+    evaluate(source, args, &settings)
+        .map(|value| match value {
+            Value::String(string) => string.to_string(),
+            value => format!("{:?}", value),
+        })
+        .map_err(error::to_user_output)
+}
+
+fn evaluate(source: &str, args: Vec<String>, settings: &project::Settings) -> Result<Value, Error> {
+    let module = parser::parse_source(source, |import| env::imported_operators(import, settings))
+        .map_err(|err| Error::ParserError(err, source.to_string()))?;
+
+    let module = ast::with_default_imports(&module);
+    let scope = env::ModuleScope::from_module(&module, settings).map_err(Error::ScopeError)?;
+    let environment = env::Environment::from_module_scope(scope);
+    checker::check_cycles(&module).map_err(Error::CheckError)?;
+
+    let usage = Usage::new(settings.max_duration);
+    evaluator::evaluate(&module, args, &environment, settings, &usage)
+        .map_err(|err| Error::EvaluateError(err, source.to_string()))
+}
@@ -0,0 +1,118 @@
+mod common;
+
+mod basics {
+
+    use crate::common::{eval, eval_unchecked};
+
+    #[test]
+    fn identity_is_polymorphic() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (identity 3) ++ identity "!"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn always_ignores_second_argument() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          always "first" 2
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn not_and_xor() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          if xor (not False) (xor True True) then "yes" else "no"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn not_requires_bool() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          if not 1 then "yes" else "no"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn cyclic_values() {
+        let src = r#"
+        module Main exposing (..)
+        x =
+          y
+        y =
+          x
+        main args =
+          x
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn cyclic_values_unchecked() {
+        let src = r#"
+        module Main exposing (..)
+        x =
+          y
+        y =
+          x
+        main args =
+          x
+        "#;
+        let result = eval_unchecked(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn compare_orders_values() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [compare 1 2, compare 'b' 'a', compare "abc" "abc", compare [1, 2] [1, 3], compare (1, "b") (1, "a"), compare 1.5 1]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn compare_result_in_case() {
+        let src = r#"
+        module Main exposing (..)
+        describe order =
+          case order of
+            LT -> "less"
+            EQ -> "equal"
+            GT -> "greater"
+        main args =
+          describe (compare 3 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn compare_requires_comparable() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (compare True False)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
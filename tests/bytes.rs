@@ -0,0 +1,52 @@
+mod common;
+
+mod bytes {
+
+    use crate::common::eval;
+
+    #[test]
+    fn string_round_trip() {
+        let src = r#"
+        module Main exposing (..)
+        import Bytes
+        main args =
+          case Bytes.toString (Bytes.append (Bytes.fromString "héllo ") (Bytes.fromString "world")) of
+            Just string ->
+              string
+            Nothing ->
+              "invalid"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn width_counts_bytes() {
+        let src = r#"
+        module Main exposing (..)
+        import Bytes
+        main args =
+          String.fromInt (Bytes.width (Bytes.fromString "héllo"))
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn decode_encoded_ints() {
+        let src = r#"
+        module Main exposing (..)
+        import Bytes
+        header =
+          Bytes.append (Bytes.encodeInt 2 513) (Bytes.encodeInt 4 -7)
+        main args =
+          case ( Bytes.decodeInt 2 0 header, Bytes.decodeInt 4 2 header, Bytes.decodeInt 4 3 header ) of
+            ( Just length, Just value, Nothing ) ->
+              String.join " " [ String.fromInt length, String.fromInt value ]
+            _ ->
+              "failed"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
@@ -2,7 +2,9 @@ mod common;
 
 mod case {
 
-    use crate::common::eval;
+    use erm::project;
+
+    use crate::common::{eval, eval_with_settings};
 
     #[test]
     fn boolean_case_statement() {
@@ -77,4 +79,280 @@ mod case {
         let result = eval(src, None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn match_strings_and_chars() {
+        let src = r#"
+        module Main exposing (..)
+
+        greet name =
+          case name of
+            "world" -> "Hello, world"
+            _ -> "Hi"
+
+        grade char =
+          case char of
+            'a' -> " top"
+            _ -> " other"
+
+        main : List String -> String
+        main args =
+            greet "world" ++ grade 'a' ++ grade 'b'
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn match_tuples_and_lists() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe pair =
+          case pair of
+            ( 0, [] ) -> "nothing"
+            ( 0, [ _ ] ) -> "one"
+            ( _, 1 :: _ ) -> "starts with one"
+            _ -> "something else"
+
+        main : List String -> String
+        main args =
+            describe ( 0, [] ) ++ ", " ++ describe ( 0, [ 5 ] ) ++ ", " ++ describe ( 2, [ 1, 2 ] ) ++ ", " ++ describe ( 2, [] )
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn match_constructors() {
+        let src = r#"
+        module Main exposing (..)
+
+        type Shape
+            = Circle Int
+            | Square Int
+            | Dot
+
+        describe shape =
+          case shape of
+            Circle 1 -> "small circle"
+            Circle _ -> "circle"
+            Square _ -> "square"
+            Dot -> "dot"
+
+        main : List String -> String
+        main args =
+            describe (Circle 1) ++ ", " ++ describe (Circle 2) ++ ", " ++ describe (Square 3) ++ ", " ++ describe Dot
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn match_nested_maybe() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe value =
+          case value of
+            Just (Just _) -> "nested"
+            Just Nothing -> "half"
+            Nothing -> "none"
+
+        main : List String -> String
+        main args =
+            describe (Just (Just 1)) ++ ", " ++ describe (Just Nothing) ++ ", " ++ describe Nothing
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn pattern_type_must_match_subject() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe value =
+          case value of
+            Just _ -> "just"
+            _ -> "other"
+
+        main : List String -> String
+        main args =
+            describe ( 1, 2 )
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn name_pattern_binds_subject() {
+        let src = r#"
+        module Main exposing (..)
+
+        increment x =
+          case x of
+            n -> n + 1
+
+        main : List String -> String
+        main args =
+            String.fromInt (increment 41)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn nested_names_bind() {
+        let src = r#"
+        module Main exposing (..)
+
+        sum list =
+          case list of
+            [] -> 0
+            first :: rest -> first + sum rest
+
+        withDefault default maybe =
+          case maybe of
+            Just ( name, _ ) -> name
+            Nothing -> default
+
+        main : List String -> String
+        main args =
+            withDefault "nobody" (Just ( "Ada", 36 )) ++ " " ++ String.fromInt (sum [ 1, 2, 3 ])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn bound_name_has_pattern_type() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe maybe =
+          case maybe of
+            Just name -> name ++ "!"
+            Nothing -> "none"
+
+        main : List String -> String
+        main args =
+            describe (Just 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn constructor_arguments_have_their_types() {
+        let src = r#"
+        module Main exposing (..)
+
+        type Pair a
+          = Pair a String
+
+        main : List String -> String
+        main args =
+          case Pair 1 "x" of
+            Pair n s -> s ++ n
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn no_matching_case_shows_value() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe value =
+          case value of
+            Just 1 -> "one"
+            Nothing -> "nothing"
+
+        main : List String -> String
+        main args =
+            describe (Just 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn branches_with_different_types_show_the_clash() {
+        let src = r#"
+        module Main exposing (..)
+
+        main args =
+          case args of
+            [] -> ["none"]
+            _ -> [Just "some"]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn case_in_call_argument_and_list() {
+        let src = r#"
+        module Main exposing (..)
+
+        double x = x * 2
+
+        main args =
+          Debug.toString [ double (case 3 of
+                                     3 -> 4
+                                     _ -> 5), case 1 of
+                                                1 -> 2
+                                                _ -> 3 ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn literal_case_needs_fallback() {
+        let src = r#"
+        module Main exposing (..)
+
+        grade char =
+          case char of
+            'a' -> "top"
+            'b' -> "middle"
+
+        main args =
+            grade 'a'
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn repeated_literal_is_reported() {
+        let src = r#"
+        module Main exposing (..)
+
+        greet name =
+          case name of
+            "world" -> "Hello, world"
+            "world" -> "Hello again"
+            _ -> "Hi"
+
+        main args =
+            greet "world"
+        "#;
+        let settings = project::Settings::new();
+        let result = eval_with_settings(src, &settings);
+
+        assert_eq!(result, "Hello, world");
+        assert_eq!(
+            settings
+                .warnings
+                .take()
+                .iter()
+                .map(|warning| warning.range())
+                .collect::<Vec<_>>(),
+            vec![Some(89..96)]
+        );
+    }
 }
@@ -46,4 +46,57 @@ mod comments {
         let result = eval(src, None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn comments_between_annotation_and_definition() {
+        let src = r#"
+        module Main exposing (..)
+
+        count : Int -- How many there are
+
+        {-| Used below
+        -}
+        count =
+            3
+
+        main : List String -> String
+        main args =
+            let
+                label : String
+                -- Describes the count
+
+                label =
+                    "items"
+            in
+            String.fromInt count ++ " " ++ label
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn comments_within_expressions() {
+        let src = r#"
+        module Main exposing (..)
+
+        main : List String -> String
+        main args = -- The answer
+            let -- Some values
+                xs = [ 1 -- one
+                     , 2 {- two -}
+                     ]
+            in
+            case List.sum xs of -- The total
+                -- Exactly three
+                3 ->
+                    String.append -- Join
+                        "three" {- and -} "!"
+
+                {- Anything else -}
+                _ ->
+                    "other"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
 }
@@ -1,6 +1,5 @@
 use std::io::Write;
 
-use logos::Logos;
 use unindent::unindent;
 
 use erm::ast;
@@ -8,8 +7,8 @@ use erm::checker;
 use erm::env;
 use erm::error::{self, Error};
 use erm::evaluator;
+use erm::evaluator::limits::Usage;
 use erm::evaluator::values::Value;
-use erm::lexer::Token;
 use erm::parser;
 use erm::project;
 
@@ -45,43 +44,89 @@ pub fn eval_with_args(
     settings: Option<project::Settings>,
 ) -> String {
     log::trace!("eval_with_args");
-    let result = eval_it(string, args, settings);
+    let settings = settings.unwrap_or_default();
+    let usage = Usage::new(settings.max_duration);
+    to_output(eval_it(string, args, &settings, &usage))
+}
+
+/* Like eval but borrows the settings so that the test can look at them afterwards */
+// Each test file builds its own copy of this module and not all of them use this
+#[allow(dead_code)]
+pub fn eval_with_settings(string: &str, settings: &project::Settings) -> String {
+    log::trace!("eval_with_settings");
+    let usage = Usage::new(settings.max_duration);
+    to_output(eval_it(string, Vec::new(), settings, &usage))
+}
+
+/* Like eval but borrows the usage so that the test can look at the work done afterwards */
+#[allow(dead_code)]
+pub fn eval_with_usage(string: &str, usage: &Usage) -> String {
+    log::trace!("eval_with_usage");
+    to_output(eval_it(
+        string,
+        Vec::new(),
+        &project::Settings::new(),
+        usage,
+    ))
+}
 
+fn to_output(result: Result<Value, Error>) -> String {
     match result {
-        Err(error) => {
-            format!("{}", error::to_user_output(error))
-        }
-        Ok(evaluator::values::Value::String(string)) => {
-            format!("{}", string)
-        }
+        Err(error) => error::to_user_output(error),
+        Ok(evaluator::values::Value::String(string)) => string.to_string(),
         Ok(value) => {
             format!("{:?}", value)
         }
     }
 }
 
+/* Like eval but only checks for values that depend on themselves, as 'erm run' does, so that the
+ * test sees the evaluator's own errors
+ */
+#[allow(dead_code)]
+pub fn eval_unchecked(string: &str, settings: Option<project::Settings>) -> String {
+    log::trace!("eval_unchecked");
+    let settings = settings.unwrap_or_default();
+    let usage = Usage::new(settings.max_duration);
+    to_output(
+        load(string, &settings).and_then(|(module, environment, source)| {
+            checker::check_cycles(&module).map_err(Error::CheckError)?;
+            evaluator::evaluate(&module, Vec::new(), &environment, &settings, &usage)
+                .map_err(|err| Error::EvaluateError(err, source))
+        }),
+    )
+}
+
 fn eval_it(
     string: &str,
     args: Vec<String>,
-    settings: Option<project::Settings>,
+    settings: &project::Settings,
+    usage: &Usage,
 ) -> Result<Value, Error> {
-    let _ = init_logger();
-
     log::trace!("eval_it");
+    let (module, environment, source) = load(string, settings)?;
 
-    let settings = settings.unwrap_or_else(project::Settings::new);
+    checker::check(&module, &environment, settings).map_err(Error::CheckError)?;
+    evaluator::evaluate(&module, args, &environment, settings, usage)
+        .map_err(|err| Error::EvaluateError(err, source))
+}
+
+/* Parses the source and builds the environment for evaluating it */
+fn load(
+    string: &str,
+    settings: &project::Settings,
+) -> Result<(ast::Module, env::Environment, String), Error> {
+    let _ = init_logger();
 
-    let source = unindent(&string);
+    let source = unindent(string);
 
-    let tokens = Token::lexer(&source);
-    let mut iter = tokens.spanned().peekable();
-    let module = parser::parse(&mut iter).map_err(|err| Error::ParserError(err, source.clone()))?;
+    let module = parser::parse_source(&source, |import| env::imported_operators(import, settings))
+        .map_err(|err| Error::ParserError(err, source.clone()))?;
 
-    let module = ast::with_default_imports(&module);
+    let module = env::optimized(ast::with_default_imports(&module), settings);
 
-    let scope = env::ModuleScope::from_module(&module, &settings).map_err(Error::ScopeError)?;
+    let scope = env::ModuleScope::from_module(&module, settings).map_err(Error::ScopeError)?;
     let environment = env::Environment::from_module_scope(scope);
 
-    checker::check(&module, &environment, &settings).map_err(Error::CheckError)?;
-    evaluator::evaluate(&module, args, &environment, &settings).map_err(Error::EvaluateError)
+    Ok((module, environment, source))
 }
@@ -0,0 +1,30 @@
+mod common;
+
+mod csv {
+
+    use crate::common::eval;
+
+    #[test]
+    fn parse_rows() {
+        let src = r#"
+        module Main exposing (..)
+        import Csv
+        main args =
+          Debug.toString (Csv.parse "name,,age")
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn parse_with_separator() {
+        let src = r#"
+        module Main exposing (..)
+        import Csv
+        main args =
+          Debug.toString (Csv.parseWith ';' "a,b;c")
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
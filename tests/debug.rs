@@ -0,0 +1,72 @@
+mod common;
+
+mod debug {
+
+    use crate::common::eval;
+
+    #[test]
+    fn to_string_list() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [ [ 1, 2 ], [ 3 ] ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn to_string_escapes_strings() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [ "a", "b c" ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn to_string_nested_values() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString ( Just [ 1, 2 ], ( 'a', Nothing ) )
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn to_string_function() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString identity
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn to_string_at_different_types() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString 1 ++ Debug.toString "a"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn log_returns_value() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (Debug.log "value" 3 + 1)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
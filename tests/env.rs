@@ -0,0 +1,65 @@
+mod common;
+
+mod env {
+
+    use crate::common::eval;
+    use erm::project;
+
+    fn allowed() -> project::Settings {
+        project::Settings {
+            capabilities: project::Capabilities {
+                env: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        }
+    }
+
+    #[test]
+    fn env_needs_permission() {
+        let src = r#"
+        module Main exposing (..)
+        import Env
+        main args =
+          case Env.get "CARGO_PKG_NAME" of
+            Just value ->
+              value
+            Nothing ->
+              "unset"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn reads_variable() {
+        let src = r#"
+        module Main exposing (..)
+        import Env
+        main args =
+          case Env.get "CARGO_PKG_NAME" of
+            Just value ->
+              value
+            Nothing ->
+              "unset"
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn missing_variable_is_nothing() {
+        let src = r#"
+        module Main exposing (..)
+        import Env
+        main args =
+          case Env.get "ERM_VARIABLE_THAT_IS_NOT_SET" of
+            Just value ->
+              value
+            Nothing ->
+              "unset"
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+}
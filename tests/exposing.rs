@@ -0,0 +1,46 @@
+mod common;
+
+mod exposing {
+
+    use crate::common::eval;
+
+    #[test]
+    fn types_and_constructors() {
+        let src = r#"
+        module Main exposing (Model, Msg(..), main)
+
+        type Model = Model Int
+
+        type Msg = Increment
+
+        main : List String -> String
+        main args =
+          "exposed"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn elm_format_layout() {
+        let src = r#"
+        module Main exposing
+            ( Model
+            , Msg (..)
+            , main
+            )
+
+        import Maybe exposing ( Maybe(..) )
+
+        type Model = Model Int
+
+        type Msg = Increment
+
+        main : List String -> String
+        main args =
+          "exposed"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
@@ -0,0 +1,85 @@
+mod common;
+
+mod file {
+
+    use crate::common::eval;
+    use erm::project;
+
+    fn allowed() -> project::Settings {
+        project::Settings {
+            capabilities: project::Capabilities {
+                fs: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        }
+    }
+
+    #[test]
+    fn file_needs_permission() {
+        let src = r#"
+        module Main exposing (..)
+        import File
+        main args =
+          case File.read "tests/modules/shared/Strings.elm" of
+            Ok contents ->
+              contents
+            Err error ->
+              error
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn reads_file() {
+        let src = r#"
+        module Main exposing (..)
+        import File
+        main args =
+          case File.read "tests/modules/shared/Strings.elm" of
+            Ok contents ->
+              String.left 21 contents
+            Err error ->
+              error
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        let src = r#"
+        module Main exposing (..)
+        import File
+        main args =
+          case File.read "tests/does-not-exist.txt" of
+            Ok _ ->
+              "read"
+            Err _ ->
+              "failed to read"
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn allow_all_allows_files() {
+        let src = r#"
+        module Main exposing (..)
+        import File
+        main args =
+          case File.read "tests/does-not-exist.txt" of
+            Ok _ ->
+              "read"
+            Err _ ->
+              "failed to read"
+        "#;
+        let settings = project::Settings {
+            capabilities: project::Capabilities::all(),
+            ..project::Settings::new()
+        };
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+}
@@ -98,6 +98,30 @@ mod full {
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    fn chained_comparison_fails() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          if 1 < 2 < 3 then "True" else "False"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn binding_referenced_repeatedly() {
+        let src = r#"
+        module Main exposing (..)
+        numbers = [1, 2, 3, 4]
+        total = List.sum numbers
+        main args =
+          String.fromInt (total + total + List.sum numbers)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
     #[test]
     fn string_concatenation() {
         let src = r#"
@@ -150,6 +174,42 @@ mod full {
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    fn if_statement_shallow_branches() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            value =
+                if False then
+              "then"
+                else
+              "else"
+          in
+          value
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn if_statement_branch_at_let_level() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            value =
+                if False then
+            "then"
+                else
+              "else"
+          in
+          value
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
     #[test]
     fn nested_if_statement() {
         let src = r#"
@@ -177,4 +237,67 @@ mod full {
         let result = eval_with_args(src, vec!["Hello".to_string(), " world".to_string()], None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn nested_parentheses() {
+        let src = format!(
+            "module Main exposing (..)\nmain args =\n  String.fromInt {}1{}\n",
+            "(".repeat(50),
+            ")".repeat(50)
+        );
+        let result = eval(&src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn too_deeply_nested_parentheses() {
+        let src = format!(
+            "module Main exposing (..)\nmain args =\n  String.fromInt {}1{}\n",
+            "(".repeat(5000),
+            ")".repeat(5000)
+        );
+        let result = eval(&src, None);
+        assert!(result.starts_with("This expression is too deeply nested."));
+    }
+
+    #[test]
+    fn long_operator_chain() {
+        // A flat chain isn't nested so it isn't held to the limit on nesting
+        let src = format!(
+            "module Main exposing (..)\nsum n =\n  {}\nmain args =\n  String.fromInt (sum 2)\n",
+            vec!["n"; 500].join(" + ")
+        );
+        let result = eval(&src, None);
+        assert_eq!(result, "1000");
+    }
+
+    #[test]
+    fn long_right_associative_operator_chain() {
+        let src = format!(
+            "module Main exposing (..)\njoined s =\n  {}\nmain args =\n  String.fromInt (String.length (joined \"a\"))\n",
+            vec!["s"; 500].join(" ++ ")
+        );
+        let result = eval(&src, None);
+        assert_eq!(result, "500");
+    }
+
+    #[test]
+    fn too_long_operator_chain() {
+        let src = format!(
+            "module Main exposing (..)\nmain args =\n  String.fromInt ({})\n",
+            vec!["1"; 10_002].join(" + ")
+        );
+        let result = eval(&src, None);
+        assert!(result.starts_with("This chain of operators is too long."));
+    }
+
+    #[test]
+    fn too_long_right_associative_operator_chain() {
+        let src = format!(
+            "module Main exposing (..)\nmain args =\n  String.fromInt (List.length ({} :: []))\n",
+            vec!["1"; 10_002].join(" :: ")
+        );
+        let result = eval(&src, None);
+        assert!(result.starts_with("This chain of operators is too long."));
+    }
 }
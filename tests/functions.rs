@@ -70,6 +70,20 @@ mod functions {
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    fn function_argument_shadows_binding() {
+        // The argument should be resolved to the local scope rather than the top level binding
+        let src = r#"
+        module Main exposing (..)
+        x = 10
+        add1 x = x + 1
+        main args =
+          String.fromInt (add1 5)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
     #[test]
     fn function_partially_applied() {
         let src = r#"
@@ -106,6 +120,42 @@ mod functions {
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    fn partially_applied_matches_annotation() {
+        let src = r#"
+        module Main exposing (..)
+        add : Int -> Int -> Int -> Int
+        add x y z = x + y + z
+
+        addSeven : Int -> Int
+        addSeven =
+          (add 3) 4
+
+        main args =
+          String.fromInt (addSeven 5)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn partially_applied_mismatches_annotation() {
+        let src = r#"
+        module Main exposing (..)
+        add : Int -> Int -> Int
+        add x y = x + y
+
+        addTwo : String -> Int
+        addTwo =
+          add 2
+
+        main args =
+          String.fromInt (addTwo "3")
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
     #[test]
     fn underscore_as_argument() {
         let src = r#"
@@ -117,4 +167,136 @@ mod functions {
         let result = eval(src, None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn underscore_in_list_and_operator() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (List.sum [1, 2 + _])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn underscore_as_body() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          _
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn mutually_recursive_functions() {
+        // isOdd is referenced before it is defined and each function refers to the other
+        let src = r#"
+        module Main exposing (..)
+        isEven n =
+          case n of
+            0 -> True
+            _ -> isOdd (n - 1)
+        isOdd n =
+          case n of
+            0 -> False
+            _ -> isEven (n - 1)
+        main args =
+          if isEven 10 then "even" else "odd"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn recursive_function() {
+        let src = r#"
+        module Main exposing (..)
+        factorial n =
+          if n < 2 then 1 else n * factorial (n - 1)
+        countdown n =
+          if n < 1 then 0 else countdown (n - 1)
+        main args =
+          String.fromInt (factorial 5 + countdown 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn too_many_arguments() {
+        let src = r#"
+        module Main exposing (..)
+        add a b =
+          a + b
+        main args =
+          String.fromInt (add 1 2 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn missing_arguments() {
+        let src = r#"
+        module Main exposing (..)
+        add a b =
+          a + b
+        main args =
+          String.fromInt (add 1)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn more_arguments_than_annotation() {
+        let src = r#"
+        module Main exposing (..)
+        add : Int -> Int
+        add a b =
+          a + b
+        main args =
+          String.fromInt (add 1 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn lambda_uses_local_names() {
+        let src = r#"
+        module Main exposing (..)
+        addAll n list =
+          List.map (\x -> x + n) list
+        main args =
+          Debug.toString (addAll 10 [1, 2, 3], (\a _ -> a * 2) 4 5)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn lambda_body_is_type_checked() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.map (\x -> x ++ "!") [1, 2])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn lambda_destructures_arguments() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.map (\( a, b ) -> a + b) [ ( 1, 2 ), ( 3, 4 ) ], (\{ name } ( x, _ ) -> name ++ x) { name = "a" } ( "b", 1 ))
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
 }
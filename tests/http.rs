@@ -0,0 +1,156 @@
+#![cfg(feature = "net")]
+
+mod common;
+
+mod http {
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use crate::common::eval;
+    use erm::project;
+
+    fn allowed() -> project::Settings {
+        project::Settings {
+            capabilities: project::Capabilities {
+                net: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        }
+    }
+
+    /* Serves a single request with the given status & body, echoing the request body back when
+     * there is one. Returns the url of the server.
+     */
+    fn serve_once(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind");
+        let address = listener.local_addr().expect("No address");
+
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let request = read_request(&mut stream);
+                let request_body = request.split("\r\n\r\n").nth(1).unwrap_or("");
+
+                let body = format!("{}{}", body, request_body);
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+            }
+        });
+
+        format!("http://{}", address)
+    }
+
+    // Reads until we have the headers and as much of the body as they say there is
+    fn read_request(stream: &mut impl Read) -> String {
+        let mut request = vec![];
+        let mut buffer = [0; 4096];
+
+        loop {
+            match stream.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => request.extend_from_slice(&buffer[..read]),
+            }
+
+            let text = String::from_utf8_lossy(&request).to_string();
+            if let Some((headers, body)) = text.split_once("\r\n\r\n") {
+                let length = headers
+                    .lines()
+                    .find_map(|line| {
+                        line.to_lowercase()
+                            .strip_prefix("content-length:")
+                            .map(|value| value.trim().to_string())
+                    })
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(0);
+                if body.len() >= length {
+                    return text;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&request).to_string()
+    }
+
+    #[test]
+    fn http_needs_permission() {
+        let src = r#"
+        module Main exposing (..)
+        import Http
+        main args =
+          case Http.getString "http://127.0.0.1:1" of
+            Ok body ->
+              body
+            Err error ->
+              error
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn get_string() {
+        let url = serve_once("200 OK", "hello");
+        let src = format!(
+            r#"
+        module Main exposing (..)
+        import Http
+        main args =
+          case Http.getString "{}" of
+            Ok body ->
+              body
+            Err error ->
+              error
+        "#,
+            url
+        );
+        let result = eval(&src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn post_sends_body() {
+        let url = serve_once("200 OK", "received ");
+        let src = format!(
+            r#"
+        module Main exposing (..)
+        import Http
+        main args =
+          case Http.post "{}" "data" of
+            Ok body ->
+              body
+            Err error ->
+              error
+        "#,
+            url
+        );
+        let result = eval(&src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn error_status_is_failure() {
+        let url = serve_once("404 Not Found", "missing");
+        let src = format!(
+            r#"
+        module Main exposing (..)
+        import Http
+        main args =
+          case Http.getString "{}" of
+            Ok body ->
+              body
+            Err error ->
+              error
+        "#,
+            url
+        );
+        let result = eval(&src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+}
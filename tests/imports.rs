@@ -5,6 +5,8 @@ mod imports {
     use std::path::PathBuf;
 
     use erm::project;
+    #[cfg(feature = "fs")]
+    use erm::{ast, env, parser};
 
     use crate::common::eval;
 
@@ -40,6 +42,7 @@ mod imports {
     */
 
     #[test]
+    #[cfg(feature = "fs")]
     fn imports_module_from_configured_folder() {
         let src = r#"
         module Main exposing (..)
@@ -50,6 +53,7 @@ mod imports {
 
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
@@ -57,6 +61,7 @@ mod imports {
     }
 
     #[test]
+    #[cfg(feature = "fs")]
     fn imports_module_from_imported_module() {
         let src = r#"
         module Main exposing (..)
@@ -67,6 +72,7 @@ mod imports {
 
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
@@ -74,6 +80,7 @@ mod imports {
     }
 
     #[test]
+    #[cfg(feature = "fs")]
     fn import_specific_symbol() {
         let src = r#"
         module Main exposing (..)
@@ -83,12 +90,353 @@ mod imports {
         "#;
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
         insta::assert_snapshot!(result);
     }
 
+    #[test]
+    #[cfg(feature = "fs")]
+    fn import_all_exposed_symbols() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Test exposing (..)
+        main args =
+          hello_from_import
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn import_all_leaves_out_hidden_symbols() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Test exposing (..)
+        main args =
+          greeting
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn unqualified_symbol_needs_exposing() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Test
+        main args =
+          hello
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn name_not_exposed_by_module() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Test
+        main args =
+          Impl.Test.greeting
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn unknown_name_in_module() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Test
+        main args =
+          Impl.Test.goodbye
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn imports_module_from_module_root() {
+        let src = r#"
+        module Main exposing (..)
+        import Shared.Strings
+        main args =
+          Shared.Strings.shout "Hello"
+        "#;
+        let settings = project::Settings {
+            module_roots: [("Shared".to_string(), PathBuf::from("tests/modules/shared"))].into(),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn core_path_overrides_core_modules() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt 5
+        "#;
+        let settings = project::Settings {
+            core_path: Some(PathBuf::from("tests/modules/core_override")),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn source_module_shadows_core_module() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt 5
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules/shadowing")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn core_module_shadows_source_module() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt 5
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules/shadowing")],
+            module_order: project::ModuleOrder::CoreFirst,
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn shadowing_is_reported_once() {
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules/shadowing")],
+            ..project::Settings::new()
+        };
+
+        let module_name = vec!["String".to_string()];
+        env::find_module_path(&module_name, &settings);
+        env::find_module_path(&module_name, &settings);
+
+        assert_eq!(
+            settings.warnings.take(),
+            vec![project::Warning::ShadowedModule {
+                module: "String".to_string(),
+                used: PathBuf::from("tests/modules/shadowing/String.elm"),
+                shadowed: PathBuf::from("core/String.elm"),
+            }]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn resolvable_bindings_leave_out_hidden_names() {
+        let src = "module Main exposing (..)\nimport Impl.Test\nmain args =\n    Impl.Test.hello\n";
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let module = parser::parse_source(src, |import| env::imported_operators(import, &settings))
+            .expect("Failed to parse");
+        let scope = env::ModuleScope::from_module(&ast::with_default_imports(&module), &settings)
+            .expect("Failed to build scope");
+        let environment = env::Environment::from_module_scope(scope);
+
+        let names: Vec<String> = environment
+            .resolvable_bindings()
+            .iter()
+            .filter(|resolvable| resolvable.module == Some(vec!["Impl".into(), "Test".into()]))
+            .map(|resolvable| resolvable.name.as_string())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec![
+                "Impl.Test.hello",
+                "Impl.Test.hello_from_import",
+                "Impl.Test.hello_from_prelude",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn vendored_modules_run_without_the_original_directories() {
+        let src = "module Main exposing (..)\nimport Impl.Test\nimport Shared.Strings\nmain args =\n    Shared.Strings.shout Impl.Test.hello_from_import\n";
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            module_roots: [("Shared".to_string(), PathBuf::from("tests/modules/shared"))].into(),
+            ..project::Settings::new()
+        };
+        let directory =
+            std::env::temp_dir().join(format!("erm-vendor-test-{}", std::process::id()));
+
+        let module = parser::parse_source(src, |import| env::imported_operators(import, &settings))
+            .expect("Failed to parse");
+        let copied = env::vendor(&module, &settings, &directory).expect("Failed to vendor");
+
+        let core = env::vendored_core_path(&directory);
+        assert!(copied.contains(&core.join("Basics.elm")));
+        assert!(copied.contains(&core.join("String.elm")));
+        assert_eq!(
+            copied
+                .iter()
+                .filter(|path| !path.starts_with(&core))
+                .collect::<Vec<_>>(),
+            vec![
+                &directory.join("Impl/Test.elm"),
+                &directory.join("Impl/Test/Other.elm"),
+                &directory.join("Shared/Strings.elm"),
+            ]
+        );
+
+        let vendored = project::Settings {
+            source_directories: vec![directory.clone()],
+            core_path: Some(core),
+            ..project::Settings::new()
+        };
+        let result = eval(src, Some(vendored));
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(result, "Hello from Impl.Test.Other!");
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn import_cycle_is_reported() {
+        let src = r#"
+        module Main exposing (..)
+        import Cycle.A
+        main args =
+          Cycle.A.a
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules/cycle")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn shared_imports_are_built_once() {
+        // Each module imports the two before it so following every import path separately would
+        // build the first modules millions of times
+        let directory = std::env::temp_dir().join(format!("erm-chain-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).expect("Failed to create directory");
+        for index in 0..40 {
+            let imports = match index {
+                0 => String::new(),
+                1 => "import Chain0\n".to_string(),
+                _ => format!("import Chain{}\nimport Chain{}\n", index - 1, index - 2),
+            };
+            let source = format!(
+                "module Chain{} exposing (value)\n{}\nvalue =\n    {}\n",
+                index, imports, index
+            );
+            std::fs::write(directory.join(format!("Chain{}.elm", index)), source)
+                .expect("Failed to write module");
+        }
+
+        let src = r#"
+        module Main exposing (..)
+        import Chain39
+        main args =
+          String.fromInt Chain39.value
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![directory.clone()],
+            ..project::Settings::new()
+        };
+        let result = eval(src, Some(settings));
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(result, "39");
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn cached_imports_are_reused_between_runs() {
+        let directory = std::env::temp_dir().join(format!("erm-cache-test-{}", std::process::id()));
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Shapes exposing (size)
+        main args =
+          Debug.toString (size 6)
+        "#;
+
+        // The second run reads the resolved import from the cache rather than parsing it again
+        let results: Vec<String> = (0..2)
+            .map(|_| {
+                let settings = project::Settings {
+                    source_directories: vec![PathBuf::from("tests/modules")],
+                    cache_directory: Some(directory.clone()),
+                    ..project::Settings::new()
+                };
+                eval(src, Some(settings))
+            })
+            .collect();
+        let entries = std::fs::read_dir(&directory).map_or(0, |entries| entries.count());
+        std::fs::remove_dir_all(&directory).ok();
+
+        assert_eq!(results, ["Size 6", "Size 6"]);
+        assert!(entries > 0);
+    }
+
     #[test]
     fn indented_first_import_fails() {
         let src = r#"
@@ -99,6 +447,7 @@ mod imports {
         "#;
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
@@ -116,6 +465,75 @@ mod imports {
         "#;
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn alias_qualifies_values_constructors_and_types() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Shapes as S
+        import Impl.Test as T
+
+        width : S.Shape -> Int
+        width shape =
+          case shape of
+            S.Square side ->
+              side
+            S.Circle radius ->
+              radius * 2
+
+        main args =
+          String.append T.hello (String.fromInt (width (S.Circle 3)))
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn hidden_constructor_is_not_exposed() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Shapes exposing (..)
+        main args =
+          case Impl.Shapes.Size 2 of
+            _ ->
+              "size"
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn constructor_needs_type_exposed_by_import() {
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Shapes exposing (Shape)
+        main args =
+          case Square 2 of
+            _ ->
+              "square"
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
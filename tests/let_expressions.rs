@@ -0,0 +1,186 @@
+mod common;
+
+mod let_expressions {
+
+    use crate::common::eval;
+
+    #[test]
+    fn single_line_let() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (let x = 2 in x * 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn destructure_tuple() {
+        let src = r#"
+        module Main exposing (..)
+
+        swap pair =
+          let
+            ( a, b ) = pair
+          in
+          ( b, a )
+
+        main args =
+          let
+            ( first, second ) =
+              swap ( "world", "hello" )
+
+            greeting = first ++ ", " ++ second
+          in
+          greeting
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn destructure_constructor() {
+        let src = r#"
+        module Main exposing (..)
+
+        type Named = Named String Int
+
+        main args =
+          let
+            (Named name age) = Named "Ada" 36
+          in
+          name ++ " " ++ String.fromInt age
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn destructured_names_are_typed() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            ( a, b ) = ( 1, "two" )
+          in
+          a ++ b
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn annotated_binding() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            count : Int
+            count = 3
+
+            label : String
+            label = "items"
+          in
+          String.fromInt count ++ " " ++ label
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn annotated_binding_mismatch() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            count : String
+            count = 3
+          in
+          count
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn let_as_last_operand() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (2 * 3 + let y = 2 in y * 10)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn function_bindings() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            offset = 10
+
+            add : Int -> Int -> Int
+            add a b =
+              a + b + offset
+
+            twice x = add x x
+          in
+          String.fromInt (twice 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn function_binding_is_type_checked() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            shout s = s ++ "!"
+          in
+          shout 3
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn recursive_function_binding() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            go n acc = if n < 1 then acc else go (n - 1) (acc + n)
+          in
+          String.fromInt (go 10 0)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn bindings_see_later_bindings() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            isEven n = if n < 1 then True else isOdd (n - 1)
+
+            ( total, label ) = ( double 4, "total" )
+
+            isOdd n = if n < 1 then False else isEven (n - 1)
+
+            double x = x * scale
+
+            scale = 2
+          in
+          Debug.toString ( isEven 10, isOdd 7, label ++ " " ++ String.fromInt total )
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
@@ -0,0 +1,219 @@
+mod common;
+
+mod limits {
+
+    use std::time::Duration;
+
+    use crate::common::{eval, eval_with_usage};
+    use erm::evaluator::limits::Usage;
+    use erm::project;
+
+    #[test]
+    fn timeout_stops_long_running_program() {
+        let src = r#"
+        module Main exposing (..)
+        countDown n =
+          if n > 0 then
+            countDown (n - 1)
+          else
+            n
+        main args =
+          String.fromInt (countDown 100)
+        "#;
+
+        let settings = project::Settings {
+            max_duration: Some(Duration::ZERO),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn finishes_within_duration() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (1 + 2)
+        "#;
+
+        let settings = project::Settings {
+            max_duration: Some(Duration::from_secs(10)),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_length_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (List.sum [ 1, 2, 3, 4, 5 ])
+        "#;
+
+        let settings = project::Settings {
+            max_list_length: Some(4),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn string_length_limit() {
+        let src = r#"
+        module Main exposing (..)
+        double text =
+          text ++ text
+        main args =
+          double (double (double "abc"))
+        "#;
+
+        let settings = project::Settings {
+            max_string_length: Some(16),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn huge_repeat_is_over_string_length_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.repeat 9223372036854775807 "ab"
+        "#;
+
+        let settings = project::Settings {
+            max_string_length: Some(16),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn huge_repeat_without_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.repeat 9223372036854775807 "ab"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn huge_padding_is_over_string_length_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.padLeft 9223372036854775807 'x' "ab" ++ String.padRight 5 'x' "ab"
+        "#;
+
+        let settings = project::Settings {
+            max_string_length: Some(16),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn huge_padding_without_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.padRight 9223372036854775807 'x' "ab"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn repeat_and_padding_within_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.repeat 3 "ab" ++ String.padLeft 5 'x' "ab" ++ String.repeat 9223372036854775807 ""
+        "#;
+
+        let settings = project::Settings {
+            max_string_length: Some(16),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn huge_random_list_is_over_list_length_limit() {
+        let src = r#"
+        module Main exposing (..)
+        import Random
+        main args =
+          case Random.step (Random.list 9223372036854775807 (Random.int 1 6)) (Random.initialSeed 42) of
+            ( rolls, _ ) ->
+              Debug.toString rolls
+        "#;
+
+        let settings = project::Settings {
+            max_list_length: Some(4),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn value_count_limit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [ [ 1, 2 ], [ 3, 4 ], [ 5, 6 ] ]
+        "#;
+
+        let settings = project::Settings {
+            max_values: Some(8),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn counts_work_done() {
+        let src = r#"
+        module Main exposing (..)
+        double text =
+          text ++ text
+        main args =
+          String.join "" [ double (double "abc") ]
+        "#;
+
+        let usage = Usage::default();
+        let result = eval_with_usage(src, &usage);
+        assert_eq!(result, "abcabcabcabc");
+
+        let stats = usage.stats();
+        // main, double twice, the append builtin that each ++ calls & join
+        assert_eq!(stats.calls, 6);
+        // The list literal, the two appended strings & the joined string
+        assert_eq!((stats.lists, stats.strings), (1, 3));
+        // Functions are evaluated in the module's top level scope with their arguments on top
+        assert_eq!(stats.max_scope_depth, 2);
+        assert!(stats.expressions > 0);
+    }
+}
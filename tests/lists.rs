@@ -47,4 +47,111 @@ mod lists {
         let result = eval(src, None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn list_product() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (List.product [2, 3, 4] + List.product [])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_sum_of_floats() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.sum [1.5, 2, 0.25], List.product [0.5, 3])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_maximum_and_minimum() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [List.maximum [3, -1, 7], List.minimum [3, -1, 7], List.maximum []]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_maximum_of_floats() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.maximum [1.5, 2.25, -3], List.minimum [1.5, 2.25, -3])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_take_and_drop() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.take 2 [1, 2, 3], List.drop 2 [1, 2, 3], List.take 5 [1], List.drop -1 [1])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_partition() {
+        let src = r#"
+        module Main exposing (..)
+        isSmall n =
+          n < 3
+        main args =
+          Debug.toString (List.partition isSmall [1, 5, 2, 4])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_partition_reports_errors_from_the_predicate() {
+        let src = r#"
+        module Main exposing (..)
+        isSmall n =
+          case Just n of
+            Just 1 -> True
+        main args =
+          Debug.toString (List.partition isSmall [1, 5])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_unzip_and_intersperse() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (List.unzip [(1, 'a'), (2, 'b')], List.intersperse 0 [1, 2, 3], List.intersperse 0 [])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn list_item_types_reach_the_function_argument() {
+        let src = r#"
+        module Main exposing (..)
+        total : List Int -> Int
+        total items =
+          List.sum items
+        main args =
+          Debug.toString (total [ 1.5, 2 ])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
 }
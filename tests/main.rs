@@ -0,0 +1,44 @@
+mod common;
+
+mod main {
+
+    use crate::common::eval;
+
+    #[test]
+    fn main_as_a_value() {
+        let src = r#"
+        module Main exposing (..)
+
+        main : String
+        main =
+          "Hello"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn main_returning_an_exit_code() {
+        let src = r#"
+        module Main exposing (..)
+
+        main : List String -> Int
+        main args =
+          List.sum [ 1, 2 ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn main_with_unsupported_type() {
+        let src = r#"
+        module Main exposing (..)
+
+        main args =
+          True
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
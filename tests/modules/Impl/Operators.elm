@@ -0,0 +1,6 @@
+module Impl.Operators exposing ((|*|), scale)
+
+infix left 8 (|*|) = scale
+
+scale x y =
+    x * y * 10
@@ -0,0 +1,12 @@
+module Impl.Shapes exposing (Shape(..), Size, size)
+
+type Shape
+    = Square Int
+    | Circle Int
+
+type Size
+    = Size Int
+
+size : Int -> Size
+size n =
+    Size n
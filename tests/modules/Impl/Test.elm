@@ -1,4 +1,4 @@
-module Impl.Test exposing (hello, hello_from_import)
+module Impl.Test exposing (hello, hello_from_import, hello_from_prelude)
 
 import Impl.Test.Other
 
@@ -10,3 +10,6 @@ hello_from_import =
 
 hello_from_prelude =
     String.append "Hello, " "from prelude"
+
+greeting =
+    "Only for Impl.Test"
@@ -0,0 +1,6 @@
+module Cycle.A exposing (a)
+
+import Cycle.B
+
+a =
+    Cycle.B.b
@@ -0,0 +1,6 @@
+module Cycle.B exposing (b)
+
+import Cycle.A
+
+b =
+    "b"
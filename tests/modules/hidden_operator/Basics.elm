@@ -0,0 +1,54 @@
+module Basics exposing ((<), (>), (+), (-), (*), Never, always, identity, never, not, xor)
+
+infix non   4 (<)  = lt
+infix non   4 (>)  = gt
+infix right 5 (++) = append
+infix left  6 (+)  = add
+infix left  6 (-)  = sub
+infix left  7 (*)  = mul
+
+lt =
+    Elm.Kernel.Basics.lt
+
+gt =
+    Elm.Kernel.Basics.gt
+
+append =
+    Elm.Kernel.Basics.append
+
+add =
+    Elm.Kernel.Basics.add
+
+sub =
+    Elm.Kernel.Basics.sub
+
+mul =
+    Elm.Kernel.Basics.mul
+
+identity x =
+    x
+
+always a _ =
+    a
+
+not bool =
+    case bool of
+        True ->
+            False
+
+        False ->
+            True
+
+xor a b =
+    case a of
+        True ->
+            not b
+
+        False ->
+            b
+
+type Never
+    = JustOneMore Never
+
+never =
+    Elm.Kernel.Basics.never
@@ -0,0 +1,10 @@
+module String exposing (append, fromInt, join)
+
+append =
+    Elm.Kernel.Basics.append
+
+join =
+    Elm.Kernel.String.join
+
+fromInt n =
+    Elm.Kernel.String.fromInt (n * 10)
@@ -0,0 +1,5 @@
+module Shared.Strings exposing (shout)
+
+
+shout text =
+    String.append text "!"
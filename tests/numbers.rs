@@ -0,0 +1,122 @@
+mod common;
+
+mod numbers {
+
+    use crate::common::eval;
+    use erm::project::{IntegerOverflow, Settings};
+
+    #[test]
+    fn integer_literal_as_float() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString (1 + 1.5 * 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn integer_arithmetic_stays_int() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (7 - 2 * 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn float_comparison() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          if 2 > 1.5 then "bigger" else "smaller"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn float_is_not_int() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (1 + 1.5)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn integers_are_64_bit() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (3000000000 * 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn integer_overflow_is_an_error() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (List.sum [9223372036854775807, 1])
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn optimized_constants_keep_their_values() {
+        let src = r#"
+        module Main exposing (..)
+        day = 60 * 60 * 24
+        halves = [ 0.5 + 0.5, 1.5 * 2 ]
+        name = "erm" ++ ".elm"
+        main args =
+          name ++ " " ++ String.fromInt day ++ " " ++ Debug.toString halves
+        "#;
+        let settings = Settings {
+            optimize: true,
+            ..Settings::new()
+        };
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn optimized_constants_still_overflow() {
+        let src = r#"
+        module Main exposing (..)
+        tooBig = 9223372036854775807 + 1
+        main args =
+          String.fromInt tooBig
+        "#;
+        let settings = Settings {
+            optimize: true,
+            ..Settings::new()
+        };
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn integer_overflow_wraps_when_asked() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (9223372036854775807 + 1)
+        "#;
+        let settings = Settings {
+            integer_overflow: IntegerOverflow::Wrap,
+            ..Settings::default()
+        };
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+}
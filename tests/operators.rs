@@ -0,0 +1,106 @@
+mod common;
+
+mod operators {
+
+    #[cfg(feature = "fs")]
+    use std::path::PathBuf;
+
+    #[cfg(feature = "fs")]
+    use erm::project;
+
+    use crate::common::eval;
+    #[cfg(feature = "fs")]
+    use crate::common::eval_unchecked;
+
+    #[test]
+    fn custom_operator() {
+        let src = r#"
+        module Main exposing (..)
+        infix left 5 (|+|) = myAdd
+        myAdd x y = x + y
+        main args =
+          String.fromInt (1 |+| 2 * 3 |+| 4)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn custom_operator_with_wrong_argument_type() {
+        let src = r#"
+        module Main exposing (..)
+        infix left 5 (|+|) = myAdd
+        myAdd x y = x + y
+        main args =
+          String.fromInt ("one" |+| 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn imported_custom_operator() {
+        // The imported operator binds more tightly than addition
+        let src = r#"
+        module Main exposing (..)
+        import Impl.Operators exposing ((|*|))
+        main args =
+          String.fromInt (1 + 2 |*| 3)
+        "#;
+        let settings = project::Settings {
+            source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn operator_not_exposed_by_module() {
+        // The Basics module here doesn't expose (++) even though it defines it
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          "Hello, " ++ "World"
+        "#;
+        let settings = project::Settings {
+            core_path: Some(PathBuf::from("tests/modules/hidden_operator")),
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn if_as_last_operand() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (10 + if False then 1 else 100)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    #[cfg(feature = "fs")]
+    fn operator_not_exposed_when_unchecked() {
+        // Without the checker the evaluator is the one to find that (++) isn't available
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          "Hello, " ++ "World"
+        "#;
+        let settings = project::Settings {
+            core_path: Some(PathBuf::from("tests/modules/hidden_operator")),
+            ..project::Settings::new()
+        };
+
+        let result = eval_unchecked(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+}
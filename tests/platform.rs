@@ -0,0 +1,55 @@
+mod platform {
+
+    use std::sync::Arc;
+
+    use unindent::unindent;
+
+    use erm::ast;
+    use erm::checker;
+    use erm::env;
+    use erm::evaluator::{self, limits::Usage, platform::Worker, values::Value};
+    use erm::parser;
+    use erm::project;
+
+    #[test]
+    fn worker_keeps_model_between_messages() {
+        let src = unindent(
+            r#"
+            module Main exposing (..)
+            import Platform
+            update line lines =
+              String.join "," [ lines, line ]
+            main args =
+              Platform.worker "start" update
+            "#,
+        );
+        let settings = project::Settings::new();
+        let module =
+            parser::parse_source(&src, |import| env::imported_operators(import, &settings))
+                .expect("Failed to parse");
+        let module = ast::with_default_imports(&module);
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+
+        checker::check(&module, &environment, &settings).expect("Failed to check");
+        let program = match evaluator::evaluate(
+            &module,
+            vec![],
+            &environment,
+            &settings,
+            &Usage::default(),
+        ) {
+            Ok(Value::Program(program)) => program,
+            result => panic!("Expected a program, got {:?}", result),
+        };
+
+        let mut worker = Worker::new(&program);
+        for line in ["one", "two"] {
+            worker
+                .update(Value::String(Arc::from(line)), &settings, &Usage::default())
+                .expect("Failed to update");
+        }
+
+        assert_eq!(worker.model().to_string(), "\"start,one,two\"");
+    }
+}
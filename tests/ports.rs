@@ -0,0 +1,112 @@
+mod common;
+
+mod ports {
+
+    use std::sync::mpsc;
+
+    use unindent::unindent;
+
+    use crate::common::eval;
+    use erm::ast;
+    use erm::env;
+    use erm::evaluator::{self, limits::Usage, ports, values::Value};
+    use erm::parser;
+    use erm::project;
+
+    #[test]
+    fn sends_values_to_host() {
+        let src = r#"
+        port module Main exposing (..)
+        port log : String -> Cmd msg
+        main args =
+          case log "hello" of
+            _ ->
+              "sent"
+        "#;
+        let (sender, receiver) = mpsc::channel();
+        let settings = project::Settings::new();
+        settings.ports.listen(sender);
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(ports::PortMessage {
+                port: "log".to_string(),
+                value: serde_json::json!("hello")
+            })
+        );
+    }
+
+    #[test]
+    fn sending_needs_listener() {
+        let src = r#"
+        port module Main exposing (..)
+        port log : String -> Cmd msg
+        main args =
+          case log "hello" of
+            _ ->
+              "sent"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn port_needs_direction() {
+        let src = r#"
+        port module Main exposing (..)
+        port log : String -> String
+        main args =
+          "hello"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn receives_values_from_host() {
+        let src = unindent(
+            r#"
+            port module Main exposing (..)
+            type Msg
+              = Received String
+            port received : (String -> msg) -> Sub msg
+            subscription =
+              received Received
+            "#,
+        );
+        let settings = project::Settings::new();
+        let module =
+            parser::parse_source(&src, |import| env::imported_operators(import, &settings))
+                .expect("Failed to parse");
+        let module = ast::with_default_imports(&module);
+        let scope = env::ModuleScope::from_module(&module, &settings).expect("Failed to load");
+        let environment = env::Environment::from_module_scope(scope);
+
+        let sub = match evaluator::evaluate_name(
+            "subscription",
+            &environment,
+            &settings,
+            &Usage::default(),
+        ) {
+            Ok(Value::Sub(sub)) => sub,
+            result => panic!("Expected a subscription, got {:?}", result),
+        };
+
+        settings
+            .ports
+            .inject("received", serde_json::json!("first"));
+        settings
+            .ports
+            .inject("received", serde_json::json!("second"));
+
+        let messages =
+            ports::receive(&sub, &settings, &Usage::default()).expect("Failed to receive");
+        let messages: Vec<String> = messages.iter().map(|value| value.to_string()).collect();
+        assert_eq!(messages, vec!["Received \"first\"", "Received \"second\""]);
+        assert!(ports::receive(&sub, &settings, &Usage::default())
+            .expect("Failed to receive")
+            .is_empty());
+    }
+}
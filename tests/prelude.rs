@@ -2,8 +2,10 @@ mod common;
 
 mod prelude {
 
+    #[cfg(feature = "fs")]
     use std::path::PathBuf;
 
+    #[cfg(feature = "fs")]
     use erm::project;
 
     use crate::common::eval;
@@ -22,6 +24,7 @@ mod prelude {
     }
 
     #[test]
+    #[cfg(feature = "fs")]
     fn imported_module_can_use_prelude() {
         let src = r#"
         module Main exposing (..)
@@ -32,6 +35,7 @@ mod prelude {
 
         let settings = project::Settings {
             source_directories: vec![PathBuf::from("tests/modules")],
+            ..project::Settings::new()
         };
 
         let result = eval(src, Some(settings));
@@ -0,0 +1,65 @@
+mod common;
+
+mod process {
+
+    use crate::common::eval;
+    use erm::project;
+
+    fn allowed() -> project::Settings {
+        project::Settings {
+            capabilities: project::Capabilities {
+                process: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        }
+    }
+
+    #[test]
+    fn process_needs_permission() {
+        let src = r#"
+        module Main exposing (..)
+        import Process
+        main args =
+          case Process.run "echo" [ "hello" ] of
+            Ok ( code, output, errors ) ->
+              output
+            Err error ->
+              error
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn captures_output() {
+        let src = r#"
+        module Main exposing (..)
+        import Process
+        main args =
+          case Process.run "echo" [ "hello", "world" ] of
+            Ok ( code, output, errors ) ->
+              String.fromInt code ++ " " ++ output
+            Err error ->
+              error
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn missing_program_is_an_error() {
+        let src = r#"
+        module Main exposing (..)
+        import Process
+        main args =
+          case Process.run "erm-program-that-does-not-exist" [] of
+            Ok _ ->
+              "ran"
+            Err _ ->
+              "failed to start"
+        "#;
+        let result = eval(src, Some(allowed()));
+        insta::assert_snapshot!(result);
+    }
+}
@@ -0,0 +1,68 @@
+mod common;
+
+mod random {
+
+    use crate::common::eval;
+
+    #[test]
+    fn int_from_seed() {
+        let src = r#"
+        module Main exposing (..)
+        import Random
+        main args =
+          case Random.step (Random.int 1 6) (Random.initialSeed 42) of
+            ( roll, seed ) ->
+              String.fromInt roll
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn float_in_range() {
+        let src = r#"
+        module Main exposing (..)
+        import Random
+        main args =
+          case Random.step (Random.float 0 1) (Random.initialSeed 7) of
+            ( value, seed ) ->
+              Debug.toString value
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn seed_is_threaded() {
+        let src = r#"
+        module Main exposing (..)
+        import Random
+        roll seed =
+          Random.step (Random.int 1 6) seed
+        main args =
+          case roll (Random.initialSeed 42) of
+            ( first, seed ) ->
+              case roll seed of
+                ( second, _ ) ->
+                  String.fromInt (first * 10 + second)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn int_with_extreme_bounds() {
+        let src = r#"
+        module Main exposing (..)
+        import Random
+        main args =
+          case Random.step (Random.int -9223372036854775807 9223372036854775807) (Random.initialSeed 42) of
+            ( value, seed ) ->
+              case Random.step (Random.int 0 4294967295) seed of
+                ( full, _ ) ->
+                  Debug.toString ( value, full )
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
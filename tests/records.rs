@@ -0,0 +1,147 @@
+mod common;
+
+mod records {
+
+    use crate::common::eval;
+
+    #[test]
+    fn record_literal() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString { name = "Ada", age = 36 }
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn multi_line_record() {
+        let src = r#"
+        module Main exposing (..)
+
+        point x =
+          { x = x
+          , y =
+              x * 2
+          , label = String.fromInt x
+          }
+
+        main args =
+          Debug.toString [ point 1, { label = "origin", x = 0, y = 0 } ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn records_with_different_fields() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString [ { a = 1 }, { b = 2 } ]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn duplicate_field() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.toString { a = 1, a = 2 }
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_in_case() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          case { name = "Ada", age = 36 } of
+            { name, age } ->
+              name ++ " is " ++ String.fromInt age
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_with_some_of_the_fields() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            { name } =
+              { name = "Ada", age = 36 }
+          in
+          name
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_inside_tuple() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          case ( 1, { total = 2 } ) of
+            ( first, { total } ) ->
+              String.fromInt (first + total)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_as_constructor_argument() {
+        let src = r#"
+        module Main exposing (..)
+        type Shape a
+          = Square a
+          | Point
+        area shape =
+          case shape of
+            Point ->
+              0
+            Square { size } ->
+              size * size
+        main args =
+          String.fromInt (area (Square { size = 3 }))
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_with_missing_field() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          case { name = "Ada" } of
+            { name, age } ->
+              name
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn record_pattern_field_types_are_checked() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          let
+            { age } =
+              { age = "old" }
+          in
+          String.fromInt age
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
@@ -0,0 +1,134 @@
+mod common;
+
+mod settings {
+
+    use std::io::Write;
+    use std::rc::Rc;
+    use std::sync::{Arc, Mutex};
+
+    use erm::builtins::{self, Func};
+    use erm::checker::term::{Term, Value as TermValue};
+    use erm::evaluator::values::Value;
+    use erm::project::{Settings, SettingsError};
+
+    use crate::common::{eval, eval_with_settings};
+
+    // Elm.Kernel.Host.greet in a host program
+    struct Greet {}
+
+    impl Func for Greet {
+        fn call(&self, args: Vec<Value>) -> Result<Value, builtins::Error> {
+            match args.as_slice() {
+                [Value::String(name)] => Ok(Value::String(Arc::from(format!("Hello, {}!", name)))),
+                _ => Err(builtins::Error::WrongArgumentType),
+            }
+        }
+
+        fn term(&self) -> Term {
+            Term::Function(
+                Box::new(Term::Constant(TermValue::String)),
+                Box::new(Term::Constant(TermValue::String)),
+            )
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(bytes)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn missing_directories_are_reported_when_built() {
+        let result = Settings::builder()
+            .source_directory("tests/modules")
+            .module_root("Shared", "tests/does-not-exist")
+            .build();
+
+        assert_eq!(
+            result.err(),
+            Some(SettingsError::MissingDirectory {
+                setting: "module root",
+                path: "tests/does-not-exist".into(),
+            })
+        );
+    }
+
+    #[test]
+    fn host_functions_need_qualified_names() {
+        let result = Settings::builder()
+            .host_function("greet", || Rc::new(Greet {}))
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(SettingsError::InvalidHostFunctionName("greet".to_string()))
+        );
+
+        let result = Settings::builder()
+            .host_function("Elm.Kernel.String.length", || Rc::new(Greet {}))
+            .build();
+        assert_eq!(
+            result.err(),
+            Some(SettingsError::HostFunctionIsBuiltIn(
+                "Elm.Kernel.String.length".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn calls_host_function() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Host.greet "erm"
+        "#;
+        let settings = Settings::builder()
+            .host_function("Host.greet", || Rc::new(Greet {}))
+            .build()
+            .expect("Failed to build settings");
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn host_functions_are_type_checked() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Host.greet 1
+        "#;
+        let settings = Settings::builder()
+            .host_function("Host.greet", || Rc::new(Greet {}))
+            .build()
+            .expect("Failed to build settings");
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn debug_log_writes_to_output() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          Debug.log "answer" "42"
+        "#;
+        let buffer = SharedBuffer::default();
+        let settings = Settings::builder()
+            .output(buffer.clone())
+            .build()
+            .expect("Failed to build settings");
+
+        assert_eq!(eval_with_settings(src, &settings), "42");
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output, "answer: \"42\"\n");
+    }
+}
@@ -0,0 +1,156 @@
+mod common;
+
+mod strings {
+
+    use crate::common::eval;
+    use erm::project::{Settings, StringUnits};
+
+    #[test]
+    fn length_counts_graphemes() {
+        // The first "é" is a single code point and the second is an "e" and a combining accent
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (String.length "héllo héllo 👨‍👩‍👧")
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn reverse_keeps_graphemes_together() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.reverse "cafés 👍🏽!"
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn left_and_right() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.join "," [String.left 2 "日本語", String.right 2 "日本語", String.left 10 "日本語", String.right -1 "日本語"]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn length_counts_code_points() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromInt (String.length "héllo héllo")
+        "#;
+        let settings = Settings {
+            string_units: StringUnits::CodePoints,
+            ..Settings::default()
+        };
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn join_with_separator() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.join ", " ["a", "b", "c"]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn concat_and_repeat() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.concat [String.repeat 3 "ab", "-", String.repeat 0 "x", String.repeat -2 "y", "!"]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn pad_left_and_right() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.join "|" [String.padLeft 5 '.' "42", String.padRight 5 '.' "héllo", String.padRight 4 ' ' "é", String.padLeft 1 '0' "123"]
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    // Literal strings can hold newlines & tabs but can't escape them so these sources are built up
+    // from Rust strings
+    #[test]
+    fn words_skip_runs_of_whitespace() {
+        let src = "module Main exposing (..)\nmain args =\n  Debug.toString (String.words \"  one two\t\tthree\nfour  \")\n";
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn lines_keep_empty_lines() {
+        let src = "module Main exposing (..)\nmain args =\n  Debug.toString (String.lines \"one\n\nthree\n\")\n";
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn lines_split_on_carriage_returns() {
+        let src = "module Main exposing (..)\nmain args =\n  Debug.toString (String.lines \"a\r\nb\rc\")\n";
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn map_filter_and_fold_characters() {
+        let src = r#"
+        module Main exposing (..)
+        dashes char =
+          case char of
+            ' ' -> '-'
+            _ -> char
+        notVowel char =
+          case char of
+            'a' -> False
+            'e' -> False
+            _ -> True
+        count char total =
+          total + 1
+        main args =
+          Debug.toString (String.map dashes "a b c", String.filter notVowel "décaféinated", String.foldl count 0 "héllo")
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn from_bool() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          String.fromBool True ++ " " ++ String.fromBool (1 > 2)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn to_string_was_removed() {
+        let src = r#"
+        module Main exposing (..)
+        main args =
+          toString 5
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+mod time {
+
+    use crate::common::eval;
+    use erm::project;
+
+    #[test]
+    fn time_needs_permission() {
+        let src = r#"
+        module Main exposing (..)
+        import Time
+        main args =
+          String.fromInt (Time.now ())
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn now_is_after_2020() {
+        let src = r#"
+        module Main exposing (..)
+        import Time
+        main args =
+          if Time.now () > 1577836800 then
+            "after"
+          else
+            "before"
+        "#;
+
+        let settings = project::Settings {
+            capabilities: project::Capabilities {
+                time: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn monotonic_time_moves_forward() {
+        let src = r#"
+        module Main exposing (..)
+        import Time
+        main args =
+          let
+            start = Time.monotonic ()
+            end = Time.monotonic ()
+          in
+          if start > end then
+            "backwards"
+          else
+            "forwards"
+        "#;
+
+        let settings = project::Settings {
+            capabilities: project::Capabilities {
+                time: true,
+                ..project::Capabilities::default()
+            },
+            ..project::Settings::new()
+        };
+
+        let result = eval(src, Some(settings));
+        insta::assert_snapshot!(result);
+    }
+}
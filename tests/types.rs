@@ -47,7 +47,7 @@ mod types {
 
         main : List String -> String
         main args =
-          String.fromInt (second 4 5)
+          String.fromInt (second [ Just 4 ] 5)
         "#;
         let result = eval(src, None);
         insta::assert_snapshot!(result);
@@ -121,4 +121,110 @@ mod types {
         let result = eval(src, None);
         insta::assert_snapshot!(result);
     }
+
+    #[test]
+    fn annotation_mismatch_in_branch() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe : Int -> String
+        describe x =
+          if x > 0 then
+            "positive"
+          else
+            x
+
+        main : List String -> String
+        main args =
+          describe 3
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn hole_in_type_annotation() {
+        let src = r#"
+        module Main exposing (..)
+
+        describe : _ -> String
+        describe x =
+          String.fromInt x
+
+        main : List String -> String
+        main args =
+          describe 3
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn tuple_type_annotation() {
+        let src = r#"
+        module Main exposing (..)
+
+        first : ( Int, String ) -> Int
+        first pair =
+          case pair of
+            ( a, _ ) -> a
+
+        main : List String -> String
+        main args =
+          String.fromInt (first ( 1, "a" ))
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn value_annotation_mismatch() {
+        let src = r#"
+        module Main exposing (..)
+
+        count : Int
+        count =
+          "three"
+
+        main : List String -> String
+        main args =
+          String.fromInt count
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn annotation_type_variables_are_rigid() {
+        let src = r#"
+        module Main exposing (..)
+
+        id : a -> a
+        id x =
+          x + 1
+
+        main : List String -> String
+        main args =
+          String.fromInt (id 3)
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
+
+    #[test]
+    fn annotation_type_variables_stay_general_for_callers() {
+        let src = r#"
+        module Main exposing (..)
+
+        id : a -> a
+        id x =
+          x
+
+        main : List String -> String
+        main args =
+          id (String.fromInt (id 3))
+        "#;
+        let result = eval(src, None);
+        insta::assert_snapshot!(result);
+    }
 }